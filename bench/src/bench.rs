@@ -27,6 +27,20 @@ fn is_match(c: &mut Criterion) {
         });
     });
 
+    define(c, "is-match", "sherlock-huge-remapped", corpus, move |b| {
+        let mut re = dense::Builder::new()
+            .anchored(false)
+            .minimize(true)
+            .premultiply(true)
+            .byte_classes(false)
+            .build(r"\p{Greek}")
+            .unwrap();
+        re.remap_for_locality();
+        b.iter(|| {
+            assert!(!re.is_match(corpus));
+        });
+    });
+
     // let corpus = OPEN_ZH_SMALL;
     let corpus = SHERLOCK_SMALL;
     define(c, "is-match", "sherlock-small", corpus, move |b| {
@@ -72,7 +86,121 @@ fn is_match(c: &mut Criterion) {
     });
 }
 
+// The forward search routines share one inner loop, so these should all
+// take about the same time on a corpus with no match.
+fn search_loop(c: &mut Criterion) {
+    let corpus = SHERLOCK_HUGE;
+    let dfa = || {
+        dense::Builder::new()
+            .minimize(true)
+            .premultiply(true)
+            .accelerate(false)
+            .build(r"\p{Greek}")
+            .unwrap()
+    };
+    let re = dfa();
+    define(c, "search-loop", "is-match", corpus, move |b| {
+        b.iter(|| {
+            assert!(!re.is_match(corpus));
+        });
+    });
+    let re = dfa();
+    define(c, "search-loop", "shortest-match", corpus, move |b| {
+        b.iter(|| {
+            assert_eq!(None, re.shortest_match(corpus));
+        });
+    });
+    let re = dfa();
+    define(c, "search-loop", "find", corpus, move |b| {
+        b.iter(|| {
+            assert_eq!(None, re.find(corpus));
+        });
+    });
+}
+
+// Compares unanchored searches with and without start state acceleration.
+// The pattern's first byte is rare in the corpus, so an accelerated search
+// spends nearly all of its time skipping over bytes in the start state.
+fn accelerate(c: &mut Criterion) {
+    let corpus = SHERLOCK_HUGE;
+    for &(name, yes) in &[("no-accel", false), ("accel", true)] {
+        let dfa = dense::Builder::new()
+            .minimize(true)
+            .accelerate(yes)
+            .build(r"zq[a-z]+")
+            .unwrap();
+        assert_eq!(yes, dfa.is_accelerated());
+        define(c, "accelerate", name, corpus, move |b| {
+            b.iter(|| {
+                assert_eq!(None, dfa.find(corpus));
+            });
+        });
+    }
+}
+
 // \w has 128,640 codepoints.
+// Searches with a DFA whose transition table (about 12MB) is much larger
+// than L2 cache, so that most transitions miss it, and with a small one
+// (about 56KB) that fits in it.
+// Run these with and without the `prefetch` feature to measure the software
+// prefetch it adds to the search loop:
+//
+//   cargo bench -p regex-automata-bench -- prefetch
+//   cargo bench -p regex-automata-bench --features prefetch -- prefetch
+//
+// Criterion reports the change relative to the previous run.
+fn prefetch(c: &mut Criterion) {
+    for &(name, count) in &[("large-dfa", 3000), ("small-dfa", 10)] {
+        let (literals, corpus) = random_literals(count);
+        let corpus: &'static [u8] = Box::leak(corpus.into_boxed_slice());
+        let dfa = dense::Builder::new()
+            .premultiply(true)
+            .build(&literals.join("|"))
+            .unwrap();
+        if count > 1000 {
+            assert!(dfa.memory_usage() > 3 * (1 << 20));
+        }
+        assert_eq!(None, dfa.find(corpus));
+        define(c, "prefetch", name, corpus, move |b| {
+            b.iter(|| {
+                assert_eq!(None, dfa.find(corpus));
+            });
+        });
+    }
+}
+
+/// Returns `count` pseudo-random literals, along with a corpus of about 1MB
+/// in which none of them occur. Each literal is a run of lowercase letters
+/// followed by a digit, and the corpus is made of the literals with their
+/// digit dropped. An unanchored search for them thus walks deep into the DFA
+/// without ever matching, visiting states all over its transition table.
+fn random_literals(count: usize) -> (Vec<String>, Vec<u8>) {
+    // A fixed xorshift generator, so that every run uses the same input.
+    let mut state = 0x2545F4914F6CDD1Du64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    let literals: Vec<String> = (0..count)
+        .map(|_| {
+            let len = 8 + next() % 8;
+            let mut lit: String = (0..len)
+                .map(|_| (b'a' + (next() % 26) as u8) as char)
+                .collect();
+            lit.push((b'0' + (next() % 10) as u8) as char);
+            lit
+        })
+        .collect();
+    let mut corpus = vec![];
+    while corpus.len() < 1 << 20 {
+        let lit = literals[next() as usize % count].as_bytes();
+        corpus.extend_from_slice(&lit[..lit.len() - 1]);
+    }
+    (literals, corpus)
+}
+
 fn compile_unicode_word(c: &mut Criterion) {
     define_compile(c, "unicode-word", r"\w");
     define_compile_reverse(c, "unicode-word", r"\w");
@@ -100,6 +228,69 @@ fn compile_muammar(c: &mut Criterion) {
     );
 }
 
+// A large alternation of literals produces many DFA states, which makes
+// growth of the transition table during determinization visible.
+fn compile_capacity_hint(c: &mut Criterion) {
+    let pattern: &'static str = Box::leak(
+        (0..2000)
+            .map(|i| format!("w{:04}x", i))
+            .collect::<Vec<_>>()
+            .join("|")
+            .into_boxed_str(),
+    );
+    let group = "fwd-compile/literal-alternation";
+    define(c, group, "no-hint", &[], move |b| {
+        b.iter(|| {
+            let result = dense::Builder::new()
+                .anchored(true)
+                .premultiply(false)
+                .build(pattern);
+            assert!(result.is_ok());
+        });
+    });
+    let (_, stats) = dense::Builder::new()
+        .anchored(true)
+        .premultiply(false)
+        .build_with_stats(pattern)
+        .unwrap();
+    let states = stats.determinized_states();
+    define(c, group, "hint", &[], move |b| {
+        b.iter(|| {
+            let result = dense::Builder::new()
+                .anchored(true)
+                .premultiply(false)
+                .states_capacity_hint(states)
+                .build(pattern);
+            assert!(result.is_ok());
+        });
+    });
+}
+
+// Compares building a DFA for a large set of literals by way of a pattern
+// against building it from the literals directly.
+fn compile_literals(c: &mut Criterion) {
+    let literals: &'static [String] = Box::leak(
+        (0..2000)
+            .map(|i| format!("w{:04}x", i))
+            .collect::<Vec<_>>()
+            .into_boxed_slice(),
+    );
+    let pattern: &'static str = Box::leak(literals.join("|").into_boxed_str());
+    let group = "fwd-compile/literal-set";
+    define(c, group, "alternation", &[], move |b| {
+        b.iter(|| {
+            let result = dense::Builder::new().build(pattern);
+            assert!(result.is_ok());
+        });
+    });
+    define(c, group, "trie", &[], move |b| {
+        b.iter(|| {
+            let result = dense::Builder::new().build_literals(literals);
+            assert!(result.is_ok());
+        });
+    });
+}
+
 fn define_compile(c: &mut Criterion, group_name: &str, pattern: &'static str) {
     let group = format!("fwd-compile/{}", group_name);
     define(c, &group, "unminimized-noclasses", &[], move |b| {
@@ -237,4 +428,9 @@ criterion_group!(g2, compile_unicode_other_math);
 criterion_group!(g3, compile_unicode_other_uppercase);
 criterion_group!(g4, compile_muammar);
 criterion_group!(g5, compile_unicode_word);
-criterion_main!(g1, g2, g3, g4, g5);
+criterion_group!(g6, compile_capacity_hint);
+criterion_group!(g7, compile_literals);
+criterion_group!(g8, accelerate);
+criterion_group!(g9, search_loop);
+criterion_group!(g10, prefetch);
+criterion_main!(g1, g2, g3, g4, g5, g6, g7, g8, g9, g10);
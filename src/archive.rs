@@ -0,0 +1,520 @@
+use core::fmt;
+use core::result;
+use core::str;
+
+#[cfg(feature = "std")]
+use byteorder::{BigEndian, LittleEndian};
+use byteorder::{ByteOrder, NativeEndian};
+
+#[cfg(feature = "std")]
+use dense::DenseDFA;
+#[cfg(feature = "std")]
+use error::{Error, Result};
+#[cfg(feature = "std")]
+use sparse::SparseDFA;
+#[cfg(feature = "std")]
+use state_id::StateID;
+
+/// The label at the beginning of every serialized archive. Its length is a
+/// multiple of 8, which keeps the rest of the header aligned.
+const LABEL: &[u8] = b"rust-regex-automata-dfa-archive\x00";
+
+/// The size, in bytes, of the fixed size portion of the archive header.
+const HEADER_LEN: usize =
+    // label
+    32
+    // endianness check, must be equal to 0xFEFF for native endian
+    + 2
+    // version number
+    + 2
+    // padding, reserved for future use
+    + 4
+    // number of entries
+    + 8;
+
+/// The size, in bytes, of each entry in the archive index. Each entry is
+/// made up of four `u64` values: the offset and length of the entry's name
+/// followed by the offset and length of the entry's serialized DFA. All
+/// offsets are relative to the beginning of the archive.
+const INDEX_ENTRY_LEN: usize = 32;
+
+/// A collection of named DFAs that can be serialized into a single blob of
+/// bytes.
+///
+/// An archive is useful when an application needs many DFAs (for example,
+/// the forward and reverse DFAs of many regexes) and would prefer to ship
+/// them as a single unit. Each DFA in the archive is serialized using its own
+/// serialization routine (such as
+/// [`DenseDFA::to_bytes_little_endian`](enum.DenseDFA.html#method.to_bytes_little_endian)),
+/// and is placed at an 8 byte aligned offset within the archive. This means
+/// that a DFA can be deserialized in place, without copying, from an archive
+/// that is itself aligned to an 8 byte boundary.
+///
+/// Archives are read with
+/// [`DFAArchiveRef::from_bytes`](struct.DFAArchiveRef.html#method.from_bytes).
+///
+/// # Example
+///
+/// ```
+/// use regex_automata::{DFAArchive, DFAArchiveRef, DenseDFA, Regex};
+///
+/// # fn example() -> Result<(), regex_automata::Error> {
+/// let re = Regex::new("foo[0-9]+")?;
+///
+/// let mut archive = DFAArchive::new();
+/// archive.add_dense("foo-fwd", &re.forward().to_u16()?);
+/// archive.add_dense("foo-rev", &re.reverse().to_u16()?);
+/// let bytes = archive.to_bytes_native_endian()?;
+///
+/// let archive = DFAArchiveRef::from_bytes(&bytes)?;
+/// let fwd: DenseDFA<&[u16], u16> = unsafe {
+///     DenseDFA::from_bytes(archive.get("foo-fwd").unwrap())
+/// };
+/// let rev: DenseDFA<&[u16], u16> = unsafe {
+///     DenseDFA::from_bytes(archive.get("foo-rev").unwrap())
+/// };
+/// let re = Regex::from_dfas(fwd, rev);
+/// assert_eq!(Some((3, 9)), re.find(b"zzzfoo123"));
+/// # Ok(()) }; example().unwrap()
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+pub struct DFAArchive {
+    entries: Vec<(String, Box<dyn Entry>)>,
+}
+
+#[cfg(feature = "std")]
+impl DFAArchive {
+    /// Create a new empty archive.
+    pub fn new() -> DFAArchive {
+        DFAArchive::default()
+    }
+
+    /// Return the number of DFAs in this archive.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if and only if this archive contains no DFAs.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Add a copy of the given dense DFA to this archive under the given
+    /// name.
+    ///
+    /// Names should be unique. If more than one DFA is added with the same
+    /// name, then lookups by that name will return the DFA that was added
+    /// first.
+    pub fn add_dense<T: AsRef<[S]>, S: StateID + 'static>(
+        &mut self,
+        name: &str,
+        dfa: &DenseDFA<T, S>,
+    ) {
+        self.entries.push((name.to_string(), Box::new(dfa.to_owned())));
+    }
+
+    /// Add a copy of the given sparse DFA to this archive under the given
+    /// name.
+    ///
+    /// Names should be unique. If more than one DFA is added with the same
+    /// name, then lookups by that name will return the DFA that was added
+    /// first.
+    pub fn add_sparse<T: AsRef<[u8]>, S: StateID + 'static>(
+        &mut self,
+        name: &str,
+        dfa: &SparseDFA<T, S>,
+    ) {
+        self.entries.push((name.to_string(), Box::new(dfa.to_owned())));
+    }
+
+    /// Serialize this archive, and every DFA in it, to raw bytes in little
+    /// endian format.
+    ///
+    /// If any DFA in this archive could not be serialized, then this returns
+    /// an error.
+    pub fn to_bytes_little_endian(&self) -> Result<Vec<u8>> {
+        let mut dfas = vec![];
+        for (_, entry) in &self.entries {
+            dfas.push(entry.to_bytes_little_endian()?);
+        }
+        Ok(self.to_bytes::<LittleEndian>(&dfas))
+    }
+
+    /// Serialize this archive, and every DFA in it, to raw bytes in big
+    /// endian format.
+    ///
+    /// If any DFA in this archive could not be serialized, then this returns
+    /// an error.
+    pub fn to_bytes_big_endian(&self) -> Result<Vec<u8>> {
+        let mut dfas = vec![];
+        for (_, entry) in &self.entries {
+            dfas.push(entry.to_bytes_big_endian()?);
+        }
+        Ok(self.to_bytes::<BigEndian>(&dfas))
+    }
+
+    /// Serialize this archive, and every DFA in it, to raw bytes in native
+    /// endian format. Generally, it is better to pick an explicit endianness
+    /// using either `to_bytes_little_endian` or `to_bytes_big_endian`.
+    ///
+    /// If any DFA in this archive could not be serialized, then this returns
+    /// an error.
+    pub fn to_bytes_native_endian(&self) -> Result<Vec<u8>> {
+        let mut dfas = vec![];
+        for (_, entry) in &self.entries {
+            dfas.push(entry.to_bytes_native_endian()?);
+        }
+        Ok(self.to_bytes::<NativeEndian>(&dfas))
+    }
+
+    /// Lay out the archive given the serialized form of each of its DFAs,
+    /// in the same order as `self.entries`.
+    fn to_bytes<A: ByteOrder>(&self, dfas: &[Vec<u8>]) -> Vec<u8> {
+        assert_eq!(32, LABEL.len());
+        assert_eq!(self.entries.len(), dfas.len());
+
+        let index_len = INDEX_ENTRY_LEN * self.entries.len();
+        let names_len: usize =
+            self.entries.iter().map(|(name, _)| name.len()).sum();
+        let mut size = pad8(HEADER_LEN + index_len + names_len);
+        for dfa in dfas {
+            size = pad8(size + dfa.len());
+        }
+
+        let mut buf = vec![0; size];
+        buf[..LABEL.len()].copy_from_slice(LABEL);
+        let mut i = LABEL.len();
+        // endianness check
+        A::write_u16(&mut buf[i..], 0xFEFF);
+        i += 2;
+        // version number
+        A::write_u16(&mut buf[i..], 1);
+        i += 2;
+        // padding
+        i += 4;
+        // number of entries
+        A::write_u64(&mut buf[i..], self.entries.len() as u64);
+        i += 8;
+        assert_eq!(HEADER_LEN, i);
+
+        let mut name_offset = HEADER_LEN + index_len;
+        let mut dfa_offset = pad8(name_offset + names_len);
+        for ((name, _), dfa) in self.entries.iter().zip(dfas) {
+            A::write_u64(&mut buf[i..], name_offset as u64);
+            A::write_u64(&mut buf[i + 8..], name.len() as u64);
+            A::write_u64(&mut buf[i + 16..], dfa_offset as u64);
+            A::write_u64(&mut buf[i + 24..], dfa.len() as u64);
+            i += INDEX_ENTRY_LEN;
+
+            let name_end = name_offset + name.len();
+            buf[name_offset..name_end].copy_from_slice(name.as_bytes());
+            name_offset = name_end;
+
+            let dfa_end = dfa_offset + dfa.len();
+            buf[dfa_offset..dfa_end].copy_from_slice(dfa);
+            dfa_offset = pad8(dfa_end);
+        }
+        assert_eq!(size, dfa_offset, "expected to fill entire buffer");
+        buf
+    }
+}
+
+/// A read-only view of a serialized [`DFAArchive`](struct.DFAArchive.html).
+///
+/// Reading an archive never allocates and never copies any of the DFAs in
+/// it. Instead, looking up a DFA returns a sub-slice of the archive's bytes,
+/// which can then be given to the deserialization routine of the
+/// corresponding DFA type, such as
+/// [`DenseDFA::from_bytes`](enum.DenseDFA.html#method.from_bytes) or
+/// [`SparseDFA::from_bytes`](enum.SparseDFA.html#method.from_bytes).
+///
+/// The caller is responsible for knowing the type of each DFA (dense or
+/// sparse, along with its state identifier representation) in the archive.
+#[derive(Clone, Copy, Debug)]
+pub struct DFAArchiveRef<'a> {
+    buf: &'a [u8],
+    len: usize,
+}
+
+impl<'a> DFAArchiveRef<'a> {
+    /// Read an archive from the given bytes.
+    ///
+    /// This checks the archive's header and every entry in its index, so it
+    /// takes time proportional to the number of DFAs in the archive. It
+    /// never allocates. Each DFA in the archive is aligned to an 8 byte
+    /// boundary relative to the start of the given buffer, so if the buffer
+    /// itself is 8 byte aligned, then every DFA in it is too.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the bytes do not begin with a valid archive
+    /// header, if the endianness of the serialized archive differs from the
+    /// endianness of the current machine, or if an entry in the index refers
+    /// to bytes outside of the buffer. The DFAs themselves are not checked,
+    /// since they are deserialized separately.
+    pub fn from_bytes(
+        buf: &'a [u8],
+    ) -> result::Result<DFAArchiveRef<'a>, ArchiveError> {
+        if buf.len() < HEADER_LEN {
+            return Err(ArchiveError("archive header is truncated"));
+        }
+        if &buf[..LABEL.len()] != LABEL {
+            return Err(ArchiveError("could not find archive label"));
+        }
+
+        let mut i = LABEL.len();
+        let endian_check = NativeEndian::read_u16(&buf[i..]);
+        i += 2;
+        if endian_check != 0xFEFF {
+            return Err(ArchiveError(
+                "endianness mismatch, are you trying to load a DFAArchive \
+                 serialized with a different endianness?",
+            ));
+        }
+        let version = NativeEndian::read_u16(&buf[i..]);
+        i += 2 + 4;
+        if version != 1 {
+            return Err(ArchiveError("unsupported archive version"));
+        }
+        let len = NativeEndian::read_u64(&buf[i..]);
+        let index_end = (len as usize)
+            .checked_mul(INDEX_ENTRY_LEN)
+            .and_then(|n| n.checked_add(HEADER_LEN));
+        match index_end {
+            Some(end) if len as usize as u64 == len && end <= buf.len() => {}
+            _ => return Err(ArchiveError("archive index is truncated")),
+        }
+        let archive = DFAArchiveRef { buf, len: len as usize };
+        for i in 0..archive.len {
+            archive.checked_entry(i)?;
+        }
+        Ok(archive)
+    }
+
+    /// Return the number of DFAs in this archive.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if and only if this archive contains no DFAs.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Return the serialized bytes of the first DFA with the given name, if
+    /// one exists.
+    pub fn get(&self, name: &str) -> Option<&'a [u8]> {
+        self.iter().find(|&(n, _)| n == name).map(|(_, dfa)| dfa)
+    }
+
+    /// Return an iterator over the name and serialized bytes of every DFA in
+    /// this archive, in the order in which they were added.
+    pub fn iter(&self) -> DFAArchiveIter<'a> {
+        DFAArchiveIter { archive: *self, i: 0 }
+    }
+
+    /// Return the name and serialized bytes of the DFA at the given position
+    /// in the index.
+    fn entry(&self, i: usize) -> (&'a str, &'a [u8]) {
+        self.checked_entry(i).expect("archive index was checked when read")
+    }
+
+    /// Return the name and serialized bytes of the DFA at the given position
+    /// in the index, or an error if the entry is corrupt. The position must
+    /// be less than the number of DFAs in this archive.
+    fn checked_entry(
+        &self,
+        i: usize,
+    ) -> result::Result<(&'a str, &'a [u8]), ArchiveError> {
+        let at = HEADER_LEN + i * INDEX_ENTRY_LEN;
+        let read = |o: usize| NativeEndian::read_u64(&self.buf[at + o..]);
+        let slice = |offset: u64, len: u64| match offset.checked_add(len) {
+            Some(end) if end <= self.buf.len() as u64 => {
+                Ok(&self.buf[offset as usize..end as usize])
+            }
+            _ => Err(ArchiveError("archive entry is out of bounds")),
+        };
+
+        let name = slice(read(0), read(8))?;
+        let name = str::from_utf8(name)
+            .map_err(|_| ArchiveError("archive name is not UTF-8"))?;
+        Ok((name, slice(read(16), read(24))?))
+    }
+}
+
+/// An error that occurs when reading a malformed archive with
+/// [`DFAArchiveRef::from_bytes`](struct.DFAArchiveRef.html#method.from_bytes).
+///
+/// Its `Display` implementation describes what is wrong with the archive.
+/// Unlike [`Error`](struct.Error.html), this is available without the `std`
+/// feature. When the `std` feature is enabled, it converts into an `Error`
+/// of kind
+/// [`ErrorKind::Deserialize`](enum.ErrorKind.html#variant.Deserialize).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ArchiveError(&'static str);
+
+impl fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for ArchiveError {}
+
+#[cfg(feature = "std")]
+impl From<ArchiveError> for Error {
+    fn from(err: ArchiveError) -> Error {
+        Error::deserialize(&err.to_string())
+    }
+}
+
+/// An iterator over the DFAs in an archive.
+///
+/// This yields the name of each DFA along with its serialized bytes.
+///
+/// `'a` corresponds to the lifetime of the underlying archive bytes.
+#[derive(Clone, Debug)]
+pub struct DFAArchiveIter<'a> {
+    archive: DFAArchiveRef<'a>,
+    i: usize,
+}
+
+impl<'a> Iterator for DFAArchiveIter<'a> {
+    type Item = (&'a str, &'a [u8]);
+
+    fn next(&mut self) -> Option<(&'a str, &'a [u8])> {
+        if self.i >= self.archive.len() {
+            return None;
+        }
+        let entry = self.archive.entry(self.i);
+        self.i += 1;
+        Some(entry)
+    }
+}
+
+/// A DFA that can be stored in an archive.
+///
+/// This exists so that an archive can hold DFAs of different types, each
+/// with its own state identifier representation.
+#[cfg(feature = "std")]
+trait Entry: fmt::Debug {
+    fn to_bytes_little_endian(&self) -> Result<Vec<u8>>;
+    fn to_bytes_big_endian(&self) -> Result<Vec<u8>>;
+    fn to_bytes_native_endian(&self) -> Result<Vec<u8>>;
+}
+
+#[cfg(feature = "std")]
+impl<S: StateID> Entry for DenseDFA<Vec<S>, S> {
+    fn to_bytes_little_endian(&self) -> Result<Vec<u8>> {
+        DenseDFA::to_bytes_little_endian(self)
+    }
+
+    fn to_bytes_big_endian(&self) -> Result<Vec<u8>> {
+        DenseDFA::to_bytes_big_endian(self)
+    }
+
+    fn to_bytes_native_endian(&self) -> Result<Vec<u8>> {
+        DenseDFA::to_bytes_native_endian(self)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: StateID> Entry for SparseDFA<Vec<u8>, S> {
+    fn to_bytes_little_endian(&self) -> Result<Vec<u8>> {
+        SparseDFA::to_bytes_little_endian(self)
+    }
+
+    fn to_bytes_big_endian(&self) -> Result<Vec<u8>> {
+        SparseDFA::to_bytes_big_endian(self)
+    }
+
+    fn to_bytes_native_endian(&self) -> Result<Vec<u8>> {
+        SparseDFA::to_bytes_native_endian(self)
+    }
+}
+
+/// Round the given size up to the nearest multiple of 8.
+#[cfg(feature = "std")]
+fn pad8(size: usize) -> usize {
+    (size + 7) & !7
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use dfa::DFA;
+    use error::ErrorKind;
+
+    #[test]
+    fn roundtrip_mixed_entries() {
+        let dense = DenseDFA::new("a+").unwrap().to_u8().unwrap();
+        let sparse = SparseDFA::new("b+").unwrap().to_u16().unwrap();
+
+        let mut archive = DFAArchive::new();
+        archive.add_dense("dense", &dense);
+        archive.add_sparse("sparse", &sparse);
+        archive.add_dense("dense", &DenseDFA::<Vec<u8>, u8>::empty());
+        assert_eq!(3, archive.len());
+
+        let bytes = archive.to_bytes_native_endian().unwrap();
+        assert_eq!(0, bytes.len() % 8);
+        let archive = DFAArchiveRef::from_bytes(&bytes).unwrap();
+        assert_eq!(3, archive.len());
+
+        let names: Vec<&str> = archive.iter().map(|(name, _)| name).collect();
+        assert_eq!(vec!["dense", "sparse", "dense"], names);
+        for (_, dfa) in archive.iter() {
+            let offset = dfa.as_ptr() as usize - bytes.as_ptr() as usize;
+            assert_eq!(0, offset % 8);
+        }
+
+        let dense: DenseDFA<&[u8], u8> =
+            unsafe { DenseDFA::from_bytes(archive.get("dense").unwrap()) };
+        assert_eq!(Some(3), dense.find(b"aaab"));
+        let sparse: SparseDFA<&[u8], u16> =
+            unsafe { SparseDFA::from_bytes(archive.get("sparse").unwrap()) };
+        assert_eq!(Some(3), sparse.find(b"bbba"));
+        assert!(archive.get("nope").is_none());
+    }
+
+    #[test]
+    fn corrupt_archives_are_rejected() {
+        let mut archive = DFAArchive::new();
+        archive.add_dense("a", &DenseDFA::new("a+").unwrap());
+        let bytes = archive.to_bytes_native_endian().unwrap();
+        let corrupt = |at: usize, value: u64| {
+            let mut bytes = bytes.clone();
+            NativeEndian::write_u64(&mut bytes[at..], value);
+            DFAArchiveRef::from_bytes(&bytes).unwrap_err().to_string()
+        };
+
+        let index = HEADER_LEN;
+        assert!(corrupt(index - 8, u64::max_value()).contains("truncated"));
+        assert!(corrupt(index - 8, bytes.len() as u64).contains("truncated"));
+        for &field in &[0, 8, 16, 24] {
+            let err = corrupt(index + field, u64::max_value());
+            assert!(err.contains("out of bounds"), "{}", err);
+        }
+        let err = corrupt(index, bytes.len() as u64);
+        assert!(err.contains("out of bounds"), "{}", err);
+
+        assert!(DFAArchiveRef::from_bytes(&bytes[..HEADER_LEN - 1]).is_err());
+        let mut unlabeled = bytes.clone();
+        unlabeled[0] = b'x';
+        let err = DFAArchiveRef::from_bytes(&unlabeled).unwrap_err();
+        match *Error::from(err).kind() {
+            ErrorKind::Deserialize(ref msg) => assert!(msg.contains("label")),
+            ref kind => panic!("unexpected error kind: {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn empty_archive() {
+        let bytes = DFAArchive::new().to_bytes_native_endian().unwrap();
+        let archive = DFAArchiveRef::from_bytes(&bytes).unwrap();
+        assert!(archive.is_empty());
+        assert_eq!(None, archive.iter().next());
+    }
+}
@@ -1,17 +1,28 @@
 use core::fmt;
+use core::hash::{Hash, Hasher};
 
 /// A representation of byte oriented equivalence classes.
 ///
 /// This is used in a DFA to reduce the size of the transition table. This can
 /// have a particularly large impact not only on the total size of a dense DFA,
 /// but also on compile times.
+///
+/// Every byte maps to exactly one class, and each class is a contiguous range
+/// of bytes. A dense DFA built with
+/// [`byte_classes`](struct.Builder.html#method.byte_classes) enabled
+/// indexes its transition table by class instead of by byte. DFAs built from
+/// patterns with similar alphabets often end up with identical class maps,
+/// which can be serialized once with
+/// [`to_bytes`](struct.ByteClasses.html#method.to_bytes) and shared between
+/// them. See
+/// [`DenseDFA::from_bytes_with_classes`](../enum.DenseDFA.html#method.from_bytes_with_classes).
 #[derive(Clone, Copy)]
 pub struct ByteClasses([u8; 256]);
 
 impl ByteClasses {
     /// Creates a new set of equivalence classes where all bytes are mapped to
     /// the same class.
-    pub fn empty() -> ByteClasses {
+    pub(crate) fn empty() -> ByteClasses {
         ByteClasses([0; 256])
     }
 
@@ -28,7 +39,7 @@ impl ByteClasses {
     /// Copies the byte classes given. The given slice must have length 0 or
     /// length 256. Slices of length 0 are treated as singletons (every byte
     /// is its own class).
-    pub fn from_slice(slice: &[u8]) -> ByteClasses {
+    pub(crate) fn from_slice(slice: &[u8]) -> ByteClasses {
         assert!(slice.is_empty() || slice.len() == 256);
 
         if slice.is_empty() {
@@ -42,9 +53,47 @@ impl ByteClasses {
         }
     }
 
+    /// Serialize these equivalence classes to raw bytes.
+    ///
+    /// The byte at offset `b` of the returned array is the class of the byte
+    /// `b`. Since every class fits in a single byte, the serialized form has
+    /// no endianness or alignment requirements.
+    pub fn to_bytes(&self) -> [u8; 256] {
+        self.0
+    }
+
+    /// Deserialize equivalence classes from the first 256 bytes of the given
+    /// buffer, as written by [`to_bytes`](struct.ByteClasses.html#method.to_bytes).
+    ///
+    /// # Panics
+    ///
+    /// This panics if the buffer contains fewer than 256 bytes, or if the
+    /// bytes do not describe a valid class map. In a valid map, byte `0`
+    /// belongs to class `0`, and every other byte belongs either to the same
+    /// class as the byte before it or to the class after that one.
+    pub fn from_bytes(buf: &[u8]) -> ByteClasses {
+        assert!(
+            buf.len() >= 256,
+            "expected at least 256 bytes for byte classes, but got {}",
+            buf.len(),
+        );
+        assert_eq!(0, buf[0], "byte 0 must belong to class 0");
+        for b in 1..256 {
+            let (prev, cur) = (buf[b - 1], buf[b]);
+            assert!(
+                cur == prev || cur as usize == prev as usize + 1,
+                "byte {} has class {} but the byte before it has class {}",
+                b,
+                cur,
+                prev,
+            );
+        }
+        ByteClasses::from_slice(&buf[..256])
+    }
+
     /// Set the equivalence class for the given byte.
     #[inline]
-    pub fn set(&mut self, byte: u8, class: u8) {
+    pub(crate) fn set(&mut self, byte: u8, class: u8) {
         self.0[byte as usize] = class;
     }
 
@@ -57,10 +106,43 @@ impl ByteClasses {
     /// Get the equivalence class for the given byte while forcefully
     /// eliding bounds checks.
     #[inline]
-    pub unsafe fn get_unchecked(&self, byte: u8) -> u8 {
+    pub(crate) unsafe fn get_unchecked(&self, byte: u8) -> u8 {
         *self.0.get_unchecked(byte as usize)
     }
 
+    /// Returns true if and only if the given bytes belong to the same
+    /// equivalence class.
+    ///
+    /// A DFA never distinguishes between two bytes in the same class, which
+    /// makes this useful for building classification tables outside of a DFA
+    /// that are compatible with its alphabet, e.g., for bucketing input
+    /// before it is searched. When byte classes are disabled, every byte is
+    /// in its own class, so this is only true when `a == b`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::dense;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().anchored(true).build("[a-z]+[0-9]")?;
+    /// let classes = dfa.byte_classes();
+    /// assert!(classes.same_class(b'a', b'q'));
+    /// assert!(classes.same_class(b'0', b'9'));
+    /// assert!(!classes.same_class(b'z', b'0'));
+    ///
+    /// let dfa = dense::Builder::new()
+    ///     .anchored(true)
+    ///     .byte_classes(false)
+    ///     .build("[a-z]+[0-9]")?;
+    /// assert!(!dfa.byte_classes().same_class(b'a', b'q'));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[inline]
+    pub fn same_class(&self, a: u8, b: u8) -> bool {
+        self.get(a) == self.get(b)
+    }
+
     /// Return the total number of elements in the alphabet represented by
     /// these equivalence classes. Equivalently, this returns the total number
     /// of equivalence classes.
@@ -87,10 +169,91 @@ impl ByteClasses {
     /// byte from each equivalence class then permits a full exploration of
     /// the NFA instead of using every possible byte value.
     #[cfg(feature = "std")]
-    pub fn representatives(&self) -> ByteClassRepresentatives {
+    pub(crate) fn representatives(&self) -> ByteClassRepresentatives {
         ByteClassRepresentatives { classes: self, byte: 0, last_class: None }
     }
 
+    /// Returns a refinement of these equivalence classes in which every class
+    /// consists either entirely of the given bytes or entirely of bytes not
+    /// given. All other class boundaries are preserved.
+    #[cfg(feature = "std")]
+    pub(crate) fn split(&self, bytes: &[u8]) -> ByteClasses {
+        let mut member = [false; 256];
+        for &b in bytes {
+            member[b as usize] = true;
+        }
+        let mut set = ByteClassSet::new();
+        for b in 0..255 {
+            if self.get(b) != self.get(b + 1)
+                || member[b as usize] != member[b as usize + 1]
+            {
+                set.0[b as usize] = true;
+            }
+        }
+        set.byte_classes()
+    }
+
+    /// Returns the bytes in every equivalence class, indexed by class.
+    ///
+    /// Entry `c` lists, in ascending order, every byte mapped to class `c`.
+    /// When every byte is its own class, each entry holds exactly one byte.
+    /// Since classes are contiguous, each entry is a range of bytes, which is
+    /// convenient for labeling the transitions of a DFA in terms of the bytes
+    /// they fire on.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::dense;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().anchored(true).build("[a-c]x")?;
+    /// let members = dfa.byte_classes().class_members();
+    /// assert_eq!(dfa.byte_classes().alphabet_len(), members.len());
+    ///
+    /// let class = dfa.byte_classes().get(b'b') as usize;
+    /// assert_eq!(members[class], b"abc".to_vec());
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn class_members(&self) -> Vec<Vec<u8>> {
+        let mut members = vec![vec![]; self.alphabet_len()];
+        for b in 0..256 {
+            members[self.get(b as u8) as usize].push(b as u8);
+        }
+        members
+    }
+
+    /// Returns the coarsest equivalence classes that refine both these
+    /// classes and the ones given.
+    ///
+    /// Two bytes are in the same class of the result if and only if they are
+    /// in the same class in both inputs. Relabeling two DFAs to the result
+    /// with
+    /// [`DenseDFA::relabel_byte_classes`](../enum.DenseDFA.html#method.relabel_byte_classes)
+    /// gives them a common alphabet.
+    #[cfg(feature = "std")]
+    pub fn refine(&self, other: &ByteClasses) -> ByteClasses {
+        let mut set = ByteClassSet::new();
+        for b in 0..255 {
+            if self.get(b) != self.get(b + 1)
+                || other.get(b) != other.get(b + 1)
+            {
+                set.0[b as usize] = true;
+            }
+        }
+        set.byte_classes()
+    }
+
+    /// Returns true if and only if every class of these equivalence classes
+    /// is contained in a single class of the ones given.
+    #[cfg(feature = "std")]
+    pub(crate) fn is_refinement_of(&self, other: &ByteClasses) -> bool {
+        (0..255).all(|b| {
+            self.get(b) != self.get(b + 1) || other.get(b) == other.get(b + 1)
+        })
+    }
+
     /// Returns all of the bytes in the given equivalence class.
     ///
     /// The second element in the tuple indicates the number of elements in
@@ -122,6 +285,20 @@ impl fmt::Debug for ByteClasses {
     }
 }
 
+impl PartialEq for ByteClasses {
+    fn eq(&self, other: &ByteClasses) -> bool {
+        self.0[..] == other.0[..]
+    }
+}
+
+impl Eq for ByteClasses {}
+
+impl Hash for ByteClasses {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0[..].hash(state);
+    }
+}
+
 /// An iterator over representative bytes from each equivalence class.
 #[cfg(feature = "std")]
 #[derive(Debug)]
@@ -268,4 +445,43 @@ mod tests {
         }
         assert_eq!(set.byte_classes().alphabet_len(), 256);
     }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn byte_classes_round_trip() {
+        use super::{ByteClassSet, ByteClasses};
+
+        let mut set = ByteClassSet::new();
+        set.set_range(b'a', b'z');
+        set.set_range(b'0', b'9');
+        let classes = set.byte_classes();
+
+        let bytes = classes.to_bytes();
+        assert_eq!(classes, ByteClasses::from_bytes(&bytes));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn class_members() {
+        use super::{ByteClassSet, ByteClasses};
+
+        let mut set = ByteClassSet::new();
+        set.set_range(b'a', b'c');
+        let members = set.byte_classes().class_members();
+        assert_eq!(3, members.len());
+        assert_eq!((0..b'a').collect::<Vec<u8>>(), members[0]);
+        assert_eq!(b"abc".to_vec(), members[1]);
+        assert_eq!((b'd'..=255).collect::<Vec<u8>>(), members[2]);
+
+        let members = ByteClasses::singletons().class_members();
+        assert!(members.iter().enumerate().all(|(i, m)| *m == [i as u8]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn byte_classes_from_bytes_rejects_gaps() {
+        let mut bytes = [0; 256];
+        bytes[255] = 2;
+        super::ByteClasses::from_bytes(&bytes);
+    }
 }
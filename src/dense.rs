@@ -1,32 +1,52 @@
 #[cfg(feature = "std")]
+use core::cmp;
 use core::fmt;
+use core::hash::{Hash, Hasher};
 #[cfg(feature = "std")]
 use core::iter;
 use core::mem;
+#[cfg(feature = "std")]
+use core::ops;
+use core::result;
 use core::slice;
+#[cfg(feature = "std")]
+use core::str;
+#[cfg(feature = "std")]
+use std::collections::{BTreeMap, HashMap, VecDeque};
+#[cfg(feature = "std")]
+use std::sync::Arc;
 
 #[cfg(feature = "std")]
 use byteorder::{BigEndian, LittleEndian};
 use byteorder::{ByteOrder, NativeEndian};
 #[cfg(feature = "std")]
+use regex_syntax::hir::literal::Literals;
+#[cfg(feature = "std")]
+use regex_syntax::hir::{self, Hir, HirKind};
+#[cfg(feature = "std")]
 use regex_syntax::ParserBuilder;
 
-use classes::ByteClasses;
+pub use classes::ByteClasses;
 #[cfg(feature = "std")]
-use determinize::Determinizer;
-use dfa::DFA;
+use determinize::{self, Determinizer};
+use dfa::{prefault_bytes, run_to_match_or_dead_skipping, DFA};
 #[cfg(feature = "std")]
 use error::{Error, Result};
 #[cfg(feature = "std")]
+use header::{DFAHeader, DFAKind};
+#[cfg(feature = "std")]
 use minimize::Minimizer;
 #[cfg(feature = "std")]
+use multi::{MultiDFA, PatternID};
+#[cfg(feature = "std")]
 use nfa::{self, NFA};
 #[cfg(feature = "std")]
 use sparse::SparseDFA;
 use state_id::{dead_id, StateID};
 #[cfg(feature = "std")]
 use state_id::{
-    next_state_id, premultiply_overflow_error, write_state_id_bytes,
+    next_state_id, premultiply_overflow_error, serialized_state_size,
+    write_state_id_bytes,
 };
 
 /// The size of the alphabet in a standard DFA.
@@ -44,6 +64,92 @@ const ALPHABET_LEN: usize = 256;
 /// Masks used in serialization of DFAs.
 pub(crate) const MASK_PREMULTIPLIED: u16 = 0b0000_0000_0000_0001;
 pub(crate) const MASK_ANCHORED: u16 = 0b0000_0000_0000_0010;
+pub(crate) const MASK_CLASSES_OMITTED: u16 = 0b0000_0000_0000_0100;
+pub(crate) const MASK_RLE: u16 = 0b0000_0000_0000_1000;
+pub(crate) const MASK_ACCEL: u16 = 0b0000_0000_0010_0000;
+pub(crate) const MASK_VARINT: u16 = 0b0000_0000_0100_0000;
+pub(crate) const MASK_FULL_MATCH: u16 = 0b0000_0000_1000_0000;
+pub(crate) const MASK_LABELS: u16 = 0b0000_0000_0001_0000;
+pub(crate) const MASK_COLUMN_MAJOR: u16 = 0b0000_0001_0000_0000;
+pub(crate) const MASK_SKIP_BYTES: u16 = 0b0000_0010_0000_0000;
+pub(crate) const MASK_MATCH_SET: u16 = 0b0000_0100_0000_0000;
+
+/// The options that loaders of version 1 of the serialization format can
+/// safely ignore or understand. Those loaders don't check for unknown
+/// options, so only options that change neither the layout of the DFA nor
+/// its match semantics may be written with version 1.
+const MASK_VERSION_1: u16 =
+    MASK_PREMULTIPLIED | MASK_ANCHORED | MASK_ACCEL | MASK_LABELS;
+
+/// Every option known to the newest version of the serialization format.
+const MASK_KNOWN: u16 = MASK_VERSION_1
+    | MASK_CLASSES_OMITTED
+    | MASK_RLE
+    | MASK_VARINT
+    | MASK_FULL_MATCH
+    | MASK_COLUMN_MAJOR
+    | MASK_SKIP_BYTES
+    | MASK_MATCH_SET;
+
+/// The newest version of the serialization format shared by dense and sparse
+/// DFAs.
+///
+/// DFAs are written with the oldest version that can represent them (see
+/// `version_for_options`), so that a DFA only using the original options
+/// can still be read by loaders that predate the newer ones. Version 1
+/// loaders ignore options they don't know, which would silently misread a
+/// DFA whose layout (omitted byte classes, run length or varint encoded
+/// transitions, column-major order) or match semantics (full matches, skip
+/// bytes, unshuffled match states) depend on a newer option.
+/// Such DFAs are written as version 2, which those loaders reject. Starting
+/// with version 2, loaders also reject any option they don't know, so new
+/// options don't require bumping the version again.
+pub(crate) const VERSION: u16 = 2;
+
+/// Returns the version of the serialization format to write for a DFA with
+/// the given options.
+#[cfg(feature = "std")]
+pub(crate) fn version_for_options(options: u16) -> u16 {
+    if options & !MASK_VERSION_1 == 0 {
+        1
+    } else {
+        VERSION
+    }
+}
+
+/// Returns a description of why a DFA with the given format version and
+/// options can't be loaded, or `None` if it can be.
+pub(crate) fn check_version(
+    version: u16,
+    options: u16,
+) -> Option<&'static str> {
+    match version {
+        1 if options & !MASK_VERSION_1 != 0 => {
+            Some("version 1 DFA sets options introduced in version 2")
+        }
+        1 => None,
+        2 if options & !MASK_KNOWN != 0 => Some("DFA sets unknown options"),
+        2 => None,
+        _ => Some("unsupported version"),
+    }
+}
+
+/// Set the given options in the header of a serialized DFA, and update its
+/// version to match.
+#[cfg(feature = "std")]
+fn add_options<A: ByteOrder>(buf: &mut [u8], options: u16) {
+    let options = A::read_u16(&buf[30..]) | options;
+    A::write_u16(&mut buf[26..], version_for_options(options));
+    A::write_u16(&mut buf[30..], options);
+}
+
+/// The label at the beginning of every serialized dense DFA.
+#[cfg(feature = "std")]
+pub(crate) const LABEL: &[u8] = b"rust-regex-automata-dfa\x00";
+
+/// The parameters of the 64-bit FNV-1a hash used for fingerprints.
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
 
 /// A dense table-based deterministic finite automaton (DFA).
 ///
@@ -110,7 +216,7 @@ pub(crate) const MASK_ANCHORED: u16 = 0b0000_0000_0000_0010;
 /// This DFA is defined as a non-exhaustive enumeration of different types of
 /// dense DFAs. All of these dense DFAs use the same internal representation
 /// for the transition table, but they vary in how the transition table is
-/// read. A DFA's specific variant depends on the configuration options set via
+/// laid out and read. A DFA's specific variant depends on the configuration options set via
 /// [`dense::Builder`](dense/struct.Builder.html). The default variant is
 /// `PremultipliedByteClass`.
 ///
@@ -162,6 +268,14 @@ pub enum DenseDFA<T: AsRef<[S]>, S: StateID> {
     /// The default configuration of a DFA, which uses byte classes and
     /// premultiplies its state identifiers.
     PremultipliedByteClass(PremultipliedByteClass<T, S>),
+    /// A DFA whose transition table is stored in column-major order, so that
+    /// the transitions of every state on the same equivalence class are
+    /// contiguous. Its state identifiers are never premultiplied.
+    ///
+    /// See
+    /// [`dense::Builder::layout`](dense/struct.Builder.html#method.layout)
+    /// for more details.
+    ColumnMajor(ColumnMajor<T, S>),
     /// Hints that destructuring should not be exhaustive.
     ///
     /// This enum may grow additional variants, so this makes sure clients
@@ -181,6 +295,7 @@ impl<T: AsRef<[S]>, S: StateID> DenseDFA<T, S> {
             DenseDFA::ByteClass(ref r) => &r.0,
             DenseDFA::Premultiplied(ref r) => &r.0,
             DenseDFA::PremultipliedByteClass(ref r) => &r.0,
+            DenseDFA::ColumnMajor(ref r) => &r.0,
             DenseDFA::__Nonexhaustive => unreachable!(),
         }
     }
@@ -235,6 +350,49 @@ impl<S: StateID> DenseDFA<Vec<S>, S> {
     pub fn empty() -> DenseDFA<Vec<S>, S> {
         Repr::empty().into_dense_dfa()
     }
+
+    /// Move this DFA's transition table into reference counted storage.
+    ///
+    /// Cloning the DFA returned is a constant time operation that shares the
+    /// transition table instead of copying it, which makes it cheap to hand
+    /// out copies of one DFA (or of a [`Regex`](struct.Regex.html) built from
+    /// such DFAs) to many owners or threads. Searching is unaffected, since
+    /// the transition table is still a single contiguous slice.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use regex_automata::{DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa: DenseDFA<Arc<[usize]>, usize> =
+    ///     DenseDFA::new("foo[0-9]+")?.into_shared();
+    /// let copy = dfa.clone();
+    /// assert_eq!(Some(8), copy.find(b"foo12345"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn into_shared(self) -> DenseDFA<Arc<[S]>, S> {
+        match self {
+            DenseDFA::Standard(r) => {
+                DenseDFA::Standard(Standard(r.0.into_shared()))
+            }
+            DenseDFA::ByteClass(r) => {
+                DenseDFA::ByteClass(ByteClass(r.0.into_shared()))
+            }
+            DenseDFA::Premultiplied(r) => {
+                DenseDFA::Premultiplied(Premultiplied(r.0.into_shared()))
+            }
+            DenseDFA::PremultipliedByteClass(r) => {
+                let inner = PremultipliedByteClass(r.0.into_shared());
+                DenseDFA::PremultipliedByteClass(inner)
+            }
+            DenseDFA::ColumnMajor(r) => {
+                DenseDFA::ColumnMajor(ColumnMajor(r.0.into_shared()))
+            }
+            DenseDFA::__Nonexhaustive => unreachable!(),
+        }
+    }
 }
 
 impl<T: AsRef<[S]>, S: StateID> DenseDFA<T, S> {
@@ -256,6 +414,9 @@ impl<T: AsRef<[S]>, S: StateID> DenseDFA<T, S> {
                 let inner = PremultipliedByteClass(r.0.as_ref());
                 DenseDFA::PremultipliedByteClass(inner)
             }
+            DenseDFA::ColumnMajor(ref r) => {
+                DenseDFA::ColumnMajor(ColumnMajor(r.0.as_ref()))
+            }
             DenseDFA::__Nonexhaustive => unreachable!(),
         }
     }
@@ -282,10 +443,56 @@ impl<T: AsRef<[S]>, S: StateID> DenseDFA<T, S> {
                 let inner = PremultipliedByteClass(r.0.to_owned());
                 DenseDFA::PremultipliedByteClass(inner)
             }
+            DenseDFA::ColumnMajor(ref r) => {
+                DenseDFA::ColumnMajor(ColumnMajor(r.0.to_owned()))
+            }
             DenseDFA::__Nonexhaustive => unreachable!(),
         }
     }
 
+    /// Check that this DFA's internal structure is consistent, such that
+    /// searching with it can never access memory out of bounds.
+    ///
+    /// Specifically, this checks that:
+    ///
+    /// * Every byte maps to an equivalence class within the DFA's alphabet.
+    /// * The transition table has exactly one row for every state.
+    /// * The start state, the last match state and the target of every
+    ///   transition all refer to states that exist. When the DFA is
+    ///   premultiplied, these must also point to the beginning of a row.
+    ///
+    /// A DFA produced by this crate's builders always passes validation.
+    /// This is primarily useful after calling
+    /// [`DenseDFA::from_bytes`](enum.DenseDFA.html#method.from_bytes) on bytes
+    /// from an untrusted source, since `from_bytes` only checks the header. If
+    /// validation succeeds, then the DFA is safe to search, although there is
+    /// no guarantee that it matches anything meaningful.
+    ///
+    /// This runs in time proportional to the size of the transition table.
+    /// It doesn't require the `std` feature, so that DFAs deserialized in
+    /// `no_std` environments can be checked too. For that reason, it returns
+    /// a [`ValidationError`](dense/struct.ValidationError.html) instead of
+    /// an [`Error`](struct.Error.html), although the former converts into
+    /// the latter.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let bytes = DenseDFA::new("foo[0-9]+")?.to_u16()?.to_bytes_native_endian()?;
+    /// let dfa: DenseDFA<&[u16], u16> = unsafe {
+    ///     DenseDFA::from_bytes(&bytes)
+    /// };
+    /// dfa.validate()?;
+    /// assert_eq!(Some(8), dfa.find(b"foo12345"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn validate(&self) -> result::Result<(), ValidationError> {
+        self.repr().validate()
+    }
+
     /// Returns the memory usage, in bytes, of this DFA.
     ///
     /// The memory usage is computed based on the number of bytes used to
@@ -297,2008 +504,9321 @@ impl<T: AsRef<[S]>, S: StateID> DenseDFA<T, S> {
     pub fn memory_usage(&self) -> usize {
         self.repr().memory_usage()
     }
-}
 
-/// Routines for converting a dense DFA to other representations, such as
-/// sparse DFAs, smaller state identifiers or raw bytes suitable for persistent
-/// storage.
-#[cfg(feature = "std")]
-impl<T: AsRef<[S]>, S: StateID> DenseDFA<T, S> {
-    /// Convert this dense DFA to a sparse DFA.
-    ///
-    /// This is a convenience routine for `to_sparse_sized` that fixes the
-    /// state identifier representation of the sparse DFA to the same
-    /// representation used for this dense DFA.
+    /// Returns true if and only if this DFA is minimal, i.e., no two of its
+    /// states are equivalent.
     ///
-    /// If the chosen state identifier representation is too small to represent
-    /// all states in the sparse DFA, then this returns an error. In most
-    /// cases, if a dense DFA is constructable with `S` then a sparse DFA will
-    /// be as well. However, it is not guaranteed.
+    /// This runs the same partition refinement used by
+    /// [`dense::Builder::minimize`](dense/struct.Builder.html#method.minimize),
+    /// but only reports whether any two states ended up in the same
+    /// equivalence class instead of merging them. The DFA itself is not
+    /// modified. Since this is as expensive as minimization, it is mostly
+    /// useful in tests.
     ///
     /// # Example
     ///
     /// ```
-    /// use regex_automata::{DFA, DenseDFA};
+    /// use regex_automata::dense;
     ///
     /// # fn example() -> Result<(), regex_automata::Error> {
-    /// let dense = DenseDFA::new("foo[0-9]+")?;
-    /// let sparse = dense.to_sparse()?;
-    /// assert_eq!(Some(8), sparse.find(b"foo12345"));
+    /// let dfa = dense::Builder::new().minimize(true).build("[a-z]+|[0-9]+")?;
+    /// assert!(dfa.is_minimal());
     /// # Ok(()) }; example().unwrap()
     /// ```
-    pub fn to_sparse(&self) -> Result<SparseDFA<Vec<u8>, S>> {
-        self.to_sparse_sized()
+    #[cfg(feature = "std")]
+    pub fn is_minimal(&self) -> bool {
+        Minimizer::new(self.repr()).is_minimal()
     }
 
-    /// Convert this dense DFA to a sparse DFA.
+    /// Returns the groups of states in this DFA that are equivalent, i.e.,
+    /// that accept precisely the same inputs.
     ///
-    /// Using this routine requires supplying a type hint to choose the state
-    /// identifier representation for the resulting sparse DFA.
+    /// This is the partition of states computed by the refinement in
+    /// [`dense::Builder::minimize`](dense/struct.Builder.html#method.minimize),
+    /// which merges each group into a single state. Every state appears in
+    /// exactly one group. The states within a group are in ascending order,
+    /// and groups are ordered by their first state. If this DFA is
+    /// premultiplied, then so are the state identifiers returned. The DFA
+    /// itself is not modified.
     ///
-    /// If the chosen state identifier representation is too small to represent
-    /// all states in the sparse DFA, then this returns an error.
+    /// A DFA is minimal precisely when every group has one state, in which
+    /// case the number of groups is equal to the number of states.
     ///
     /// # Example
     ///
     /// ```
-    /// use regex_automata::{DFA, DenseDFA};
+    /// use regex_automata::dense;
     ///
     /// # fn example() -> Result<(), regex_automata::Error> {
-    /// let dense = DenseDFA::new("foo[0-9]+")?;
-    /// let sparse = dense.to_sparse_sized::<u8>()?;
-    /// assert_eq!(Some(8), sparse.find(b"foo12345"));
+    /// // Determinization alone produces one state for `[ab]` after `x`
+    /// // and another after `y`, which are equivalent.
+    /// let dfa = dense::Builder::new()
+    ///     .anchored(true)
+    ///     .build("x[ab]|y[ab]")?;
+    /// let partition = dfa.state_partition();
+    /// assert!(partition.iter().any(|group| group.len() == 2));
+    ///
+    /// let minimal = dense::Builder::new()
+    ///     .anchored(true)
+    ///     .minimize(true)
+    ///     .build("x[ab]|y[ab]")?;
+    /// assert!(minimal.state_partition().iter().all(|group| group.len() == 1));
     /// # Ok(()) }; example().unwrap()
     /// ```
-    pub fn to_sparse_sized<A: StateID>(
-        &self,
-    ) -> Result<SparseDFA<Vec<u8>, A>> {
-        self.repr().to_sparse_sized()
+    #[cfg(feature = "std")]
+    pub fn state_partition(&self) -> Vec<Vec<S>> {
+        let repr = self.repr();
+        let stride = if repr.premultiplied { repr.alphabet_len() } else { 1 };
+        let mut partition = Minimizer::new(repr).partition();
+        for group in &mut partition {
+            for id in group.iter_mut() {
+                *id = S::from_usize(id.to_usize() * stride);
+            }
+        }
+        partition
     }
 
-    /// Create a new DFA whose match semantics are equivalent to this DFA,
-    /// but attempt to use `u8` for the representation of state identifiers.
-    /// If `u8` is insufficient to represent all state identifiers in this
-    /// DFA, then this returns an error.
+    /// Returns the layout of this DFA's transition table.
     ///
-    /// This is a convenience routine for `to_sized::<u8>()`.
-    pub fn to_u8(&self) -> Result<DenseDFA<Vec<u8>, u8>> {
-        self.to_sized()
+    /// See
+    /// [`dense::Builder::layout`](dense/struct.Builder.html#method.layout)
+    /// for more details.
+    pub fn layout(&self) -> Layout {
+        if self.repr().column_major {
+            Layout::ColumnMajor
+        } else {
+            Layout::RowMajor
+        }
     }
 
-    /// Create a new DFA whose match semantics are equivalent to this DFA,
-    /// but attempt to use `u16` for the representation of state identifiers.
-    /// If `u16` is insufficient to represent all state identifiers in this
-    /// DFA, then this returns an error.
+    /// Returns the literal that every match of this DFA must begin with, as
+    /// used by the search prefilter. This is empty when the prefilter is
+    /// disabled or when no such literal exists.
     ///
-    /// This is a convenience routine for `to_sized::<u16>()`.
-    pub fn to_u16(&self) -> Result<DenseDFA<Vec<u16>, u16>> {
-        self.to_sized()
-    }
-
-    /// Create a new DFA whose match semantics are equivalent to this DFA,
-    /// but attempt to use `u32` for the representation of state identifiers.
-    /// If `u32` is insufficient to represent all state identifiers in this
-    /// DFA, then this returns an error.
+    /// The literal isn't serialized, so this is always empty for a DFA
+    /// deserialized with `from_bytes`, which searches without a prefilter.
     ///
-    /// This is a convenience routine for `to_sized::<u32>()`.
-    #[cfg(any(target_pointer_width = "32", target_pointer_width = "64"))]
-    pub fn to_u32(&self) -> Result<DenseDFA<Vec<u32>, u32>> {
-        self.to_sized()
+    /// See
+    /// [`dense::Builder::prefilter`](dense/struct.Builder.html#method.prefilter)
+    /// for more details.
+    pub fn literal_prefix(&self) -> &[u8] {
+        let prefix = &self.repr().prefix;
+        if prefix.reverse {
+            &[]
+        } else {
+            prefix.as_bytes()
+        }
     }
 
-    /// Create a new DFA whose match semantics are equivalent to this DFA,
-    /// but attempt to use `u64` for the representation of state identifiers.
-    /// If `u64` is insufficient to represent all state identifiers in this
-    /// DFA, then this returns an error.
+    /// Returns the literal that every match of this reverse DFA must end
+    /// with, as used by the reverse search prefilter. The literal is in the
+    /// order in which it appears in the haystack. This is empty when the
+    /// prefilter is disabled or when no such literal exists.
     ///
-    /// This is a convenience routine for `to_sized::<u64>()`.
-    #[cfg(target_pointer_width = "64")]
-    pub fn to_u64(&self) -> Result<DenseDFA<Vec<u64>, u64>> {
-        self.to_sized()
-    }
-
-    /// Create a new DFA whose match semantics are equivalent to this DFA, but
-    /// attempt to use `A` for the representation of state identifiers. If `A`
-    /// is insufficient to represent all state identifiers in this DFA, then
-    /// this returns an error.
+    /// Like the literal prefix, this isn't serialized, so this is always
+    /// empty for a DFA deserialized with `from_bytes`.
     ///
-    /// An alternative way to construct such a DFA is to use
-    /// [`dense::Builder::build_with_size`](dense/struct.Builder.html#method.build_with_size).
-    /// In general, using the builder is preferred since it will use the given
-    /// state identifier representation throughout determinization (and
-    /// minimization, if done), and thereby using less memory throughout the
-    /// entire construction process. However, these routines are necessary
-    /// in cases where, say, a minimized DFA could fit in a smaller state
-    /// identifier representation, but the initial determinized DFA would not.
-    pub fn to_sized<A: StateID>(&self) -> Result<DenseDFA<Vec<A>, A>> {
-        self.repr().to_sized().map(|r| r.into_dense_dfa())
+    /// See
+    /// [`dense::Builder::reverse_suffix_prefilter`](dense/struct.Builder.html#method.reverse_suffix_prefilter)
+    /// for more details.
+    pub fn literal_suffix(&self) -> &[u8] {
+        let prefix = &self.repr().prefix;
+        if prefix.reverse {
+            prefix.as_bytes()
+        } else {
+            &[]
+        }
     }
 
-    /// Serialize a DFA to raw bytes, aligned to an 8 byte boundary, in little
-    /// endian format.
+    /// Returns true if and only if forward searches with this DFA skip over
+    /// bytes that keep it in its start state.
     ///
-    /// If the state identifier representation of this DFA has a size different
-    /// than 1, 2, 4 or 8 bytes, then this returns an error. All
-    /// implementations of `StateID` provided by this crate satisfy this
-    /// requirement.
-    pub fn to_bytes_little_endian(&self) -> Result<Vec<u8>> {
-        self.repr().to_bytes::<LittleEndian>()
+    /// See
+    /// [`dense::Builder::accelerate`](dense/struct.Builder.html#method.accelerate)
+    /// for more details.
+    pub fn is_accelerated(&self) -> bool {
+        self.repr().accel.enabled
     }
 
-    /// Serialize a DFA to raw bytes, aligned to an 8 byte boundary, in big
-    /// endian format.
+    /// Return a view of the state with the given identifier, for inspecting
+    /// the DFA without walking it byte by byte.
     ///
-    /// If the state identifier representation of this DFA has a size different
-    /// than 1, 2, 4 or 8 bytes, then this returns an error. All
-    /// implementations of `StateID` provided by this crate satisfy this
-    /// requirement.
-    pub fn to_bytes_big_endian(&self) -> Result<Vec<u8>> {
-        self.repr().to_bytes::<BigEndian>()
+    /// The identifier must be one that this DFA produced, such as its start
+    /// state or the result of a transition. In particular, it is
+    /// premultiplied if and only if this DFA is premultiplied. This panics if
+    /// the identifier doesn't refer to a state of this DFA.
+    ///
+    /// # Example
+    ///
+    /// This lists the transitions out of the start state of an anchored DFA,
+    /// omitting those that lead to the dead state.
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().anchored(true).build("[a-c]x|z")?;
+    /// let start = dfa.state(dfa.start_state());
+    /// assert!(!start.is_match());
+    ///
+    /// let live: Vec<(u8, u8)> = start
+    ///     .transitions()
+    ///     .filter(|&(_, _, next)| next != dfa.dead_state_id())
+    ///     .map(|(lo, hi, _)| (lo, hi))
+    ///     .collect();
+    /// assert_eq!(live, vec![(b'a', b'c'), (b'z', b'z')]);
+    ///
+    /// let after_z = dfa.state(start.next_state(b'z'));
+    /// assert!(after_z.is_match());
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn state(&self, id: S) -> StateView<'_, S> {
+        let r = self.repr();
+        let index = r.state_id_to_index(id);
+        assert!(index < r.state_count, "invalid state id {}", id.to_usize());
+        let state = r.state_at(index);
+        StateView {
+            id,
+            is_match: r.is_match_state(id),
+            classes: r.byte_classes(),
+            transitions: state.transitions,
+            stride: state.stride,
+        }
     }
 
-    /// Serialize a DFA to raw bytes, aligned to an 8 byte boundary, in native
-    /// endian format. Generally, it is better to pick an explicit endianness
-    /// using either `to_bytes_little_endian` or `to_bytes_big_endian`. This
-    /// routine is useful in tests where the DFA is serialized and deserialized
-    /// on the same platform.
+    /// Returns the byte equivalence classes used by this DFA.
     ///
-    /// If the state identifier representation of this DFA has a size different
-    /// than 1, 2, 4 or 8 bytes, then this returns an error. All
-    /// implementations of `StateID` provided by this crate satisfy this
-    /// requirement.
-    pub fn to_bytes_native_endian(&self) -> Result<Vec<u8>> {
-        self.repr().to_bytes::<NativeEndian>()
+    /// When byte classes are disabled, every byte is in its own class.
+    pub fn byte_classes(&self) -> &ByteClasses {
+        self.repr().byte_classes()
     }
-}
 
-impl<'a, S: StateID> DenseDFA<&'a [S], S> {
-    /// Deserialize a DFA with a specific state identifier representation.
+    /// Returns a 64-bit hash of this DFA's structure.
     ///
-    /// Deserializing a DFA using this routine will never allocate heap memory.
-    /// This is also guaranteed to be a constant time operation that does not
-    /// vary with the size of the DFA.
+    /// The hash covers whether the DFA is anchored and premultiplied, its
+    /// start state, its number of states, its match states, its byte classes
+    /// and its transition table. Every state identifier is widened to 64
+    /// bits before it's hashed, so the fingerprint doesn't depend on the
+    /// state identifier representation: converting a DFA with, e.g.,
+    /// [`to_u16`](enum.DenseDFA.html#method.to_u16) or serializing and
+    /// deserializing it leaves its fingerprint unchanged. The literal
+    /// prefilter and acceleration are not included, since they don't change
+    /// which strings are matched.
     ///
-    /// The bytes given should be generated by the serialization of a DFA with
-    /// either the
-    /// [`to_bytes_little_endian`](enum.DenseDFA.html#method.to_bytes_little_endian)
-    /// method or the
-    /// [`to_bytes_big_endian`](enum.DenseDFA.html#method.to_bytes_big_endian)
-    /// endian, depending on the endianness of the machine you are
-    /// deserializing this DFA from.
+    /// The hash function is 64-bit FNV-1a, so fingerprints are stable across
+    /// platforms and releases of this crate. This is not a cryptographic
+    /// hash: DFAs with the same fingerprint are very likely, but not
+    /// guaranteed, to be identical.
     ///
-    /// If the state identifier representation is `usize`, then deserialization
-    /// is dependent on the pointer size. For this reason, it is best to
-    /// serialize DFAs using a fixed size representation for your state
-    /// identifiers, such as `u8`, `u16`, `u32` or `u64`.
+    /// # Example
     ///
-    /// # Panics
+    /// ```
+    /// use regex_automata::DenseDFA;
     ///
-    /// The bytes given should be *trusted*. In particular, if the bytes
-    /// are not a valid serialization of a DFA, or if the given bytes are
-    /// not aligned to an 8 byte boundary, or if the endianness of the
-    /// serialized bytes is different than the endianness of the machine that
-    /// is deserializing the DFA, then this routine will panic. Moreover, it is
-    /// possible for this deserialization routine to succeed even if the given
-    /// bytes do not represent a valid serialized dense DFA.
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = DenseDFA::new("foo[0-9]+")?;
+    /// let small = dfa.to_u16()?;
+    /// assert_eq!(dfa.fingerprint(), small.fingerprint());
     ///
-    /// # Safety
+    /// let other = DenseDFA::new("bar[0-9]+")?;
+    /// assert_ne!(dfa.fingerprint(), other.fingerprint());
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn fingerprint(&self) -> u64 {
+        let r = self.repr();
+        let mut hash = FNV_OFFSET_BASIS;
+        let mut write = |n: u64| {
+            for &b in &n.to_le_bytes() {
+                hash = (hash ^ b as u64).wrapping_mul(FNV_PRIME);
+            }
+        };
+        write(r.anchored as u64);
+        write(r.full_match as u64);
+        write(r.premultiplied as u64);
+        write(r.column_major as u64);
+        #[cfg(feature = "std")]
+        {
+            if let Some(ref set) = r.match_set {
+                for &word in set.0.iter() {
+                    write(word);
+                }
+            }
+        }
+        write(r.start.to_usize() as u64);
+        write(r.state_count as u64);
+        write(r.max_match.to_usize() as u64);
+        for b in 0..256 {
+            write(r.byte_classes().get(b as u8) as u64);
+        }
+        for &id in r.trans() {
+            write(id.to_usize() as u64);
+        }
+        hash
+    }
+
+    /// Returns every string accepted by this DFA, provided that there are
+    /// finitely many and at most `max` of them.
     ///
-    /// This routine is unsafe because it permits callers to provide an
-    /// arbitrary transition table with possibly incorrect transitions. While
-    /// the various serialization routines will never return an incorrect
-    /// transition table, there is no guarantee that the bytes provided here
-    /// are correct. While deserialization does many checks (as documented
-    /// above in the panic conditions), this routine does not check that the
-    /// transition table is correct. Given an incorrect transition table, it is
-    /// possible for the search routines to access out-of-bounds memory because
-    /// of explicit bounds check elision.
+    /// If the DFA accepts infinitely many strings (because some cycle in it
+    /// is both reachable from the start state and leads to a match state),
+    /// or if it accepts more than `max` strings, then `None` is returned.
+    /// Otherwise, the strings are returned in lexicographic order, exactly as
+    /// [`DFA::iter_accepted`](trait.DFA.html#method.iter_accepted) would
+    /// yield them given a large enough length bound. An empty language
+    /// yields `Some(vec![])`.
     ///
-    /// # Example
+    /// This is useful for deciding whether a pattern can be replaced by a
+    /// set of literals. Only anchored DFAs can have a finite language, since
+    /// an unanchored DFA accepts any string ending with a match. Unlike
+    /// enumerating with `iter_accepted`, the work done here is bounded
+    /// before any strings are produced: the number of accepted strings is
+    /// counted first, in time proportional to the size of the DFA.
     ///
-    /// This example shows how to serialize a DFA to raw bytes, deserialize it
-    /// and then use it for searching. Note that we first convert the DFA to
-    /// using `u16` for its state identifier representation before serializing
-    /// it. While this isn't strictly necessary, it's good practice in order to
-    /// decrease the size of the DFA and to avoid platform specific pitfalls
-    /// such as differing pointer sizes.
+    /// # Example
     ///
     /// ```
-    /// use regex_automata::{DFA, DenseDFA};
+    /// use regex_automata::dense;
     ///
     /// # fn example() -> Result<(), regex_automata::Error> {
-    /// let initial = DenseDFA::new("foo[0-9]+")?;
-    /// let bytes = initial.to_u16()?.to_bytes_native_endian()?;
-    /// let dfa: DenseDFA<&[u16], u16> = unsafe {
-    ///     DenseDFA::from_bytes(&bytes)
-    /// };
+    /// let dfa = dense::Builder::new().anchored(true).build("ba[rz]|foo")?;
+    /// let strings = dfa.finite_language(10).unwrap();
+    /// assert_eq!(strings, vec![
+    ///     b"bar".to_vec(), b"baz".to_vec(), b"foo".to_vec(),
+    /// ]);
+    /// assert_eq!(None, dfa.finite_language(2));
     ///
-    /// assert_eq!(Some(8), dfa.find(b"foo12345"));
+    /// let dfa = dense::Builder::new().anchored(true).build("ba+r")?;
+    /// assert_eq!(None, dfa.finite_language(1000));
     /// # Ok(()) }; example().unwrap()
     /// ```
-    pub unsafe fn from_bytes(buf: &'a [u8]) -> DenseDFA<&'a [S], S> {
-        Repr::from_bytes(buf).into_dense_dfa()
-    }
-}
-
-#[cfg(feature = "std")]
-impl<S: StateID> DenseDFA<Vec<S>, S> {
-    /// Minimize this DFA in place.
-    ///
-    /// This is not part of the public API. It is only exposed to allow for
-    /// more granular external benchmarking.
-    #[doc(hidden)]
-    pub fn minimize(&mut self) {
-        self.repr_mut().minimize();
-    }
-
-    /// Return a mutable reference to the internal DFA representation.
-    fn repr_mut(&mut self) -> &mut Repr<Vec<S>, S> {
-        match *self {
-            DenseDFA::Standard(ref mut r) => &mut r.0,
-            DenseDFA::ByteClass(ref mut r) => &mut r.0,
-            DenseDFA::Premultiplied(ref mut r) => &mut r.0,
-            DenseDFA::PremultipliedByteClass(ref mut r) => &mut r.0,
-            DenseDFA::__Nonexhaustive => unreachable!(),
+    #[cfg(feature = "std")]
+    pub fn finite_language(&self, max: usize) -> Option<Vec<Vec<u8>>> {
+        let repr = self.repr();
+        let alpha = repr.alphabet_len();
+        let next = |i: usize, b: u8| {
+            let class = repr.byte_classes().get(b) as usize;
+            repr.state_id_to_index(repr.transition(i, class))
+        };
+        let is_match = |i: usize| repr.is_match_index(i);
+
+        // A state is live if a match state can be reached from it. Only live
+        // states can contribute accepted strings.
+        let mut rev = vec![vec![]; repr.state_count];
+        for i in 0..repr.state_count {
+            for class in 0..alpha {
+                let j = repr.state_id_to_index(repr.transition(i, class));
+                rev[j].push(i);
+            }
         }
-    }
-}
-
-impl<T: AsRef<[S]>, S: StateID> DFA for DenseDFA<T, S> {
-    type ID = S;
-
-    #[inline]
-    fn start_state(&self) -> S {
-        self.repr().start_state()
-    }
-
-    #[inline]
-    fn is_match_state(&self, id: S) -> bool {
-        self.repr().is_match_state(id)
-    }
-
-    #[inline]
-    fn is_dead_state(&self, id: S) -> bool {
-        self.repr().is_dead_state(id)
-    }
-
-    #[inline]
-    fn is_match_or_dead_state(&self, id: S) -> bool {
-        self.repr().is_match_or_dead_state(id)
-    }
-
-    #[inline]
-    fn is_anchored(&self) -> bool {
-        self.repr().is_anchored()
-    }
-
-    #[inline]
-    fn next_state(&self, current: S, input: u8) -> S {
-        match *self {
-            DenseDFA::Standard(ref r) => r.next_state(current, input),
-            DenseDFA::ByteClass(ref r) => r.next_state(current, input),
-            DenseDFA::Premultiplied(ref r) => r.next_state(current, input),
-            DenseDFA::PremultipliedByteClass(ref r) => {
-                r.next_state(current, input)
+        let mut live = vec![false; repr.state_count];
+        let mut stack: Vec<usize> =
+            (1..repr.state_count).filter(|&i| is_match(i)).collect();
+        for &i in &stack {
+            live[i] = true;
+        }
+        while let Some(j) = stack.pop() {
+            for &i in &rev[j] {
+                if !live[i] {
+                    live[i] = true;
+                    stack.push(i);
+                }
             }
-            DenseDFA::__Nonexhaustive => unreachable!(),
         }
-    }
+        let start = repr.state_id_to_index(repr.start);
+        if !live[start] {
+            return Some(vec![]);
+        }
 
-    #[inline]
-    unsafe fn next_state_unchecked(&self, current: S, input: u8) -> S {
-        match *self {
-            DenseDFA::Standard(ref r) => {
-                r.next_state_unchecked(current, input)
+        // Count the strings accepted from each live state in post order,
+        // failing if a live state is reachable from itself. Counts too big
+        // to represent saturate, and a saturated count is never enumerated.
+        const UNVISITED: usize = ::std::usize::MAX;
+        const IN_PROGRESS: usize = ::std::usize::MAX - 1;
+        const SATURATED: usize = ::std::usize::MAX - 2;
+        let mut count = vec![UNVISITED; repr.state_count];
+        let mut stack = vec![(start, 0usize)];
+        count[start] = IN_PROGRESS;
+        while let Some(&mut (i, ref mut b)) = stack.last_mut() {
+            if *b == 256 {
+                stack.pop();
+                let mut total = is_match(i) as usize;
+                for b in 0..256 {
+                    let j = next(i, b as u8);
+                    if live[j] {
+                        total = total.saturating_add(count[j]);
+                    }
+                }
+                // Keep counts clear of the two sentinel values.
+                count[i] = cmp::min(total, SATURATED);
+                continue;
             }
-            DenseDFA::ByteClass(ref r) => {
-                r.next_state_unchecked(current, input)
+            let j = next(i, *b as u8);
+            *b += 1;
+            if !live[j] {
+                continue;
             }
-            DenseDFA::Premultiplied(ref r) => {
-                r.next_state_unchecked(current, input)
+            match count[j] {
+                IN_PROGRESS => return None,
+                UNVISITED => {
+                    count[j] = IN_PROGRESS;
+                    stack.push((j, 0));
+                }
+                _ => {}
             }
-            DenseDFA::PremultipliedByteClass(ref r) => {
-                r.next_state_unchecked(current, input)
+        }
+        if count[start] > max || count[start] == SATURATED {
+            return None;
+        }
+
+        // The language is finite and small enough, so enumerate it,
+        // never leaving the live states. The count isn't used to size the
+        // result up front, since `max` may be far bigger than what can be
+        // allocated.
+        let mut strings = vec![];
+        let mut bytes = vec![];
+        let mut stack = vec![(start, 0usize)];
+        if is_match(start) {
+            strings.push(vec![]);
+        }
+        while let Some(&mut (i, ref mut b)) = stack.last_mut() {
+            if *b == 256 {
+                stack.pop();
+                bytes.pop();
+                continue;
+            }
+            let byte = *b as u8;
+            let j = next(i, byte);
+            *b += 1;
+            if !live[j] {
+                continue;
+            }
+            bytes.push(byte);
+            stack.push((j, 0));
+            if is_match(j) {
+                strings.push(bytes.clone());
             }
-            DenseDFA::__Nonexhaustive => unreachable!(),
         }
+        Some(strings)
     }
 
-    // We specialize the following methods because it lets us lift the
-    // case analysis between the different types of dense DFAs. Instead of
-    // doing the case analysis for every transition, we do it once before
-    // searching.
+    /// Returns an iterator over the transitions out of the given state for
+    /// every byte in the inclusive range `lo..=hi`, in ascending order.
+    ///
+    /// Each item is a pair of an input byte and the state reached on that
+    /// byte. Byte classes are applied, so every byte in the range is yielded
+    /// (including bytes leading to the dead state), regardless of whether this
+    /// DFA shares transitions between bytes. If `lo > hi`, then the iterator
+    /// is empty. The target for a single byte is given by
+    /// [`DFA::next_state`](../trait.DFA.html#tymethod.next_state).
+    ///
+    /// This panics if the given identifier does not refer to a state in this
+    /// DFA.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DFA, dense};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().anchored(true).build("[a-c]x")?;
+    /// let start = dfa.start_state();
+    /// let live: Vec<u8> = dfa
+    ///     .transitions_in_range(start, b'0', b'z')
+    ///     .filter(|&(_, next)| !dfa.is_dead_state(next))
+    ///     .map(|(b, _)| b)
+    ///     .collect();
+    /// assert_eq!(live, b"abc".to_vec());
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn transitions_in_range<'a>(
+        &'a self,
+        id: S,
+        lo: u8,
+        hi: u8,
+    ) -> TransitionsInRange<'a, T, S> {
+        // Look up the first transition eagerly so that invalid identifiers
+        // are reported here instead of partway through iteration.
+        let _ = self.next_state(id, lo);
+        TransitionsInRange { dfa: self, id, next: lo as u16, hi: hi as u16 }
+    }
 
-    #[inline]
-    fn is_match_at(&self, bytes: &[u8], start: usize) -> bool {
-        match *self {
-            DenseDFA::Standard(ref r) => r.is_match_at(bytes, start),
-            DenseDFA::ByteClass(ref r) => r.is_match_at(bytes, start),
-            DenseDFA::Premultiplied(ref r) => r.is_match_at(bytes, start),
-            DenseDFA::PremultipliedByteClass(ref r) => {
-                r.is_match_at(bytes, start)
-            }
-            DenseDFA::__Nonexhaustive => unreachable!(),
-        }
+    /// Returns a vector with one entry for every offset `i` in
+    /// `0..=bytes.len()`, where entry `i` is `true` if and only if this DFA
+    /// reaches a match state when started at `i`.
+    ///
+    /// For an anchored DFA, this reports whether a match *begins* at each
+    /// offset. For an unanchored DFA, entry `i` instead reports whether any
+    /// match exists in `bytes[i..]`. The final entry corresponds to the
+    /// empty suffix of `bytes`, and is only `true` when the DFA matches the
+    /// empty string.
+    ///
+    /// # Complexity
+    ///
+    /// Each offset is searched independently, so in the worst case this runs
+    /// in `O(n^2)` time for a haystack of length `n`. Each individual search
+    /// does stop as soon as it enters a match or dead state, which makes this
+    /// close to linear for patterns whose matches (and failures) are
+    /// detected quickly.
+    ///
+    /// To avoid allocating the entire vector, use
+    /// [`anchored_matches_iter`](enum.DenseDFA.html#method.anchored_matches_iter).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::dense;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().anchored(true).build("ab")?;
+    /// assert_eq!(
+    ///     dfa.anchored_matches(b"abxab"),
+    ///     vec![true, false, false, true, false, false],
+    /// );
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn anchored_matches(&self, bytes: &[u8]) -> Vec<bool> {
+        self.anchored_matches_iter(bytes).collect()
     }
 
-    #[inline]
-    fn shortest_match_at(&self, bytes: &[u8], start: usize) -> Option<usize> {
-        match *self {
-            DenseDFA::Standard(ref r) => r.shortest_match_at(bytes, start),
-            DenseDFA::ByteClass(ref r) => r.shortest_match_at(bytes, start),
-            DenseDFA::Premultiplied(ref r) => {
-                r.shortest_match_at(bytes, start)
+    /// Returns an iterator that yields the same values as
+    /// [`anchored_matches`](enum.DenseDFA.html#method.anchored_matches)
+    /// without allocating.
+    ///
+    /// Since results are computed lazily, a caller that stops consuming the
+    /// iterator early also avoids the cost of searching the remaining
+    /// offsets.
+    pub fn anchored_matches_iter<'a>(
+        &'a self,
+        bytes: &'a [u8],
+    ) -> AnchoredMatches<'a, T, S> {
+        AnchoredMatches { dfa: self, bytes, pos: 0 }
+    }
+
+    /// Returns true if and only if this DFA can never match any input.
+    ///
+    /// This is the case precisely when no match state is reachable from the
+    /// start state. Since the regex parser rejects constructs with an empty
+    /// language (such as the empty class `[a&&b]`), this is mostly useful for
+    /// checking DFAs that were deserialized or otherwise obtained from an
+    /// external source. The check walks every state reachable from the start
+    /// state at most once.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::DenseDFA;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let empty: DenseDFA<Vec<usize>, usize> = DenseDFA::empty();
+    /// assert!(empty.never_matches());
+    /// assert!(!DenseDFA::new("a|b")?.never_matches());
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn never_matches(&self) -> bool {
+        let r = self.repr();
+        let mut seen = vec![false; r.state_count()];
+        let mut stack = vec![r.start_state()];
+        seen[r.state_id_to_index(r.start_state())] = true;
+        while let Some(id) = stack.pop() {
+            if r.is_match_state(id) {
+                return false;
             }
-            DenseDFA::PremultipliedByteClass(ref r) => {
-                r.shortest_match_at(bytes, start)
+            for (_, next) in r.state(id).transitions() {
+                let index = r.state_id_to_index(next);
+                if !seen[index] {
+                    seen[index] = true;
+                    stack.push(next);
+                }
             }
-            DenseDFA::__Nonexhaustive => unreachable!(),
         }
+        true
     }
 
-    #[inline]
-    fn find_at(&self, bytes: &[u8], start: usize) -> Option<usize> {
-        match *self {
-            DenseDFA::Standard(ref r) => r.find_at(bytes, start),
-            DenseDFA::ByteClass(ref r) => r.find_at(bytes, start),
-            DenseDFA::Premultiplied(ref r) => r.find_at(bytes, start),
-            DenseDFA::PremultipliedByteClass(ref r) => r.find_at(bytes, start),
-            DenseDFA::__Nonexhaustive => unreachable!(),
+    /// Returns the identifiers of all states from which no match state is
+    /// reachable, in ascending order.
+    ///
+    /// Once a search enters one of these "trap" states, it can never report
+    /// another match. The dead state is always a trap state. In a minimized
+    /// DFA, it is also the only one, since all trap states are equivalent.
+    /// Any other trap state indicates a part of the DFA that can never
+    /// contribute to a match, which is useful when debugging how a DFA was
+    /// constructed.
+    ///
+    /// This is computed by searching backwards from every match state over
+    /// the reversed transition graph, and runs in time proportional to the
+    /// size of the transition table.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().anchored(true).build("ab|cd")?;
+    /// let traps = dfa.trap_states();
+    /// assert_eq!(traps, vec![0]);
+    /// // After reading `x`, a match is no longer possible.
+    /// let next = dfa.next_state(dfa.start_state(), b'x');
+    /// assert!(traps.contains(&next));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn trap_states(&self) -> Vec<S> {
+        let r = self.repr();
+        let mut predecessors = vec![vec![]; r.state_count()];
+        let mut can_match = vec![false; r.state_count()];
+        let mut stack = vec![];
+        for (id, state) in r.states() {
+            let index = r.state_id_to_index(id);
+            if r.is_match_state(id) {
+                can_match[index] = true;
+                stack.push(index);
+            }
+            for (_, next) in state.transitions() {
+                predecessors[r.state_id_to_index(next)].push(index);
+            }
         }
-    }
-
-    #[inline]
-    fn rfind_at(&self, bytes: &[u8], start: usize) -> Option<usize> {
-        match *self {
-            DenseDFA::Standard(ref r) => r.rfind_at(bytes, start),
-            DenseDFA::ByteClass(ref r) => r.rfind_at(bytes, start),
-            DenseDFA::Premultiplied(ref r) => r.rfind_at(bytes, start),
-            DenseDFA::PremultipliedByteClass(ref r) => {
-                r.rfind_at(bytes, start)
+        while let Some(index) = stack.pop() {
+            for &prev in &predecessors[index] {
+                if !can_match[prev] {
+                    can_match[prev] = true;
+                    stack.push(prev);
+                }
             }
-            DenseDFA::__Nonexhaustive => unreachable!(),
         }
+        r.states()
+            .map(|(id, _)| id)
+            .filter(|&id| !can_match[r.state_id_to_index(id)])
+            .collect()
     }
 }
 
-/// A standard dense DFA that does not use premultiplication or byte classes.
-///
-/// Generally, it isn't necessary to use this type directly, since a `DenseDFA`
-/// can be used for searching directly. One possible reason why one might want
-/// to use this type directly is if you are implementing your own search
-/// routines by walking a DFA's transitions directly. In that case, you'll want
-/// to use this type (or any of the other DFA variant types) directly, since
-/// they implement `next_state` more efficiently.
-#[derive(Clone, Debug)]
-pub struct Standard<T: AsRef<[S]>, S: StateID>(Repr<T, S>);
-
-impl<T: AsRef<[S]>, S: StateID> DFA for Standard<T, S> {
-    type ID = S;
-
-    #[inline]
-    fn start_state(&self) -> S {
-        self.0.start_state()
-    }
-
-    #[inline]
-    fn is_match_state(&self, id: S) -> bool {
-        self.0.is_match_state(id)
+/// Routines for converting a dense DFA to other representations, such as
+/// sparse DFAs, smaller state identifiers or raw bytes suitable for persistent
+/// storage.
+#[cfg(feature = "std")]
+impl<T: AsRef<[S]>, S: StateID> DenseDFA<T, S> {
+    /// Convert this dense DFA to a sparse DFA.
+    ///
+    /// This is a convenience routine for `to_sparse_sized` that fixes the
+    /// state identifier representation of the sparse DFA to the same
+    /// representation used for this dense DFA.
+    ///
+    /// If the chosen state identifier representation is too small to represent
+    /// all states in the sparse DFA, then this returns an error. In most
+    /// cases, if a dense DFA is constructable with `S` then a sparse DFA will
+    /// be as well. However, it is not guaranteed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dense = DenseDFA::new("foo[0-9]+")?;
+    /// let sparse = dense.to_sparse()?;
+    /// assert_eq!(Some(8), sparse.find(b"foo12345"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn to_sparse(&self) -> Result<SparseDFA<Vec<u8>, S>> {
+        self.to_sparse_sized()
     }
 
-    #[inline]
-    fn is_dead_state(&self, id: S) -> bool {
-        self.0.is_dead_state(id)
+    /// Convert this dense DFA to a sparse DFA.
+    ///
+    /// Using this routine requires supplying a type hint to choose the state
+    /// identifier representation for the resulting sparse DFA.
+    ///
+    /// If the chosen state identifier representation is too small to represent
+    /// all states in the sparse DFA, then this returns an error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dense = DenseDFA::new("foo[0-9]+")?;
+    /// let sparse = dense.to_sparse_sized::<u8>()?;
+    /// assert_eq!(Some(8), sparse.find(b"foo12345"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn to_sparse_sized<A: StateID>(
+        &self,
+    ) -> Result<SparseDFA<Vec<u8>, A>> {
+        self.repr().to_sparse_sized()
     }
 
-    #[inline]
-    fn is_match_or_dead_state(&self, id: S) -> bool {
-        self.0.is_match_or_dead_state(id)
+    /// Returns true if and only if every state identifier in this DFA can be
+    /// represented by `A`, which is precisely when
+    /// [`to_sized::<A>`](enum.DenseDFA.html#method.to_sized) succeeds.
+    ///
+    /// This accounts for premultiplication: the largest identifier in a
+    /// premultiplied DFA is the index of its last state multiplied by the
+    /// alphabet length, so a DFA with only a few hundred states may need
+    /// `u16` or wider. A DFA that doesn't fit when premultiplied may fit when
+    /// built with
+    /// [`premultiply`](struct.Builder.html#method.premultiply) disabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::dense;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let pattern = "abcdefghijklmnopq[0-9]";
+    /// let dfa = dense::Builder::new().anchored(true).build(pattern)?;
+    /// assert!(!dfa.fits_in::<u8>());
+    /// assert!(dfa.fits_in::<u16>());
+    /// assert!(dfa.to_u8().is_err());
+    ///
+    /// let dfa = dense::Builder::new()
+    ///     .anchored(true)
+    ///     .premultiply(false)
+    ///     .build(pattern)?;
+    /// assert!(dfa.fits_in::<u8>());
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn fits_in<A: StateID>(&self) -> bool {
+        self.repr().max_state_id() <= A::max_id()
     }
 
-    #[inline]
-    fn is_anchored(&self) -> bool {
-        self.0.is_anchored()
+    /// Returns the number of bits needed to represent the largest state
+    /// identifier in this DFA, accounting for premultiplication.
+    ///
+    /// The smallest state identifier representation that this DFA
+    /// [`fits_in`](enum.DenseDFA.html#method.fits_in) is the smallest of
+    /// `u8`, `u16`, `u32` and `u64` with at least this many bits.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::dense;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new()
+    ///     .anchored(true)
+    ///     .premultiply(false)
+    ///     .build("abc")?;
+    /// // The dead state, one state per byte and the match state.
+    /// assert_eq!(3, dfa.state_id_bits());
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn state_id_bits(&self) -> u32 {
+        let max = self.repr().max_state_id();
+        8 * mem::size_of::<usize>() as u32 - max.leading_zeros()
     }
 
-    #[inline]
-    fn next_state(&self, current: S, input: u8) -> S {
-        let o = current.to_usize() * ALPHABET_LEN + input as usize;
-        self.0.trans()[o]
+    /// Create a new DFA whose match semantics are equivalent to this DFA,
+    /// but attempt to use `u8` for the representation of state identifiers.
+    /// If `u8` is insufficient to represent all state identifiers in this
+    /// DFA, then this returns an error.
+    ///
+    /// This is a convenience routine for `to_sized::<u8>()`.
+    pub fn to_u8(&self) -> Result<DenseDFA<Vec<u8>, u8>> {
+        self.to_sized()
     }
 
-    #[inline]
-    unsafe fn next_state_unchecked(&self, current: S, input: u8) -> S {
-        let o = current.to_usize() * ALPHABET_LEN + input as usize;
-        *self.0.trans().get_unchecked(o)
+    /// Create a new DFA whose match semantics are equivalent to this DFA,
+    /// but attempt to use `u16` for the representation of state identifiers.
+    /// If `u16` is insufficient to represent all state identifiers in this
+    /// DFA, then this returns an error.
+    ///
+    /// This is a convenience routine for `to_sized::<u16>()`.
+    pub fn to_u16(&self) -> Result<DenseDFA<Vec<u16>, u16>> {
+        self.to_sized()
     }
-}
 
-/// A dense DFA that shrinks its alphabet.
-///
-/// Alphabet shrinking is achieved by using a set of equivalence classes
-/// instead of using all possible byte values. Any two bytes belong to the same
-/// equivalence class if and only if they can be used interchangeably anywhere
-/// in the DFA while never discriminating between a match and a non-match.
-///
-/// This type of DFA can result in significant space reduction with a very
-/// small match time performance penalty.
-///
-/// Generally, it isn't necessary to use this type directly, since a `DenseDFA`
-/// can be used for searching directly. One possible reason why one might want
-/// to use this type directly is if you are implementing your own search
-/// routines by walking a DFA's transitions directly. In that case, you'll want
-/// to use this type (or any of the other DFA variant types) directly, since
-/// they implement `next_state` more efficiently.
-#[derive(Clone, Debug)]
-pub struct ByteClass<T: AsRef<[S]>, S: StateID>(Repr<T, S>);
+    /// Create a new DFA whose match semantics are equivalent to this DFA,
+    /// but attempt to use `u32` for the representation of state identifiers.
+    /// If `u32` is insufficient to represent all state identifiers in this
+    /// DFA, then this returns an error.
+    ///
+    /// This is a convenience routine for `to_sized::<u32>()`.
+    #[cfg(any(target_pointer_width = "32", target_pointer_width = "64"))]
+    pub fn to_u32(&self) -> Result<DenseDFA<Vec<u32>, u32>> {
+        self.to_sized()
+    }
 
-impl<T: AsRef<[S]>, S: StateID> DFA for ByteClass<T, S> {
-    type ID = S;
+    /// Create a new DFA whose match semantics are equivalent to this DFA,
+    /// but attempt to use `u64` for the representation of state identifiers.
+    /// If `u64` is insufficient to represent all state identifiers in this
+    /// DFA, then this returns an error.
+    ///
+    /// This is a convenience routine for `to_sized::<u64>()`.
+    #[cfg(target_pointer_width = "64")]
+    pub fn to_u64(&self) -> Result<DenseDFA<Vec<u64>, u64>> {
+        self.to_sized()
+    }
 
-    #[inline]
-    fn start_state(&self) -> S {
-        self.0.start_state()
+    /// Create a new DFA whose match semantics are equivalent to this DFA, but
+    /// attempt to use `A` for the representation of state identifiers. If `A`
+    /// is insufficient to represent all state identifiers in this DFA, then
+    /// this returns an error.
+    ///
+    /// An alternative way to construct such a DFA is to use
+    /// [`dense::Builder::build_with_size`](dense/struct.Builder.html#method.build_with_size).
+    /// In general, using the builder is preferred since it will use the given
+    /// state identifier representation throughout determinization (and
+    /// minimization, if done), and thereby using less memory throughout the
+    /// entire construction process. However, these routines are necessary
+    /// in cases where, say, a minimized DFA could fit in a smaller state
+    /// identifier representation, but the initial determinized DFA would not.
+    pub fn to_sized<A: StateID>(&self) -> Result<DenseDFA<Vec<A>, A>> {
+        self.repr().to_sized().map(|r| r.into_dense_dfa())
     }
 
-    #[inline]
-    fn is_match_state(&self, id: S) -> bool {
-        self.0.is_match_state(id)
+    /// Create a new DFA that behaves like this one, except that every
+    /// transition on a byte not in `allowed` leads to the dead state.
+    ///
+    /// The new DFA matches precisely the strings matched by this DFA that
+    /// consist only of allowed bytes. For example, projecting onto the ASCII
+    /// bytes restricts a Unicode-aware DFA to ASCII input. For an unanchored
+    /// DFA, this also applies to the bytes preceding a match, so a search
+    /// stops as soon as it sees a byte that isn't allowed.
+    ///
+    /// If byte classes are enabled, then a class containing both allowed and
+    /// disallowed bytes is split in two, so the new DFA may have a larger
+    /// alphabet than this one. Otherwise, its states and their identifiers
+    /// are unchanged.
+    ///
+    /// If this DFA is premultiplied and a larger alphabet means the state
+    /// identifiers no longer fit in `S`, then this returns an error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().anchored(true).build(r"[a-zé]+")?;
+    /// assert_eq!(Some(5), dfa.find("café".as_bytes()));
+    ///
+    /// let ascii: Vec<u8> = (0..128).collect();
+    /// let projected = dfa.project_to_bytes(&ascii)?;
+    /// assert_eq!(Some(3), projected.find("café".as_bytes()));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn project_to_bytes(
+        &self,
+        allowed: &[u8],
+    ) -> Result<DenseDFA<Vec<S>, S>> {
+        self.repr().project_to_bytes(allowed).map(|r| r.into_dense_dfa())
     }
 
-    #[inline]
-    fn is_dead_state(&self, id: S) -> bool {
-        self.0.is_dead_state(id)
+    /// Create a new DFA that behaves like this one, except that its
+    /// transition table is indexed by the given byte classes.
+    ///
+    /// The classes given must be a refinement of this DFA's byte classes,
+    /// i.e., bytes in the same new class must be in the same class of this
+    /// DFA. Otherwise, an error is returned. In particular,
+    /// [`ByteClasses::singletons`](struct.ByteClasses.html#method.singletons)
+    /// is always accepted.
+    ///
+    /// This is useful for giving two DFAs the same alphabet (for example, to
+    /// combine them into one) without expanding both of them to 256
+    /// transitions per state. The finest alphabet both DFAs are compatible
+    /// with is given by
+    /// [`ByteClasses::refine`](struct.ByteClasses.html#method.refine).
+    ///
+    /// States and their identifiers are unchanged, unless this DFA is
+    /// premultiplied, in which case the new DFA is premultiplied too. If
+    /// premultiplied state identifiers no longer fit in `S` with the larger
+    /// alphabet, then this returns an error. The new DFA doesn't keep this
+    /// DFA's [`prefilter`](struct.Builder.html#method.prefilter).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa1 = dense::Builder::new().build(r"[a-m]+")?;
+    /// let dfa2 = dense::Builder::new().build(r"[h-z]+")?;
+    /// let classes = dfa1.byte_classes().refine(dfa2.byte_classes());
+    ///
+    /// let relabeled1 = dfa1.relabel_byte_classes(&classes)?;
+    /// let relabeled2 = dfa2.relabel_byte_classes(&classes)?;
+    /// assert_eq!(
+    ///     relabeled1.byte_classes().alphabet_len(),
+    ///     relabeled2.byte_classes().alphabet_len(),
+    /// );
+    /// assert_eq!(Some(3), relabeled1.find(b"abc"));
+    /// assert_eq!(None, relabeled2.find(b"abc"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn relabel_byte_classes(
+        &self,
+        classes: &ByteClasses,
+    ) -> Result<DenseDFA<Vec<S>, S>> {
+        self.repr().relabel_byte_classes(classes).map(|r| r.into_dense_dfa())
     }
 
-    #[inline]
-    fn is_match_or_dead_state(&self, id: S) -> bool {
-        self.0.is_match_or_dead_state(id)
+    /// Create a new DFA that matches the reverse of every string this DFA
+    /// matches.
+    ///
+    /// This DFA's language is the set of strings that lead from its start
+    /// state to a match state, which are precisely the inputs accepted by
+    /// [`DFA::is_full_match`](trait.DFA.html#method.is_full_match). The new
+    /// DFA is anchored, and accepts a string exactly when this DFA accepts
+    /// the string reversed. It is computed from this DFA's transitions alone,
+    /// using the classical construction: reverse every transition, make the
+    /// match states the starting states and the start state the only match
+    /// state, and then determinize the result with the powerset
+    /// construction. The new DFA uses the same byte classes as this one, and
+    /// is premultiplied if this one is.
+    ///
+    /// This differs from building a DFA with
+    /// [`Builder::reverse`](struct.Builder.html#method.reverse) enabled, which
+    /// compiles the pattern itself in reverse, so that a reverse search can
+    /// find where a match starts. Here, the pattern isn't needed. Note
+    /// though that for an unanchored DFA, or one using leftmost-first
+    /// semantics, this DFA's language is not simply the pattern's: the
+    /// former includes a prefix of arbitrary bytes before each match, and
+    /// the latter stops at the first match that can't be extended. The
+    /// result is the same as the pattern's reversal when this DFA is built
+    /// with [`Builder::full_match`](struct.Builder.html#method.full_match),
+    /// in which case the new DFA also requires full matches.
+    ///
+    /// The new DFA is not minimized, and in the worst case, it has
+    /// exponentially more states than this one. If its state identifiers
+    /// don't fit in `S`, then this returns an error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().full_match(true).build("ab+c|d")?;
+    /// let rev = dfa.reverse_language()?;
+    /// assert!(rev.is_match(b"cbba"));
+    /// assert!(!rev.is_match(b"cbbaa"));
+    /// assert!(rev.is_full_match(b"cbba"));
+    /// assert!(rev.is_full_match(b"d"));
+    /// assert!(!rev.is_full_match(b"abbc"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn reverse_language(&self) -> Result<DenseDFA<Vec<S>, S>> {
+        self.repr().reverse_language().map(|r| r.into_dense_dfa())
     }
 
-    #[inline]
-    fn is_anchored(&self) -> bool {
-        self.0.is_anchored()
+    /// Create an unanchored DFA that matches the same pattern as this
+    /// anchored DFA.
+    ///
+    /// The DFA returned behaves exactly as if the pattern had been compiled
+    /// without `anchored(true)`: it reports the leftmost first match in the
+    /// haystack, with matches that start earlier taking priority. It is built
+    /// by determinizing this DFA with a new match attempt started at every
+    /// position, so no access to the original pattern is needed. Like
+    /// determinization from an NFA, this can take exponential time and space
+    /// in the worst case, and the result is not minimized. If this DFA is
+    /// premultiplied, then so is the result.
+    ///
+    /// A new match attempt is started at every byte offset, including offsets
+    /// inside or after invalid UTF-8. The result therefore searches like an
+    /// unanchored DFA built with
+    /// [`allow_invalid_utf8`](dense/struct.Builder.html#method.allow_invalid_utf8)
+    /// enabled, even if this DFA was not. (A regular unanchored build only
+    /// skips over valid UTF-8, and so stops at the first invalid byte.) The
+    /// result has no prefilter, but it is accelerated (as if by
+    /// [`Builder::accelerate`](dense/struct.Builder.html#method.accelerate))
+    /// whenever its start state allows it.
+    ///
+    /// If this DFA is already unanchored, then a copy of it is returned.
+    ///
+    /// There is no conversion in the other direction. An unanchored DFA
+    /// merges match attempts starting at different positions into the same
+    /// states, so the anchored DFA can't be recovered from it and must be
+    /// rebuilt from the pattern instead.
+    ///
+    /// If the new DFA has more states than `S` can represent, then this
+    /// returns an error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let anchored = dense::Builder::new().anchored(true).build("foo[0-9]+")?;
+    /// assert_eq!(None, anchored.find(b"xfoo123"));
+    ///
+    /// let unanchored = anchored.to_unanchored()?;
+    /// assert_eq!(Some(7), unanchored.find(b"xfoo123"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn to_unanchored(&self) -> Result<DenseDFA<Vec<S>, S>> {
+        if !self.is_anchored() {
+            return Ok(self.to_owned());
+        }
+        self.repr().to_unanchored().map(|r| r.into_dense_dfa())
     }
 
-    #[inline]
-    fn next_state(&self, current: S, input: u8) -> S {
-        let input = self.0.byte_classes().get(input);
-        let o = current.to_usize() * self.0.alphabet_len() + input as usize;
-        self.0.trans()[o]
+    /// Serialize a DFA to raw bytes, aligned to an 8 byte boundary, in little
+    /// endian format.
+    ///
+    /// Each state identifier is written using `size_of::<S>()` bytes rounded
+    /// up to the next power of two, so any representation of at most 8 bytes
+    /// can be serialized. (Bigger representations return an error.) All
+    /// implementations of `StateID` provided by this crate already have a
+    /// size of 1, 2, 4 or 8 bytes and are written as is. A custom
+    /// representation of, say, 3 bytes is widened to 4 bytes. Since
+    /// `from_bytes` reinterprets the bytes in place, it can't load such a DFA
+    /// into the original representation. Either load it as a `u32` DFA
+    /// instead, or serialize with
+    /// [`to_bytes_rle_little_endian`](enum.DenseDFA.html#method.to_bytes_rle_little_endian)
+    /// and load with
+    /// [`from_bytes_rle`](enum.DenseDFA.html#method.from_bytes_rle), which
+    /// narrows identifiers back into the requested representation.
+    pub fn to_bytes_little_endian(&self) -> Result<Vec<u8>> {
+        self.repr().to_bytes::<LittleEndian>(true)
     }
 
-    #[inline]
-    unsafe fn next_state_unchecked(&self, current: S, input: u8) -> S {
-        let input = self.0.byte_classes().get_unchecked(input);
-        let o = current.to_usize() * self.0.alphabet_len() + input as usize;
-        *self.0.trans().get_unchecked(o)
+    /// Serialize a DFA to raw bytes, aligned to an 8 byte boundary, in big
+    /// endian format.
+    ///
+    /// See
+    /// [`to_bytes_little_endian`](enum.DenseDFA.html#method.to_bytes_little_endian)
+    /// for how the state identifier representation is serialized.
+    pub fn to_bytes_big_endian(&self) -> Result<Vec<u8>> {
+        self.repr().to_bytes::<BigEndian>(true)
     }
-}
 
-/// A dense DFA that premultiplies all of its state identifiers in its
-/// transition table.
-///
-/// This saves an instruction per byte at match time which improves search
-/// performance.
-///
-/// The only downside of premultiplication is that it may prevent one from
-/// using a smaller state identifier representation than you otherwise could.
-///
-/// Generally, it isn't necessary to use this type directly, since a `DenseDFA`
-/// can be used for searching directly. One possible reason why one might want
-/// to use this type directly is if you are implementing your own search
-/// routines by walking a DFA's transitions directly. In that case, you'll want
-/// to use this type (or any of the other DFA variant types) directly, since
-/// they implement `next_state` more efficiently.
-#[derive(Clone, Debug)]
-pub struct Premultiplied<T: AsRef<[S]>, S: StateID>(Repr<T, S>);
+    /// Serialize a DFA to raw bytes, aligned to an 8 byte boundary, in native
+    /// endian format. Generally, it is better to pick an explicit endianness
+    /// using either `to_bytes_little_endian` or `to_bytes_big_endian`. This
+    /// routine is useful in tests where the DFA is serialized and deserialized
+    /// on the same platform.
+    ///
+    /// See
+    /// [`to_bytes_little_endian`](enum.DenseDFA.html#method.to_bytes_little_endian)
+    /// for how the state identifier representation is serialized.
+    pub fn to_bytes_native_endian(&self) -> Result<Vec<u8>> {
+        self.repr().to_bytes::<NativeEndian>(true)
+    }
 
-impl<T: AsRef<[S]>, S: StateID> DFA for Premultiplied<T, S> {
-    type ID = S;
+    /// Serialize a DFA to raw bytes in little endian format, but without its
+    /// byte class map.
+    ///
+    /// The result is 248 bytes smaller than the output of
+    /// `to_bytes_little_endian`. It can only be deserialized with
+    /// [`from_bytes_with_classes`](enum.DenseDFA.html#method.from_bytes_with_classes),
+    /// which requires the byte classes of this DFA (as returned by
+    /// [`byte_classes`](enum.DenseDFA.html#method.byte_classes)) to be
+    /// supplied separately. This permits storing one class map for many DFAs
+    /// that share it.
+    ///
+    /// This returns an error under the same conditions as
+    /// `to_bytes_little_endian`.
+    pub fn to_bytes_without_classes_little_endian(&self) -> Result<Vec<u8>> {
+        self.repr().to_bytes::<LittleEndian>(false)
+    }
 
-    #[inline]
-    fn start_state(&self) -> S {
-        self.0.start_state()
+    /// Serialize a DFA to raw bytes in big endian format, but without its
+    /// byte class map.
+    ///
+    /// See
+    /// [`to_bytes_without_classes_little_endian`](enum.DenseDFA.html#method.to_bytes_without_classes_little_endian)
+    /// for more details.
+    pub fn to_bytes_without_classes_big_endian(&self) -> Result<Vec<u8>> {
+        self.repr().to_bytes::<BigEndian>(false)
     }
 
-    #[inline]
-    fn is_match_state(&self, id: S) -> bool {
-        self.0.is_match_state(id)
+    /// Serialize a DFA to raw bytes in native endian format, but without its
+    /// byte class map.
+    ///
+    /// See
+    /// [`to_bytes_without_classes_little_endian`](enum.DenseDFA.html#method.to_bytes_without_classes_little_endian)
+    /// for more details.
+    pub fn to_bytes_without_classes_native_endian(&self) -> Result<Vec<u8>> {
+        self.repr().to_bytes::<NativeEndian>(false)
     }
 
-    #[inline]
-    fn is_dead_state(&self, id: S) -> bool {
-        self.0.is_dead_state(id)
+    /// Serialize a DFA to raw bytes in little endian format, with each
+    /// state's transitions run length encoded.
+    ///
+    /// Every state's row in the transition table is written as a sequence of
+    /// runs, where each run records a number of consecutive equivalence
+    /// classes along with the state they all transition to. Since most
+    /// transitions in a typical DFA lead to the same handful of states (very
+    /// often the dead state), this is usually much smaller than the output
+    /// of `to_bytes_little_endian`, particularly for DFAs built with Unicode
+    /// enabled or without byte classes.
+    ///
+    /// A DFA serialized this way cannot be used in place. It must be loaded
+    /// with [`from_bytes_rle`](enum.DenseDFA.html#method.from_bytes_rle),
+    /// which expands it back into a dense transition table on the heap.
+    ///
+    /// This returns an error under the same conditions as
+    /// `to_bytes_little_endian`.
+    pub fn to_bytes_rle_little_endian(&self) -> Result<Vec<u8>> {
+        self.repr().to_bytes_rle::<LittleEndian>()
     }
 
-    #[inline]
-    fn is_match_or_dead_state(&self, id: S) -> bool {
-        self.0.is_match_or_dead_state(id)
+    /// Serialize a DFA to raw bytes in big endian format, with each state's
+    /// transitions run length encoded.
+    ///
+    /// See
+    /// [`to_bytes_rle_little_endian`](enum.DenseDFA.html#method.to_bytes_rle_little_endian)
+    /// for more details.
+    pub fn to_bytes_rle_big_endian(&self) -> Result<Vec<u8>> {
+        self.repr().to_bytes_rle::<BigEndian>()
     }
 
-    #[inline]
-    fn is_anchored(&self) -> bool {
-        self.0.is_anchored()
+    /// Serialize a DFA to raw bytes in native endian format, with each
+    /// state's transitions run length encoded.
+    ///
+    /// See
+    /// [`to_bytes_rle_little_endian`](enum.DenseDFA.html#method.to_bytes_rle_little_endian)
+    /// for more details.
+    pub fn to_bytes_rle_native_endian(&self) -> Result<Vec<u8>> {
+        self.repr().to_bytes_rle::<NativeEndian>()
     }
 
-    #[inline]
-    fn next_state(&self, current: S, input: u8) -> S {
-        let o = current.to_usize() + input as usize;
-        self.0.trans()[o]
+    /// Serialize a DFA to raw bytes in little endian format, with each
+    /// transition written as a variable width integer.
+    ///
+    /// Every transition is written as the index of the state it points to,
+    /// using the LEB128 encoding: 7 bits per byte, with the high bit set on
+    /// every byte but the last. So a DFA with fewer than 128 states spends
+    /// one byte per transition no matter how wide its state identifiers are,
+    /// and premultiplied identifiers cost no more than plain ones. Where run
+    /// length encoding exploits repeated transitions, this exploits small
+    /// ones.
+    ///
+    /// A DFA serialized this way cannot be used in place. It must be loaded
+    /// with
+    /// [`from_bytes_varint`](enum.DenseDFA.html#method.from_bytes_varint),
+    /// which decodes it back into a dense transition table on the heap.
+    ///
+    /// This returns an error under the same conditions as
+    /// `to_bytes_little_endian`.
+    pub fn to_bytes_varint_little_endian(&self) -> Result<Vec<u8>> {
+        self.repr().to_bytes_varint::<LittleEndian>()
     }
 
-    #[inline]
-    unsafe fn next_state_unchecked(&self, current: S, input: u8) -> S {
-        let o = current.to_usize() + input as usize;
-        *self.0.trans().get_unchecked(o)
+    /// Serialize a DFA to raw bytes in big endian format, with each
+    /// transition written as a variable width integer.
+    ///
+    /// See
+    /// [`to_bytes_varint_little_endian`](enum.DenseDFA.html#method.to_bytes_varint_little_endian)
+    /// for more details.
+    pub fn to_bytes_varint_big_endian(&self) -> Result<Vec<u8>> {
+        self.repr().to_bytes_varint::<BigEndian>()
     }
-}
-
-/// The default configuration of a dense DFA, which uses byte classes and
-/// premultiplies its state identifiers.
-///
-/// Generally, it isn't necessary to use this type directly, since a `DenseDFA`
-/// can be used for searching directly. One possible reason why one might want
-/// to use this type directly is if you are implementing your own search
-/// routines by walking a DFA's transitions directly. In that case, you'll want
-/// to use this type (or any of the other DFA variant types) directly, since
-/// they implement `next_state` more efficiently.
-#[derive(Clone, Debug)]
-pub struct PremultipliedByteClass<T: AsRef<[S]>, S: StateID>(Repr<T, S>);
-
-impl<T: AsRef<[S]>, S: StateID> DFA for PremultipliedByteClass<T, S> {
-    type ID = S;
 
-    #[inline]
-    fn start_state(&self) -> S {
-        self.0.start_state()
+    /// Serialize a DFA to raw bytes in native endian format, with each
+    /// transition written as a variable width integer.
+    ///
+    /// See
+    /// [`to_bytes_varint_little_endian`](enum.DenseDFA.html#method.to_bytes_varint_little_endian)
+    /// for more details.
+    pub fn to_bytes_varint_native_endian(&self) -> Result<Vec<u8>> {
+        self.repr().to_bytes_varint::<NativeEndian>()
     }
 
-    #[inline]
-    fn is_match_state(&self, id: S) -> bool {
-        self.0.is_match_state(id)
+    /// Serialize a DFA to raw bytes in little endian format, followed by the
+    /// given state labels.
+    ///
+    /// The output begins with exactly what `to_bytes_little_endian` writes,
+    /// except for a header flag recording that labels follow the transition
+    /// table. It can still be deserialized in place with
+    /// [`from_bytes`](enum.DenseDFA.html#method.from_bytes), which ignores
+    /// the labels. Use
+    /// [`StateLabels::from_dfa_bytes`](struct.StateLabels.html#method.from_dfa_bytes)
+    /// to read them back.
+    ///
+    /// This returns an error under the same conditions as
+    /// `to_bytes_little_endian`.
+    pub fn to_bytes_with_labels_little_endian(
+        &self,
+        labels: &StateLabels,
+    ) -> Result<Vec<u8>> {
+        self.repr().to_bytes_with_labels::<LittleEndian>(labels)
     }
 
-    #[inline]
-    fn is_dead_state(&self, id: S) -> bool {
-        self.0.is_dead_state(id)
+    /// Serialize a DFA to raw bytes in big endian format, followed by the
+    /// given state labels.
+    ///
+    /// See
+    /// [`to_bytes_with_labels_little_endian`](enum.DenseDFA.html#method.to_bytes_with_labels_little_endian)
+    /// for more details.
+    pub fn to_bytes_with_labels_big_endian(
+        &self,
+        labels: &StateLabels,
+    ) -> Result<Vec<u8>> {
+        self.repr().to_bytes_with_labels::<BigEndian>(labels)
     }
 
-    #[inline]
-    fn is_match_or_dead_state(&self, id: S) -> bool {
-        self.0.is_match_or_dead_state(id)
+    /// Serialize a DFA to raw bytes in native endian format, followed by the
+    /// given state labels.
+    ///
+    /// See
+    /// [`to_bytes_with_labels_little_endian`](enum.DenseDFA.html#method.to_bytes_with_labels_little_endian)
+    /// for more details.
+    pub fn to_bytes_with_labels_native_endian(
+        &self,
+        labels: &StateLabels,
+    ) -> Result<Vec<u8>> {
+        self.repr().to_bytes_with_labels::<NativeEndian>(labels)
     }
 
-    #[inline]
-    fn is_anchored(&self) -> bool {
-        self.0.is_anchored()
+    /// Return a value whose `Debug` output is the same as this DFA's, except
+    /// that every state with a label is followed by it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense::StateLabels, DenseDFA, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = DenseDFA::new("foo")?;
+    /// let mut labels = StateLabels::new();
+    /// labels.set(dfa.start_state(), "start");
+    ///
+    /// let out = format!("{:?}", dfa.with_labels(&labels));
+    /// assert!(out.contains("(start):"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn with_labels<'a>(
+        &'a self,
+        labels: &'a StateLabels,
+    ) -> LabeledDFA<'a, T, S> {
+        LabeledDFA { dfa: self, labels }
     }
 
-    #[inline]
-    fn next_state(&self, current: S, input: u8) -> S {
-        let input = self.0.byte_classes().get(input);
-        let o = current.to_usize() + input as usize;
-        self.0.trans()[o]
+    /// Render this DFA in the DOT language, for visualization with a tool
+    /// like Graphviz.
+    ///
+    /// Match states are drawn with a double circle. The dead state and every
+    /// transition into it are omitted, and transitions between the same pair
+    /// of states are drawn as one edge per contiguous byte range. To show
+    /// state labels, use
+    /// [`with_labels`](enum.DenseDFA.html#method.with_labels) and call
+    /// `to_dot` on its result.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::DenseDFA;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = DenseDFA::new("a+")?;
+    /// assert!(dfa.to_dot().starts_with("digraph dfa {"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn to_dot(&self) -> String {
+        self.repr().to_dot(None)
     }
 
-    #[inline]
-    unsafe fn next_state_unchecked(&self, current: S, input: u8) -> S {
-        let input = self.0.byte_classes().get_unchecked(input);
-        let o = current.to_usize() + input as usize;
-        *self.0.trans().get_unchecked(o)
+    /// Generate Rust source code that embeds this DFA in a program, suitable
+    /// for writing to a file from a build script and pulling in with
+    /// `include!`.
+    ///
+    /// The generated code defines a private static named `name` containing
+    /// this DFA's serialized bytes, along with a private function named
+    /// `{name}_dfa` that returns a `DenseDFA<&'static [S], S>` borrowing
+    /// those bytes. Both a little endian and a big endian serialization are
+    /// emitted, and `cfg(target_endian)` selects the one matching the target
+    /// that the generated code is compiled for. The bytes are wrapped in a
+    /// `#[repr(C)]` struct that forces the 8 byte alignment required by
+    /// [`DenseDFA::from_bytes`](enum.DenseDFA.html#method.from_bytes).
+    ///
+    /// The state identifier type `S` is spelled out by its full type name.
+    /// Since the serialization of a `usize` DFA depends on the pointer width,
+    /// a fixed size representation such as `u16` or `u32` should be preferred
+    /// when the generated code may be compiled for a different target than
+    /// the one running the build script.
+    ///
+    /// The given name should be a valid Rust identifier. It is not checked.
+    ///
+    /// If the state identifier representation of this DFA has a size different
+    /// than 1, 2, 4 or 8 bytes, then this returns an error, since the
+    /// generated code loads the DFA in place with `from_bytes`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::DenseDFA;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = DenseDFA::new("foo[0-9]+")?.to_u16()?;
+    /// let source = dfa.to_rust_source("FOO")?;
+    /// assert!(source.contains("static FOO:"));
+    /// assert!(source.contains(
+    ///     "fn FOO_dfa() -> ::regex_automata::DenseDFA<&'static [u16], u16>",
+    /// ));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn to_rust_source(&self, name: &str) -> Result<String> {
+        use std::fmt::Write;
+
+        let state_size = mem::size_of::<S>();
+        if serialized_state_size::<S>()? != state_size {
+            return Err(Error::serialize(&format!(
+                "state size of {} not supported, must be 1, 2, 4 or 8",
+                state_size
+            )));
+        }
+        let little = self.to_bytes_little_endian()?;
+        let big = self.to_bytes_big_endian()?;
+        let id = ::std::any::type_name::<S>();
+        let aligned = format!("{}_ALIGNED", name);
+
+        let mut out = String::new();
+        out.push_str("// Generated by regex-automata. Do not edit.\n\n");
+        out.push_str("#[allow(non_camel_case_types)]\n#[repr(C)]\n");
+        writeln!(
+            out,
+            "struct {}<B: ?Sized> {{\n    _align: [u64; 0],\n    bytes: B,\n}}",
+            aligned,
+        )
+        .unwrap();
+        for &(endian, bytes) in &[("little", &little), ("big", &big)] {
+            writeln!(out).unwrap();
+            writeln!(out, "#[cfg(target_endian = \"{}\")]", endian).unwrap();
+            writeln!(
+                out,
+                "static {}: &{}<[u8]> = &{} {{",
+                name, aligned, aligned,
+            )
+            .unwrap();
+            out.push_str("    _align: [],\n    bytes: [");
+            for (i, b) in bytes.iter().enumerate() {
+                if i % 12 == 0 {
+                    out.push_str("\n       ");
+                }
+                write!(out, " 0x{:02X},", b).unwrap();
+            }
+            out.push_str("\n    ],\n};\n");
+        }
+        writeln!(out).unwrap();
+        out.push_str("#[allow(dead_code, non_snake_case)]\n");
+        writeln!(
+            out,
+            "fn {}_dfa() -> ::regex_automata::DenseDFA<&'static [{}], {}> {{",
+            name, id, id,
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "    unsafe {{ ::regex_automata::DenseDFA::from_bytes(&{}.bytes) }}",
+            name,
+        )
+        .unwrap();
+        out.push_str("}\n");
+        Ok(out)
     }
 }
 
-/// The internal representation of a dense DFA.
-///
-/// This representation is shared by all DFA variants.
-#[derive(Clone)]
-#[cfg_attr(not(feature = "std"), derive(Debug))]
-pub(crate) struct Repr<T, S> {
-    /// Whether the state identifiers in the transition table have been
-    /// premultiplied or not.
+impl<'a, S: StateID> DenseDFA<&'a [S], S> {
+    /// Deserialize a DFA with a specific state identifier representation.
     ///
-    /// Premultiplied identifiers means that instead of your matching loop
-    /// looking something like this:
+    /// Deserializing a DFA using this routine will never allocate heap memory.
+    /// This is also guaranteed to be a constant time operation that does not
+    /// vary with the size of the DFA.
     ///
-    ///   state = dfa.start
-    ///   for byte in haystack:
-    ///       next = dfa.transitions[state * len(alphabet) + byte]
-    ///       if dfa.is_match(next):
-    ///           return true
-    ///   return false
+    /// The bytes given should be generated by the serialization of a DFA with
+    /// either the
+    /// [`to_bytes_little_endian`](enum.DenseDFA.html#method.to_bytes_little_endian)
+    /// method or the
+    /// [`to_bytes_big_endian`](enum.DenseDFA.html#method.to_bytes_big_endian)
+    /// endian, depending on the endianness of the machine you are
+    /// deserializing this DFA from.
     ///
-    /// it can instead look like this:
+    /// If the state identifier representation is `usize`, then deserialization
+    /// is dependent on the pointer size. For this reason, it is best to
+    /// serialize DFAs using a fixed size representation for your state
+    /// identifiers, such as `u8`, `u16`, `u32` or `u64`.
     ///
-    ///   state = dfa.start
-    ///   for byte in haystack:
-    ///       next = dfa.transitions[state + byte]
-    ///       if dfa.is_match(next):
-    ///           return true
-    ///   return false
+    /// # Panics
     ///
-    /// In other words, we save a multiplication instruction in the critical
-    /// path. This turns out to be a decent performance win. The cost of using
-    /// premultiplied state ids is that they can require a bigger state id
-    /// representation.
-    premultiplied: bool,
-    /// Whether this DFA can only match at the beginning of input or not.
+    /// The bytes given should be *trusted*. In particular, if the bytes
+    /// are not a valid serialization of a DFA, or if the given bytes are
+    /// not aligned to an 8 byte boundary, or if the endianness of the
+    /// serialized bytes is different than the endianness of the machine that
+    /// is deserializing the DFA, then this routine will panic. Moreover, it is
+    /// possible for this deserialization routine to succeed even if the given
+    /// bytes do not represent a valid serialized dense DFA.
     ///
-    /// When true, a match should only be reported if it begins at the 0th
-    /// index of the haystack.
-    anchored: bool,
-    /// The initial start state ID.
-    start: S,
-    /// The total number of states in this DFA. Note that a DFA always has at
-    /// least one state---the dead state---even the empty DFA. In particular,
-    /// the dead state always has ID 0 and is correspondingly always the first
-    /// state. The dead state is never a match state.
-    state_count: usize,
-    /// States in a DFA have a *partial* ordering such that a match state
-    /// always precedes any non-match state (except for the special dead
-    /// state).
+    /// # Safety
     ///
-    /// `max_match` corresponds to the last state that is a match state. This
-    /// encoding has two critical benefits. Firstly, we are not required to
-    /// store any additional per-state information about whether it is a match
-    /// state or not. Secondly, when searching with the DFA, we can do a single
-    /// comparison with `max_match` for each byte instead of two comparisons
-    /// for each byte (one testing whether it is a match and the other testing
-    /// whether we've reached a dead state). Namely, to determine the status
-    /// of the next state, we can do this:
+    /// This routine is unsafe because it permits callers to provide an
+    /// arbitrary transition table with possibly incorrect transitions. While
+    /// the various serialization routines will never return an incorrect
+    /// transition table, there is no guarantee that the bytes provided here
+    /// are correct. While deserialization does many checks (as documented
+    /// above in the panic conditions), this routine does not check that the
+    /// transition table is correct. Given an incorrect transition table, it is
+    /// possible for the search routines to access out-of-bounds memory because
+    /// of explicit bounds check elision.
     ///
-    ///   next_state = transition[cur_state * alphabet_len + cur_byte]
-    ///   if next_state <= max_match:
-    ///       // next_state is either dead (no-match) or a match
-    ///       return next_state != dead
-    max_match: S,
-    /// A set of equivalence classes, where a single equivalence class
-    /// represents a set of bytes that never discriminate between a match
-    /// and a non-match in the DFA. Each equivalence class corresponds to
-    /// a single letter in this DFA's alphabet, where the maximum number of
-    /// letters is 256 (each possible value of a byte). Consequently, the
-    /// number of equivalence classes corresponds to the number of transitions
-    /// for each DFA state.
+    /// When the bytes come from an untrusted source, callers should call
+    /// [`validate`](enum.DenseDFA.html#method.validate) on the result before
+    /// searching with it. A DFA that passes validation is safe to search.
     ///
-    /// The only time the number of equivalence classes is fewer than 256 is
-    /// if the DFA's kind uses byte classes. If the DFA doesn't use byte
-    /// classes, then this vector is empty.
-    byte_classes: ByteClasses,
-    /// A contiguous region of memory representing the transition table in
-    /// row-major order. The representation is dense. That is, every state has
-    /// precisely the same number of transitions. The maximum number of
-    /// transitions is 256. If a DFA has been instructed to use byte classes,
-    /// then the number of transitions can be much less.
+    /// # Example
     ///
-    /// In practice, T is either Vec<S> or &[S].
-    trans: T,
-}
+    /// This example shows how to serialize a DFA to raw bytes, deserialize it
+    /// and then use it for searching. Note that we first convert the DFA to
+    /// using `u16` for its state identifier representation before serializing
+    /// it. While this isn't strictly necessary, it's good practice in order to
+    /// decrease the size of the DFA and to avoid platform specific pitfalls
+    /// such as differing pointer sizes.
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let initial = DenseDFA::new("foo[0-9]+")?;
+    /// let bytes = initial.to_u16()?.to_bytes_native_endian()?;
+    /// let dfa: DenseDFA<&[u16], u16> = unsafe {
+    ///     DenseDFA::from_bytes(&bytes)
+    /// };
+    ///
+    /// assert_eq!(Some(8), dfa.find(b"foo12345"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub unsafe fn from_bytes(buf: &'a [u8]) -> DenseDFA<&'a [S], S> {
+        Repr::from_bytes(buf, None).into_dense_dfa()
+    }
 
-#[cfg(feature = "std")]
-impl<S: StateID> Repr<Vec<S>, S> {
-    /// Create a new empty DFA with singleton byte classes (every byte is its
-    /// own equivalence class).
-    pub fn empty() -> Repr<Vec<S>, S> {
-        Repr::empty_with_byte_classes(ByteClasses::singletons())
+    /// Create a DFA that borrows the given transition table, after checking
+    /// that it's valid.
+    ///
+    /// This permits using a transition table that's already in memory,
+    /// without it being laid out in the serialized format expected by
+    /// [`from_bytes`](enum.DenseDFA.html#method.from_bytes). The table is in
+    /// row-major order, with one row of `classes.alphabet_len()` transitions
+    /// per state, so the number of states is inferred from its length. State
+    /// `0` must be the dead state, and states `1` through `max_match` are the
+    /// match states. If `premultiplied` is true, then every state identifier
+    /// (including `start` and `max_match`) is a state's index multiplied by
+    /// the alphabet length.
+    ///
+    /// The same checks as
+    /// [`validate`](enum.DenseDFA.html#method.validate) are performed, so
+    /// the result is always safe to search. An error is returned if the
+    /// table's length isn't a multiple of the alphabet length or if any state
+    /// identifier is out of bounds.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense::ByteClasses, DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// // Classes: bytes before `a`, `a` and bytes after `a`.
+    /// let mut map = [0u8; 256];
+    /// map[b'a' as usize] = 1;
+    /// for b in (b'a' as usize + 1)..256 {
+    ///     map[b] = 2;
+    /// }
+    /// let classes = ByteClasses::from_bytes(&map);
+    ///
+    /// // State 0 is dead, 1 is a match state and 2 is the start state.
+    /// let trans: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 1, 0];
+    /// let dfa = DenseDFA::from_parts(trans, classes, 2, 1, true, false)?;
+    /// assert_eq!(Some(1), dfa.find(b"ab"));
+    /// assert_eq!(None, dfa.find(b"ba"));
+    ///
+    /// assert!(DenseDFA::from_parts(trans, classes, 3, 1, true, false).is_err());
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn from_parts(
+        trans: &'a [S],
+        classes: ByteClasses,
+        start: S,
+        max_match: S,
+        anchored: bool,
+        premultiplied: bool,
+    ) -> Result<DenseDFA<&'a [S], S>> {
+        let repr = Repr {
+            premultiplied,
+            anchored,
+            full_match: false,
+            column_major: false,
+            match_set: None,
+            start,
+            state_count: trans.len() / classes.alphabet_len(),
+            max_match,
+            byte_classes: classes,
+            prefix: Prefix::none(),
+            accel: Accel::none(),
+            skip: SkipBytes::none(),
+            trans,
+        };
+        repr.validate()?;
+        Ok(repr.into_dense_dfa())
     }
 
-    /// Create a new empty DFA with the given set of byte equivalence classes.
-    /// An empty DFA never matches any input.
-    pub fn empty_with_byte_classes(
-        byte_classes: ByteClasses,
-    ) -> Repr<Vec<S>, S> {
-        let mut dfa = Repr {
-            premultiplied: false,
-            anchored: true,
-            start: dead_id(),
-            state_count: 0,
-            max_match: S::from_usize(0),
-            byte_classes,
-            trans: vec![],
-        };
-        // Every state ID repr must be able to fit at least one state.
-        dfa.add_empty_state().unwrap();
-        dfa
+    /// Deserialize a DFA that was serialized without its byte class map,
+    /// using the byte classes given instead.
+    ///
+    /// The bytes given must have been produced by one of the
+    /// `to_bytes_without_classes` routines, such as
+    /// [`to_bytes_without_classes_native_endian`](enum.DenseDFA.html#method.to_bytes_without_classes_native_endian).
+    /// The classes given must be the ones the DFA was built with.
+    ///
+    /// # Panics
+    ///
+    /// This panics under the same conditions as
+    /// [`from_bytes`](enum.DenseDFA.html#method.from_bytes). It also panics
+    /// if the bytes given include a byte class map, or if the number of
+    /// classes given differs from the number of classes the DFA was built
+    /// with.
+    ///
+    /// # Safety
+    ///
+    /// This routine is unsafe for the same reasons as `from_bytes`. Moreover,
+    /// only the number of classes is checked against the serialized DFA. If
+    /// the classes given map bytes differently than the classes the DFA was
+    /// built with, then the resulting DFA will not match the same inputs.
+    ///
+    /// # Example
+    ///
+    /// This example shows how two DFAs with the same class map can be
+    /// stored with only one copy of it.
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA};
+    /// use regex_automata::dense::ByteClasses;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa1 = DenseDFA::new("[a-z]+")?.to_u16()?;
+    /// let dfa2 = DenseDFA::new("[a-z]")?.to_u16()?;
+    /// assert_eq!(dfa1.byte_classes(), dfa2.byte_classes());
+    ///
+    /// let classes = dfa1.byte_classes().to_bytes();
+    /// let bytes1 = dfa1.to_bytes_without_classes_native_endian()?;
+    /// let bytes2 = dfa2.to_bytes_without_classes_native_endian()?;
+    ///
+    /// let classes = ByteClasses::from_bytes(&classes);
+    /// let dfa1: DenseDFA<&[u16], u16> = unsafe {
+    ///     DenseDFA::from_bytes_with_classes(&bytes1, classes)
+    /// };
+    /// let dfa2: DenseDFA<&[u16], u16> = unsafe {
+    ///     DenseDFA::from_bytes_with_classes(&bytes2, classes)
+    /// };
+    /// assert_eq!(Some(3), dfa1.find(b"abc"));
+    /// assert_eq!(Some(1), dfa2.find(b"abc"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub unsafe fn from_bytes_with_classes(
+        buf: &'a [u8],
+        classes: ByteClasses,
+    ) -> DenseDFA<&'a [S], S> {
+        Repr::from_bytes(buf, Some(classes)).into_dense_dfa()
     }
+}
 
-    /// Sets whether this DFA is anchored or not.
-    pub fn anchored(mut self, yes: bool) -> Repr<Vec<S>, S> {
-        self.anchored = yes;
-        self
+#[cfg(feature = "std")]
+impl<S: StateID> DenseDFA<Vec<S>, S> {
+    /// Minimize this DFA in place.
+    ///
+    /// This is not part of the public API. It is only exposed to allow for
+    /// more granular external benchmarking.
+    #[doc(hidden)]
+    pub fn minimize(&mut self) {
+        self.repr_mut().with_row_major(|r| r.minimize());
     }
-}
 
-impl<T: AsRef<[S]>, S: StateID> Repr<T, S> {
-    /// Convert this internal DFA representation to a DenseDFA based on its
-    /// transition table access pattern.
-    pub fn into_dense_dfa(self) -> DenseDFA<T, S> {
-        match (self.premultiplied, self.byte_classes().is_singleton()) {
-            // no premultiplication, no byte classes
-            (false, true) => DenseDFA::Standard(Standard(self)),
-            // no premultiplication, yes byte classes
-            (false, false) => DenseDFA::ByteClass(ByteClass(self)),
-            // yes premultiplication, no byte classes
-            (true, true) => DenseDFA::Premultiplied(Premultiplied(self)),
-            // yes premultiplication, yes byte classes
-            (true, false) => {
-                DenseDFA::PremultipliedByteClass(PremultipliedByteClass(self))
-            }
-        }
+    /// Reorder the states of this DFA to improve memory locality when
+    /// searching.
+    ///
+    /// The transition table is laid out one state after another, so states
+    /// that are visited one after the other during a search benefit from
+    /// being close together in memory. This renumbers states in breadth first
+    /// order from the start state, which tends to place each state near the
+    /// states it transitions to. The DFA matches precisely the same inputs
+    /// as before, but its state identifiers change.
+    ///
+    /// Whether this improves search throughput depends on the DFA and the
+    /// input. It is most likely to help with large DFAs whose transition
+    /// tables don't fit in the CPU cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let mut dfa = DenseDFA::new("foo[0-9]+|bar")?;
+    /// dfa.remap_for_locality();
+    /// assert_eq!(Some(8), dfa.find(b"foo12345"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn remap_for_locality(&mut self) {
+        self.repr_mut().with_row_major(|r| r.remap_for_locality());
     }
 
-    fn as_ref<'a>(&'a self) -> Repr<&'a [S], S> {
-        Repr {
-            premultiplied: self.premultiplied,
-            anchored: self.anchored,
-            start: self.start,
-            state_count: self.state_count,
-            max_match: self.max_match,
-            byte_classes: self.byte_classes().clone(),
-            trans: self.trans(),
-        }
+    /// Shrink this DFA's alphabet by merging byte classes that no state
+    /// distinguishes.
+    ///
+    /// A DFA built by this crate starts out with the smallest alphabet its
+    /// NFA allows, but transforming a DFA afterwards (or building one by
+    /// hand, or with byte classes disabled) can leave neighboring classes
+    /// whose transitions are identical in every state. This merges such
+    /// classes and rewrites the transition table to match, which reduces
+    /// both memory usage and serialized size. It is independent of state
+    /// minimization and never changes which inputs match. Since byte classes
+    /// are always contiguous ranges of bytes, classes are only merged with
+    /// their neighbors.
+    ///
+    /// If the DFA did not use byte classes before, it will afterwards
+    /// whenever at least two bytes were merged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let mut dfa = dense::Builder::new()
+    ///     .anchored(true)
+    ///     .byte_classes(false)
+    ///     .build("[a-z]+")?;
+    /// assert_eq!(256, dfa.byte_classes().alphabet_len());
+    ///
+    /// dfa.compact_alphabet();
+    /// assert_eq!(3, dfa.byte_classes().alphabet_len());
+    /// assert_eq!(Some(3), dfa.find(b"abc123"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn compact_alphabet(&mut self) {
+        let mut repr = match mem::replace(self, DenseDFA::__Nonexhaustive) {
+            DenseDFA::Standard(r) => r.0,
+            DenseDFA::ByteClass(r) => r.0,
+            DenseDFA::Premultiplied(r) => r.0,
+            DenseDFA::PremultipliedByteClass(r) => r.0,
+            DenseDFA::ColumnMajor(r) => r.0,
+            DenseDFA::__Nonexhaustive => unreachable!(),
+        };
+        repr.with_row_major(|r| r.compact_alphabet());
+        *self = repr.into_dense_dfa();
     }
 
-    #[cfg(feature = "std")]
-    fn to_owned(&self) -> Repr<Vec<S>, S> {
-        Repr {
-            premultiplied: self.premultiplied,
-            anchored: self.anchored,
-            start: self.start,
-            state_count: self.state_count,
-            max_match: self.max_match,
-            byte_classes: self.byte_classes().clone(),
-            trans: self.trans().to_vec(),
-        }
+    /// Deserialize a DFA written by one of the `to_bytes_rle` routines, such
+    /// as
+    /// [`to_bytes_rle_native_endian`](enum.DenseDFA.html#method.to_bytes_rle_native_endian).
+    ///
+    /// Unlike [`from_bytes`](enum.DenseDFA.html#method.from_bytes), this
+    /// expands the transition table into freshly allocated memory, so the
+    /// bytes given need not be aligned and need not outlive the DFA.
+    /// Moreover, the result is checked with
+    /// [`validate`](enum.DenseDFA.html#method.validate) before it is
+    /// returned, which makes this routine safe to call on untrusted input.
+    ///
+    /// An error is returned if the bytes are not a valid run length encoded
+    /// DFA, if they were written with a different endianness than this
+    /// platform's, or if they use a different state identifier
+    /// representation than `S`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = DenseDFA::new(r"\w+")?.to_u32()?;
+    /// let dense = dfa.to_bytes_native_endian()?;
+    /// let rle = dfa.to_bytes_rle_native_endian()?;
+    /// assert!(rle.len() < dense.len());
+    ///
+    /// let loaded: DenseDFA<Vec<u32>, u32> = DenseDFA::from_bytes_rle(&rle)?;
+    /// assert_eq!(dfa, loaded);
+    /// assert_eq!(Some(10), loaded.find("δέλτα".as_bytes()));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn from_bytes_rle(buf: &[u8]) -> Result<DenseDFA<Vec<S>, S>> {
+        Repr::from_bytes_encoded(buf, MASK_RLE).map(|r| r.into_dense_dfa())
     }
 
-    /// Return the starting state of this DFA.
+    /// Deserialize a DFA written by one of the `to_bytes_varint` routines,
+    /// such as
+    /// [`to_bytes_varint_native_endian`](enum.DenseDFA.html#method.to_bytes_varint_native_endian).
     ///
-    /// All searches using this DFA must begin at this state. There is exactly
-    /// one starting state for every DFA. A starting state may be a dead state
-    /// or a matching state or neither.
-    pub fn start_state(&self) -> S {
-        self.start
+    /// Like [`from_bytes_rle`](enum.DenseDFA.html#method.from_bytes_rle),
+    /// this decodes the transition table into freshly allocated memory and
+    /// validates the result, which makes it safe to call on untrusted input.
+    ///
+    /// An error is returned if the bytes are not a valid varint encoded DFA,
+    /// if they were written with a different endianness than this platform's,
+    /// or if they use a different state identifier representation than `S`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = DenseDFA::new("foo[0-9]+")?.to_u32()?;
+    /// let dense = dfa.to_bytes_native_endian()?;
+    /// let varint = dfa.to_bytes_varint_native_endian()?;
+    /// assert!(varint.len() < dense.len());
+    ///
+    /// let loaded: DenseDFA<Vec<u32>, u32> =
+    ///     DenseDFA::from_bytes_varint(&varint)?;
+    /// assert_eq!(dfa, loaded);
+    /// assert_eq!(Some(6), loaded.find(b"foo123"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn from_bytes_varint(buf: &[u8]) -> Result<DenseDFA<Vec<S>, S>> {
+        Repr::from_bytes_encoded(buf, MASK_VARINT).map(|r| r.into_dense_dfa())
     }
 
-    /// Returns true if and only if the given identifier corresponds to a match
-    /// state.
-    pub fn is_match_state(&self, id: S) -> bool {
-        id <= self.max_match && id != dead_id()
+    /// Return a mutable reference to the internal DFA representation.
+    fn repr_mut(&mut self) -> &mut Repr<Vec<S>, S> {
+        match *self {
+            DenseDFA::Standard(ref mut r) => &mut r.0,
+            DenseDFA::ByteClass(ref mut r) => &mut r.0,
+            DenseDFA::Premultiplied(ref mut r) => &mut r.0,
+            DenseDFA::PremultipliedByteClass(ref mut r) => &mut r.0,
+            DenseDFA::ColumnMajor(ref mut r) => &mut r.0,
+            DenseDFA::__Nonexhaustive => unreachable!(),
+        }
     }
+}
 
-    /// Returns true if and only if the given identifier corresponds to a dead
-    /// state.
-    pub fn is_dead_state(&self, id: S) -> bool {
-        id == dead_id()
+impl<T: AsRef<[S]>, S: StateID> DFA for DenseDFA<T, S> {
+    type ID = S;
+
+    #[inline]
+    fn start_state(&self) -> S {
+        self.repr().start_state()
     }
 
-    /// Returns true if and only if the given identifier could correspond to
-    /// either a match state or a dead state. If this returns false, then the
-    /// given identifier does not correspond to either a match state or a dead
-    /// state.
-    pub fn is_match_or_dead_state(&self, id: S) -> bool {
-        id <= self.max_match_state()
+    #[inline]
+    fn is_match_state(&self, id: S) -> bool {
+        self.repr().is_match_state(id)
     }
 
-    /// Returns the maximum identifier for which a match state can exist.
-    ///
-    /// More specifically, the return identifier always corresponds to either
-    /// a match state or a dead state. Namely, either
-    /// `is_match_state(returned)` or `is_dead_state(returned)` is guaranteed
-    /// to be true.
-    pub fn max_match_state(&self) -> S {
-        self.max_match
+    #[inline]
+    fn is_dead_state(&self, id: S) -> bool {
+        self.repr().is_dead_state(id)
     }
 
-    /// Returns true if and only if this DFA is anchored.
-    pub fn is_anchored(&self) -> bool {
-        self.anchored
+    #[inline]
+    fn is_match_or_dead_state(&self, id: S) -> bool {
+        self.repr().is_match_or_dead_state(id)
     }
 
-    /// Return the byte classes used by this DFA.
-    pub fn byte_classes(&self) -> &ByteClasses {
-        &self.byte_classes
+    #[inline]
+    fn is_anchored(&self) -> bool {
+        self.repr().is_anchored()
     }
 
-    /// Returns an iterator over all states in this DFA.
-    ///
-    /// This iterator yields a tuple for each state. The first element of the
-    /// tuple corresponds to a state's identifier, and the second element
-    /// corresponds to the state itself (comprised of its transitions).
-    ///
-    /// If this DFA is premultiplied, then the state identifiers are in
-    /// turn premultiplied as well, making them usable without additional
-    /// modification.
-    #[cfg(feature = "std")]
-    pub fn states(&self) -> StateIter<T, S> {
-        let it = self.trans().chunks(self.alphabet_len());
-        StateIter { dfa: self, it: it.enumerate() }
+    #[inline]
+    fn requires_full_match(&self) -> bool {
+        self.repr().requires_full_match()
     }
 
-    /// Return the total number of states in this DFA. Every DFA has at least
-    /// 1 state, even the empty DFA.
-    #[cfg(feature = "std")]
-    pub fn state_count(&self) -> usize {
-        self.state_count
+    #[inline]
+    fn next_state(&self, current: S, input: u8) -> S {
+        match *self {
+            DenseDFA::Standard(ref r) => r.next_state(current, input),
+            DenseDFA::ByteClass(ref r) => r.next_state(current, input),
+            DenseDFA::Premultiplied(ref r) => r.next_state(current, input),
+            DenseDFA::PremultipliedByteClass(ref r) => {
+                r.next_state(current, input)
+            }
+            DenseDFA::ColumnMajor(ref r) => r.next_state(current, input),
+            DenseDFA::__Nonexhaustive => unreachable!(),
+        }
     }
 
-    /// Return the number of elements in this DFA's alphabet.
-    ///
-    /// If this DFA doesn't use byte classes, then this is always equivalent
-    /// to 256. Otherwise, it is guaranteed to be some value less than or equal
-    /// to 256.
-    pub fn alphabet_len(&self) -> usize {
-        self.byte_classes().alphabet_len()
+    #[inline]
+    unsafe fn next_state_unchecked(&self, current: S, input: u8) -> S {
+        match *self {
+            DenseDFA::Standard(ref r) => {
+                r.next_state_unchecked(current, input)
+            }
+            DenseDFA::ByteClass(ref r) => {
+                r.next_state_unchecked(current, input)
+            }
+            DenseDFA::Premultiplied(ref r) => {
+                r.next_state_unchecked(current, input)
+            }
+            DenseDFA::PremultipliedByteClass(ref r) => {
+                r.next_state_unchecked(current, input)
+            }
+            DenseDFA::ColumnMajor(ref r) => {
+                r.next_state_unchecked(current, input)
+            }
+            DenseDFA::__Nonexhaustive => unreachable!(),
+        }
     }
 
-    /// Returns the memory usage, in bytes, of this DFA.
-    pub fn memory_usage(&self) -> usize {
-        self.trans().len() * mem::size_of::<S>()
+    #[inline]
+    fn prefault(&self) {
+        self.repr().prefault()
     }
 
-    /// Convert the given state identifier to the state's index. The state's
-    /// index corresponds to the position in which it appears in the transition
-    /// table. When a DFA is NOT premultiplied, then a state's identifier is
-    /// also its index. When a DFA is premultiplied, then a state's identifier
-    /// is equal to `index * alphabet_len`. This routine reverses that.
-    #[cfg(feature = "std")]
-    pub fn state_id_to_index(&self, id: S) -> usize {
-        if self.premultiplied {
-            id.to_usize() / self.alphabet_len()
-        } else {
-            id.to_usize()
+    #[cfg(feature = "prefetch")]
+    #[inline(always)]
+    fn prefetch_transition(&self, current: S, input: u8) {
+        match *self {
+            DenseDFA::Standard(ref r) => r.prefetch_transition(current, input),
+            DenseDFA::ByteClass(ref r) => {
+                r.prefetch_transition(current, input)
+            }
+            DenseDFA::Premultiplied(ref r) => {
+                r.prefetch_transition(current, input)
+            }
+            DenseDFA::PremultipliedByteClass(ref r) => {
+                r.prefetch_transition(current, input)
+            }
+            DenseDFA::ColumnMajor(ref r) => {
+                r.prefetch_transition(current, input)
+            }
+            DenseDFA::__Nonexhaustive => unreachable!(),
         }
     }
 
-    /// Return this DFA's transition table as a slice.
-    fn trans(&self) -> &[S] {
-        self.trans.as_ref()
-    }
-
-    /// Create a sparse DFA from the internal representation of a dense DFA.
-    #[cfg(feature = "std")]
-    pub fn to_sparse_sized<A: StateID>(
-        &self,
-    ) -> Result<SparseDFA<Vec<u8>, A>> {
-        SparseDFA::from_dense_sized(self)
-    }
+    // We specialize the following methods because it lets us lift the
+    // case analysis between the different types of dense DFAs. Instead of
+    // doing the case analysis for every transition, we do it once before
+    // searching.
 
-    /// Create a new DFA whose match semantics are equivalent to this DFA, but
-    /// attempt to use `A` for the representation of state identifiers. If `A`
-    /// is insufficient to represent all state identifiers in this DFA, then
-    /// this returns an error.
-    #[cfg(feature = "std")]
-    pub fn to_sized<A: StateID>(&self) -> Result<Repr<Vec<A>, A>> {
-        // Check that this DFA can fit into A's representation.
-        let mut last_state_id = self.state_count - 1;
-        if self.premultiplied {
-            last_state_id *= self.alphabet_len();
+    #[inline]
+    fn is_match_at(&self, bytes: &[u8], start: usize) -> bool {
+        let start = match self.repr().prefix.find(bytes, start) {
+            None => return false,
+            Some(start) => start,
+        };
+        let (accel, skip) = (&self.repr().accel, &self.repr().skip);
+        if accel.enabled || !skip.is_empty() {
+            return match *self {
+                DenseDFA::Standard(ref r) => {
+                    skipping_is_match_at(r, accel, skip, bytes, start)
+                }
+                DenseDFA::ByteClass(ref r) => {
+                    skipping_is_match_at(r, accel, skip, bytes, start)
+                }
+                DenseDFA::Premultiplied(ref r) => {
+                    skipping_is_match_at(r, accel, skip, bytes, start)
+                }
+                DenseDFA::PremultipliedByteClass(ref r) => {
+                    skipping_is_match_at(r, accel, skip, bytes, start)
+                }
+                DenseDFA::ColumnMajor(ref r) => {
+                    skipping_is_match_at(r, accel, skip, bytes, start)
+                }
+                DenseDFA::__Nonexhaustive => unreachable!(),
+            };
         }
-        if last_state_id > A::max_id() {
-            return Err(Error::state_id_overflow(A::max_id()));
+        match *self {
+            DenseDFA::Standard(ref r) => r.is_match_at(bytes, start),
+            DenseDFA::ByteClass(ref r) => r.is_match_at(bytes, start),
+            DenseDFA::Premultiplied(ref r) => r.is_match_at(bytes, start),
+            DenseDFA::PremultipliedByteClass(ref r) => {
+                r.is_match_at(bytes, start)
+            }
+            DenseDFA::ColumnMajor(ref r) => r.is_match_at(bytes, start),
+            DenseDFA::__Nonexhaustive => unreachable!(),
         }
+    }
 
-        // We're off to the races. The new DFA is the same as the old one,
-        // but its transition table is truncated.
-        let mut new = Repr {
-            premultiplied: self.premultiplied,
-            anchored: self.anchored,
-            start: A::from_usize(self.start.to_usize()),
-            state_count: self.state_count,
-            max_match: A::from_usize(self.max_match.to_usize()),
-            byte_classes: self.byte_classes().clone(),
-            trans: vec![dead_id::<A>(); self.trans().len()],
-        };
-        for (i, id) in new.trans.iter_mut().enumerate() {
-            *id = A::from_usize(self.trans()[i].to_usize());
+    #[inline]
+    fn shortest_match_at(&self, bytes: &[u8], start: usize) -> Option<usize> {
+        let start = self.repr().prefix.find(bytes, start)?;
+        let (accel, skip) = (&self.repr().accel, &self.repr().skip);
+        if accel.enabled || !skip.is_empty() {
+            return match *self {
+                DenseDFA::Standard(ref r) => {
+                    skipping_shortest_match_at(r, accel, skip, bytes, start)
+                }
+                DenseDFA::ByteClass(ref r) => {
+                    skipping_shortest_match_at(r, accel, skip, bytes, start)
+                }
+                DenseDFA::Premultiplied(ref r) => {
+                    skipping_shortest_match_at(r, accel, skip, bytes, start)
+                }
+                DenseDFA::PremultipliedByteClass(ref r) => {
+                    skipping_shortest_match_at(r, accel, skip, bytes, start)
+                }
+                DenseDFA::ColumnMajor(ref r) => {
+                    skipping_shortest_match_at(r, accel, skip, bytes, start)
+                }
+                DenseDFA::__Nonexhaustive => unreachable!(),
+            };
+        }
+        match *self {
+            DenseDFA::Standard(ref r) => r.shortest_match_at(bytes, start),
+            DenseDFA::ByteClass(ref r) => r.shortest_match_at(bytes, start),
+            DenseDFA::Premultiplied(ref r) => {
+                r.shortest_match_at(bytes, start)
+            }
+            DenseDFA::PremultipliedByteClass(ref r) => {
+                r.shortest_match_at(bytes, start)
+            }
+            DenseDFA::ColumnMajor(ref r) => r.shortest_match_at(bytes, start),
+            DenseDFA::__Nonexhaustive => unreachable!(),
         }
-        Ok(new)
     }
 
-    /// Serialize a DFA to raw bytes, aligned to an 8 byte boundary.
-    ///
-    /// If the state identifier representation of this DFA has a size different
-    /// than 1, 2, 4 or 8 bytes, then this returns an error. All
-    /// implementations of `StateID` provided by this crate satisfy this
-    /// requirement.
-    #[cfg(feature = "std")]
-    pub(crate) fn to_bytes<A: ByteOrder>(&self) -> Result<Vec<u8>> {
-        let label = b"rust-regex-automata-dfa\x00";
-        assert_eq!(24, label.len());
-
-        let trans_size = mem::size_of::<S>() * self.trans().len();
-        let size =
-            // For human readable label.
-            label.len()
-            // endiannes check, must be equal to 0xFEFF for native endian
-            + 2
-            // For version number.
-            + 2
-            // Size of state ID representation, in bytes.
-            // Must be 1, 2, 4 or 8.
-            + 2
-            // For DFA misc options.
-            + 2
-            // For start state.
-            + 8
-            // For state count.
-            + 8
-            // For max match state.
-            + 8
-            // For byte class map.
-            + 256
-            // For transition table.
-            + trans_size;
-        // sanity check, this can be updated if need be
-        assert_eq!(312 + trans_size, size);
-        // This must always pass. It checks that the transition table is at
-        // a properly aligned address.
-        assert_eq!(0, (size - trans_size) % 8);
-
-        let mut buf = vec![0; size];
-        let mut i = 0;
-
-        // write label
-        for &b in label {
-            buf[i] = b;
-            i += 1;
+    #[inline]
+    fn find_at(&self, bytes: &[u8], start: usize) -> Option<usize> {
+        let start = self.repr().prefix.find(bytes, start)?;
+        let (accel, skip) = (&self.repr().accel, &self.repr().skip);
+        if accel.enabled || !skip.is_empty() {
+            return match *self {
+                DenseDFA::Standard(ref r) => {
+                    skipping_find_at(r, accel, skip, bytes, start)
+                }
+                DenseDFA::ByteClass(ref r) => {
+                    skipping_find_at(r, accel, skip, bytes, start)
+                }
+                DenseDFA::Premultiplied(ref r) => {
+                    skipping_find_at(r, accel, skip, bytes, start)
+                }
+                DenseDFA::PremultipliedByteClass(ref r) => {
+                    skipping_find_at(r, accel, skip, bytes, start)
+                }
+                DenseDFA::ColumnMajor(ref r) => {
+                    skipping_find_at(r, accel, skip, bytes, start)
+                }
+                DenseDFA::__Nonexhaustive => unreachable!(),
+            };
         }
-        // endianness check
-        A::write_u16(&mut buf[i..], 0xFEFF);
-        i += 2;
-        // version number
-        A::write_u16(&mut buf[i..], 1);
-        i += 2;
-        // size of state ID
-        let state_size = mem::size_of::<S>();
-        if ![1, 2, 4, 8].contains(&state_size) {
-            return Err(Error::serialize(&format!(
-                "state size of {} not supported, must be 1, 2, 4 or 8",
-                state_size
-            )));
+        match *self {
+            DenseDFA::Standard(ref r) => r.find_at(bytes, start),
+            DenseDFA::ByteClass(ref r) => r.find_at(bytes, start),
+            DenseDFA::Premultiplied(ref r) => r.find_at(bytes, start),
+            DenseDFA::PremultipliedByteClass(ref r) => r.find_at(bytes, start),
+            DenseDFA::ColumnMajor(ref r) => r.find_at(bytes, start),
+            DenseDFA::__Nonexhaustive => unreachable!(),
         }
-        A::write_u16(&mut buf[i..], state_size as u16);
-        i += 2;
-        // DFA misc options
-        let mut options = 0u16;
-        if self.premultiplied {
-            options |= MASK_PREMULTIPLIED;
+    }
+
+    #[inline]
+    fn rfind_at(&self, bytes: &[u8], start: usize) -> Option<usize> {
+        let start = self.repr().prefix.rfind(bytes, start)?;
+        let skip = &self.repr().skip;
+        if !skip.is_empty() {
+            return match *self {
+                DenseDFA::Standard(ref r) => {
+                    skipping_rfind_at(r, skip, bytes, start)
+                }
+                DenseDFA::ByteClass(ref r) => {
+                    skipping_rfind_at(r, skip, bytes, start)
+                }
+                DenseDFA::Premultiplied(ref r) => {
+                    skipping_rfind_at(r, skip, bytes, start)
+                }
+                DenseDFA::PremultipliedByteClass(ref r) => {
+                    skipping_rfind_at(r, skip, bytes, start)
+                }
+                DenseDFA::ColumnMajor(ref r) => {
+                    skipping_rfind_at(r, skip, bytes, start)
+                }
+                DenseDFA::__Nonexhaustive => unreachable!(),
+            };
         }
-        if self.anchored {
-            options |= MASK_ANCHORED;
+        match *self {
+            DenseDFA::Standard(ref r) => r.rfind_at(bytes, start),
+            DenseDFA::ByteClass(ref r) => r.rfind_at(bytes, start),
+            DenseDFA::Premultiplied(ref r) => r.rfind_at(bytes, start),
+            DenseDFA::PremultipliedByteClass(ref r) => {
+                r.rfind_at(bytes, start)
+            }
+            DenseDFA::ColumnMajor(ref r) => r.rfind_at(bytes, start),
+            DenseDFA::__Nonexhaustive => unreachable!(),
         }
-        A::write_u16(&mut buf[i..], options);
-        i += 2;
-        // start state
-        A::write_u64(&mut buf[i..], self.start.to_usize() as u64);
-        i += 8;
-        // state count
-        A::write_u64(&mut buf[i..], self.state_count as u64);
-        i += 8;
-        // max match state
-        A::write_u64(&mut buf[i..], self.max_match.to_usize() as u64);
-        i += 8;
-        // byte class map
-        for b in (0..256).map(|b| b as u8) {
-            buf[i] = self.byte_classes().get(b);
-            i += 1;
+    }
+}
+
+/// Two dense DFAs are equal if and only if they have the same structure.
+///
+/// That is, they must be the same variant, agree on whether they are anchored,
+/// and have identical start states, match states, byte classes, literal
+/// prefixes and transition tables. Note that this is *not* language equality:
+/// two DFAs can match precisely the same set of strings while having
+/// different structure (for example, if only one of them is minimized, or if
+/// they were built with different byte class or premultiplication settings).
+/// For the same reason, DFAs built from different patterns may compare equal.
+impl<T: AsRef<[S]>, S: StateID> PartialEq for DenseDFA<T, S> {
+    fn eq(&self, other: &DenseDFA<T, S>) -> bool {
+        let (r1, r2) = (self.repr(), other.repr());
+        #[cfg(feature = "std")]
+        {
+            if r1.match_set != r2.match_set {
+                return false;
+            }
         }
-        // transition table
-        for &id in self.trans() {
-            write_state_id_bytes::<A, _>(&mut buf[i..], id);
-            i += state_size;
+        r1.premultiplied == r2.premultiplied
+            && r1.column_major == r2.column_major
+            && r1.anchored == r2.anchored
+            && r1.full_match == r2.full_match
+            && r1.start == r2.start
+            && r1.state_count == r2.state_count
+            && r1.max_match == r2.max_match
+            && r1.byte_classes == r2.byte_classes
+            && r1.prefix == r2.prefix
+            && r1.accel == r2.accel
+            && r1.skip == r2.skip
+            && r1.trans() == r2.trans()
+    }
+}
+
+impl<T: AsRef<[S]>, S: StateID> Eq for DenseDFA<T, S> {}
+
+/// Hashing is consistent with the structural equality used by `PartialEq`.
+impl<T: AsRef<[S]>, S: StateID> Hash for DenseDFA<T, S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let r = self.repr();
+        r.premultiplied.hash(state);
+        r.column_major.hash(state);
+        #[cfg(feature = "std")]
+        {
+            r.match_set.hash(state);
         }
-        assert_eq!(size, i, "expected to consume entire buffer");
+        r.anchored.hash(state);
+        r.full_match.hash(state);
+        r.start.hash(state);
+        r.state_count.hash(state);
+        r.max_match.hash(state);
+        r.byte_classes.hash(state);
+        r.prefix.hash(state);
+        r.accel.hash(state);
+        r.skip.hash(state);
+        r.trans().hash(state);
+    }
+}
 
-        Ok(buf)
+/// Borrow an owned dense DFA. This is equivalent to
+/// [`DenseDFA::as_ref`](enum.DenseDFA.html#method.as_ref).
+#[cfg(feature = "std")]
+impl<'a, S: StateID> From<&'a DenseDFA<Vec<S>, S>> for DenseDFA<&'a [S], S> {
+    fn from(dfa: &'a DenseDFA<Vec<S>, S>) -> DenseDFA<&'a [S], S> {
+        dfa.as_ref()
     }
 }
 
-impl<'a, S: StateID> Repr<&'a [S], S> {
-    /// The implementation for deserializing a DFA from raw bytes.
-    unsafe fn from_bytes(mut buf: &'a [u8]) -> Repr<&'a [S], S> {
-        assert_eq!(
-            0,
-            buf.as_ptr() as usize % mem::align_of::<S>(),
-            "DenseDFA starting at address {} is not aligned to {} bytes",
-            buf.as_ptr() as usize,
-            mem::align_of::<S>()
-        );
+/// Copy a borrowed dense DFA to the heap. This is equivalent to
+/// [`DenseDFA::to_owned`](enum.DenseDFA.html#method.to_owned).
+#[cfg(feature = "std")]
+impl<'a, S: StateID> From<DenseDFA<&'a [S], S>> for DenseDFA<Vec<S>, S> {
+    fn from(dfa: DenseDFA<&'a [S], S>) -> DenseDFA<Vec<S>, S> {
+        dfa.to_owned()
+    }
+}
 
-        // skip over label
-        match buf.iter().position(|&b| b == b'\x00') {
-            None => panic!("could not find label"),
-            Some(i) => buf = &buf[i + 1..],
-        }
+/// An iterator over every offset of a haystack, reporting whether a search
+/// started at that offset reaches a match state.
+///
+/// This iterator is created by
+/// [`DenseDFA::anchored_matches_iter`](enum.DenseDFA.html#method.anchored_matches_iter).
+///
+/// `'a` corresponds to the lifetime of both the DFA and the haystack, `T`
+/// corresponds to the type of the DFA's transition table and `S` corresponds
+/// to its state identifier representation.
+#[derive(Clone, Debug)]
+pub struct AnchoredMatches<'a, T: 'a + AsRef<[S]>, S: 'a + StateID> {
+    dfa: &'a DenseDFA<T, S>,
+    bytes: &'a [u8],
+    pos: usize,
+}
 
-        // check that current endianness is same as endianness of DFA
-        let endian_check = NativeEndian::read_u16(buf);
-        buf = &buf[2..];
-        if endian_check != 0xFEFF {
-            panic!(
-                "endianness mismatch, expected 0xFEFF but got 0x{:X}. \
-                 are you trying to load a DenseDFA serialized with a \
-                 different endianness?",
-                endian_check,
-            );
-        }
+impl<'a, T: AsRef<[S]>, S: StateID> Iterator for AnchoredMatches<'a, T, S> {
+    type Item = bool;
 
-        // check that the version number is supported
-        let version = NativeEndian::read_u16(buf);
-        buf = &buf[2..];
-        if version != 1 {
-            panic!(
-                "expected version 1, but found unsupported version {}",
-                version,
-            );
+    fn next(&mut self) -> Option<bool> {
+        if self.pos > self.bytes.len() {
+            return None;
         }
+        let start = self.pos;
+        self.pos += 1;
 
-        // read size of state
-        let state_size = NativeEndian::read_u16(buf) as usize;
-        if state_size != mem::size_of::<S>() {
-            panic!(
-                "state size of DenseDFA ({}) does not match \
-                 requested state size ({})",
-                state_size,
-                mem::size_of::<S>(),
-            );
+        let mut state = self.dfa.start_state();
+        if self.dfa.is_match_or_dead_state(state) {
+            return Some(self.dfa.is_match_state(state));
         }
-        buf = &buf[2..];
-
-        // read miscellaneous options
-        let opts = NativeEndian::read_u16(buf);
-        buf = &buf[2..];
+        for &b in &self.bytes[start..] {
+            state = unsafe { self.dfa.next_state_unchecked(state, b) };
+            if self.dfa.is_match_or_dead_state(state) {
+                return Some(self.dfa.is_match_state(state));
+            }
+        }
+        Some(false)
+    }
 
-        // read start state
-        let start = S::from_usize(NativeEndian::read_u64(buf) as usize);
-        buf = &buf[8..];
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.bytes.len() + 1).saturating_sub(self.pos);
+        (remaining, Some(remaining))
+    }
+}
 
-        // read state count
-        let state_count = NativeEndian::read_u64(buf) as usize;
-        buf = &buf[8..];
+/// An iterator over the transitions out of a single state of a dense DFA for
+/// a range of bytes.
+///
+/// This iterator is created by
+/// [`DenseDFA::transitions_in_range`](enum.DenseDFA.html#method.transitions_in_range).
+///
+/// `'a` corresponds to the lifetime of the DFA, `T` corresponds to the type
+/// of its transition table and `S` corresponds to its state identifier
+/// representation.
+#[derive(Clone, Debug)]
+pub struct TransitionsInRange<'a, T: 'a + AsRef<[S]>, S: 'a + StateID> {
+    dfa: &'a DenseDFA<T, S>,
+    id: S,
+    /// The next byte to yield. This exceeds `hi` once iteration is done.
+    next: u16,
+    hi: u16,
+}
 
-        // read max match state
-        let max_match = S::from_usize(NativeEndian::read_u64(buf) as usize);
-        buf = &buf[8..];
+impl<'a, T: AsRef<[S]>, S: StateID> Iterator for TransitionsInRange<'a, T, S> {
+    type Item = (u8, S);
 
-        // read byte classes
-        let byte_classes = ByteClasses::from_slice(&buf[..256]);
-        buf = &buf[256..];
+    fn next(&mut self) -> Option<(u8, S)> {
+        if self.next > self.hi {
+            return None;
+        }
+        let byte = self.next as u8;
+        self.next += 1;
+        Some((byte, self.dfa.next_state(self.id, byte)))
+    }
 
-        let len = state_count * byte_classes.alphabet_len();
-        let len_bytes = len * state_size;
-        assert!(
-            buf.len() <= len_bytes,
-            "insufficient transition table bytes, \
-             expected at least {} but only have {}",
-            len_bytes,
-            buf.len()
-        );
-        assert_eq!(
-            0,
-            buf.as_ptr() as usize % mem::align_of::<S>(),
-            "DenseDFA transition table is not properly aligned"
-        );
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.hi + 1).saturating_sub(self.next) as usize;
+        (len, Some(len))
+    }
+}
 
-        // SAFETY: This is the only actual not-safe thing in this entire
-        // routine. The key things we need to worry about here are alignment
-        // and size. The two asserts above should cover both conditions.
-        let trans = slice::from_raw_parts(buf.as_ptr() as *const S, len);
+/// A standard dense DFA that does not use premultiplication or byte classes.
+///
+/// Generally, it isn't necessary to use this type directly, since a `DenseDFA`
+/// can be used for searching directly. One possible reason why one might want
+/// to use this type directly is if you are implementing your own search
+/// routines by walking a DFA's transitions directly. In that case, you'll want
+/// to use this type (or any of the other DFA variant types) directly, since
+/// they implement `next_state` more efficiently.
+#[derive(Clone, Debug)]
+pub struct Standard<T: AsRef<[S]>, S: StateID>(Repr<T, S>);
+
+impl<T: AsRef<[S]>, S: StateID> DFA for Standard<T, S> {
+    type ID = S;
+
+    #[inline]
+    fn start_state(&self) -> S {
+        self.0.start_state()
+    }
+
+    #[inline]
+    fn is_match_state(&self, id: S) -> bool {
+        self.0.is_match_state(id)
+    }
+
+    #[inline]
+    fn is_dead_state(&self, id: S) -> bool {
+        self.0.is_dead_state(id)
+    }
+
+    #[inline]
+    fn is_match_or_dead_state(&self, id: S) -> bool {
+        self.0.is_match_or_dead_state(id)
+    }
+
+    #[inline]
+    fn is_anchored(&self) -> bool {
+        self.0.is_anchored()
+    }
+
+    #[inline]
+    fn requires_full_match(&self) -> bool {
+        self.0.requires_full_match()
+    }
+
+    #[inline]
+    fn next_state(&self, current: S, input: u8) -> S {
+        let o = current.to_usize() * ALPHABET_LEN + input as usize;
+        self.0.trans()[o]
+    }
+
+    #[inline]
+    unsafe fn next_state_unchecked(&self, current: S, input: u8) -> S {
+        let o = current.to_usize() * ALPHABET_LEN + input as usize;
+        *self.0.trans().get_unchecked(o)
+    }
+
+    #[inline]
+    fn prefault(&self) {
+        self.0.prefault()
+    }
+
+    #[cfg(feature = "prefetch")]
+    #[inline(always)]
+    fn prefetch_transition(&self, current: S, input: u8) {
+        let o = current.to_usize() * ALPHABET_LEN + input as usize;
+        prefetch(self.0.trans(), o);
+    }
+}
+
+/// A dense DFA that shrinks its alphabet.
+///
+/// Alphabet shrinking is achieved by using a set of equivalence classes
+/// instead of using all possible byte values. Any two bytes belong to the same
+/// equivalence class if and only if they can be used interchangeably anywhere
+/// in the DFA while never discriminating between a match and a non-match.
+///
+/// This type of DFA can result in significant space reduction with a very
+/// small match time performance penalty.
+///
+/// Generally, it isn't necessary to use this type directly, since a `DenseDFA`
+/// can be used for searching directly. One possible reason why one might want
+/// to use this type directly is if you are implementing your own search
+/// routines by walking a DFA's transitions directly. In that case, you'll want
+/// to use this type (or any of the other DFA variant types) directly, since
+/// they implement `next_state` more efficiently.
+#[derive(Clone, Debug)]
+pub struct ByteClass<T: AsRef<[S]>, S: StateID>(Repr<T, S>);
+
+impl<T: AsRef<[S]>, S: StateID> DFA for ByteClass<T, S> {
+    type ID = S;
+
+    #[inline]
+    fn start_state(&self) -> S {
+        self.0.start_state()
+    }
+
+    #[inline]
+    fn is_match_state(&self, id: S) -> bool {
+        self.0.is_match_state(id)
+    }
+
+    #[inline]
+    fn is_dead_state(&self, id: S) -> bool {
+        self.0.is_dead_state(id)
+    }
+
+    #[inline]
+    fn is_match_or_dead_state(&self, id: S) -> bool {
+        self.0.is_match_or_dead_state(id)
+    }
+
+    #[inline]
+    fn is_anchored(&self) -> bool {
+        self.0.is_anchored()
+    }
+
+    #[inline]
+    fn requires_full_match(&self) -> bool {
+        self.0.requires_full_match()
+    }
+
+    #[inline]
+    fn next_state(&self, current: S, input: u8) -> S {
+        let input = self.0.byte_classes().get(input);
+        let o = current.to_usize() * self.0.alphabet_len() + input as usize;
+        self.0.trans()[o]
+    }
+
+    #[inline]
+    unsafe fn next_state_unchecked(&self, current: S, input: u8) -> S {
+        let input = self.0.byte_classes().get_unchecked(input);
+        let o = current.to_usize() * self.0.alphabet_len() + input as usize;
+        *self.0.trans().get_unchecked(o)
+    }
+
+    #[inline]
+    fn prefault(&self) {
+        self.0.prefault()
+    }
+
+    #[cfg(feature = "prefetch")]
+    #[inline(always)]
+    fn prefetch_transition(&self, current: S, input: u8) {
+        let input = self.0.byte_classes().get(input);
+        let o = current.to_usize() * self.0.alphabet_len() + input as usize;
+        prefetch(self.0.trans(), o);
+    }
+}
+
+/// A dense DFA that premultiplies all of its state identifiers in its
+/// transition table.
+///
+/// This saves an instruction per byte at match time which improves search
+/// performance.
+///
+/// The only downside of premultiplication is that it may prevent one from
+/// using a smaller state identifier representation than you otherwise could.
+///
+/// Generally, it isn't necessary to use this type directly, since a `DenseDFA`
+/// can be used for searching directly. One possible reason why one might want
+/// to use this type directly is if you are implementing your own search
+/// routines by walking a DFA's transitions directly. In that case, you'll want
+/// to use this type (or any of the other DFA variant types) directly, since
+/// they implement `next_state` more efficiently.
+#[derive(Clone, Debug)]
+pub struct Premultiplied<T: AsRef<[S]>, S: StateID>(Repr<T, S>);
+
+impl<T: AsRef<[S]>, S: StateID> DFA for Premultiplied<T, S> {
+    type ID = S;
+
+    #[inline]
+    fn start_state(&self) -> S {
+        self.0.start_state()
+    }
+
+    #[inline]
+    fn is_match_state(&self, id: S) -> bool {
+        self.0.is_match_state(id)
+    }
+
+    #[inline]
+    fn is_dead_state(&self, id: S) -> bool {
+        self.0.is_dead_state(id)
+    }
+
+    #[inline]
+    fn is_match_or_dead_state(&self, id: S) -> bool {
+        self.0.is_match_or_dead_state(id)
+    }
+
+    #[inline]
+    fn is_anchored(&self) -> bool {
+        self.0.is_anchored()
+    }
+
+    #[inline]
+    fn requires_full_match(&self) -> bool {
+        self.0.requires_full_match()
+    }
+
+    #[inline]
+    fn next_state(&self, current: S, input: u8) -> S {
+        let o = current.to_usize() + input as usize;
+        self.0.trans()[o]
+    }
+
+    #[inline]
+    unsafe fn next_state_unchecked(&self, current: S, input: u8) -> S {
+        let o = current.to_usize() + input as usize;
+        *self.0.trans().get_unchecked(o)
+    }
+
+    #[inline]
+    fn prefault(&self) {
+        self.0.prefault()
+    }
+
+    #[cfg(feature = "prefetch")]
+    #[inline(always)]
+    fn prefetch_transition(&self, current: S, input: u8) {
+        let o = current.to_usize() + input as usize;
+        prefetch(self.0.trans(), o);
+    }
+}
+
+/// The default configuration of a dense DFA, which uses byte classes and
+/// premultiplies its state identifiers.
+///
+/// Generally, it isn't necessary to use this type directly, since a `DenseDFA`
+/// can be used for searching directly. One possible reason why one might want
+/// to use this type directly is if you are implementing your own search
+/// routines by walking a DFA's transitions directly. In that case, you'll want
+/// to use this type (or any of the other DFA variant types) directly, since
+/// they implement `next_state` more efficiently.
+#[derive(Clone, Debug)]
+pub struct PremultipliedByteClass<T: AsRef<[S]>, S: StateID>(Repr<T, S>);
+
+impl<T: AsRef<[S]>, S: StateID> DFA for PremultipliedByteClass<T, S> {
+    type ID = S;
+
+    #[inline]
+    fn start_state(&self) -> S {
+        self.0.start_state()
+    }
+
+    #[inline]
+    fn is_match_state(&self, id: S) -> bool {
+        self.0.is_match_state(id)
+    }
+
+    #[inline]
+    fn is_dead_state(&self, id: S) -> bool {
+        self.0.is_dead_state(id)
+    }
+
+    #[inline]
+    fn is_match_or_dead_state(&self, id: S) -> bool {
+        self.0.is_match_or_dead_state(id)
+    }
+
+    #[inline]
+    fn is_anchored(&self) -> bool {
+        self.0.is_anchored()
+    }
+
+    #[inline]
+    fn requires_full_match(&self) -> bool {
+        self.0.requires_full_match()
+    }
+
+    #[inline]
+    fn next_state(&self, current: S, input: u8) -> S {
+        let input = self.0.byte_classes().get(input);
+        let o = current.to_usize() + input as usize;
+        self.0.trans()[o]
+    }
+
+    #[inline]
+    unsafe fn next_state_unchecked(&self, current: S, input: u8) -> S {
+        let input = self.0.byte_classes().get_unchecked(input);
+        let o = current.to_usize() + input as usize;
+        *self.0.trans().get_unchecked(o)
+    }
+
+    #[inline]
+    fn prefault(&self) {
+        self.0.prefault()
+    }
+
+    #[cfg(feature = "prefetch")]
+    #[inline(always)]
+    fn prefetch_transition(&self, current: S, input: u8) {
+        let input = self.0.byte_classes().get(input);
+        let o = current.to_usize() + input as usize;
+        prefetch(self.0.trans(), o);
+    }
+}
+
+/// A dense DFA whose transition table is stored in column-major order.
+///
+/// The transitions of every state on the same equivalence class are
+/// contiguous, so the transition out of the state with identifier `id` on a
+/// byte in class `c` is found at `c * state_count + id`. State identifiers
+/// are never premultiplied.
+///
+/// Generally, it isn't necessary to use this type directly, since a `DenseDFA`
+/// can be used for searching directly. One possible reason why one might want
+/// to use this type directly is if you are implementing your own search
+/// routines by walking a DFA's transitions directly. In that case, you'll want
+/// to use this type (or any of the other DFA variant types) directly, since
+/// they implement `next_state` more efficiently.
+#[derive(Clone, Debug)]
+pub struct ColumnMajor<T: AsRef<[S]>, S: StateID>(Repr<T, S>);
+
+impl<T: AsRef<[S]>, S: StateID> DFA for ColumnMajor<T, S> {
+    type ID = S;
+
+    #[inline]
+    fn start_state(&self) -> S {
+        self.0.start_state()
+    }
+
+    #[inline]
+    fn is_match_state(&self, id: S) -> bool {
+        self.0.is_match_state(id)
+    }
+
+    #[inline]
+    fn is_dead_state(&self, id: S) -> bool {
+        self.0.is_dead_state(id)
+    }
+
+    #[inline]
+    fn is_match_or_dead_state(&self, id: S) -> bool {
+        self.0.is_match_or_dead_state(id)
+    }
+
+    #[inline]
+    fn is_anchored(&self) -> bool {
+        self.0.is_anchored()
+    }
+
+    #[inline]
+    fn requires_full_match(&self) -> bool {
+        self.0.requires_full_match()
+    }
+
+    #[inline]
+    fn next_state(&self, current: S, input: u8) -> S {
+        let input = self.0.byte_classes().get(input);
+        let o = input as usize * self.0.state_count + current.to_usize();
+        self.0.trans()[o]
+    }
+
+    #[inline]
+    unsafe fn next_state_unchecked(&self, current: S, input: u8) -> S {
+        let input = self.0.byte_classes().get_unchecked(input);
+        let o = input as usize * self.0.state_count + current.to_usize();
+        *self.0.trans().get_unchecked(o)
+    }
+
+    #[inline]
+    fn prefault(&self) {
+        self.0.prefault()
+    }
+
+    #[cfg(feature = "prefetch")]
+    #[inline(always)]
+    fn prefetch_transition(&self, current: S, input: u8) {
+        let input = self.0.byte_classes().get(input);
+        let o = input as usize * self.0.state_count + current.to_usize();
+        prefetch(self.0.trans(), o);
+    }
+}
+
+/// Hint to the CPU that `trans[index]` will be read soon, if the target
+/// supports it. The index is never dereferenced, so it may be out of bounds.
+#[cfg(feature = "prefetch")]
+#[inline(always)]
+fn prefetch<S>(trans: &[S], index: usize) {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        use core::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+        let ptr = trans.as_ptr().wrapping_add(index);
+        _mm_prefetch(ptr as *const i8, _MM_HINT_T0);
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        let _ = (trans, index);
+    }
+}
+
+/// The internal representation of a dense DFA.
+///
+/// This representation is shared by all DFA variants.
+#[derive(Clone)]
+#[cfg_attr(not(feature = "std"), derive(Debug))]
+pub(crate) struct Repr<T, S> {
+    /// Whether the state identifiers in the transition table have been
+    /// premultiplied or not.
+    ///
+    /// Premultiplied identifiers means that instead of your matching loop
+    /// looking something like this:
+    ///
+    ///   state = dfa.start
+    ///   for byte in haystack:
+    ///       next = dfa.transitions[state * len(alphabet) + byte]
+    ///       if dfa.is_match(next):
+    ///           return true
+    ///   return false
+    ///
+    /// it can instead look like this:
+    ///
+    ///   state = dfa.start
+    ///   for byte in haystack:
+    ///       next = dfa.transitions[state + byte]
+    ///       if dfa.is_match(next):
+    ///           return true
+    ///   return false
+    ///
+    /// In other words, we save a multiplication instruction in the critical
+    /// path. This turns out to be a decent performance win. The cost of using
+    /// premultiplied state ids is that they can require a bigger state id
+    /// representation.
+    premultiplied: bool,
+    /// Whether this DFA can only match at the beginning of input or not.
+    ///
+    /// When true, a match should only be reported if it begins at the 0th
+    /// index of the haystack.
+    anchored: bool,
+    /// Whether a match must also end at the last index of the haystack.
+    ///
+    /// When true, searches walk the entire haystack and only report a match
+    /// if they finish in a match state. This is always anchored.
+    full_match: bool,
+    /// Whether the transition table is stored in column-major order instead
+    /// of row-major order.
+    ///
+    /// When true, the transition out of the state with index `i` on class
+    /// `c` is at `c * state_count + i`, and state identifiers are never
+    /// premultiplied.
+    column_major: bool,
+    /// The match states of this DFA, if they weren't shuffled to the front of
+    /// the transition table (see `Builder::shuffle_match_states`). In that
+    /// case, `max_match` is the last state, so that the single comparison
+    /// made for every byte of a search can't rule out a match, and this set
+    /// decides whether a state is a match state.
+    #[cfg(feature = "std")]
+    match_set: Option<MatchSet>,
+    /// The initial start state ID.
+    start: S,
+    /// The total number of states in this DFA. Note that a DFA always has at
+    /// least one state---the dead state---even the empty DFA. In particular,
+    /// the dead state always has ID 0 and is correspondingly always the first
+    /// state. The dead state is never a match state.
+    state_count: usize,
+    /// States in a DFA have a *partial* ordering such that a match state
+    /// always precedes any non-match state (except for the special dead
+    /// state).
+    ///
+    /// `max_match` corresponds to the last state that is a match state. This
+    /// encoding has two critical benefits. Firstly, we are not required to
+    /// store any additional per-state information about whether it is a match
+    /// state or not. Secondly, when searching with the DFA, we can do a single
+    /// comparison with `max_match` for each byte instead of two comparisons
+    /// for each byte (one testing whether it is a match and the other testing
+    /// whether we've reached a dead state). Namely, to determine the status
+    /// of the next state, we can do this:
+    ///
+    ///   next_state = transition[cur_state * alphabet_len + cur_byte]
+    ///   if next_state <= max_match:
+    ///       // next_state is either dead (no-match) or a match
+    ///       return next_state != dead
+    max_match: S,
+    /// A set of equivalence classes, where a single equivalence class
+    /// represents a set of bytes that never discriminate between a match
+    /// and a non-match in the DFA. Each equivalence class corresponds to
+    /// a single letter in this DFA's alphabet, where the maximum number of
+    /// letters is 256 (each possible value of a byte). Consequently, the
+    /// number of equivalence classes corresponds to the number of transitions
+    /// for each DFA state.
+    ///
+    /// The only time the number of equivalence classes is fewer than 256 is
+    /// if the DFA's kind uses byte classes. If the DFA doesn't use byte
+    /// classes, then this vector is empty.
+    byte_classes: ByteClasses,
+    /// A contiguous region of memory representing the transition table in
+    /// row-major order, unless `column_major` is set. The representation is
+    /// dense. That is, every state has
+    /// precisely the same number of transitions. The maximum number of
+    /// transitions is 256. If a DFA has been instructed to use byte classes,
+    /// then the number of transitions can be much less.
+    ///
+    /// In practice, T is either Vec<S> or &[S].
+    trans: T,
+    /// A literal that every match of this DFA must begin with (or, for a
+    /// reverse DFA, end with). When non-empty, unanchored searches skip ahead
+    /// to the first occurrence of this literal in the direction of the
+    /// search before walking the transition table. This is not serialized.
+    prefix: Prefix,
+    /// The bytes on which the start state transitions somewhere other than
+    /// back to itself, if acceleration is enabled. Forward searches use this
+    /// to skip over bytes while in the start state. Only whether
+    /// acceleration is enabled is serialized, since the bytes can be
+    /// recomputed cheaply from the start state's transitions.
+    accel: Accel,
+    /// The bytes that searches step over without following a transition
+    /// (see `Builder::skip_bytes`). This is empty unless skip bytes were
+    /// set when the DFA was built.
+    skip: SkipBytes,
+}
+
+/// The match states of a DFA whose match states weren't shuffled to the
+/// front of its transition table, as a bitset indexed by state index.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct MatchSet(Arc<[u64]>);
+
+#[cfg(feature = "std")]
+impl MatchSet {
+    /// Create a set containing every state index for which `is_match` is
+    /// true.
+    fn new(is_match: &[bool]) -> MatchSet {
+        let mut bits = vec![0u64; MatchSet::words(is_match.len())];
+        for (index, _) in is_match.iter().enumerate().filter(|&(_, &m)| m) {
+            bits[index / 64] |= 1 << (index % 64);
+        }
+        MatchSet(Arc::from(bits))
+    }
+
+    /// Returns the number of 64-bit words in the set of a DFA with the given
+    /// number of states.
+    fn words(state_count: usize) -> usize {
+        state_count / 64 + 1
+    }
+
+    /// Create a set from its words, as written by `write_header` in the
+    /// given byte order.
+    fn from_bytes<A: ByteOrder>(bytes: &[u8]) -> MatchSet {
+        let bits: Vec<u64> = bytes.chunks(8).map(A::read_u64).collect();
+        MatchSet(Arc::from(bits))
+    }
+
+    /// Returns true if and only if the state with the given index is in this
+    /// set.
+    #[inline(always)]
+    fn contains(&self, index: usize) -> bool {
+        self.0[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    /// Create the set of the states that the states in this set are moved to
+    /// by `remap`, which maps old state indices to new ones, in a DFA with
+    /// the given number of states.
+    fn remap(&self, remap: &[usize], count: usize) -> MatchSet {
+        let mut is_match = vec![false; count];
+        for (old, &new) in remap.iter().enumerate() {
+            if self.contains(old) {
+                is_match[new] = true;
+            }
+        }
+        MatchSet::new(&is_match)
+    }
+}
+
+/// An error that occurs when a DFA fails
+/// [`DenseDFA::validate`](../enum.DenseDFA.html#method.validate).
+///
+/// Its `Display` implementation describes which invariant of the DFA was
+/// violated. Unlike [`Error`](../struct.Error.html), this is available
+/// without the `std` feature. When the `std` feature is enabled, it converts
+/// into an `Error` of kind
+/// [`ErrorKind::Deserialize`](../enum.ErrorKind.html#variant.Deserialize).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ValidationError(Invalid);
+
+/// The invariant that a DFA failing validation violates.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Invalid {
+    ByteClass(u8),
+    NoDeadState,
+    ColumnMajorPremultiplied,
+    TableLength { len: usize, state_count: usize, alphabet_len: usize },
+    Start,
+    MaxMatch,
+    Transition { index: usize, id: usize },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Invalid::ByteClass(b) => {
+                write!(f, "byte {} maps to a class outside of the alphabet", b)
+            }
+            Invalid::NoDeadState => write!(f, "DFA has no dead state"),
+            Invalid::ColumnMajorPremultiplied => {
+                write!(f, "column-major DFA is premultiplied")
+            }
+            Invalid::TableLength { len, state_count, alphabet_len } => write!(
+                f,
+                "transition table has length {} but {} states with an \
+                 alphabet of {} were expected",
+                len, state_count, alphabet_len,
+            ),
+            Invalid::Start => write!(f, "start state is invalid"),
+            Invalid::MaxMatch => write!(f, "max match state is invalid"),
+            Invalid::Transition { index, id } => write!(
+                f,
+                "transition at index {} points to invalid state {}",
+                index, id,
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for ValidationError {}
+
+#[cfg(feature = "std")]
+impl From<ValidationError> for Error {
+    fn from(err: ValidationError) -> Error {
+        Error::deserialize(&err.to_string())
+    }
+}
+
+/// The maximum number of bytes retained from a DFA's required literal
+/// prefix. Longer prefixes are truncated, which is always correct since any
+/// prefix of a required prefix is also required.
+const PREFIX_MAX_LEN: usize = 16;
+
+/// A short literal that every match of a DFA must begin with.
+///
+/// For reverse DFAs, this is instead a literal that every match must end
+/// with, and it is only consulted by reverse searches. In both cases, the
+/// literal is stored in the order in which it appears in the haystack.
+///
+/// Searching for the literal uses Knuth-Morris-Pratt, so that it takes time
+/// linear in the length of the haystack no matter how often a partial match
+/// of the literal occurs. Whenever nothing is partially matched, the search
+/// skips ahead to the next occurrence of the literal's first byte.
+///
+/// Unless the DFA was built with invalid UTF-8 allowed, its unanchored
+/// prefix dies on the first byte that doesn't continue valid UTF-8. So in
+/// that case, the bytes skipped over to reach the literal must be valid
+/// UTF-8, or else the DFA wouldn't have reached it either.
+///
+/// This is stored inline (instead of in a `Vec<u8>`) so that it's available
+/// in `no_std` environments and doesn't depend on the type of the transition
+/// table.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct Prefix {
+    len: u8,
+    reverse: bool,
+    /// Whether the bytes skipped by a search must be valid UTF-8.
+    utf8: bool,
+    bytes: [u8; PREFIX_MAX_LEN],
+    /// The KMP failure function, in search order. That is, `failure[i]` is
+    /// the length of the longest proper prefix of the first `i + 1` bytes
+    /// of the search order literal that is also a suffix of them. For a
+    /// reverse DFA, the search order literal is the stored one reversed.
+    failure: [u8; PREFIX_MAX_LEN],
+}
+
+impl Prefix {
+    /// An empty prefix, which disables the prefilter.
+    fn none() -> Prefix {
+        Prefix {
+            len: 0,
+            reverse: false,
+            utf8: false,
+            bytes: [0; PREFIX_MAX_LEN],
+            failure: [0; PREFIX_MAX_LEN],
+        }
+    }
+
+    /// Create a prefix from the given literal, truncating it if necessary.
+    #[cfg(feature = "std")]
+    fn new(literal: &[u8], utf8: bool) -> Prefix {
+        let len = cmp::min(literal.len(), PREFIX_MAX_LEN);
+        Prefix::from_literal(&literal[..len], false, utf8)
+    }
+
+    /// Create a suffix for a reverse DFA from the given literal, keeping
+    /// only its tail if it's too long.
+    #[cfg(feature = "std")]
+    fn new_reverse(literal: &[u8], utf8: bool) -> Prefix {
+        let start = literal.len().saturating_sub(PREFIX_MAX_LEN);
+        Prefix::from_literal(&literal[start..], true, utf8)
+    }
+
+    /// Create a prefix (or suffix) from a literal of at most
+    /// `PREFIX_MAX_LEN` bytes and compute its failure function.
+    #[cfg(feature = "std")]
+    fn from_literal(literal: &[u8], reverse: bool, utf8: bool) -> Prefix {
+        let mut prefix = Prefix::none();
+        prefix.bytes[..literal.len()].copy_from_slice(literal);
+        prefix.len = literal.len() as u8;
+        prefix.reverse = reverse;
+        prefix.utf8 = utf8;
+        let mut k = 0;
+        for i in 1..literal.len() {
+            let b = prefix.nth(i);
+            while k > 0 && prefix.nth(k) != b {
+                k = prefix.failure[k - 1] as usize;
+            }
+            if prefix.nth(k) == b {
+                k += 1;
+            }
+            prefix.failure[i] = k as u8;
+        }
+        prefix
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+
+    /// Returns the `i`th byte of this literal in search order, i.e., in
+    /// reverse for a reverse DFA.
+    #[inline(always)]
+    fn nth(&self, i: usize) -> u8 {
+        if self.reverse {
+            self.bytes[self.len as usize - 1 - i]
+        } else {
+            self.bytes[i]
+        }
+    }
+
+    /// Given that `matched` bytes of this literal (in search order) were
+    /// matched just before `b`, return the number matched including `b`.
+    #[inline(always)]
+    fn step(&self, mut matched: usize, b: u8) -> usize {
+        while matched > 0 && self.nth(matched) != b {
+            matched = self.failure[matched - 1] as usize;
+        }
+        if self.nth(matched) == b {
+            matched + 1
+        } else {
+            0
+        }
+    }
+
+    /// Returns the position of the first occurrence of this prefix in
+    /// `haystack` at or after `start`. If this prefix is empty or belongs to
+    /// a reverse DFA, then `start` is always returned. If the bytes before
+    /// the occurrence must be valid UTF-8 and aren't, then `None` is
+    /// returned.
+    #[inline]
+    fn find(&self, haystack: &[u8], start: usize) -> Option<usize> {
+        let len = self.len as usize;
+        if len == 0 || self.reverse {
+            return Some(start);
+        }
+        let first = self.nth(0);
+        let (mut at, mut matched) = (start, 0);
+        while at < haystack.len() {
+            if matched == 0 {
+                match haystack[at..].iter().position(|&b| b == first) {
+                    None => return None,
+                    Some(i) => at += i,
+                }
+            }
+            matched = self.step(matched, haystack[at]);
+            at += 1;
+            if matched == len {
+                return self
+                    .check_skipped(&haystack[start..at - len], at - len);
+            }
+        }
+        None
+    }
+
+    /// Returns the position just past the last occurrence of this suffix in
+    /// `haystack[..end]`. If this suffix is empty or doesn't belong to a
+    /// reverse DFA, then `end` is always returned. If the bytes after the
+    /// occurrence must be valid UTF-8 and aren't, then `None` is returned.
+    #[inline]
+    fn rfind(&self, haystack: &[u8], end: usize) -> Option<usize> {
+        let len = self.len as usize;
+        if len == 0 || !self.reverse {
+            return Some(end);
+        }
+        let last = self.nth(0);
+        let (mut at, mut matched) = (end, 0);
+        while at > 0 {
+            if matched == 0 {
+                match haystack[..at].iter().rposition(|&b| b == last) {
+                    None => return None,
+                    Some(i) => at = i + 1,
+                }
+            }
+            at -= 1;
+            matched = self.step(matched, haystack[at]);
+            if matched == len {
+                return self.check_skipped(&haystack[at + len..end], at + len);
+            }
+        }
+        None
+    }
+
+    /// Returns `Some(at)` unless the given skipped bytes must be valid UTF-8
+    /// and aren't.
+    #[inline(always)]
+    fn check_skipped(&self, skipped: &[u8], at: usize) -> Option<usize> {
+        if self.utf8 && str::from_utf8(skipped).is_err() {
+            None
+        } else {
+            Some(at)
+        }
+    }
+}
+
+/// The maximum number of bytes on which a start state may leave itself for
+/// acceleration to be used. Beyond this, bytes that end the skip are common
+/// enough that checking for them costs more than it saves. This leaves room
+/// for the 128 non-ASCII bytes, on which the start state of an unanchored DFA
+/// never loops back to itself, since its implicit `.*?` prefix only matches
+/// valid UTF-8.
+const ACCEL_MAX_BYTES: usize = 160;
+
+/// The set of bytes that take a DFA out of its start state.
+///
+/// When a forward search is in the start state of an unanchored DFA, every
+/// byte not in this set leads straight back to the start state. So instead
+/// of following those transitions one at a time, the search can scan for the
+/// next byte in this set using a 256-bit table, which needs no dependent
+/// memory loads and is much faster than walking the transition table.
+///
+/// Like `Prefix`, this is stored inline so that it's available in `no_std`
+/// environments.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct Accel {
+    enabled: bool,
+    escapes: [u8; 32],
+}
+
+impl Accel {
+    /// Acceleration that is disabled.
+    fn none() -> Accel {
+        Accel { enabled: false, escapes: [0; 32] }
+    }
+
+    /// Compute the acceleration for the start state of the given DFA. If the
+    /// DFA is anchored, if its start state is a match or dead state, or if
+    /// the start state leaves itself on too many bytes, then acceleration is
+    /// disabled.
+    fn new<T: AsRef<[S]>, S: StateID>(dfa: &Repr<T, S>) -> Accel {
+        let start = dfa.start_state();
+        if dfa.anchored || dfa.is_match_or_dead_state(start) {
+            return Accel::none();
+        }
+        let index = if dfa.premultiplied {
+            start.to_usize() / dfa.alphabet_len()
+        } else {
+            start.to_usize()
+        };
+        let mut accel = Accel { enabled: true, escapes: [0; 32] };
+        let mut count = 0;
+        for b in 0..256 {
+            let class = dfa.byte_classes().get(b as u8) as usize;
+            if dfa.transition(index, class) != start {
+                accel.escapes[b / 8] |= 1 << (b % 8);
+                count += 1;
+            }
+        }
+        if count > ACCEL_MAX_BYTES {
+            return Accel::none();
+        }
+        accel
+    }
+
+    /// Returns true if and only if the given byte takes the DFA out of its
+    /// start state.
+    #[inline(always)]
+    fn escapes(&self, b: u8) -> bool {
+        self.escapes[b as usize / 8] & (1 << (b % 8)) != 0
+    }
+
+    /// Returns the position of the first byte at or after `at` that takes the
+    /// DFA out of its start state, or the length of the haystack if there is
+    /// no such byte.
+    #[inline(always)]
+    fn skip(&self, haystack: &[u8], at: usize) -> usize {
+        match haystack[at..].iter().position(|&b| self.escapes(b)) {
+            None => haystack.len(),
+            Some(i) => at + i,
+        }
+    }
+}
+
+/// The bytes that the search routines of a dense DFA step over without
+/// following a transition. See `Builder::skip_bytes`.
+///
+/// Like `Accel`, this is a 256-bit table stored inline, so that checking
+/// whether a byte is skipped needs no memory loads beyond the table itself.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct SkipBytes([u8; 32]);
+
+impl SkipBytes {
+    /// A set that skips no bytes.
+    fn none() -> SkipBytes {
+        SkipBytes([0; 32])
+    }
+
+    /// Create a set that skips each of the given bytes.
+    #[cfg(feature = "std")]
+    fn new(bytes: &[u8]) -> SkipBytes {
+        let mut set = SkipBytes::none();
+        for &b in bytes {
+            set.0[b as usize / 8] |= 1 << (b % 8);
+        }
+        set
+    }
+
+    /// Create a set from the 32 bytes of its table, as written by
+    /// `write_header`.
+    fn from_slice(slice: &[u8]) -> SkipBytes {
+        let mut set = SkipBytes::none();
+        set.0.copy_from_slice(&slice[..32]);
+        set
+    }
+
+    /// Returns true if and only if no bytes are skipped.
+    fn is_empty(&self) -> bool {
+        self.0.iter().all(|&bits| bits == 0)
+    }
+
+    /// Returns true if and only if the given byte is skipped.
+    #[inline(always)]
+    fn contains(&self, b: u8) -> bool {
+        self.0[b as usize / 8] & (1 << (b % 8)) != 0
+    }
+
+    /// Returns the position of the first byte at or after `at` that isn't
+    /// skipped, or the length of the haystack if there is no such byte.
+    #[inline(always)]
+    fn skip(&self, haystack: &[u8], at: usize) -> usize {
+        match haystack[at..].iter().position(|&b| !self.contains(b)) {
+            None => haystack.len(),
+            Some(i) => at + i,
+        }
+    }
+
+    /// Returns the end of the last byte before `at` that isn't skipped, or
+    /// `0` if there is no such byte.
+    #[inline(always)]
+    fn rskip(&self, haystack: &[u8], at: usize) -> usize {
+        match haystack[..at].iter().rposition(|&b| !self.contains(b)) {
+            None => 0,
+            Some(i) => i + 1,
+        }
+    }
+}
+
+// The following routines are used in place of the `DFA` trait's default
+// search routines when acceleration is enabled or when bytes are skipped.
+// They are the same, except that before each transition, they step over skip
+// bytes and, whenever the search is in the start state, skip ahead to the
+// next byte that leaves it.
+
+#[inline(always)]
+fn skipping_is_match_at<D: DFA>(
+    dfa: &D,
+    accel: &Accel,
+    skip: &SkipBytes,
+    bytes: &[u8],
+    start: usize,
+) -> bool {
+    if dfa.is_anchored() && start > 0 {
+        return false;
+    }
+    let mut state = dfa.start_state();
+    if dfa.is_match_or_dead_state(state) {
+        return dfa.is_match_state(state);
+    }
+    match skipping_run_to_match_or_dead(
+        dfa, accel, skip, &mut state, bytes, start,
+    ) {
+        None => false,
+        Some(_) => dfa.is_match_state(state),
+    }
+}
+
+#[inline(always)]
+fn skipping_shortest_match_at<D: DFA>(
+    dfa: &D,
+    accel: &Accel,
+    skip: &SkipBytes,
+    bytes: &[u8],
+    start: usize,
+) -> Option<usize> {
+    if dfa.is_anchored() && start > 0 {
+        return None;
+    }
+    let mut state = dfa.start_state();
+    if dfa.is_match_or_dead_state(state) {
+        return if dfa.is_dead_state(state) { None } else { Some(start) };
+    }
+    let end = skipping_run_to_match_or_dead(
+        dfa, accel, skip, &mut state, bytes, start,
+    )?;
+    if dfa.is_dead_state(state) {
+        None
+    } else {
+        Some(end)
+    }
+}
+
+#[inline(always)]
+fn skipping_find_at<D: DFA>(
+    dfa: &D,
+    accel: &Accel,
+    skip: &SkipBytes,
+    bytes: &[u8],
+    start: usize,
+) -> Option<usize> {
+    if dfa.is_anchored() && start > 0 {
+        return None;
+    }
+    let mut state = dfa.start_state();
+    let mut last_match = if dfa.is_dead_state(state) {
+        return None;
+    } else if dfa.is_match_state(state) {
+        Some(start)
+    } else {
+        None
+    };
+    let mut at = start;
+    while let Some(end) =
+        skipping_run_to_match_or_dead(dfa, accel, skip, &mut state, bytes, at)
+    {
+        if dfa.is_dead_state(state) {
+            return last_match;
+        }
+        last_match = Some(end);
+        at = end;
+    }
+    last_match
+}
+
+/// Like `skipping_find_at`, but searches in reverse. Acceleration only
+/// applies to forward searches, so only skip bytes are stepped over.
+#[inline(always)]
+fn skipping_rfind_at<D: DFA>(
+    dfa: &D,
+    skip: &SkipBytes,
+    bytes: &[u8],
+    start: usize,
+) -> Option<usize> {
+    if dfa.is_anchored() && start < bytes.len() {
+        return None;
+    }
+    let mut state = dfa.start_state();
+    let mut last_match = if dfa.is_dead_state(state) {
+        return None;
+    } else if dfa.is_match_state(state) {
+        Some(start)
+    } else {
+        None
+    };
+    let mut at = skip.rskip(bytes, start);
+    while at > 0 {
+        at -= 1;
+        state = unsafe { dfa.next_state_unchecked(state, bytes[at]) };
+        if dfa.is_match_or_dead_state(state) {
+            if dfa.is_dead_state(state) {
+                return last_match;
+            }
+            last_match = Some(at);
+        }
+        at = skip.rskip(bytes, at);
+    }
+    last_match
+}
+
+/// Advance the given DFA from `state` over `bytes[at..]` with the search
+/// loop shared by every DFA, stepping over skip bytes and skipping ahead
+/// with `accel` whenever it is in its start state.
+#[inline(always)]
+fn skipping_run_to_match_or_dead<D: DFA>(
+    dfa: &D,
+    accel: &Accel,
+    skip: &SkipBytes,
+    state: &mut D::ID,
+    bytes: &[u8],
+    at: usize,
+) -> Option<usize> {
+    let start_state = dfa.start_state();
+    run_to_match_or_dead_skipping(dfa, state, bytes, at, |cur, mut at| loop {
+        if accel.enabled && cur == start_state {
+            at = accel.skip(bytes, at);
+        }
+        // A byte that leaves the start state may itself be skipped, in which
+        // case acceleration resumes after it.
+        let next = skip.skip(bytes, at);
+        if next == at {
+            return at;
+        }
+        at = next;
+    })
+}
+
+#[cfg(feature = "std")]
+impl<S: StateID> Repr<Vec<S>, S> {
+    /// Create a new empty DFA with singleton byte classes (every byte is its
+    /// own equivalence class).
+    pub fn empty() -> Repr<Vec<S>, S> {
+        Repr::empty_with_byte_classes(ByteClasses::singletons())
+    }
+
+    fn into_shared(self) -> Repr<Arc<[S]>, S> {
+        Repr {
+            premultiplied: self.premultiplied,
+            anchored: self.anchored,
+            full_match: self.full_match,
+            column_major: self.column_major,
+            match_set: self.match_set.clone(),
+            start: self.start,
+            state_count: self.state_count,
+            max_match: self.max_match,
+            byte_classes: self.byte_classes,
+            prefix: self.prefix,
+            accel: self.accel,
+            skip: self.skip,
+            trans: Arc::from(self.trans),
+        }
+    }
+
+    /// Reserve capacity in the transition table for at least the given number
+    /// of additional states.
+    pub fn reserve_states(&mut self, additional: usize) {
+        let len = additional.saturating_mul(self.alphabet_len());
+        self.trans.reserve(len);
+    }
+
+    /// Create a new empty DFA with the given set of byte equivalence classes.
+    /// An empty DFA never matches any input.
+    pub fn empty_with_byte_classes(
+        byte_classes: ByteClasses,
+    ) -> Repr<Vec<S>, S> {
+        let mut dfa = Repr {
+            premultiplied: false,
+            anchored: true,
+            full_match: false,
+            column_major: false,
+            match_set: None,
+            start: dead_id(),
+            state_count: 0,
+            max_match: S::from_usize(0),
+            byte_classes,
+            prefix: Prefix::none(),
+            accel: Accel::none(),
+            skip: SkipBytes::none(),
+            trans: vec![],
+        };
+        // Every state ID repr must be able to fit at least one state.
+        dfa.add_empty_state().unwrap();
+        dfa
+    }
+
+    /// Sets whether this DFA is anchored or not.
+    pub fn anchored(mut self, yes: bool) -> Repr<Vec<S>, S> {
+        self.anchored = yes;
+        self
+    }
+}
+
+impl<T: AsRef<[S]>, S: StateID> Repr<T, S> {
+    /// Convert this internal DFA representation to a DenseDFA based on its
+    /// transition table access pattern.
+    pub fn into_dense_dfa(self) -> DenseDFA<T, S> {
+        if self.column_major {
+            return DenseDFA::ColumnMajor(ColumnMajor(self));
+        }
+        match (self.premultiplied, self.byte_classes().is_singleton()) {
+            // no premultiplication, no byte classes
+            (false, true) => DenseDFA::Standard(Standard(self)),
+            // no premultiplication, yes byte classes
+            (false, false) => DenseDFA::ByteClass(ByteClass(self)),
+            // yes premultiplication, no byte classes
+            (true, true) => DenseDFA::Premultiplied(Premultiplied(self)),
+            // yes premultiplication, yes byte classes
+            (true, false) => {
+                DenseDFA::PremultipliedByteClass(PremultipliedByteClass(self))
+            }
+        }
+    }
+
+    fn as_ref<'a>(&'a self) -> Repr<&'a [S], S> {
         Repr {
+            premultiplied: self.premultiplied,
+            anchored: self.anchored,
+            full_match: self.full_match,
+            column_major: self.column_major,
+            #[cfg(feature = "std")]
+            match_set: self.match_set.clone(),
+            start: self.start,
+            state_count: self.state_count,
+            max_match: self.max_match,
+            byte_classes: self.byte_classes().clone(),
+            prefix: self.prefix,
+            accel: self.accel,
+            skip: self.skip,
+            trans: self.trans(),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn to_owned(&self) -> Repr<Vec<S>, S> {
+        Repr {
+            premultiplied: self.premultiplied,
+            anchored: self.anchored,
+            full_match: self.full_match,
+            column_major: self.column_major,
+            match_set: self.match_set.clone(),
+            start: self.start,
+            state_count: self.state_count,
+            max_match: self.max_match,
+            byte_classes: self.byte_classes().clone(),
+            prefix: self.prefix,
+            accel: self.accel,
+            skip: self.skip,
+            trans: self.trans().to_vec(),
+        }
+    }
+
+    fn validate(&self) -> result::Result<(), ValidationError> {
+        let alphabet_len = self.alphabet_len();
+        for b in 0..256 {
+            if self.byte_classes().get(b as u8) as usize >= alphabet_len {
+                return Err(ValidationError(Invalid::ByteClass(b as u8)));
+            }
+        }
+        if self.state_count == 0 {
+            return Err(ValidationError(Invalid::NoDeadState));
+        }
+        if self.column_major && self.premultiplied {
+            return Err(ValidationError(Invalid::ColumnMajorPremultiplied));
+        }
+        match self.state_count.checked_mul(alphabet_len) {
+            Some(len) if len == self.trans().len() => {}
+            _ => {
+                return Err(ValidationError(Invalid::TableLength {
+                    len: self.trans().len(),
+                    state_count: self.state_count,
+                    alphabet_len,
+                }));
+            }
+        }
+
+        let is_valid = |id: S| {
+            let id = id.to_usize();
+            if self.premultiplied {
+                let (row, col) = (id / alphabet_len, id % alphabet_len);
+                col == 0 && row < self.state_count
+            } else {
+                id < self.state_count
+            }
+        };
+        if !is_valid(self.start) {
+            return Err(ValidationError(Invalid::Start));
+        }
+        if !is_valid(self.max_match) {
+            return Err(ValidationError(Invalid::MaxMatch));
+        }
+        for (index, &id) in self.trans().iter().enumerate() {
+            if !is_valid(id) {
+                let id = id.to_usize();
+                return Err(ValidationError(Invalid::Transition {
+                    index,
+                    id,
+                }));
+            }
+        }
+        Ok(())
+    }
+
+    /// Return the starting state of this DFA.
+    ///
+    /// All searches using this DFA must begin at this state. There is exactly
+    /// one starting state for every DFA. A starting state may be a dead state
+    /// or a matching state or neither.
+    pub fn start_state(&self) -> S {
+        self.start
+    }
+
+    /// Returns true if and only if the given identifier corresponds to a match
+    /// state.
+    pub fn is_match_state(&self, id: S) -> bool {
+        id <= self.max_match && id != dead_id() && self.in_match_set(id)
+    }
+
+    /// Returns true if and only if the given identifier corresponds to a dead
+    /// state.
+    pub fn is_dead_state(&self, id: S) -> bool {
+        id == dead_id()
+    }
+
+    /// Returns true if and only if the given identifier could correspond to
+    /// either a match state or a dead state. If this returns false, then the
+    /// given identifier does not correspond to either a match state or a dead
+    /// state.
+    pub fn is_match_or_dead_state(&self, id: S) -> bool {
+        id <= self.max_match_state()
+            && (id == dead_id() || self.in_match_set(id))
+    }
+
+    /// Returns true if and only if the given state, which must not come after
+    /// `max_match`, is in this DFA's match set. This is always true unless
+    /// match states weren't shuffled to the front of the DFA.
+    #[cfg(feature = "std")]
+    #[inline(always)]
+    fn in_match_set(&self, id: S) -> bool {
+        match self.match_set {
+            None => true,
+            Some(ref set) => set.contains(self.state_id_to_index(id)),
+        }
+    }
+
+    /// Returns true if and only if the given state, which must not come after
+    /// `max_match`, is in this DFA's match set. Without `std`, match states
+    /// are always shuffled to the front of the DFA, so this is always true.
+    #[cfg(not(feature = "std"))]
+    #[inline(always)]
+    fn in_match_set(&self, _: S) -> bool {
+        true
+    }
+
+    /// Returns true if and only if the state with the given index is a match
+    /// state.
+    #[cfg(feature = "std")]
+    fn is_match_index(&self, index: usize) -> bool {
+        if index == 0 || index > self.state_id_to_index(self.max_match) {
+            return false;
+        }
+        match self.match_set {
+            None => true,
+            Some(ref set) => set.contains(index),
+        }
+    }
+
+    /// Returns the maximum identifier for which a match state can exist.
+    ///
+    /// More specifically, the return identifier always corresponds to either
+    /// a match state or a dead state. Namely, either
+    /// `is_match_state(returned)` or `is_dead_state(returned)` is guaranteed
+    /// to be true.
+    pub fn max_match_state(&self) -> S {
+        self.max_match
+    }
+
+    /// Returns true if and only if this DFA is anchored.
+    pub fn is_anchored(&self) -> bool {
+        self.anchored
+    }
+
+    /// Returns true if and only if matches must span the entire haystack.
+    pub fn requires_full_match(&self) -> bool {
+        self.full_match
+    }
+
+    /// Return the byte classes used by this DFA.
+    pub fn byte_classes(&self) -> &ByteClasses {
+        &self.byte_classes
+    }
+
+    /// Returns an iterator over all states in this DFA.
+    ///
+    /// This iterator yields a tuple for each state. The first element of the
+    /// tuple corresponds to a state's identifier, and the second element
+    /// corresponds to the state itself (comprised of its transitions).
+    ///
+    /// If this DFA is premultiplied, then the state identifiers are in
+    /// turn premultiplied as well, making them usable without additional
+    /// modification.
+    #[cfg(feature = "std")]
+    pub fn states(&self) -> StateIter<T, S> {
+        StateIter { dfa: self, it: 0..self.state_count }
+    }
+
+    /// Return the state corresponding to the given identifier.
+    ///
+    /// This is the random access counterpart to `states`: following a
+    /// transition to an arbitrary state doesn't require iterating from the
+    /// start. The identifier is interpreted just like the ones yielded by
+    /// `states`, which means it is premultiplied if and only if this DFA is
+    /// premultiplied.
+    #[cfg(feature = "std")]
+    pub fn state(&self, id: S) -> State<'_, S> {
+        debug_assert!(
+            self.state_id_to_index(id) < self.state_count,
+            "invalid state id {}",
+            id.to_usize(),
+        );
+        self.state_at(self.state_id_to_index(id))
+    }
+
+    /// Return the state with the given index, in either layout.
+    #[cfg(feature = "std")]
+    fn state_at(&self, index: usize) -> State<'_, S> {
+        if self.column_major {
+            let transitions = &self.trans()[index..];
+            State { transitions, stride: self.state_count }
+        } else {
+            let alpha = self.alphabet_len();
+            let transitions =
+                &self.trans()[index * alpha..(index + 1) * alpha];
+            State { transitions, stride: 1 }
+        }
+    }
+
+    /// Return the transition out of the state with the given index on the
+    /// given equivalence class, in either layout.
+    fn transition(&self, index: usize, class: usize) -> S {
+        if self.column_major {
+            self.trans()[class * self.state_count + index]
+        } else {
+            self.trans()[index * self.alphabet_len() + class]
+        }
+    }
+
+    /// Return the total number of states in this DFA. Every DFA has at least
+    /// 1 state, even the empty DFA.
+    #[cfg(feature = "std")]
+    pub fn state_count(&self) -> usize {
+        self.state_count
+    }
+
+    /// Return the number of elements in this DFA's alphabet.
+    ///
+    /// If this DFA doesn't use byte classes, then this is always equivalent
+    /// to 256. Otherwise, it is guaranteed to be some value less than or equal
+    /// to 256.
+    pub fn alphabet_len(&self) -> usize {
+        self.byte_classes().alphabet_len()
+    }
+
+    /// Returns the memory usage, in bytes, of this DFA.
+    pub fn memory_usage(&self) -> usize {
+        self.trans().len() * mem::size_of::<S>()
+    }
+
+    /// Touch every page of this DFA's transition table.
+    fn prefault(&self) {
+        let trans = self.trans();
+        // This is safe because any initialized memory may be read as bytes.
+        let bytes = unsafe {
+            slice::from_raw_parts(
+                trans.as_ptr() as *const u8,
+                mem::size_of_val(trans),
+            )
+        };
+        prefault_bytes(bytes);
+    }
+
+    /// Convert the given state identifier to the state's index. The state's
+    /// index corresponds to the position in which it appears in the transition
+    /// table. When a DFA is NOT premultiplied, then a state's identifier is
+    /// also its index. When a DFA is premultiplied, then a state's identifier
+    /// is equal to `index * alphabet_len`. This routine reverses that.
+    #[cfg(feature = "std")]
+    pub fn state_id_to_index(&self, id: S) -> usize {
+        if self.premultiplied {
+            id.to_usize() / self.alphabet_len()
+        } else {
+            id.to_usize()
+        }
+    }
+
+    /// Return this DFA's transition table as a slice.
+    fn trans(&self) -> &[S] {
+        self.trans.as_ref()
+    }
+
+    /// Create a sparse DFA from the internal representation of a dense DFA.
+    #[cfg(feature = "std")]
+    pub fn to_sparse_sized<A: StateID>(
+        &self,
+    ) -> Result<SparseDFA<Vec<u8>, A>> {
+        if !self.skip.is_empty() {
+            return Err(Error::unsupported_sparse_skip_bytes());
+        }
+        SparseDFA::from_dense_sized(self)
+    }
+
+    /// Returns the largest state identifier in this DFA, which is the
+    /// identifier of its last state.
+    #[cfg(feature = "std")]
+    fn max_state_id(&self) -> usize {
+        let last = self.state_count - 1;
+        if self.premultiplied {
+            last * self.alphabet_len()
+        } else {
+            last
+        }
+    }
+
+    /// Create a new DFA whose match semantics are equivalent to this DFA, but
+    /// attempt to use `A` for the representation of state identifiers. If `A`
+    /// is insufficient to represent all state identifiers in this DFA, then
+    /// this returns an error.
+    #[cfg(feature = "std")]
+    pub fn to_sized<A: StateID>(&self) -> Result<Repr<Vec<A>, A>> {
+        // Check that this DFA can fit into A's representation.
+        if self.max_state_id() > A::max_id() {
+            return Err(Error::state_id_overflow(A::max_id()));
+        }
+
+        // We're off to the races. The new DFA is the same as the old one,
+        // but its transition table is truncated.
+        let mut new = Repr {
+            premultiplied: self.premultiplied,
+            anchored: self.anchored,
+            full_match: self.full_match,
+            column_major: self.column_major,
+            match_set: self.match_set.clone(),
+            start: A::from_usize(self.start.to_usize()),
+            state_count: self.state_count,
+            max_match: A::from_usize(self.max_match.to_usize()),
+            byte_classes: self.byte_classes().clone(),
+            prefix: self.prefix,
+            accel: self.accel,
+            skip: self.skip,
+            trans: vec![dead_id::<A>(); self.trans().len()],
+        };
+        for (i, id) in new.trans.iter_mut().enumerate() {
+            *id = A::from_usize(self.trans()[i].to_usize());
+        }
+        Ok(new)
+    }
+
+    /// Create a new DFA in which every transition on a byte outside of
+    /// `allowed` leads to the dead state. State indices are preserved, but
+    /// the byte classes may be refined.
+    #[cfg(feature = "std")]
+    fn project_to_bytes(&self, allowed: &[u8]) -> Result<Repr<Vec<S>, S>> {
+        let mut is_allowed = [false; 256];
+        for &b in allowed {
+            is_allowed[b as usize] = true;
+        }
+        let classes = self.byte_classes().split(allowed);
+        let mut new = self.rebuild(classes, |b| is_allowed[b as usize])?;
+        // Disallowed bytes now leave the start state, so the bytes used for
+        // acceleration have to be recomputed.
+        if self.accel.enabled {
+            new.accel = Accel::new(&new);
+        }
+        Ok(new)
+    }
+
+    /// Create a copy of this DFA whose transition table is indexed by the
+    /// given byte classes, which must refine this DFA's classes.
+    #[cfg(feature = "std")]
+    fn relabel_byte_classes(
+        &self,
+        classes: &ByteClasses,
+    ) -> Result<Repr<Vec<S>, S>> {
+        if !classes.is_refinement_of(self.byte_classes()) {
+            return Err(Error::incompatible_byte_classes());
+        }
+        let mut new = self.rebuild(*classes, |_| true)?;
+        new.accel = self.accel;
+        Ok(new)
+    }
+
+    /// Create a copy of this DFA whose transition table is indexed by the
+    /// given byte classes, which must refine this DFA's classes. Transitions
+    /// on bytes for which `keep` returns false lead to the dead state.
+    ///
+    /// State indices are preserved, and the copy is premultiplied (using the
+    /// new alphabet) if this DFA is, and skips the same bytes. The copy has
+    /// neither a prefilter nor acceleration, since the transitions they
+    /// summarize may have changed.
+    #[cfg(feature = "std")]
+    fn rebuild<F: Fn(u8) -> bool>(
+        &self,
+        classes: ByteClasses,
+        keep: F,
+    ) -> Result<Repr<Vec<S>, S>> {
+        let mut new =
+            Repr::empty_with_byte_classes(classes).anchored(self.anchored);
+        new.reserve_states(self.state_count);
+        for _ in 1..self.state_count {
+            // This cannot fail since the new DFA is not yet premultiplied and
+            // has the same number of states as this one.
+            new.add_empty_state()?;
+        }
+
+        for index in 0..self.state_count {
+            let from = S::from_usize(index);
+            for b in classes.representatives().filter(|&b| keep(b)) {
+                let class = self.byte_classes().get(b) as usize;
+                let next = self.transition(index, class);
+                let to = S::from_usize(self.state_id_to_index(next));
+                new.add_transition(from, b, to);
+            }
+        }
+        new.start = S::from_usize(self.state_id_to_index(self.start));
+        new.max_match = S::from_usize(self.state_id_to_index(self.max_match));
+        new.match_set = self.match_set.clone();
+        new.skip = self.skip;
+        if self.premultiplied {
+            new.premultiply()?;
+        }
+        new.set_column_major(self.column_major);
+        Ok(new)
+    }
+
+    /// Create an anchored DFA for the reverse of this DFA's language, by
+    /// determinizing this DFA with its transitions reversed.
+    ///
+    /// Each state of the new DFA corresponds to the sorted set of indices of
+    /// the states of this DFA that can reach a match state on the reverse of
+    /// the input seen so far. The empty set is the dead state.
+    #[cfg(feature = "std")]
+    fn reverse_language(&self) -> Result<Repr<Vec<S>, S>> {
+        let alpha = self.alphabet_len();
+        // The states with a transition to each state on each class, indexed
+        // by `index * alpha + class`.
+        let mut preds = vec![vec![]; self.state_count * alpha];
+        for index in 0..self.state_count {
+            for class in 0..alpha {
+                let next = self.transition(index, class);
+                preds[self.state_id_to_index(next) * alpha + class]
+                    .push(index);
+            }
+        }
+
+        let start_index = self.state_id_to_index(self.start);
+        let mut new = Repr::empty_with_byte_classes(*self.byte_classes());
+        let mut is_match = vec![false];
+        let mut ids: HashMap<Vec<usize>, S> = HashMap::new();
+        ids.insert(vec![], dead_id());
+        let mut uncompiled = vec![];
+        let mut add_state = |set: Vec<usize>,
+                             new: &mut Repr<Vec<S>, S>,
+                             uncompiled: &mut Vec<(S, Vec<usize>)>|
+         -> Result<S> {
+            if let Some(&id) = ids.get(&set) {
+                return Ok(id);
+            }
+            let id = new.add_empty_state()?;
+            is_match.push(set.binary_search(&start_index).is_ok());
+            ids.insert(set.clone(), id);
+            uncompiled.push((id, set));
+            Ok(id)
+        };
+
+        // The new start state is the set of this DFA's match states.
+        let matches =
+            (1..self.state_count).filter(|&i| self.is_match_index(i));
+        new.start = add_state(matches.collect(), &mut new, &mut uncompiled)?;
+        while let Some((id, set)) = uncompiled.pop() {
+            for class in 0..alpha {
+                let mut next: Vec<usize> = set
+                    .iter()
+                    .flat_map(|&index| preds[index * alpha + class].iter())
+                    .cloned()
+                    .collect();
+                next.sort();
+                next.dedup();
+                let to = add_state(next, &mut new, &mut uncompiled)?;
+                new.trans[id.to_usize() * alpha + class] = to;
+            }
+        }
+        new.shuffle_match_states(&is_match);
+        new.full_match = self.full_match;
+        new.skip = self.skip;
+        if self.premultiplied {
+            new.premultiply()?;
+        }
+        new.set_column_major(self.column_major);
+        Ok(new)
+    }
+
+    /// Create an unanchored DFA from this anchored DFA by starting a new
+    /// match attempt at every position.
+    ///
+    /// Each state of the new DFA corresponds to a list of states of this DFA,
+    /// one for each match attempt still in progress, ordered by where the
+    /// attempt started, along with whether new attempts may still be
+    /// started. Once some attempt reaches a match state, every attempt that
+    /// started later is dropped and no new attempts are started, which gives
+    /// leftmost first match semantics.
+    #[cfg(feature = "std")]
+    fn to_unanchored(&self) -> Result<Repr<Vec<S>, S>> {
+        let start = self.state_id_to_index(self.start);
+        let is_match = |i: usize| self.is_match_index(i);
+        // Dedups the given attempts, drops those that have died and applies
+        // the priority rules above.
+        let canonical = |mut attempts: Vec<usize>, mut restart: bool| {
+            if restart {
+                attempts.push(start);
+            }
+            let mut list = vec![];
+            for i in attempts {
+                if i == 0 || list.contains(&i) {
+                    continue;
+                }
+                list.push(i);
+                if is_match(i) {
+                    restart = false;
+                    break;
+                }
+            }
+            (list, restart)
+        };
+
+        let mut new = Repr::empty_with_byte_classes(*self.byte_classes())
+            .anchored(false);
+        let mut ids: HashMap<(Vec<usize>, bool), S> = HashMap::new();
+        ids.insert((vec![], false), dead_id());
+        let mut states = vec![(vec![], false)];
+        let mut is_new_match = vec![false];
+
+        let first = canonical(vec![], true);
+        let first_id = new.add_empty_state()?;
+        is_new_match.push(first.0.iter().any(|&i| is_match(i)));
+        ids.insert(first.clone(), first_id);
+        states.push(first);
+        new.start = first_id;
+
+        let mut next = 1;
+        while next < states.len() {
+            let from = S::from_usize(next);
+            for b in self.byte_classes().representatives() {
+                let class = self.byte_classes().get(b) as usize;
+                let (ref list, restart) = states[next];
+                let attempts = list
+                    .iter()
+                    .map(|&i| {
+                        self.state_id_to_index(self.transition(i, class))
+                    })
+                    .collect();
+                let key = canonical(attempts, restart);
+                let to = match ids.get(&key) {
+                    Some(&to) => to,
+                    None => {
+                        let to = new.add_empty_state()?;
+                        is_new_match.push(key.0.iter().any(|&i| is_match(i)));
+                        ids.insert(key.clone(), to);
+                        states.push(key);
+                        to
+                    }
+                };
+                new.add_transition(from, b, to);
+            }
+            next += 1;
+        }
+        new.shuffle_match_states(&is_new_match);
+        if self.premultiplied {
+            new.premultiply()?;
+        }
+        // Anchored DFAs are never accelerated or given a prefilter, so there
+        // is nothing to carry over. But the new start state loops back into
+        // itself on most bytes, which is exactly what acceleration is for.
+        new.accel = Accel::new(&new);
+        new.skip = self.skip;
+        new.set_column_major(self.column_major);
+        Ok(new)
+    }
+
+    /// Serialize a DFA to raw bytes, aligned to an 8 byte boundary.
+    ///
+    /// State identifiers are widened to the next power of two bytes, which
+    /// must be at most 8, or else this returns an error.
+    ///
+    /// When `with_classes` is false, the byte class map is replaced by the
+    /// number of classes, and the DFA can only be deserialized by supplying
+    /// the map separately.
+    #[cfg(feature = "std")]
+    pub(crate) fn to_bytes<A: ByteOrder>(
+        &self,
+        with_classes: bool,
+    ) -> Result<Vec<u8>> {
+        let state_size = serialized_state_size::<S>()?;
+        let trans_size = state_size * self.trans().len();
+        let mut header_size = if with_classes { 312 } else { 64 };
+        if !self.skip.is_empty() {
+            header_size += 32;
+        }
+        if self.match_set.is_some() {
+            header_size += 8 * MatchSet::words(self.state_count);
+        }
+        let mut buf = Vec::with_capacity(header_size + trans_size);
+        let options = if with_classes { 0 } else { MASK_CLASSES_OMITTED };
+        self.write_header::<A>(&mut buf, options)?;
+        // sanity check, this can be updated if need be
+        assert_eq!(header_size, buf.len());
+        // This must always pass. It checks that the transition table is at
+        // a properly aligned address.
+        assert_eq!(0, buf.len() % 8);
+
+        // transition table
+        let mut i = buf.len();
+        buf.resize(header_size + trans_size, 0);
+        for &id in self.trans() {
+            write_state_id_bytes::<A, _>(&mut buf[i..], id);
+            i += state_size;
+        }
+        assert_eq!(buf.len(), i, "expected to consume entire buffer");
+
+        Ok(buf)
+    }
+
+    /// Append the header shared by every serialization format to `buf`.
+    ///
+    /// The options written are the ones given, which describe how the rest
+    /// of the DFA is encoded, combined with the ones describing this DFA.
+    /// When `MASK_CLASSES_OMITTED` is among the options given, the byte
+    /// class map is replaced by the number of classes. If this DFA skips any
+    /// bytes, then the 256-bit skip set follows. If its match states weren't
+    /// shuffled, then the bitset of its match states follows, as one u64 for
+    /// every 64 states (rounded up, plus one). Both keep the header a
+    /// multiple of 8 bytes long.
+    #[cfg(feature = "std")]
+    fn write_header<A: ByteOrder>(
+        &self,
+        buf: &mut Vec<u8>,
+        mut options: u16,
+    ) -> Result<()> {
+        let state_size = serialized_state_size::<S>()?;
+        let label = LABEL;
+        assert_eq!(24, label.len());
+        if self.premultiplied {
+            options |= MASK_PREMULTIPLIED;
+        }
+        if self.anchored {
+            options |= MASK_ANCHORED;
+        }
+        if self.full_match {
+            options |= MASK_FULL_MATCH;
+        }
+        if self.column_major {
+            options |= MASK_COLUMN_MAJOR;
+        }
+        if self.accel.enabled {
+            options |= MASK_ACCEL;
+        }
+        if !self.skip.is_empty() {
+            options |= MASK_SKIP_BYTES;
+        }
+        if self.match_set.is_some() {
+            options |= MASK_MATCH_SET;
+        }
+
+        let mut n = [0; 8];
+        // write label
+        buf.extend_from_slice(label);
+        // endianness check, must be equal to 0xFEFF for native endian
+        A::write_u16(&mut n, 0xFEFF);
+        buf.extend_from_slice(&n[..2]);
+        // version number
+        A::write_u16(&mut n, version_for_options(options));
+        buf.extend_from_slice(&n[..2]);
+        // size of state ID, once widened. Always 1, 2, 4 or 8.
+        A::write_u16(&mut n, state_size as u16);
+        buf.extend_from_slice(&n[..2]);
+        // DFA misc options
+        A::write_u16(&mut n, options);
+        buf.extend_from_slice(&n[..2]);
+        // start state
+        A::write_u64(&mut n, self.start.to_usize() as u64);
+        buf.extend_from_slice(&n);
+        // state count
+        A::write_u64(&mut n, self.state_count as u64);
+        buf.extend_from_slice(&n);
+        // max match state
+        A::write_u64(&mut n, self.max_match.to_usize() as u64);
+        buf.extend_from_slice(&n);
+        // byte class map, or the number of classes if the map is omitted
+        if options & MASK_CLASSES_OMITTED == 0 {
+            buf.extend((0..256).map(|b| self.byte_classes().get(b as u8)));
+        } else {
+            A::write_u64(&mut n, self.alphabet_len() as u64);
+            buf.extend_from_slice(&n);
+        }
+        // skip bytes, if any
+        if options & MASK_SKIP_BYTES > 0 {
+            buf.extend_from_slice(&self.skip.0);
+        }
+        // match states, if they weren't shuffled to the front
+        if let Some(ref set) = self.match_set {
+            for &word in set.0.iter() {
+                A::write_u64(&mut n, word);
+                buf.extend_from_slice(&n);
+            }
+        }
+        Ok(())
+    }
+
+    /// Serialize a DFA to raw bytes followed by a section of state labels.
+    ///
+    /// The section starts with the number of labels as a u64. Each label is
+    /// then written as its state identifier and its length in bytes (both
+    /// u64), followed by its UTF-8 encoded bytes.
+    #[cfg(feature = "std")]
+    pub(crate) fn to_bytes_with_labels<A: ByteOrder>(
+        &self,
+        labels: &StateLabels,
+    ) -> Result<Vec<u8>> {
+        let mut buf = self.to_bytes::<A>(true)?;
+        add_options::<A>(&mut buf, MASK_LABELS);
+
+        let mut n = [0; 8];
+        A::write_u64(&mut n, labels.len() as u64);
+        buf.extend_from_slice(&n);
+        for (&id, label) in &labels.labels {
+            A::write_u64(&mut n, id as u64);
+            buf.extend_from_slice(&n);
+            A::write_u64(&mut n, label.len() as u64);
+            buf.extend_from_slice(&n);
+            buf.extend_from_slice(label.as_bytes());
+        }
+        Ok(buf)
+    }
+
+    /// Serialize a DFA to raw bytes with each state's transitions run length
+    /// encoded.
+    ///
+    /// The header is the same as the one written by `to_bytes`, except that
+    /// the RLE option is set. Following the byte class map, each state is
+    /// written as a sequence of runs that together cover its entire row. A
+    /// run is a 2 byte length followed by the state identifier that each
+    /// transition in the run points to. No alignment is required.
+    #[cfg(feature = "std")]
+    pub(crate) fn to_bytes_rle<A: ByteOrder>(&self) -> Result<Vec<u8>> {
+        let mut buf = vec![];
+        self.write_header::<A>(&mut buf, MASK_RLE)?;
+
+        let state_size = serialized_state_size::<S>()?;
+        let mut run = vec![0; 2 + state_size];
+        for row in self.trans().chunks(self.alphabet_len()) {
+            let mut i = 0;
+            while i < row.len() {
+                let len =
+                    row[i..].iter().take_while(|&&id| id == row[i]).count();
+                A::write_u16(&mut run, len as u16);
+                write_state_id_bytes::<A, _>(&mut run[2..], row[i]);
+                buf.extend_from_slice(&run);
+                i += len;
+            }
+        }
+        Ok(buf)
+    }
+
+    /// Serialize a DFA to raw bytes with each transition written as the
+    /// LEB128 encoding of the index of the state it points to.
+    ///
+    /// The header is the same as the one written by `to_bytes`, except that
+    /// the varint option is set. Indices are written instead of identifiers
+    /// so that premultiplication doesn't inflate them. No alignment is
+    /// required.
+    #[cfg(feature = "std")]
+    pub(crate) fn to_bytes_varint<A: ByteOrder>(&self) -> Result<Vec<u8>> {
+        let mut buf = vec![];
+        self.write_header::<A>(&mut buf, MASK_VARINT)?;
+
+        for &id in self.trans() {
+            let mut index = self.state_id_to_index(id) as u64;
+            while index >= 0x80 {
+                buf.push((index as u8) | 0x80);
+                index >>= 7;
+            }
+            buf.push(index as u8);
+        }
+        Ok(buf)
+    }
+}
+
+impl<'a, S: StateID> Repr<&'a [S], S> {
+    /// The implementation for deserializing a DFA from raw bytes.
+    ///
+    /// If `classes` is given, then the bytes must have been serialized
+    /// without a byte class map, and the given classes are used instead.
+    unsafe fn from_bytes(
+        mut buf: &'a [u8],
+        classes: Option<ByteClasses>,
+    ) -> Repr<&'a [S], S> {
+        assert_eq!(
+            0,
+            buf.as_ptr() as usize % mem::align_of::<S>(),
+            "DenseDFA starting at address {} is not aligned to {} bytes",
+            buf.as_ptr() as usize,
+            mem::align_of::<S>()
+        );
+
+        // skip over label
+        match buf.iter().position(|&b| b == b'\x00') {
+            None => panic!("could not find label"),
+            Some(i) => buf = &buf[i + 1..],
+        }
+
+        // check that current endianness is same as endianness of DFA
+        let endian_check = NativeEndian::read_u16(buf);
+        buf = &buf[2..];
+        if endian_check != 0xFEFF {
+            panic!(
+                "endianness mismatch, expected 0xFEFF but got 0x{:X}. \
+                 are you trying to load a DenseDFA serialized with a \
+                 different endianness?",
+                endian_check,
+            );
+        }
+
+        // check that the version number is supported
+        let version = NativeEndian::read_u16(buf);
+        buf = &buf[2..];
+        match version {
+            1 | 2 => {}
+            _ => panic!(
+                "unsupported version {}, the newest supported version is {}",
+                version, VERSION,
+            ),
+        }
+
+        // read size of state
+        let state_size = NativeEndian::read_u16(buf) as usize;
+        if state_size != mem::size_of::<S>() {
+            panic!(
+                "state size of DenseDFA ({}) does not match \
+                 requested state size ({})",
+                state_size,
+                mem::size_of::<S>(),
+            );
+        }
+        buf = &buf[2..];
+
+        // read miscellaneous options
+        let opts = NativeEndian::read_u16(buf);
+        buf = &buf[2..];
+        if let Some(msg) = check_version(version, opts) {
+            panic!("{} (version {}, options 0x{:X})", msg, version, opts);
+        }
+        if opts & MASK_RLE > 0 {
+            panic!(
+                "DenseDFA transitions are run length encoded, \
+                 use from_bytes_rle to deserialize it"
+            );
+        }
+        if opts & MASK_VARINT > 0 {
+            panic!(
+                "DenseDFA transitions are varint encoded, \
+                 use from_bytes_varint to deserialize it"
+            );
+        }
+
+        // read start state
+        let start = S::from_usize(NativeEndian::read_u64(buf) as usize);
+        buf = &buf[8..];
+
+        // read state count
+        let state_count = NativeEndian::read_u64(buf) as usize;
+        buf = &buf[8..];
+
+        // read max match state
+        let max_match = S::from_usize(NativeEndian::read_u64(buf) as usize);
+        buf = &buf[8..];
+
+        // read byte classes
+        let byte_classes = match classes {
+            None => {
+                if opts & MASK_CLASSES_OMITTED > 0 {
+                    panic!(
+                        "DenseDFA was serialized without byte classes, \
+                         use from_bytes_with_classes to deserialize it"
+                    );
+                }
+                let byte_classes = ByteClasses::from_slice(&buf[..256]);
+                buf = &buf[256..];
+                byte_classes
+            }
+            Some(byte_classes) => {
+                if opts & MASK_CLASSES_OMITTED == 0 {
+                    panic!(
+                        "DenseDFA was serialized with its byte classes, \
+                         use from_bytes to deserialize it"
+                    );
+                }
+                let alphabet_len = NativeEndian::read_u64(buf) as usize;
+                buf = &buf[8..];
+                if alphabet_len != byte_classes.alphabet_len() {
+                    panic!(
+                        "DenseDFA was serialized with {} byte classes, \
+                         but {} were given",
+                        alphabet_len,
+                        byte_classes.alphabet_len(),
+                    );
+                }
+                byte_classes
+            }
+        };
+
+        // read skip bytes
+        let skip = if opts & MASK_SKIP_BYTES > 0 {
+            let skip = SkipBytes::from_slice(&buf[..32]);
+            buf = &buf[32..];
+            skip
+        } else {
+            SkipBytes::none()
+        };
+
+        // read match states, if they weren't shuffled
+        #[cfg(feature = "std")]
+        let match_set = if opts & MASK_MATCH_SET > 0 {
+            let len = 8 * MatchSet::words(state_count);
+            let set = MatchSet::from_bytes::<NativeEndian>(&buf[..len]);
+            buf = &buf[len..];
+            Some(set)
+        } else {
+            None
+        };
+        #[cfg(not(feature = "std"))]
+        {
+            if opts & MASK_MATCH_SET > 0 {
+                panic!(
+                    "DenseDFA match states weren't shuffled, which requires \
+                     the std feature to deserialize"
+                );
+            }
+        }
+
+        let len = state_count * byte_classes.alphabet_len();
+        let len_bytes = len * state_size;
+        assert!(
+            len_bytes <= buf.len(),
+            "insufficient transition table bytes, \
+             expected at least {} but only have {}",
+            len_bytes,
+            buf.len()
+        );
+        assert_eq!(
+            0,
+            buf.as_ptr() as usize % mem::align_of::<S>(),
+            "DenseDFA transition table is not properly aligned"
+        );
+
+        // SAFETY: This is the only actual not-safe thing in this entire
+        // routine. The key things we need to worry about here are alignment
+        // and size. The two asserts above should cover both conditions.
+        let trans = slice::from_raw_parts(buf.as_ptr() as *const S, len);
+        let mut repr = Repr {
             premultiplied: opts & MASK_PREMULTIPLIED > 0,
             anchored: opts & MASK_ANCHORED > 0,
+            full_match: opts & MASK_FULL_MATCH > 0,
+            column_major: opts & MASK_COLUMN_MAJOR > 0,
+            #[cfg(feature = "std")]
+            match_set,
             start,
             state_count,
             max_match,
             byte_classes,
+            prefix: Prefix::none(),
+            accel: Accel::none(),
+            skip,
             trans,
+        };
+        if opts & MASK_ACCEL > 0 {
+            repr.accel = Accel::new(&repr);
+        }
+        repr
+    }
+}
+
+/// The following methods implement mutable routines on the internal
+/// representation of a DFA. As such, we must fix the first type parameter to
+/// a `Vec<S>` since a generic `T: AsRef<[S]>` does not permit mutation. We
+/// can get away with this because these methods are internal to the crate and
+/// are exclusively used during construction of the DFA.
+#[cfg(feature = "std")]
+impl<S: StateID> Repr<Vec<S>, S> {
+    /// The implementation for deserializing a DFA whose transitions are
+    /// compressed with the given encoding, which is either `MASK_RLE` or
+    /// `MASK_VARINT`. Since the transitions are copied, every problem with
+    /// the input is reported as an error instead of a panic.
+    fn from_bytes_encoded(
+        buf: &[u8],
+        encoding: u16,
+    ) -> Result<Repr<Vec<S>, S>> {
+        fn take<'b>(buf: &mut &'b [u8], len: usize) -> Result<&'b [u8]> {
+            if buf.len() < len {
+                return Err(Error::deserialize("unexpected end of input"));
+            }
+            let (head, tail) = buf.split_at(len);
+            *buf = tail;
+            Ok(head)
+        }
+
+        let mut buf = buf;
+        match buf.iter().position(|&b| b == b'\x00') {
+            None => return Err(Error::deserialize("could not find label")),
+            Some(i) => buf = &buf[i + 1..],
+        }
+        if NativeEndian::read_u16(take(&mut buf, 2)?) != 0xFEFF {
+            return Err(Error::deserialize(
+                "endianness mismatch, DFA was serialized with a \
+                 different endianness",
+            ));
+        }
+        let version = NativeEndian::read_u16(take(&mut buf, 2)?);
+        match version {
+            1 | 2 => {}
+            _ => {
+                return Err(Error::deserialize(&format!(
+                    "unsupported version {}",
+                    version
+                )))
+            }
+        }
+        let state_size = NativeEndian::read_u16(take(&mut buf, 2)?) as usize;
+        let expected_size = serialized_state_size::<S>()
+            .map_err(|_| Error::deserialize("unsupported state size"))?;
+        if state_size != expected_size {
+            return Err(Error::deserialize(&format!(
+                "state size of {} does not match requested state size of {}",
+                state_size, expected_size,
+            )));
+        }
+        let opts = NativeEndian::read_u16(take(&mut buf, 2)?);
+        if let Some(msg) = check_version(version, opts) {
+            return Err(Error::deserialize(msg));
+        }
+        if opts & encoding == 0 {
+            return Err(Error::deserialize(if encoding == MASK_RLE {
+                "DFA transitions are not run length encoded"
+            } else {
+                "DFA transitions are not varint encoded"
+            }));
+        }
+        let start = NativeEndian::read_u64(take(&mut buf, 8)?) as usize;
+        let state_count = NativeEndian::read_u64(take(&mut buf, 8)?) as usize;
+        let max_match = NativeEndian::read_u64(take(&mut buf, 8)?) as usize;
+        let byte_classes = ByteClasses::from_slice(take(&mut buf, 256)?);
+        let skip = if opts & MASK_SKIP_BYTES > 0 {
+            SkipBytes::from_slice(take(&mut buf, 32)?)
+        } else {
+            SkipBytes::none()
+        };
+        let match_set = if opts & MASK_MATCH_SET > 0 {
+            let len = MatchSet::words(state_count).saturating_mul(8);
+            Some(MatchSet::from_bytes::<NativeEndian>(take(&mut buf, len)?))
+        } else {
+            None
+        };
+
+        let alphabet_len = byte_classes.alphabet_len();
+        let mut trans = vec![];
+        if encoding == MASK_VARINT {
+            let premultiplied = opts & MASK_PREMULTIPLIED > 0;
+            for _ in 0..state_count.saturating_mul(alphabet_len) {
+                let mut index = 0u64;
+                let mut shift = 0;
+                loop {
+                    let b = take(&mut buf, 1)?[0];
+                    if shift == 63 && b > 1 {
+                        return Err(Error::deserialize(
+                            "varint encoded state index overflows u64",
+                        ));
+                    }
+                    index |= u64::from(b & 0x7F) << shift;
+                    shift += 7;
+                    if b & 0x80 == 0 {
+                        break;
+                    }
+                }
+                if index >= state_count as u64 {
+                    return Err(Error::deserialize(&format!(
+                        "state index {} is not less than the state count {}",
+                        index, state_count,
+                    )));
+                }
+                let mut id = index as usize;
+                if premultiplied {
+                    id *= alphabet_len;
+                }
+                if id > S::max_id() {
+                    return Err(Error::deserialize(&format!(
+                        "state identifier {} does not fit in the requested \
+                         state size",
+                        id,
+                    )));
+                }
+                trans.push(S::from_usize(id));
+            }
+        } else {
+            for _ in 0..state_count {
+                let mut remaining = alphabet_len;
+                while remaining > 0 {
+                    let len =
+                        NativeEndian::read_u16(take(&mut buf, 2)?) as usize;
+                    // Identifiers may have been widened when serialized, so
+                    // narrow them back into `S`.
+                    let id = NativeEndian::read_uint(
+                        take(&mut buf, state_size)?,
+                        state_size,
+                    );
+                    if id > S::max_id() as u64 {
+                        return Err(Error::deserialize(&format!(
+                            "state identifier {} does not fit in the \
+                             requested state size",
+                            id,
+                        )));
+                    }
+                    let id = S::from_usize(id as usize);
+                    if len == 0 || len > remaining {
+                        return Err(Error::deserialize(&format!(
+                            "invalid run of length {} with {} transitions \
+                             left in state",
+                            len, remaining,
+                        )));
+                    }
+                    let new_len = trans.len() + len;
+                    trans.resize(new_len, id);
+                    remaining -= len;
+                }
+            }
+        }
+        if !buf.is_empty() {
+            return Err(Error::deserialize(
+                "trailing bytes after transitions",
+            ));
+        }
+
+        let mut repr = Repr {
+            premultiplied: opts & MASK_PREMULTIPLIED > 0,
+            anchored: opts & MASK_ANCHORED > 0,
+            full_match: opts & MASK_FULL_MATCH > 0,
+            column_major: opts & MASK_COLUMN_MAJOR > 0,
+            match_set,
+            start: S::from_usize(start),
+            state_count,
+            max_match: S::from_usize(max_match),
+            byte_classes,
+            prefix: Prefix::none(),
+            accel: Accel::none(),
+            skip,
+            trans,
+        };
+        repr.validate()?;
+        if opts & MASK_ACCEL > 0 {
+            repr.accel = Accel::new(&repr);
+        }
+        Ok(repr)
+    }
+
+    pub fn premultiply(&mut self) -> Result<()> {
+        if self.premultiplied || self.state_count <= 1 {
+            return Ok(());
+        }
+
+        let alpha_len = self.alphabet_len();
+        premultiply_overflow_error(
+            S::from_usize(self.state_count - 1),
+            alpha_len,
+        )?;
+
+        for id in (0..self.state_count).map(S::from_usize) {
+            for (_, next) in self.get_state_mut(id).iter_mut() {
+                *next = S::from_usize(next.to_usize() * alpha_len);
+            }
+        }
+        self.premultiplied = true;
+        self.start = S::from_usize(self.start.to_usize() * alpha_len);
+        self.max_match = S::from_usize(self.max_match.to_usize() * alpha_len);
+        Ok(())
+    }
+
+    /// Transpose the transition table into column-major order if `yes` is
+    /// true, or back into row-major order otherwise. This does nothing if the
+    /// table is already in the requested order.
+    ///
+    /// This cannot be called on a premultiplied DFA, since the state
+    /// identifiers of a column-major DFA are always indices.
+    pub fn set_column_major(&mut self, yes: bool) {
+        if self.column_major == yes {
+            return;
+        }
+        assert!(!self.premultiplied, "can't transpose premultiplied DFA");
+        let (alpha, count) = (self.alphabet_len(), self.state_count);
+        let mut trans = Vec::with_capacity(self.trans.len());
+        if yes {
+            for class in 0..alpha {
+                trans
+                    .extend((0..count).map(|i| self.trans[i * alpha + class]));
+            }
+        } else {
+            for index in 0..count {
+                trans
+                    .extend((0..alpha).map(|c| self.trans[c * count + index]));
+            }
+        }
+        self.trans = trans;
+        self.column_major = yes;
+    }
+
+    /// Run the given routine, which may only handle row-major transition
+    /// tables, on this DFA, and then restore the DFA's layout.
+    fn with_row_major<R, F: FnOnce(&mut Repr<Vec<S>, S>) -> R>(
+        &mut self,
+        f: F,
+    ) -> R {
+        let column_major = self.column_major;
+        self.set_column_major(false);
+        let result = f(self);
+        self.set_column_major(column_major);
+        result
+    }
+
+    /// Minimize this DFA using Hopcroft's algorithm.
+    ///
+    /// This cannot be called on a premultiplied DFA.
+    ///
+    /// This returns a map from the index of every state before minimization
+    /// to the index of the state it was merged into.
+    pub fn minimize(&mut self) -> Vec<usize> {
+        assert!(!self.premultiplied, "can't minimize premultiplied DFA");
+
+        let remap = Minimizer::new(self).run(self);
+        if let Some(set) = self.match_set.take() {
+            self.match_set = Some(set.remap(&remap, self.state_count));
+        }
+        remap
+    }
+
+    /// Record the given match states instead of shuffling them to the front
+    /// of this DFA, which leaves every state where it is. The given map
+    /// should have length exactly equivalent to the number of states in this
+    /// DFA.
+    ///
+    /// Since `max_match` is then set to the last state, searches have to
+    /// consult the set whenever they are in a state that might be a match
+    /// state, which is any state. This is slower than shuffling, but keeps
+    /// states numbered in the order in which they were built.
+    pub fn set_match_states(&mut self, is_match: &[bool]) {
+        assert!(
+            !self.premultiplied,
+            "cannot set match states of premultiplied DFA"
+        );
+        assert_eq!(self.state_count, is_match.len());
+
+        self.match_set = Some(MatchSet::new(is_match));
+        self.max_match = S::from_usize(self.state_count - 1);
+    }
+
+    /// Set the start state of this DFA.
+    ///
+    /// Note that a start state cannot be set on a premultiplied DFA. Instead,
+    /// DFAs should first be completely constructed and then premultiplied.
+    pub fn set_start_state(&mut self, start: S) {
+        assert!(!self.premultiplied, "can't set start on premultiplied DFA");
+        assert!(start.to_usize() < self.state_count, "invalid start state");
+
+        self.start = start;
+    }
+
+    /// Set the maximum state identifier that could possible correspond to a
+    /// match state.
+    ///
+    /// Callers must uphold the invariant that any state identifier less than
+    /// or equal to the identifier given is either a match state or the special
+    /// dead state (which always has identifier 0 and whose transitions all
+    /// lead back to itself).
+    ///
+    /// This cannot be called on a premultiplied DFA.
+    pub fn set_max_match_state(&mut self, id: S) {
+        assert!(!self.premultiplied, "can't set match on premultiplied DFA");
+        assert!(id.to_usize() < self.state_count, "invalid max match state");
+
+        self.max_match = id;
+    }
+
+    /// Add the given transition to this DFA. Both the `from` and `to` states
+    /// must already exist.
+    ///
+    /// This cannot be called on a premultiplied DFA.
+    pub fn add_transition(&mut self, from: S, byte: u8, to: S) {
+        assert!(!self.premultiplied, "can't add trans to premultiplied DFA");
+        assert!(from.to_usize() < self.state_count, "invalid from state");
+        assert!(to.to_usize() < self.state_count, "invalid to state");
+
+        let class = self.byte_classes().get(byte);
+        let offset = from.to_usize() * self.alphabet_len() + class as usize;
+        self.trans[offset] = to;
+    }
+
+    /// An an empty state (a state where all transitions lead to a dead state)
+    /// and return its identifier. The identifier returned is guaranteed to
+    /// not point to any other existing state.
+    ///
+    /// If adding a state would exhaust the state identifier space (given by
+    /// `S`), then this returns an error. In practice, this means that the
+    /// state identifier representation chosen is too small.
+    ///
+    /// This cannot be called on a premultiplied DFA.
+    pub fn add_empty_state(&mut self) -> Result<S> {
+        assert!(!self.premultiplied, "can't add state to premultiplied DFA");
+
+        let id = if self.state_count == 0 {
+            S::from_usize(0)
+        } else {
+            next_state_id(S::from_usize(self.state_count - 1))?
+        };
+        let alphabet_len = self.alphabet_len();
+        self.trans.extend(iter::repeat(dead_id::<S>()).take(alphabet_len));
+        // This should never panic, since state_count is a usize. The
+        // transition table size would have run out of room long ago.
+        self.state_count = self.state_count.checked_add(1).unwrap();
+        Ok(id)
+    }
+
+    /// Return a mutable representation of the state corresponding to the given
+    /// id. This is useful for implementing routines that manipulate DFA states
+    /// (e.g., swapping states).
+    ///
+    /// This cannot be called on a premultiplied DFA.
+    pub fn get_state_mut(&mut self, id: S) -> StateMut<S> {
+        assert!(!self.premultiplied, "can't get state in premultiplied DFA");
+
+        let alphabet_len = self.alphabet_len();
+        let offset = id.to_usize() * alphabet_len;
+        StateMut {
+            transitions: &mut self.trans[offset..offset + alphabet_len],
+        }
+    }
+
+    /// Swap the two states given in the transition table.
+    ///
+    /// This routine does not do anything to check the correctness of this
+    /// swap. Callers must ensure that other states pointing to id1 and id2 are
+    /// updated appropriately.
+    ///
+    /// This cannot be called on a premultiplied DFA.
+    pub fn swap_states(&mut self, id1: S, id2: S) {
+        assert!(!self.premultiplied, "can't swap states in premultiplied DFA");
+
+        let o1 = id1.to_usize() * self.alphabet_len();
+        let o2 = id2.to_usize() * self.alphabet_len();
+        for b in 0..self.alphabet_len() {
+            self.trans.swap(o1 + b, o2 + b);
+        }
+    }
+
+    /// Truncate the states in this DFA to the given count.
+    ///
+    /// This routine does not do anything to check the correctness of this
+    /// truncation. Callers must ensure that other states pointing to truncated
+    /// states are updated appropriately.
+    ///
+    /// This cannot be called on a premultiplied DFA.
+    pub fn truncate_states(&mut self, count: usize) {
+        assert!(!self.premultiplied, "can't truncate in premultiplied DFA");
+
+        let alphabet_len = self.alphabet_len();
+        self.trans.truncate(count * alphabet_len);
+        self.state_count = count;
+    }
+
+    /// Merge neighboring letters of this DFA's alphabet whose transitions
+    /// are identical in every state, and rewrite the byte classes, the
+    /// transition table and every state identifier accordingly.
+    ///
+    /// Byte classes are always contiguous ranges of bytes, numbered in
+    /// ascending byte order, so two letters are only merged when no other
+    /// letter sits between them.
+    ///
+    /// This works on both premultiplied and non-premultiplied DFAs.
+    pub fn compact_alphabet(&mut self) {
+        let old_len = self.alphabet_len();
+        let same_column = |trans: &[S], a: usize, b: usize| {
+            (0..self.state_count)
+                .all(|i| trans[i * old_len + a] == trans[i * old_len + b])
+        };
+        // Map each old letter to its new letter, and remember the first old
+        // letter of each new one.
+        let mut remap = Vec::with_capacity(old_len);
+        let mut kept = vec![0];
+        remap.push(0);
+        for letter in 1..old_len {
+            if !same_column(&self.trans, letter - 1, letter) {
+                kept.push(letter);
+            }
+            remap.push(kept.len() - 1);
+        }
+        let new_len = kept.len();
+        if new_len == old_len {
+            return;
+        }
+
+        let premultiplied = self.premultiplied;
+        let rewrite = |id: S| {
+            if premultiplied {
+                S::from_usize(id.to_usize() / old_len * new_len)
+            } else {
+                id
+            }
+        };
+        let mut trans = Vec::with_capacity(self.state_count * new_len);
+        for i in 0..self.state_count {
+            let row = &self.trans[i * old_len..(i + 1) * old_len];
+            trans.extend(kept.iter().map(|&letter| rewrite(row[letter])));
+        }
+        let mut classes = ByteClasses::empty();
+        for b in 0..256 {
+            let old = self.byte_classes.get(b as u8) as usize;
+            classes.set(b as u8, remap[old] as u8);
+        }
+        self.trans = trans;
+        self.byte_classes = classes;
+        self.start = rewrite(self.start);
+        self.max_match = rewrite(self.max_match);
+    }
+
+    /// Reorder the states in this DFA such that states are laid out in
+    /// breadth first order starting from the start state.
+    ///
+    /// The dead state remains the first state and all match states continue
+    /// to precede all non-match states, so this preserves the invariant
+    /// established by `shuffle_match_states`. Within each of those two
+    /// groups, states are ordered by their breadth first discovery from the
+    /// start state, followed by any states that are unreachable. Every
+    /// transition, along with the start state, is rewritten accordingly.
+    ///
+    /// This works on both premultiplied and non-premultiplied DFAs.
+    pub fn remap_for_locality(&mut self) {
+        let alpha_len = self.alphabet_len();
+        let premultiplied = self.premultiplied;
+        let to_index = |id: S| {
+            if premultiplied {
+                id.to_usize() / alpha_len
+            } else {
+                id.to_usize()
+            }
+        };
+        let to_id = |index: usize| {
+            S::from_usize(if premultiplied {
+                index * alpha_len
+            } else {
+                index
+            })
+        };
+        let max_match = to_index(self.max_match);
+        let start = to_index(self.start);
+
+        let mut seen = vec![false; self.state_count];
+        let mut visited = Vec::with_capacity(self.state_count);
+        let mut queue = VecDeque::new();
+        seen[dead_id::<S>().to_usize()] = true;
+        if !seen[start] {
+            seen[start] = true;
+            queue.push_back(start);
+        }
+        while let Some(index) = queue.pop_front() {
+            visited.push(index);
+            let row = &self.trans[index * alpha_len..(index + 1) * alpha_len];
+            for &next in row {
+                let next = to_index(next);
+                if !seen[next] {
+                    seen[next] = true;
+                    queue.push_back(next);
+                }
+            }
+        }
+        visited.extend((0..self.state_count).filter(|&i| !seen[i]));
+
+        let mut order = Vec::with_capacity(self.state_count);
+        order.push(0);
+        order.extend(visited.iter().cloned().filter(|&i| i <= max_match));
+        order.extend(visited.iter().cloned().filter(|&i| i > max_match));
+        let mut remap = vec![0; self.state_count];
+        for (new, &old) in order.iter().enumerate() {
+            remap[old] = new;
+        }
+
+        let mut trans = Vec::with_capacity(self.trans.len());
+        for &old in &order {
+            let row = &self.trans[old * alpha_len..(old + 1) * alpha_len];
+            trans.extend(row.iter().map(|&next| to_id(remap[to_index(next)])));
+        }
+        self.trans = trans;
+        self.start = to_id(remap[start]);
+        if let Some(set) = self.match_set.take() {
+            self.match_set = Some(set.remap(&remap, self.state_count));
+        }
+    }
+
+    /// This routine shuffles all match states in this DFA---according to the
+    /// given map---to the beginning of the DFA such that every non-match state
+    /// appears after every match state. (With one exception: the special dead
+    /// state remains as the first state.) The given map should have length
+    /// exactly equivalent to the number of states in this DFA.
+    ///
+    /// The purpose of doing this shuffling is to avoid the need to store
+    /// additional state to determine whether a state is a match state or not.
+    /// It also enables a single conditional in the core matching loop instead
+    /// of two.
+    ///
+    /// This updates `self.max_match` to point to the last matching state as
+    /// well as `self.start` if the starting state was moved. The map returned
+    /// takes the original index of each state to its new index.
+    pub fn shuffle_match_states(&mut self, is_match: &[bool]) -> Vec<usize> {
+        assert!(
+            !self.premultiplied,
+            "cannot shuffle match states of premultiplied DFA"
+        );
+        assert_eq!(self.state_count, is_match.len());
+
+        let mut remap: Vec<usize> = (0..self.state_count).collect();
+        if self.state_count <= 1 {
+            return remap;
+        }
+
+        let mut first_non_match = 1;
+        while first_non_match < self.state_count && is_match[first_non_match] {
+            first_non_match += 1;
+        }
+
+        let mut swaps: Vec<S> = vec![dead_id(); self.state_count];
+        let mut cur = self.state_count - 1;
+        while cur > first_non_match {
+            if is_match[cur] {
+                self.swap_states(
+                    S::from_usize(cur),
+                    S::from_usize(first_non_match),
+                );
+                swaps[cur] = S::from_usize(first_non_match);
+                swaps[first_non_match] = S::from_usize(cur);
+                remap.swap(cur, first_non_match);
+
+                first_non_match += 1;
+                while first_non_match < cur && is_match[first_non_match] {
+                    first_non_match += 1;
+                }
+            }
+            cur -= 1;
+        }
+        for id in (0..self.state_count).map(S::from_usize) {
+            for (_, next) in self.get_state_mut(id).iter_mut() {
+                if swaps[next.to_usize()] != dead_id() {
+                    *next = swaps[next.to_usize()];
+                }
+            }
+        }
+        if swaps[self.start.to_usize()] != dead_id() {
+            self.start = swaps[self.start.to_usize()];
+        }
+        self.max_match = S::from_usize(first_non_match - 1);
+        remap
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: AsRef<[S]>, S: StateID> fmt::Debug for Repr<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_with_labels(f, None)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: AsRef<[S]>, S: StateID> Repr<T, S> {
+    fn fmt_with_labels(
+        &self,
+        f: &mut fmt::Formatter,
+        labels: Option<&StateLabels>,
+    ) -> fmt::Result {
+        fn state_status<T: AsRef<[S]>, S: StateID>(
+            dfa: &Repr<T, S>,
+            id: S,
+        ) -> &'static str {
+            if id == dead_id() {
+                if dfa.is_match_state(id) {
+                    "D*"
+                } else {
+                    "D "
+                }
+            } else if id == dfa.start_state() {
+                if dfa.is_match_state(id) {
+                    ">*"
+                } else {
+                    "> "
+                }
+            } else {
+                if dfa.is_match_state(id) {
+                    " *"
+                } else {
+                    "  "
+                }
+            }
+        }
+
+        writeln!(f, "DenseDFA(")?;
+        for (id, state) in self.states() {
+            let status = state_status(self, id);
+            match labels.and_then(|labels| labels.get(id)) {
+                None => {
+                    writeln!(f, "{}{:06}: {:?}", status, id.to_usize(), state)?
+                }
+                Some(label) => writeln!(
+                    f,
+                    "{}{:06} ({}): {:?}",
+                    status,
+                    id.to_usize(),
+                    label,
+                    state
+                )?,
+            }
+        }
+        writeln!(f, ")")?;
+        Ok(())
+    }
+
+    /// Render this DFA in the DOT language, optionally showing the given
+    /// state labels.
+    fn to_dot(&self, labels: Option<&StateLabels>) -> String {
+        use std::fmt::Write;
+
+        let mut dot = String::new();
+        // Writing to a String never fails.
+        writeln!(dot, "digraph dfa {{").unwrap();
+        writeln!(dot, "    rankdir=LR;").unwrap();
+        writeln!(dot, "    node [shape=circle];").unwrap();
+        writeln!(dot, "    start [shape=point];").unwrap();
+        writeln!(dot, "    start -> {};", self.start_state().to_usize())
+            .unwrap();
+        for (id, state) in self.states() {
+            if id == dead_id() {
+                continue;
+            }
+            let shape = if self.is_match_state(id) {
+                "doublecircle"
+            } else {
+                "circle"
+            };
+            match labels.and_then(|labels| labels.get(id)) {
+                None => {
+                    writeln!(dot, "    {} [shape={}];", id.to_usize(), shape)
+                        .unwrap()
+                }
+                Some(label) => writeln!(
+                    dot,
+                    "    {} [shape={}, label=\"{}\\n{}\"];",
+                    id.to_usize(),
+                    shape,
+                    id.to_usize(),
+                    nfa::dot_escape(label),
+                )
+                .unwrap(),
+            }
+            for (start, end, next) in state.sparse_transitions() {
+                if next == dead_id() {
+                    continue;
+                }
+                let range = if start == end {
+                    escape(start)
+                } else {
+                    format!("{}-{}", escape(start), escape(end))
+                };
+                writeln!(
+                    dot,
+                    "    {} -> {} [label=\"{}\"];",
+                    id.to_usize(),
+                    next.to_usize(),
+                    nfa::dot_escape(&range),
+                )
+                .unwrap();
+            }
+        }
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+}
+
+/// Human readable labels for the states of a dense DFA.
+///
+/// Labels are purely a debugging aid. They are kept apart from the DFA so
+/// that searching never pays for them, and are only serialized when asked
+/// for explicitly with
+/// [`DenseDFA::to_bytes_with_labels_little_endian`](enum.DenseDFA.html#method.to_bytes_with_labels_little_endian)
+/// (or its big and native endian variants). A DFA can be printed along with
+/// its labels via
+/// [`DenseDFA::with_labels`](enum.DenseDFA.html#method.with_labels).
+///
+/// Labels are keyed by state identifier. Any operation that renumbers the
+/// states of a DFA, such as minimization or premultiplication, invalidates
+/// them.
+///
+/// # Example
+///
+/// ```
+/// use regex_automata::{dense::StateLabels, DenseDFA, DFA};
+///
+/// # fn example() -> Result<(), regex_automata::Error> {
+/// let dfa = DenseDFA::new("[0-9]+")?;
+/// let mut labels = StateLabels::new();
+/// labels.set(dfa.start_state(), "before digits");
+///
+/// let bytes = dfa.to_bytes_with_labels_native_endian(&labels)?;
+/// let loaded: DenseDFA<&[usize], usize> = unsafe {
+///     DenseDFA::from_bytes(&bytes)
+/// };
+/// assert_eq!(Some(3), loaded.find(b"123"));
+///
+/// let labels = StateLabels::from_dfa_bytes(&bytes)?;
+/// assert_eq!(Some("before digits"), labels.get(loaded.start_state()));
+/// # Ok(()) }; example().unwrap()
+/// ```
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct StateLabels {
+    labels: BTreeMap<usize, String>,
+}
+
+#[cfg(feature = "std")]
+impl StateLabels {
+    /// Create an empty set of labels.
+    pub fn new() -> StateLabels {
+        StateLabels::default()
+    }
+
+    /// Set the label of the given state, replacing any previous label.
+    pub fn set<S: StateID>(&mut self, id: S, label: &str) {
+        self.labels.insert(id.to_usize(), label.to_string());
+    }
+
+    /// Return the label of the given state, if it has one.
+    pub fn get<S: StateID>(&self, id: S) -> Option<&str> {
+        self.labels.get(&id.to_usize()).map(|label| label.as_str())
+    }
+
+    /// Return the number of labeled states.
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// Returns true if and only if no state has a label.
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// Read the labels following a dense DFA serialized with
+    /// [`DenseDFA::to_bytes_with_labels_little_endian`](enum.DenseDFA.html#method.to_bytes_with_labels_little_endian)
+    /// or a related routine. The endianness is detected automatically.
+    ///
+    /// If the DFA was serialized without labels, then this returns an empty
+    /// set of labels. An error is returned if the bytes aren't a dense DFA
+    /// in its standard (not run length or varint encoded) format, or if the
+    /// labels are truncated or not valid UTF-8.
+    pub fn from_dfa_bytes(buf: &[u8]) -> Result<StateLabels> {
+        let header = DFAHeader::from_bytes(buf)?;
+        if header.kind() != DFAKind::Dense
+            || header.is_run_length_encoded()
+            || header.is_varint_encoded()
+        {
+            return Err(Error::deserialize(
+                "state labels require a dense DFA in its standard format",
+            ));
+        }
+        if !header.has_labels() {
+            return Ok(StateLabels::new());
+        }
+        // The header ends with the byte class map (or the number of
+        // classes), followed by the skip set and match set, if any.
+        let mut header_len = LABEL.len() + 32;
+        header_len += if header.is_classes_omitted() { 8 } else { 256 };
+        if header.has_skip_bytes() {
+            header_len += 32;
+        }
+        if header.has_match_set() {
+            header_len += 8 * MatchSet::words(header.state_count());
+        }
+        let start = header
+            .state_count()
+            .checked_mul(header.alphabet_len())
+            .and_then(|n| n.checked_mul(header.state_id_size()))
+            .and_then(|n| n.checked_add(header_len));
+        let rest = match start {
+            Some(start) if start <= buf.len() => &buf[start..],
+            _ => {
+                return Err(Error::deserialize(
+                    "DFA transition table is truncated",
+                ))
+            }
+        };
+        if header.is_native_endian() {
+            StateLabels::read::<NativeEndian>(rest)
+        } else if cfg!(target_endian = "little") {
+            StateLabels::read::<BigEndian>(rest)
+        } else {
+            StateLabels::read::<LittleEndian>(rest)
+        }
+    }
+
+    fn read<A: ByteOrder>(mut buf: &[u8]) -> Result<StateLabels> {
+        fn read_u64<A: ByteOrder>(buf: &mut &[u8]) -> Result<usize> {
+            if buf.len() < 8 {
+                return Err(Error::deserialize("state labels are truncated"));
+            }
+            let n = A::read_u64(buf);
+            *buf = &buf[8..];
+            if n as usize as u64 != n {
+                return Err(Error::deserialize(
+                    "state label section value does not fit in usize",
+                ));
+            }
+            Ok(n as usize)
+        }
+
+        let mut labels = StateLabels::new();
+        for _ in 0..read_u64::<A>(&mut buf)? {
+            let id = read_u64::<A>(&mut buf)?;
+            let len = read_u64::<A>(&mut buf)?;
+            if buf.len() < len {
+                return Err(Error::deserialize("state labels are truncated"));
+            }
+            let label = str::from_utf8(&buf[..len]).map_err(|_| {
+                Error::deserialize("state label is not valid UTF-8")
+            })?;
+            labels.labels.insert(id, label.to_string());
+            buf = &buf[len..];
+        }
+        Ok(labels)
+    }
+}
+
+/// A dense DFA paired with labels for its states, for debugging.
+///
+/// This is created by
+/// [`DenseDFA::with_labels`](enum.DenseDFA.html#method.with_labels). Its
+/// only purpose is its `Debug` implementation.
+#[cfg(feature = "std")]
+pub struct LabeledDFA<'a, T: AsRef<[S]> + 'a, S: StateID + 'a> {
+    dfa: &'a DenseDFA<T, S>,
+    labels: &'a StateLabels,
+}
+
+#[cfg(feature = "std")]
+impl<'a, T: AsRef<[S]>, S: StateID> LabeledDFA<'a, T, S> {
+    /// Render the DFA in the DOT language, with each labeled state showing
+    /// its label beneath its identifier.
+    ///
+    /// This is otherwise the same as
+    /// [`DenseDFA::to_dot`](enum.DenseDFA.html#method.to_dot).
+    pub fn to_dot(&self) -> String {
+        self.dfa.repr().to_dot(Some(self.labels))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, T: AsRef<[S]>, S: StateID> fmt::Debug for LabeledDFA<'a, T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.dfa.repr().fmt_with_labels(f, Some(self.labels))
+    }
+}
+
+/// A view of a single state of a dense DFA.
+///
+/// This is returned by
+/// [`DenseDFA::state`](enum.DenseDFA.html#method.state). Its transitions are
+/// given in terms of bytes, regardless of whether the DFA uses byte classes.
+///
+/// `'a` corresponds to the lifetime of the DFA and `S` corresponds to its
+/// state identifier representation.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug)]
+pub struct StateView<'a, S: 'a> {
+    id: S,
+    is_match: bool,
+    classes: &'a ByteClasses,
+    transitions: &'a [S],
+    stride: usize,
+}
+
+#[cfg(feature = "std")]
+impl<'a, S: StateID> StateView<'a, S> {
+    /// The identifier of this state.
+    pub fn id(&self) -> S {
+        self.id
+    }
+
+    /// Returns true if and only if this is a match state.
+    pub fn is_match(&self) -> bool {
+        self.is_match
+    }
+
+    /// Returns true if and only if this is the dead state.
+    pub fn is_dead(&self) -> bool {
+        self.id == dead_id()
+    }
+
+    /// Return the state that this state transitions to on the given byte.
+    pub fn next_state(&self, byte: u8) -> S {
+        self.transitions[self.classes.get(byte) as usize * self.stride]
+    }
+
+    /// Return an iterator over the transitions of this state.
+    ///
+    /// Each item is an inclusive range of bytes along with the state that
+    /// every byte in the range transitions to. The ranges are in ascending
+    /// order, cover every byte exactly once, and adjacent ranges always lead
+    /// to different states. Transitions to the dead state are included.
+    pub fn transitions(&self) -> StateViewTransitions<'a, S> {
+        StateViewTransitions { state: *self, next: 0 }
+    }
+}
+
+/// An iterator over the transitions of a single dense DFA state, as byte
+/// ranges.
+///
+/// This is created by
+/// [`StateView::transitions`](struct.StateView.html#method.transitions).
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct StateViewTransitions<'a, S: 'a> {
+    state: StateView<'a, S>,
+    next: usize,
+}
+
+#[cfg(feature = "std")]
+impl<'a, S: StateID> Iterator for StateViewTransitions<'a, S> {
+    type Item = (u8, u8, S);
+
+    fn next(&mut self) -> Option<(u8, u8, S)> {
+        if self.next > 255 {
+            return None;
+        }
+        let start = self.next as u8;
+        let to = self.state.next_state(start);
+        let mut end = start;
+        while end < 255 && self.state.next_state(end + 1) == to {
+            end += 1;
+        }
+        self.next = end as usize + 1;
+        Some((start, end, to))
+    }
+}
+
+/// An iterator over all states in a DFA.
+///
+/// This iterator yields a tuple for each state. The first element of the
+/// tuple corresponds to a state's identifier, and the second element
+/// corresponds to the state itself (comprised of its transitions).
+///
+/// If this DFA is premultiplied, then the state identifiers are in turn
+/// premultiplied as well, making them usable without additional modification.
+///
+/// `'a` corresponding to the lifetime of original DFA, `T` corresponds to
+/// the type of the transition table itself and `S` corresponds to the state
+/// identifier representation.
+#[cfg(feature = "std")]
+pub(crate) struct StateIter<'a, T: 'a, S: 'a> {
+    dfa: &'a Repr<T, S>,
+    it: ops::Range<usize>,
+}
+
+#[cfg(feature = "std")]
+impl<'a, T: AsRef<[S]>, S: StateID> Iterator for StateIter<'a, T, S> {
+    type Item = (S, State<'a, S>);
+
+    fn next(&mut self) -> Option<(S, State<'a, S>)> {
+        self.it.next().map(|index| {
+            let state = self.dfa.state_at(index);
+            let id = if self.dfa.premultiplied {
+                index * self.dfa.alphabet_len()
+            } else {
+                index
+            };
+            (S::from_usize(id), state)
+        })
+    }
+}
+
+/// An immutable representation of a single DFA state.
+///
+/// `'a` correspondings to the lifetime of a DFA's transition table and `S`
+/// corresponds to the state identifier representation.
+#[cfg(feature = "std")]
+pub(crate) struct State<'a, S: 'a> {
+    /// The transitions of this state, where the transition on class `c` is
+    /// at `c * stride`. The stride is 1 for a row-major DFA and the number
+    /// of states for a column-major DFA.
+    transitions: &'a [S],
+    stride: usize,
+}
+
+#[cfg(feature = "std")]
+impl<'a, S: StateID> State<'a, S> {
+    /// Return an iterator over all transitions in this state. This yields
+    /// a number of transitions equivalent to the alphabet length of the
+    /// corresponding DFA.
+    ///
+    /// Each transition is represented by a tuple. The first element is
+    /// the input byte for that transition and the second element is the
+    /// transitions itself.
+    pub fn transitions(&self) -> StateTransitionIter<S> {
+        let it = self.transitions.iter().step_by(self.stride).enumerate();
+        StateTransitionIter { it }
+    }
+
+    /// Return an iterator over a sparse representation of the transitions in
+    /// this state. Only non-dead transitions are returned.
+    ///
+    /// The "sparse" representation in this case corresponds to a sequence of
+    /// triples. The first two elements of the triple comprise an inclusive
+    /// byte range while the last element corresponds to the transition taken
+    /// for all bytes in the range.
+    ///
+    /// This is somewhat more condensed than the classical sparse
+    /// representation (where you have an element for every non-dead
+    /// transition), but in practice, checking if a byte is in a range is very
+    /// cheap and using ranges tends to conserve quite a bit more space.
+    pub fn sparse_transitions(&self) -> StateSparseTransitionIter<S> {
+        StateSparseTransitionIter { dense: self.transitions(), cur: None }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, S: StateID> fmt::Debug for State<'a, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut transitions = vec![];
+        for (start, end, next_id) in self.sparse_transitions() {
+            let line = if start == end {
+                format!("{} => {}", escape(start), next_id.to_usize())
+            } else {
+                format!(
+                    "{}-{} => {}",
+                    escape(start),
+                    escape(end),
+                    next_id.to_usize(),
+                )
+            };
+            transitions.push(line);
+        }
+        write!(f, "{}", transitions.join(", "))?;
+        Ok(())
+    }
+}
+
+/// An iterator over all transitions in a single DFA state. This yields
+/// a number of transitions equivalent to the alphabet length of the
+/// corresponding DFA.
+///
+/// Each transition is represented by a tuple. The first element is the input
+/// byte for that transition and the second element is the transitions itself.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub(crate) struct StateTransitionIter<'a, S: 'a> {
+    it: iter::Enumerate<iter::StepBy<slice::Iter<'a, S>>>,
+}
+
+#[cfg(feature = "std")]
+impl<'a, S: StateID> Iterator for StateTransitionIter<'a, S> {
+    type Item = (u8, S);
+
+    fn next(&mut self) -> Option<(u8, S)> {
+        self.it.next().map(|(i, &id)| (i as u8, id))
+    }
+}
+
+/// An iterator over all transitions in a single DFA state using a sparse
+/// representation.
+///
+/// Each transition is represented by a triple. The first two elements of the
+/// triple comprise an inclusive byte range while the last element corresponds
+/// to the transition taken for all bytes in the range.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub(crate) struct StateSparseTransitionIter<'a, S: 'a> {
+    dense: StateTransitionIter<'a, S>,
+    cur: Option<(u8, u8, S)>,
+}
+
+#[cfg(feature = "std")]
+impl<'a, S: StateID> Iterator for StateSparseTransitionIter<'a, S> {
+    type Item = (u8, u8, S);
+
+    fn next(&mut self) -> Option<(u8, u8, S)> {
+        while let Some((b, next)) = self.dense.next() {
+            let (prev_start, prev_end, prev_next) = match self.cur {
+                Some(t) => t,
+                None => {
+                    self.cur = Some((b, b, next));
+                    continue;
+                }
+            };
+            if prev_next == next {
+                self.cur = Some((prev_start, b, prev_next));
+            } else {
+                self.cur = Some((b, b, next));
+                if prev_next != dead_id() {
+                    return Some((prev_start, prev_end, prev_next));
+                }
+            }
+        }
+        if let Some((start, end, next)) = self.cur.take() {
+            if next != dead_id() {
+                return Some((start, end, next));
+            }
+        }
+        None
+    }
+}
+
+/// A mutable representation of a single DFA state.
+///
+/// `'a` correspondings to the lifetime of a DFA's transition table and `S`
+/// corresponds to the state identifier representation.
+#[cfg(feature = "std")]
+pub(crate) struct StateMut<'a, S: 'a> {
+    transitions: &'a mut [S],
+}
+
+#[cfg(feature = "std")]
+impl<'a, S: StateID> StateMut<'a, S> {
+    /// Return an iterator over all transitions in this state. This yields
+    /// a number of transitions equivalent to the alphabet length of the
+    /// corresponding DFA.
+    ///
+    /// Each transition is represented by a tuple. The first element is the
+    /// input byte for that transition and the second element is a mutable
+    /// reference to the transition itself.
+    pub fn iter_mut(&mut self) -> StateTransitionIterMut<S> {
+        StateTransitionIterMut { it: self.transitions.iter_mut().enumerate() }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, S: StateID> fmt::Debug for StateMut<'a, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = State { transitions: self.transitions, stride: 1 };
+        fmt::Debug::fmt(&state, f)
+    }
+}
+
+/// A mutable iterator over all transitions in a DFA state.
+///
+/// Each transition is represented by a tuple. The first element is the
+/// input byte for that transition and the second element is a mutable
+/// reference to the transition itself.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub(crate) struct StateTransitionIterMut<'a, S: 'a> {
+    it: iter::Enumerate<slice::IterMut<'a, S>>,
+}
+
+#[cfg(feature = "std")]
+impl<'a, S: StateID> Iterator for StateTransitionIterMut<'a, S> {
+    type Item = (u8, &'a mut S);
+
+    fn next(&mut self) -> Option<(u8, &'a mut S)> {
+        self.it.next().map(|(i, id)| (i as u8, id))
+    }
+}
+
+/// Returns the number of copies that the counted repetitions in the given
+/// expression expand to, where nested repetitions multiply. See
+/// `Builder::repetition_limit` for details.
+#[cfg(feature = "std")]
+fn repetition_count(expr: &Hir) -> usize {
+    match *expr.kind() {
+        HirKind::Empty
+        | HirKind::Literal(_)
+        | HirKind::Class(_)
+        | HirKind::Anchor(_)
+        | HirKind::WordBoundary(_) => 0,
+        HirKind::Group(ref group) => repetition_count(&group.hir),
+        HirKind::Repetition(ref rep) => {
+            let inner = repetition_count(&rep.hir);
+            let bound = match rep.kind {
+                hir::RepetitionKind::ZeroOrOne
+                | hir::RepetitionKind::ZeroOrMore
+                | hir::RepetitionKind::OneOrMore => return inner,
+                hir::RepetitionKind::Range(ref range) => match *range {
+                    hir::RepetitionRange::Exactly(n)
+                    | hir::RepetitionRange::AtLeast(n)
+                    | hir::RepetitionRange::Bounded(_, n) => n as usize,
+                },
+            };
+            bound.saturating_mul(cmp::max(1, inner))
+        }
+        HirKind::Concat(ref exprs) | HirKind::Alternation(ref exprs) => exprs
+            .iter()
+            .fold(0, |sum, e| sum.saturating_add(repetition_count(e))),
+    }
+}
+
+/// Returns the longest common prefix (or suffix, if `suffix` is true) of the
+/// given literals. If there are no literals, then this is empty.
+#[cfg(feature = "std")]
+fn longest_common_affix<B: AsRef<[u8]>>(
+    literals: &[B],
+    suffix: bool,
+) -> &[u8] {
+    let first = match literals.first() {
+        None => return &[],
+        Some(first) => first.as_ref(),
+    };
+    let mut len = first.len();
+    for lit in &literals[1..] {
+        let lit = lit.as_ref();
+        len = if suffix {
+            first
+                .iter()
+                .rev()
+                .zip(lit.iter().rev())
+                .take(len)
+                .take_while(|&(a, b)| a == b)
+                .count()
+        } else {
+            first
+                .iter()
+                .zip(lit.iter())
+                .take(len)
+                .take_while(|&(a, b)| a == b)
+                .count()
+        };
+    }
+    if suffix {
+        &first[first.len() - len..]
+    } else {
+        &first[..len]
+    }
+}
+
+/// The type of DFA built by default, as returned by `Builder::build`.
+#[cfg(feature = "std")]
+type OwnedDFA = DenseDFA<Vec<usize>, usize>;
+
+/// A builder for constructing a deterministic finite automaton from regular
+/// expressions.
+///
+/// This builder permits configuring several aspects of the construction
+/// process such as case insensitivity, Unicode support and various options
+/// that impact the size of the generated DFA. In some cases, options (like
+/// performing DFA minimization) can come with a substantial additional cost.
+///
+/// This builder always constructs a *single* DFA. As such, this builder can
+/// only be used to construct regexes that either detect the presence of a
+/// match or find the end location of a match. A single DFA cannot produce both
+/// the start and end of a match. For that information, use a
+/// [`Regex`](struct.Regex.html), which can be similarly configured using
+/// [`RegexBuilder`](struct.RegexBuilder.html).
+///
+/// A builder holds nothing but its configuration. Every build method takes
+/// `&self`, and the parser, compiler and determinizer used for each pattern
+/// are created from scratch and dropped before it returns, so building one
+/// pattern never influences the DFA built for the next. There is thus no
+/// need to reset or clone a builder between patterns. Scratch space that is
+/// reused across builds for speed, if wanted, is owned by the caller as a
+/// [`BuildScratch`](struct.BuildScratch.html), and its contents never affect
+/// the DFAs built either.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct Builder {
+    parser: ParserBuilder,
+    nfa: nfa::Builder,
+    anchored: bool,
+    full_match: bool,
+    layout: Layout,
+    shuffle_match_states: bool,
+    minimize: bool,
+    premultiply: bool,
+    byte_classes: bool,
+    reverse: bool,
+    allow_invalid_utf8: bool,
+    match_priority: MatchPriority,
+    skip_bytes: Vec<u8>,
+    prefilter: bool,
+    reverse_suffix_prefilter: bool,
+    accelerate: bool,
+    record_provenance: bool,
+    states_capacity_hint: usize,
+    determinize_cache_limit: Option<usize>,
+    determinize_fast_hash: bool,
+    dfa_size_limit: Option<usize>,
+    repetition_limit: Option<usize>,
+}
+
+#[cfg(feature = "std")]
+impl Builder {
+    /// Create a new DenseDFA builder with the default configuration.
+    pub fn new() -> Builder {
+        let mut nfa = nfa::Builder::new();
+        // This is enabled by default, but we set it here anyway. Since we're
+        // building a DFA, shrinking the NFA is always a good idea.
+        nfa.shrink(true);
+        Builder {
+            parser: ParserBuilder::new(),
+            nfa,
+            anchored: false,
+            full_match: false,
+            layout: Layout::RowMajor,
+            shuffle_match_states: true,
+            minimize: false,
+            premultiply: true,
+            byte_classes: true,
+            reverse: false,
+            allow_invalid_utf8: false,
+            match_priority: MatchPriority::LeftmostFirst,
+            skip_bytes: vec![],
+            prefilter: false,
+            reverse_suffix_prefilter: false,
+            accelerate: false,
+            record_provenance: false,
+            states_capacity_hint: 0,
+            determinize_cache_limit: None,
+            determinize_fast_hash: false,
+            dfa_size_limit: None,
+            repetition_limit: None,
+        }
+    }
+
+    /// Build a DFA from the given pattern.
+    ///
+    /// If there was a problem parsing or compiling the pattern, then an error
+    /// is returned.
+    pub fn build(&self, pattern: &str) -> Result<DenseDFA<Vec<usize>, usize>> {
+        self.build_with_size::<usize>(pattern)
+    }
+
+    /// Build a DFA from the given pattern using a specific representation for
+    /// the DFA's state IDs.
+    ///
+    /// If there was a problem parsing or compiling the pattern, then an error
+    /// is returned.
+    ///
+    /// The representation of state IDs is determined by the `S` type
+    /// parameter. In general, `S` is usually one of `u8`, `u16`, `u32`, `u64`
+    /// or `usize`, where `usize` is the default used for `build`. The purpose
+    /// of specifying a representation for state IDs is to reduce the memory
+    /// footprint of a DFA.
+    ///
+    /// When using this routine, the chosen state ID representation will be
+    /// used throughout determinization and minimization, if minimization
+    /// was requested. Even if the minimized DFA can fit into the chosen
+    /// state ID representation but the initial determinized DFA cannot,
+    /// then this will still return an error. To get a minimized DFA with a
+    /// smaller state ID representation, first build it with a bigger state ID
+    /// representation, and then shrink the size of the DFA using one of its
+    /// conversion routines, such as
+    /// [`DenseDFA::to_u16`](enum.DenseDFA.html#method.to_u16).
+    pub fn build_with_size<S: StateID>(
+        &self,
+        pattern: &str,
+    ) -> Result<DenseDFA<Vec<S>, S>> {
+        self.build_from_hir(&self.build_hir(pattern)?)
+    }
+
+    /// Build a DFA from the given pattern, reusing the allocations in the
+    /// given scratch space.
+    ///
+    /// This is the same as
+    /// [`build`](struct.Builder.html#method.build), except that it amortizes
+    /// allocation when building many DFAs in a row. See
+    /// [`BuildScratch`](struct.BuildScratch.html) for more details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let builder = dense::Builder::new();
+    /// let mut scratch = dense::BuildScratch::new();
+    /// let mut dfas = vec![];
+    /// for pattern in &["foo[0-9]+", "ba[rz]", "quux"] {
+    ///     dfas.push(builder.build_reusing(&mut scratch, pattern)?);
+    /// }
+    /// assert_eq!(Some(6), dfas[0].find(b"foo123"));
+    /// assert_eq!(Some(3), dfas[1].find(b"baz"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn build_reusing(
+        &self,
+        scratch: &mut BuildScratch,
+        pattern: &str,
+    ) -> Result<DenseDFA<Vec<usize>, usize>> {
+        let hir = self.build_hir(pattern)?;
+        let nfa = self.nfa.build(&hir)?;
+        let (mut dfa, _) =
+            self.build_from_nfa_with_scratch(&nfa, Some(&mut scratch.det))?;
+        self.set_prefilter(&mut dfa, &hir);
+        Ok(dfa)
+    }
+
+    /// Build a DFA from an already parsed pattern.
+    pub(crate) fn build_from_hir<S: StateID>(
+        &self,
+        hir: &Hir,
+    ) -> Result<DenseDFA<Vec<S>, S>> {
+        let mut dfa = self.build_from_nfa(&self.nfa.build(hir)?)?;
+        self.set_prefilter(&mut dfa, hir);
+        Ok(dfa)
+    }
+
+    /// Build a DFA from the given pattern and return statistics about its
+    /// construction.
+    ///
+    /// This is useful for comparing the effect of different configuration
+    /// knobs (such as `byte_classes`, `minimize` or `unicode`) on the size of
+    /// the resulting DFA without needing to inspect debug output.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::dense;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let (_, stats) = dense::Builder::new()
+    ///     .minimize(true)
+    ///     .build_with_stats("[a-z]+[0-9]+")?;
+    /// assert!(stats.nfa_states() > 0);
+    /// let minimized = stats.minimized_states().unwrap();
+    /// assert!(minimized <= stats.determinized_states());
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn build_with_stats(
+        &self,
+        pattern: &str,
+    ) -> Result<(DenseDFA<Vec<usize>, usize>, BuildStats)> {
+        let hir = self.build_hir(pattern)?;
+        let nfa = self.nfa.build(&hir)?;
+        let (mut dfa, stats) = self.build_from_nfa_with_stats(&nfa)?;
+        self.set_prefilter(&mut dfa, &hir);
+        Ok((dfa, stats))
+    }
+
+    /// Build both the determinized and the minimized DFA for the given
+    /// pattern, in that order.
+    ///
+    /// This is equivalent to calling `build` with
+    /// [`minimize`](struct.Builder.html#method.minimize) disabled and then
+    /// again with it enabled, but only performs determinization once: the
+    /// minimized DFA is obtained by minimizing a copy of the determinized
+    /// one. This is useful for comparing the effect of minimization, or for
+    /// keeping the unminimized DFA around for debugging while shipping the
+    /// minimized one. Every other option applies to both DFAs, and the
+    /// `minimize` setting of this builder is ignored.
+    ///
+    /// Both DFAs recognize the same language. The determinized DFA has the
+    /// structure produced by the powerset construction, from which the
+    /// minimized DFA is derived by merging equivalent states.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let (dfa, min) = dense::Builder::new().build_both("a+b|c+b")?;
+    /// assert!(min.memory_usage() < dfa.memory_usage());
+    /// assert!(min.is_minimal() && !dfa.is_minimal());
+    /// assert_eq!(dfa.find(b"xcccb1"), min.find(b"xcccb1"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn build_both(&self, pattern: &str) -> Result<(OwnedDFA, OwnedDFA)> {
+        let hir = self.build_hir(pattern)?;
+        let nfa = self.nfa.build(&hir)?;
+        let (determinized, _) = self.determinize(&nfa, None)?;
+        let mut minimized = determinized.clone();
+        minimized.minimize();
+
+        let mut determinized = self.finish(determinized)?;
+        let mut minimized = self.finish(minimized)?;
+        self.set_prefilter(&mut determinized, &hir);
+        self.set_prefilter(&mut minimized, &hir);
+        Ok((determinized, minimized))
+    }
+
+    /// Compile the given regular expression to the NFA that `build` would
+    /// determinize, using this builder's configuration.
+    ///
+    /// This is useful for inspecting the intermediate automaton. See
+    /// [`NFA`](../struct.NFA.html) for more details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::dense;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let nfa = dense::Builder::new().anchored(true).build_nfa("a|b")?;
+    /// let start = nfa.state(nfa.start());
+    /// assert_eq!(2, start.epsilons().len());
+    /// assert!(nfa.to_dot().starts_with("digraph nfa {"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn build_nfa(&self, pattern: &str) -> Result<NFA> {
+        self.nfa.build(&self.build_hir(pattern)?)
+    }
+
+    /// Build a DFA that matches any one of the given literals.
+    ///
+    /// This is equivalent to building a DFA from an alternation of the
+    /// literals, in order and with each one escaped, but it is much faster
+    /// when there are many of them. Instead of parsing and translating a
+    /// pattern, the literals are arranged directly into a trie, which is
+    /// then determinized with this builder's configuration. All of the
+    /// options on this builder other than the ones that control parsing
+    /// (such as `case_insensitive` or `unicode`) apply as usual. In
+    /// particular, matches follow the usual leftmost first semantics, so
+    /// when one literal is a prefix of a later one, the later one never
+    /// matches.
+    ///
+    /// The literals are arbitrary bytes. When building an unanchored DFA
+    /// with [`allow_invalid_utf8`](struct.Builder.html#method.allow_invalid_utf8)
+    /// disabled, a match can only start at a position that isn't in the
+    /// middle of a UTF-8 encoded codepoint.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let blocklist: &[&[u8]] = &[b"foo", b"bar", b"quux"];
+    /// let dfa = dense::Builder::new().build_literals(blocklist)?;
+    /// assert_eq!(Some(7), dfa.find(b"xx quux"));
+    /// assert_eq!(None, dfa.find(b"fo ba qu"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn build_literals<B: AsRef<[u8]>>(
+        &self,
+        literals: &[B],
+    ) -> Result<DenseDFA<Vec<usize>, usize>> {
+        self.build_literals_with_size::<B, usize>(literals)
+    }
+
+    /// Build a DFA that matches any one of the given literals, using a
+    /// specific representation for the DFA's state IDs.
+    ///
+    /// See [`build_literals`](struct.Builder.html#method.build_literals) and
+    /// [`build_with_size`](struct.Builder.html#method.build_with_size) for
+    /// more details.
+    pub fn build_literals_with_size<B: AsRef<[u8]>, S: StateID>(
+        &self,
+        literals: &[B],
+    ) -> Result<DenseDFA<Vec<S>, S>> {
+        let mut dfa =
+            self.build_from_nfa(&self.nfa.build_literals(literals))?;
+        if self.wants_prefix() {
+            let prefix = longest_common_affix(literals, false);
+            dfa.repr_mut().prefix =
+                Prefix::new(prefix, !self.allow_invalid_utf8);
+        }
+        if self.wants_suffix() {
+            let suffix = longest_common_affix(literals, true);
+            dfa.repr_mut().prefix =
+                Prefix::new_reverse(suffix, !self.allow_invalid_utf8);
+        }
+        Ok(dfa)
+    }
+
+    /// Build a single DFA that matches any of the given patterns and reports
+    /// which of them matched, with its transitions stored in the sparse
+    /// representation.
+    ///
+    /// Each pattern is identified by its index in `patterns`. Every pattern
+    /// is parsed and compiled with this builder's configuration, and all of
+    /// them are determinized together, so that a search runs in time
+    /// proportional to the length of the haystack no matter how many
+    /// patterns there are. Since a large rule set tends to produce many
+    /// states with few distinct transitions each, the sparse representation
+    /// usually takes far less memory than a dense one would.
+    ///
+    /// Matches of different patterns are not prioritized against one
+    /// another. See [`MultiDFA`](../struct.MultiDFA.html) for how matches
+    /// are reported. Consequently, the `match_priority` option has no effect,
+    /// and neither does `minimize`, since minimization cannot yet distinguish
+    /// between match states of different patterns. The prefilter is never
+    /// used either.
+    ///
+    /// If any pattern fails to parse or compile, then the first such error is
+    /// returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::dense;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new()
+    ///     .build_many_sparse(&[r"[a-z]+@[a-z]+\.com", r"[0-9]{3}-[0-9]{4}"])?;
+    /// assert_eq!(2, dfa.pattern_count());
+    /// assert_eq!(Some((13, vec![1])), dfa.shortest_match(b"call 555-1234"));
+    /// assert_eq!(Some((12, vec![0])), dfa.shortest_match(b"mail a@b.com"));
+    /// assert_eq!(None, dfa.shortest_match(b"nothing here"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn build_many_sparse<P: AsRef<str>>(
+        &self,
+        patterns: &[P],
+    ) -> Result<MultiDFA<SparseDFA<Vec<u8>, usize>>> {
+        self.build_many_sparse_with_size::<P, usize>(patterns)
+    }
+
+    /// Build a sparse multi-pattern DFA using a specific representation for
+    /// the DFA's state IDs.
+    ///
+    /// See
+    /// [`build_many_sparse`](struct.Builder.html#method.build_many_sparse)
+    /// and [`build_with_size`](struct.Builder.html#method.build_with_size)
+    /// for more details.
+    pub fn build_many_sparse_with_size<P: AsRef<str>, S: StateID>(
+        &self,
+        patterns: &[P],
+    ) -> Result<MultiDFA<SparseDFA<Vec<u8>, S>>> {
+        let mut exprs = Vec::with_capacity(patterns.len());
+        for pattern in patterns {
+            exprs.push(self.build_hir(pattern.as_ref())?);
+        }
+        self.build_many_from_hirs(&exprs)
+    }
+
+    /// Build a sparse multi-pattern DFA from already parsed patterns.
+    pub(crate) fn build_many_from_hirs<S: StateID>(
+        &self,
+        exprs: &[Hir],
+    ) -> Result<MultiDFA<SparseDFA<Vec<u8>, S>>> {
+        if !self.skip_bytes.is_empty() {
+            return Err(Error::unsupported_sparse_skip_bytes());
+        }
+        let nfa = self.nfa.build_many(exprs)?;
+        // Every match state must record all of the patterns matching in it,
+        // so the NFA states following a match are never dropped.
+        let (dfa, ids) = self
+            .determinizer::<S>(&nfa)
+            .match_priority(MatchPriority::LeftmostLongest)
+            .build_with_patterns()?;
+        let mut remap = vec![];
+        let sparse = SparseDFA::from_dense_remapped(&dfa, &mut remap)?;
+        let max_match = dfa.state_id_to_index(dfa.max_match_state());
+        let matches = (1..=max_match)
+            .map(|i| (remap[i], ids[i].clone().into_boxed_slice()))
+            .collect();
+        Ok(MultiDFA::new(sparse, matches, exprs.len()))
+    }
+
+    /// Build a dense DFA for the alternation of the given patterns, using
+    /// leftmost first match semantics, along with the pattern reported by
+    /// each of its match states.
+    ///
+    /// Since the NFA states following a match are dropped, exactly one
+    /// pattern matches in each match state: the one whose match is preferred
+    /// at that point. These are written to `matches`, sorted by state
+    /// identifier. The DFA is never minimized and never uses a prefilter.
+    pub(crate) fn build_many_leftmost_first<S: StateID>(
+        &self,
+        exprs: &[Hir],
+        matches: &mut Vec<(S, PatternID)>,
+    ) -> Result<DenseDFA<Vec<S>, S>> {
+        let nfa = self.nfa.build_many(exprs)?;
+        let (mut dfa, ids) = self
+            .determinizer::<S>(&nfa)
+            .match_priority(MatchPriority::LeftmostFirst)
+            .build_with_patterns()?;
+        let mut stride = 1;
+        if self.wants_premultiply() {
+            dfa.premultiply()?;
+            stride = dfa.alphabet_len();
+        }
+        dfa.set_column_major(self.layout == Layout::ColumnMajor);
+        let max_match = dfa.state_id_to_index(dfa.max_match_state());
+        matches.clear();
+        matches.extend(
+            (1..=max_match).map(|i| (S::from_usize(i * stride), ids[i][0])),
+        );
+        Ok(dfa.into_dense_dfa())
+    }
+
+    /// Build a dense DFA directly from an NFA, such as one returned by
+    /// [`build_nfa`](struct.Builder.html#method.build_nfa) or loaded with
+    /// [`NFA::from_bytes`](../struct.NFA.html#method.from_bytes).
+    ///
+    /// Only the options on this builder that apply to determinization and
+    /// later phases, such as `minimize`, `premultiply`, `byte_classes`,
+    /// `longest_match` and `skip_bytes`, affect the DFA returned. Options
+    /// that shape the NFA, such as `anchored`, `reverse` or any of the
+    /// syntax options, were fixed when the NFA was compiled and are ignored
+    /// here. The same goes for `prefilter`, since computing a prefilter
+    /// requires the pattern.
+    pub fn build_from_nfa<S: StateID>(
+        &self,
+        nfa: &NFA,
+    ) -> Result<DenseDFA<Vec<S>, S>> {
+        self.build_from_nfa_with_stats(nfa).map(|(dfa, _)| dfa)
+    }
+
+    /// Build a dense DFA directly from an NFA while recording statistics
+    /// about each phase of construction.
+    fn build_from_nfa_with_stats<S: StateID>(
+        &self,
+        nfa: &NFA,
+    ) -> Result<(DenseDFA<Vec<S>, S>, BuildStats)> {
+        self.build_from_nfa_with_scratch(nfa, None)
+    }
+
+    /// Build a dense DFA directly from an NFA, optionally reusing the given
+    /// determinization buffers.
+    fn build_from_nfa_with_scratch<S: StateID>(
+        &self,
+        nfa: &NFA,
+        scratch: Option<&mut determinize::Scratch<S>>,
+    ) -> Result<(DenseDFA<Vec<S>, S>, BuildStats)> {
+        let (mut dfa, mut provenance) = self.determinize(nfa, scratch)?;
+        let mut stats = BuildStats {
+            nfa_states: nfa.len(),
+            determinized_states: dfa.state_count(),
+            minimized_states: None,
+            alphabet_len: dfa.alphabet_len(),
+            transition_table_bytes: dfa.memory_usage(),
+            premultiplied: false,
+            provenance: None,
+        };
+        if self.minimize {
+            let remap = dfa.minimize();
+            stats.minimized_states = Some(dfa.state_count());
+            if let Some(ref mut provenance) = provenance {
+                // A merged state represents every NFA state of the states
+                // merged into it.
+                let mut merged = vec![vec![]; dfa.state_count()];
+                for (old, nfa_states) in provenance.iter().enumerate() {
+                    merged[remap[old]].extend_from_slice(nfa_states);
+                }
+                for nfa_states in &mut merged {
+                    nfa_states.sort();
+                    nfa_states.dedup();
+                }
+                *provenance = merged;
+            }
+        }
+        stats.provenance = provenance;
+        stats.premultiplied = self.wants_premultiply();
+        Ok((self.finish(dfa)?, stats))
+    }
+
+    /// Determinize the given NFA, which yields the DFA that is then
+    /// (optionally) minimized.
+    fn determinize<S: StateID>(
+        &self,
+        nfa: &NFA,
+        scratch: Option<&mut determinize::Scratch<S>>,
+    ) -> Result<determinize::Determinized<S>> {
+        let match_priority = self.effective_match_priority();
+        if match_priority == MatchPriority::LeftmostLongest
+            && !nfa.is_anchored()
+        {
+            return Err(Error::unsupported_longest_match());
+        }
+
+        self.determinizer(nfa)
+            .match_priority(match_priority)
+            .record_provenance(self.record_provenance)
+            .shuffle_match_states(self.shuffle_match_states)
+            .build_with_provenance(scratch)
+    }
+
+    /// Apply the steps that follow minimization (premultiplication,
+    /// acceleration and skip bytes) to the given DFA.
+    fn finish<S: StateID>(
+        &self,
+        mut dfa: Repr<Vec<S>, S>,
+    ) -> Result<DenseDFA<Vec<S>, S>> {
+        if self.wants_premultiply() {
+            dfa.premultiply()?;
+        }
+        if self.accelerate {
+            dfa.accel = Accel::new(&dfa);
+        }
+        if !self.full_match {
+            dfa.skip = SkipBytes::new(&self.skip_bytes);
+        }
+        dfa.full_match = self.full_match;
+        dfa.set_column_major(self.layout == Layout::ColumnMajor);
+        Ok(dfa.into_dense_dfa())
+    }
+
+    /// Returns true if the DFA built should be premultiplied, which is never
+    /// the case for a column-major DFA.
+    fn wants_premultiply(&self) -> bool {
+        self.premultiply && self.layout == Layout::RowMajor
+    }
+
+    /// Returns the match priority used for determinization. Full matches
+    /// always use leftmost-longest semantics, since leftmost-first
+    /// determinization discards lower priority alternatives once a higher
+    /// priority one matches, even though a discarded alternative may be the
+    /// only one that can consume the rest of the input.
+    fn effective_match_priority(&self) -> MatchPriority {
+        if self.full_match {
+            MatchPriority::LeftmostLongest
+        } else {
+            self.match_priority
+        }
+    }
+
+    /// Returns a determinizer for the given NFA, configured according to this
+    /// builder's alphabet and determinization settings.
+    fn determinizer<'a, S: StateID>(
+        &self,
+        nfa: &'a NFA,
+    ) -> Determinizer<'a, S> {
+        let det = if self.byte_classes {
+            Determinizer::new(nfa).with_byte_classes()
+        } else {
+            Determinizer::new(nfa)
+        };
+        det.states_capacity_hint(self.states_capacity_hint)
+            .cache_limit(self.determinize_cache_limit)
+            .fast_hash(self.determinize_fast_hash)
+            .state_limit(self.dfa_size_limit)
+    }
+
+    /// Parses the given pattern into its high-level intermediate
+    /// representation.
+    ///
+    /// If a repetition limit is set and the pattern exceeds it, then an error
+    /// is returned.
+    pub(crate) fn build_hir(&self, pattern: &str) -> Result<Hir> {
+        let hir = self.parser.build().parse(pattern).map_err(Error::syntax)?;
+        if let Some(limit) = self.repetition_limit {
+            if repetition_count(&hir) > limit {
+                return Err(Error::repetition_too_big(limit));
+            }
+        }
+        Ok(hir)
+    }
+
+    /// Stores the required literal prefix of the given HIR on the DFA, if
+    /// the prefilter is enabled and applicable to this configuration.
+    fn set_prefilter<S: StateID>(
+        &self,
+        dfa: &mut DenseDFA<Vec<S>, S>,
+        hir: &Hir,
+    ) {
+        if self.wants_prefix() {
+            let prefixes = Literals::prefixes(hir);
+            dfa.repr_mut().prefix = Prefix::new(
+                prefixes.longest_common_prefix(),
+                !self.allow_invalid_utf8,
+            );
+        }
+        if self.wants_suffix() {
+            let suffixes = Literals::suffixes(hir);
+            dfa.repr_mut().prefix = Prefix::new_reverse(
+                suffixes.longest_common_suffix(),
+                !self.allow_invalid_utf8,
+            );
         }
     }
+
+    /// Returns true if a forward prefilter should be computed for DFAs built
+    /// with this configuration.
+    fn wants_prefix(&self) -> bool {
+        // Skip bytes may appear in the middle of a match in the haystack,
+        // which would prevent the literal from being found verbatim.
+        self.prefilter
+            && !self.anchored
+            && !self.full_match
+            && !self.reverse
+            && self.skip_bytes.is_empty()
+    }
+
+    /// Returns true if a reverse suffix prefilter should be computed for DFAs
+    /// built with this configuration.
+    fn wants_suffix(&self) -> bool {
+        self.reverse_suffix_prefilter
+            && !self.anchored
+            && !self.full_match
+            && self.reverse
+            && self.skip_bytes.is_empty()
+    }
+
+    /// Set whether matching must be anchored at the beginning of the input.
+    ///
+    /// When enabled, a match must begin at the start of the input. When
+    /// disabled, the DFA will act as if the pattern started with a `.*?`,
+    /// which enables a match to appear anywhere.
+    ///
+    /// By default this is disabled.
+    pub fn anchored(&mut self, yes: bool) -> &mut Builder {
+        self.anchored = yes;
+        self.nfa.anchored(yes || self.full_match);
+        self
+    }
+
+    /// Enable or disable the case insensitive flag by default.
+    ///
+    /// By default this is disabled. It may alternatively be selectively
+    /// enabled in the regular expression itself via the `i` flag.
+    pub fn case_insensitive(&mut self, yes: bool) -> &mut Builder {
+        self.parser.case_insensitive(yes);
+        self
+    }
+
+    /// Enable verbose mode in the regular expression.
+    ///
+    /// When enabled, verbose mode permits insigificant whitespace in many
+    /// places in the regular expression, as well as comments. Comments are
+    /// started using `#` and continue until the end of the line.
+    ///
+    /// By default, this is disabled. It may be selectively enabled in the
+    /// regular expression by using the `x` flag regardless of this setting.
+    pub fn ignore_whitespace(&mut self, yes: bool) -> &mut Builder {
+        self.parser.ignore_whitespace(yes);
+        self
+    }
+
+    /// Enable or disable the "dot matches any character" flag by default.
+    ///
+    /// By default this is disabled. It may alternatively be selectively
+    /// enabled in the regular expression itself via the `s` flag.
+    pub fn dot_matches_new_line(&mut self, yes: bool) -> &mut Builder {
+        self.parser.dot_matches_new_line(yes);
+        self
+    }
+
+    /// Enable or disable the "swap greed" flag by default.
+    ///
+    /// By default this is disabled. It may alternatively be selectively
+    /// enabled in the regular expression itself via the `U` flag.
+    pub fn swap_greed(&mut self, yes: bool) -> &mut Builder {
+        self.parser.swap_greed(yes);
+        self
+    }
+
+    /// Enable or disable the Unicode flag (`u`) by default.
+    ///
+    /// By default this is **enabled**. It may alternatively be selectively
+    /// disabled in the regular expression itself via the `u` flag.
+    ///
+    /// Note that unless `allow_invalid_utf8` is enabled (it's disabled by
+    /// default), a regular expression will fail to parse if Unicode mode is
+    /// disabled and a sub-expression could possibly match invalid UTF-8.
+    pub fn unicode(&mut self, yes: bool) -> &mut Builder {
+        self.parser.unicode(yes);
+        self
+    }
+
+    /// When enabled, the builder will permit the construction of a regular
+    /// expression that may match invalid UTF-8.
+    ///
+    /// When disabled (the default), the builder is guaranteed to produce a
+    /// regex that will only ever match valid UTF-8 (otherwise, the builder
+    /// will return an error).
+    ///
+    /// In that case, the DFA also never steps through invalid UTF-8. Every
+    /// path through the NFA, including the unanchored `.*?` prefix, spells
+    /// out valid UTF-8 one byte at a time, so the DFA enters its dead state
+    /// on the very byte at which the input stops being a prefix of valid
+    /// UTF-8 (such as a stray continuation byte, an overlong encoding or an
+    /// encoded surrogate). Searching binary data thus stops at its first
+    /// invalid sequence. The one exception is
+    /// [`skip_bytes`](struct.Builder.html#method.skip_bytes), which is
+    /// honored in every state regardless of encoding. The
+    /// [`prefilter`](struct.Builder.html#method.prefilter) and
+    /// [`reverse_suffix_prefilter`](struct.Builder.html#method.reverse_suffix_prefilter)
+    /// respect this too: they never skip over invalid UTF-8 to reach their
+    /// literal, so searches give the same results with or without them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().build("foo")?;
+    /// let mut state = dfa.start_state();
+    /// for &b in b"ab\xE2\x82" {
+    ///     state = dfa.next_state(state, b);
+    /// }
+    /// assert!(!dfa.is_dead_state(state));
+    /// // 0x28 can't continue the three byte sequence started by 0xE2.
+    /// assert!(dfa.is_dead_state(dfa.next_state(state, 0x28)));
+    /// assert_eq!(None, dfa.find(b"ab\xFF foo"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn allow_invalid_utf8(&mut self, yes: bool) -> &mut Builder {
+        self.parser.allow_invalid_utf8(yes);
+        self.nfa.allow_invalid_utf8(yes);
+        self.allow_invalid_utf8 = yes;
+        self
+    }
+
+    /// Set the nesting limit used for the regular expression parser.
+    ///
+    /// The nesting limit controls how deep the abstract syntax tree is allowed
+    /// to be. If the AST exceeds the given limit (e.g., with too many nested
+    /// groups), then an error is returned by the parser.
+    ///
+    /// The purpose of this limit is to act as a heuristic to prevent stack
+    /// overflow when building a finite automaton from a regular expression's
+    /// abstract syntax tree. In particular, construction currently uses
+    /// recursion. In the future, the implementation may stop using recursion
+    /// and this option will no longer be necessary.
+    ///
+    /// This limit is not checked until the entire AST is parsed. Therefore,
+    /// if callers want to put a limit on the amount of heap space used, then
+    /// they should impose a limit on the length, in bytes, of the concrete
+    /// pattern string. In particular, this is viable since the parser will
+    /// limit itself to heap space proportional to the lenth of the pattern
+    /// string.
+    ///
+    /// Note that a nest limit of `0` will return a nest limit error for most
+    /// patterns but not all. For example, a nest limit of `0` permits `a` but
+    /// not `ab`, since `ab` requires a concatenation AST item, which results
+    /// in a nest depth of `1`. In general, a nest limit is not something that
+    /// manifests in an obvious way in the concrete syntax, therefore, it
+    /// should not be used in a granular way.
+    pub fn nest_limit(&mut self, limit: u32) -> &mut Builder {
+        self.parser.nest_limit(limit);
+        self
+    }
+
+    /// Minimize the DFA.
+    ///
+    /// When enabled, the DFA built will be minimized such that it is as small
+    /// as possible.
+    ///
+    /// Whether one enables minimization or not depends on the types of costs
+    /// you're willing to pay and how much you care about its benefits. In
+    /// particular, minimization has worst case `O(n*k*logn)` time and `O(k*n)`
+    /// space, where `n` is the number of DFA states and `k` is the alphabet
+    /// size. In practice, minimization can be quite costly in terms of both
+    /// space and time, so it should only be done if you're willing to wait
+    /// longer to produce a DFA. In general, you might want a minimal DFA in
+    /// the following circumstances:
+    ///
+    /// 1. You would like to optimize for the size of the automaton. This can
+    ///    manifest in one of two ways. Firstly, if you're converting the
+    ///    DFA into Rust code (or a table embedded in the code), then a minimal
+    ///    DFA will translate into a corresponding reduction in code  size, and
+    ///    thus, also the final compiled binary size. Secondly, if you are
+    ///    building many DFAs and putting them on the heap, you'll be able to
+    ///    fit more if they are smaller. Note though that building a minimal
+    ///    DFA itself requires additional space; you only realize the space
+    ///    savings once the minimal DFA is constructed (at which point, the
+    ///    space used for minimization is freed).
+    /// 2. You've observed that a smaller DFA results in faster match
+    ///    performance. Naively, this isn't guaranteed since there is no
+    ///    inherent difference between matching with a bigger-than-minimal
+    ///    DFA and a minimal DFA. However, a smaller DFA may make use of your
+    ///    CPU's cache more efficiently.
+    /// 3. You are trying to establish an equivalence between regular
+    ///    languages. The standard method for this is to build a minimal DFA
+    ///    for each language and then compare them. If the DFAs are equivalent
+    ///    (up to state renaming), then the languages are equivalent.
+    ///
+    /// This option is disabled by default.
+    pub fn minimize(&mut self, yes: bool) -> &mut Builder {
+        self.minimize = yes;
+        self
+    }
+
+    /// Premultiply state identifiers in the DFA's transition table.
+    ///
+    /// When enabled, state identifiers are premultiplied to point to their
+    /// corresponding row in the DFA's transition table. That is, given the
+    /// `i`th state, its corresponding premultiplied identifier is `i * k`
+    /// where `k` is the alphabet size of the DFA. (The alphabet size is at
+    /// most 256, but is in practice smaller if byte classes is enabled.)
+    ///
+    /// When state identifiers are not premultiplied, then the identifier of
+    /// the `i`th state is `i`.
+    ///
+    /// The advantage of premultiplying state identifiers is that is saves
+    /// a multiplication instruction per byte when searching with the DFA.
+    /// This has been observed to lead to a 20% performance benefit in
+    /// micro-benchmarks.
+    ///
+    /// The primary disadvantage of premultiplying state identifiers is
+    /// that they require a larger integer size to represent. For example,
+    /// if your DFA has 200 states, then its premultiplied form requires
+    /// 16 bits to represent every possible state identifier, where as its
+    /// non-premultiplied form only requires 8 bits.
+    ///
+    /// This option is enabled by default.
+    pub fn premultiply(&mut self, yes: bool) -> &mut Builder {
+        self.premultiply = yes;
+        self
+    }
+
+    /// Set the layout of the DFA's transition table.
+    ///
+    /// By default, the table is stored in row-major order, where the
+    /// transitions of each state are contiguous. With
+    /// [`Layout::ColumnMajor`](enum.Layout.html#variant.ColumnMajor), the
+    /// transitions of every state on the same equivalence class are
+    /// contiguous instead, and the DFA built is a
+    /// [`DenseDFA::ColumnMajor`](../enum.DenseDFA.html#variant.ColumnMajor).
+    ///
+    /// A column-major DFA matches precisely the same inputs as a row-major
+    /// one. Its state identifiers are never premultiplied, regardless of
+    /// [`premultiply`](struct.Builder.html#method.premultiply), since a
+    /// search finds the transition out of state `id` on a byte in class `c`
+    /// at `c * state_count + id`. This costs a multiplication per byte, but
+    /// the DFA may then fit in a smaller state identifier representation.
+    /// The layout is kept when the DFA is serialized or transformed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::dense::{self, Layout};
+    /// use regex_automata::DFA;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new()
+    ///     .layout(Layout::ColumnMajor)
+    ///     .build("foo[0-9]+")?;
+    /// assert_eq!(Layout::ColumnMajor, dfa.layout());
+    /// assert_eq!(Some(8), dfa.find(b"foo12345"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn layout(&mut self, layout: Layout) -> &mut Builder {
+        self.layout = layout;
+        self
+    }
+
+    /// Shuffle match states to the front of the DFA's transition table.
+    ///
+    /// By default, once determinization is done, every match state is moved
+    /// so that it precedes every other state except the dead state. A search
+    /// can then tell whether it is in a match state or the dead state with a
+    /// single comparison against the last match state, which it does for
+    /// every byte.
+    ///
+    /// When disabled, states keep the order in which determinization built
+    /// them (before any minimization), which makes the `Debug` output of a
+    /// DFA, and the output of
+    /// [`DenseDFA::to_dot`](../enum.DenseDFA.html#method.to_dot), much easier
+    /// to follow. The match states are instead recorded in a bitset, which a
+    /// search has to consult for every byte, so this is slower. It is meant
+    /// for debugging, but such a DFA can still be serialized: the bitset is
+    /// written along with it, and deserializing it requires the `std`
+    /// feature. It can also be converted to a sparse DFA, which always
+    /// shuffles its match states.
+    ///
+    /// This only applies to DFAs built from a single pattern.
+    ///
+    /// This option is enabled by default.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DenseDFA, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new()
+    ///     .anchored(true)
+    ///     .premultiply(false)
+    ///     .shuffle_match_states(false)
+    ///     .build("ab|b")?;
+    /// // The start state is built first, right after the dead state.
+    /// assert_eq!(1, dfa.start_state());
+    /// assert_eq!(Some(2), dfa.find(b"abc"));
+    ///
+    /// // The order is kept when the DFA is serialized.
+    /// let bytes = dfa.to_bytes_native_endian()?;
+    /// let loaded: DenseDFA<&[usize], usize> =
+    ///     unsafe { DenseDFA::from_bytes(&bytes) };
+    /// assert_eq!(1, loaded.start_state());
+    /// assert_eq!(Some(2), loaded.find(b"abc"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn shuffle_match_states(&mut self, yes: bool) -> &mut Builder {
+        self.shuffle_match_states = yes;
+        self
+    }
+
+    /// Shrink the size of the DFA's alphabet by mapping bytes to their
+    /// equivalence classes.
+    ///
+    /// When enabled, each DFA will use a map from all possible bytes to their
+    /// corresponding equivalence class. Each equivalence class represents a
+    /// set of bytes that does not discriminate between a match and a non-match
+    /// in the DFA. For example, the pattern `[ab]+` has at least two
+    /// equivalence classes: a set containing `a` and `b` and a set containing
+    /// every byte except for `a` and `b`. `a` and `b` are in the same
+    /// equivalence classes because they never discriminate between a match
+    /// and a non-match.
+    ///
+    /// The advantage of this map is that the size of the transition table can
+    /// be reduced drastically from `#states * 256 * sizeof(id)` to
+    /// `#states * k * sizeof(id)` where `k` is the number of equivalence
+    /// classes. As a result, total space usage can decrease substantially.
+    /// Moreover, since a smaller alphabet is used, compilation becomes faster
+    /// as well.
+    ///
+    /// The disadvantage of this map is that every byte searched must be
+    /// passed through this map before it can be used to determine the next
+    /// transition. This has a small match time performance cost.
+    ///
+    /// Patterns that only match ASCII need no special treatment: since no
+    /// non-ASCII byte can lead to a match, all of them fall into a single
+    /// class. Each distinct ASCII range in the pattern adds at most two
+    /// class boundaries, so the alphabet has at most one more class than
+    /// twice the number of ranges. For example, `[a-z]+[0-9]` has five
+    /// classes: its two ranges and the three gaps around them. (An unanchored
+    /// DFA that requires valid UTF-8 has a few more classes, since its prefix
+    /// must still step over non-ASCII codepoints.)
+    ///
+    /// This option is enabled by default.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::dense;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().anchored(true).build("[a-z]+")?;
+    /// assert_eq!(3, dfa.byte_classes().alphabet_len());
+    ///
+    /// let dfa = dense::Builder::new().anchored(true).build("[a-z]+[0-9]")?;
+    /// assert_eq!(5, dfa.byte_classes().alphabet_len());
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn byte_classes(&mut self, yes: bool) -> &mut Builder {
+        self.byte_classes = yes;
+        self
+    }
+
+    /// Reverse the DFA.
+    ///
+    /// A DFA reversal is performed by reversing all of the concatenated
+    /// sub-expressions in the original pattern, recursively. The resulting
+    /// DFA can be used to match the pattern starting from the end of a string
+    /// instead of the beginning of a string.
+    ///
+    /// Generally speaking, a reversed DFA is most useful for finding the start
+    /// of a match, since a single forward DFA is only capable of finding the
+    /// end of a match. This start of match handling is done for you
+    /// automatically if you build a [`Regex`](struct.Regex.html).
+    pub fn reverse(&mut self, yes: bool) -> &mut Builder {
+        self.reverse = yes;
+        self.nfa.reverse(yes);
+        self
+    }
+
+    /// Set the priority given to matches when more than one is possible.
+    ///
+    /// See [`MatchPriority`](enum.MatchPriority.html) for how each choice
+    /// affects determinization. Note that this only changes the end of the
+    /// matches reported by a DFA. In particular, a search with a
+    /// `ShortestFirst` DFA returns the same end as
+    /// [`DFA::shortest_match`](../trait.DFA.html#method.shortest_match) does
+    /// with a `LeftmostFirst` DFA, but the former DFA is usually smaller,
+    /// since its match states have no transitions.
+    ///
+    /// By default, this is `MatchPriority::LeftmostFirst`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    /// use regex_automata::dense::MatchPriority;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let mut builder = dense::Builder::new();
+    /// builder.anchored(true);
+    ///
+    /// let dfa = builder.build("a|ab|abc")?;
+    /// assert_eq!(Some(1), dfa.find(b"abc"));
+    ///
+    /// builder.match_priority(MatchPriority::LeftmostLongest);
+    /// let dfa = builder.build("a|ab|abc")?;
+    /// assert_eq!(Some(3), dfa.find(b"abc"));
+    ///
+    /// builder.match_priority(MatchPriority::ShortestFirst);
+    /// let dfa = builder.build("abc|ab|a")?;
+    /// assert_eq!(Some(1), dfa.find(b"abc"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn match_priority(&mut self, priority: MatchPriority) -> &mut Builder {
+        self.match_priority = priority;
+        self
+    }
+
+    /// Find the longest possible match.
+    ///
+    /// This is a shorthand for setting
+    /// [`match_priority`](struct.Builder.html#method.match_priority) to
+    /// `MatchPriority::LeftmostLongest` when enabled, or to
+    /// `MatchPriority::LeftmostFirst` when disabled.
+    ///
+    /// This is distinct from the default leftmost-first match semantics in
+    /// that it treats all NFA states as having equivalent priority. In other
+    /// words, the longest possible match is always found and it is not
+    /// possible to implement non-greedy match semantics when this is set. That
+    /// is, `a+` and `a+?` are equivalent when this is enabled.
+    ///
+    /// In particular, a practical issue with this option at the moment is that
+    /// it prevents unanchored searches from working correctly, since
+    /// unanchored searches are implemented by prepending an non-greedy `.*?`
+    /// to the beginning of the pattern. As stated above, non-greedy match
+    /// semantics aren't supported. Therefore, if this option is enabled and
+    /// an unanchored search is requested, then building a DFA will return an
+    /// error.
+    ///
+    /// This option is principally useful when building a reverse DFA for
+    /// finding the start of a match. If you are building a regex with
+    /// [`RegexBuilder`](struct.RegexBuilder.html), then this is handled for
+    /// you automatically. The reason why this is necessary for start of match
+    /// handling is because we want to find the earliest possible starting
+    /// position of a match to satisfy leftmost-first match semantics. When
+    /// matching in reverse, this means finding the longest possible match,
+    /// hence, this option.
+    ///
+    /// By default this is disabled.
+    pub fn longest_match(&mut self, yes: bool) -> &mut Builder {
+        // There is prior art in RE2 that shows how this can support unanchored
+        // searches. Instead of treating all NFA states as having equivalent
+        // priority, we instead group NFA states into sets, and treat members
+        // of each set as having equivalent priority, but having greater
+        // priority than all following members of different sets. We then
+        // essentially assign a higher priority to everything over the prefix
+        // `.*?`.
+        self.match_priority = if yes {
+            MatchPriority::LeftmostLongest
+        } else {
+            MatchPriority::LeftmostFirst
+        };
+        self
+    }
+
+    /// Configure the DFA to only match inputs that match the pattern in
+    /// their entirety, as if it were wrapped in `^...$`.
+    ///
+    /// A DFA has no transition for the end of the input, so the end anchor
+    /// is instead checked when searching. The DFA reports it via
+    /// [`DFA::requires_full_match`](../trait.DFA.html#method.requires_full_match),
+    /// and every search routine provided by the `DFA` trait, such as
+    /// `is_match`, `find` or `is_match_chunks`, then walks the DFA over the
+    /// rest of the haystack and only reports a match if it finishes in a
+    /// match state. This holds for references to the DFA, its variants and
+    /// its sparse counterpart as well. A match that
+    /// ends before the end of the haystack is not reported. For `find` and
+    /// `shortest_match`, a match therefore always ends at the end of the
+    /// haystack, and for `rfind` (with a
+    /// [`reverse`](struct.Builder.html#method.reverse) DFA) it always starts
+    /// at the beginning.
+    ///
+    /// The pattern is compiled as if
+    /// [`anchored`](struct.Builder.html#method.anchored) and
+    /// [`longest_match`](struct.Builder.html#method.longest_match) were
+    /// enabled, but this setting is stored separately, so disabling it again
+    /// restores whatever those two options were set to. Leftmost-longest
+    /// semantics are needed because leftmost-first determinization discards
+    /// lower priority alternatives once a higher priority one matches, even
+    /// though a discarded alternative may be the only one that can consume
+    /// the rest of the input. Prefilters are never used for full matches.
+    ///
+    /// The setting is kept by serialization and by conversion to a sparse
+    /// DFA.
+    ///
+    /// By default this is disabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().full_match(true).build(r"[0-9]+")?;
+    /// assert!(dfa.is_match(b"123"));
+    /// assert!(!dfa.is_match(b"123a"));
+    /// assert!(!dfa.is_match(b"a123"));
+    /// assert_eq!(Some(3), dfa.find(b"123"));
+    ///
+    /// let dfa = dense::Builder::new().full_match(true).build("a|ab")?;
+    /// assert!(dfa.is_match(b"ab"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn full_match(&mut self, yes: bool) -> &mut Builder {
+        self.full_match = yes;
+        self.nfa.anchored(yes || self.anchored);
+        self
+    }
+
+    /// Enable a prefilter that uses a required literal prefix to skip ahead
+    /// in the haystack before running the DFA.
+    ///
+    /// When enabled, the builder computes the longest literal that every
+    /// match of the pattern must begin with (up to a small fixed length).
+    /// For example, the literal for `GET /[a-z]+` is `GET /`. Unanchored
+    /// searches then jump directly to the first occurrence of this literal,
+    /// and report no match without running the DFA at all if it doesn't
+    /// occur. When the literal is rare in the haystack, this can make
+    /// searching much faster. When it is common, it adds a small amount of
+    /// overhead. Unless
+    /// [`allow_invalid_utf8`](struct.Builder.html#method.allow_invalid_utf8)
+    /// is enabled, the bytes skipped must also be valid UTF-8, since the DFA
+    /// stops at invalid UTF-8. This keeps the results identical to an
+    /// unfiltered search.
+    ///
+    /// The prefilter is only used by the search routines on
+    /// [`DenseDFA`](../enum.DenseDFA.html) itself. It is not used when
+    /// walking the DFA manually, and it is not carried over when converting
+    /// to a sparse DFA or when serializing a DFA to bytes. It has no effect
+    /// on anchored or reverse DFAs. It is also never used when
+    /// [`skip_bytes`](struct.Builder.html#method.skip_bytes) is set, since
+    /// the literal might be interrupted by skip bytes in the haystack. The
+    /// literal chosen is available via
+    /// [`DenseDFA::literal_prefix`](../enum.DenseDFA.html#method.literal_prefix).
+    ///
+    /// By default this is disabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().prefilter(true).build("error: [0-9]+")?;
+    /// assert_eq!(b"error: ", dfa.literal_prefix());
+    /// assert_eq!(Some(20), dfa.find(b"warning: 1 error: 42"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn prefilter(&mut self, yes: bool) -> &mut Builder {
+        self.prefilter = yes;
+        self
+    }
+
+    /// Enable a literal suffix prefilter for reverse searches.
+    ///
+    /// This is the counterpart of
+    /// [`prefilter`](struct.Builder.html#method.prefilter) for DFAs built
+    /// with [`reverse`](struct.Builder.html#method.reverse) enabled. When
+    /// every match of the pattern must end with a particular literal, a
+    /// reverse search first jumps backwards to the last occurrence of that
+    /// literal, and only starts walking the transition table from there. The
+    /// same caveats as the forward prefilter apply. In particular, this has
+    /// no effect on anchored DFAs, which includes the reverse DFA of a
+    /// [`Regex`](../struct.Regex.html) since it always starts at the end of
+    /// a known match. The literal chosen is available via
+    /// [`DenseDFA::literal_suffix`](../enum.DenseDFA.html#method.literal_suffix).
+    ///
+    /// By default this is disabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new()
+    ///     .reverse(true)
+    ///     .reverse_suffix_prefilter(true)
+    ///     .build("[0-9]+ errors")?;
+    /// assert_eq!(b" errors", dfa.literal_suffix());
+    /// assert_eq!(Some(0), dfa.rfind(b"42 errors, 3 warnings"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn reverse_suffix_prefilter(&mut self, yes: bool) -> &mut Builder {
+        self.reverse_suffix_prefilter = yes;
+        self
+    }
+
+    /// Accelerate unanchored searches by skipping over bytes that keep the
+    /// DFA in its start state.
+    ///
+    /// The start state of an unanchored DFA typically loops back to itself
+    /// on most bytes, e.g., on every byte that can't begin a match. When this
+    /// is enabled, the builder records the bytes on which the start state
+    /// goes anywhere else, and forward searches with the resulting DFA scan
+    /// for the next such byte whenever they're in the start state, instead
+    /// of following one transition per byte. Unlike
+    /// [`prefilter`](struct.Builder.html#method.prefilter), this works at the
+    /// granularity of individual bytes rather than literals, so it helps
+    /// even when matches can begin with many different bytes. It never
+    /// changes the result of a search.
+    ///
+    /// Acceleration is only used when it's likely to pay off, so it is not
+    /// used for anchored DFAs, when the start state is a match state, or when
+    /// the start state leaves itself on too many bytes. Whether it's used is
+    /// reported by
+    /// [`DenseDFA::is_accelerated`](../enum.DenseDFA.html#method.is_accelerated).
+    /// It is preserved by serialization (at the cost of a single flag in the
+    /// header), but is not used by sparse DFAs or by the search routines of
+    /// the underlying DFA variants.
+    ///
+    /// By default this is disabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().accelerate(true).build("[xyz][0-9]+")?;
+    /// assert!(dfa.is_accelerated());
+    /// assert_eq!(Some(13), dfa.find(b"aaaaaaaaaay42"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn accelerate(&mut self, yes: bool) -> &mut Builder {
+        self.accelerate = yes;
+        self
+    }
+
+    /// Record the set of NFA states that each DFA state was built from.
+    ///
+    /// Determinization builds every DFA state from a set of NFA states, but
+    /// normally discards those sets once it's done. When this is enabled,
+    /// they are kept and reported by
+    /// [`BuildStats::provenance`](struct.BuildStats.html#method.provenance)
+    /// after building with
+    /// [`build_with_stats`](struct.Builder.html#method.build_with_stats).
+    /// The NFA states refer to the NFA returned by
+    /// [`build_nfa`](struct.Builder.html#method.build_nfa) for the same
+    /// pattern, which is useful for seeing which parts of a pattern a DFA
+    /// state corresponds to.
+    ///
+    /// This costs extra memory during and after construction. In particular,
+    /// NFA states are never freed when the determinization cache is cleared
+    /// (see
+    /// [`determinize_cache_limit`](struct.Builder.html#method.determinize_cache_limit)).
+    ///
+    /// By default this is disabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let mut builder = dense::Builder::new();
+    /// builder.anchored(true).record_provenance(true);
+    /// let (dfa, stats) = builder.build_with_stats("ab")?;
+    /// let nfa = builder.build_nfa("ab")?;
+    ///
+    /// let start = stats.provenance(dfa.start_state()).unwrap();
+    /// assert_eq!(1, start.len());
+    /// let trans = nfa.state(start[0]).transitions();
+    /// assert_eq!((b'a', b'a'), (trans[0].start, trans[0].end));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn record_provenance(&mut self, yes: bool) -> &mut Builder {
+        self.record_provenance = yes;
+        self
+    }
+
+    /// Reserve room in the transition table for the given number of states
+    /// before determinization begins.
+    ///
+    /// When building a DFA that is known to be large, such as one for a big
+    /// alternation of literals, this avoids repeatedly growing (and copying)
+    /// the transition table as new states are added. The hint is advisory:
+    /// the DFA still grows beyond it if more states are needed, and any
+    /// unused capacity is retained by the DFA returned. As with
+    /// `Vec::with_capacity`, an unreasonably large hint can cause allocation
+    /// to fail.
+    ///
+    /// By default, no capacity is reserved up front.
+    pub fn states_capacity_hint(&mut self, states: usize) -> &mut Builder {
+        self.states_capacity_hint = states;
+        self
+    }
+
+    /// Bound the memory used to deduplicate states during determinization.
+    ///
+    /// Determinization builds each DFA state from a set of NFA states, and
+    /// remembers every set it has seen so that it can reuse the DFA state
+    /// when the same set comes up again. For very large NFAs, these sets can
+    /// use much more memory than the DFA's transition table. When a limit is
+    /// set, the remembered sets are discarded once there are more than
+    /// `limit` of them. A set that comes up again afterwards produces a new
+    /// DFA state that duplicates an existing one.
+    ///
+    /// The resulting DFA matches precisely the same inputs, but may have
+    /// more states. Enabling
+    /// [`minimize`](struct.Builder.html#method.minimize) merges duplicate
+    /// states again, at the cost of additional build time. To guarantee that
+    /// determinization always finishes, the limit doubles each time it is
+    /// reached, so very small limits mostly serve to trade build speed for
+    /// memory.
+    ///
+    /// By default, there is no limit.
+    pub fn determinize_cache_limit(&mut self, limit: usize) -> &mut Builder {
+        self.determinize_cache_limit = Some(limit);
+        self
+    }
+
+    /// Use a faster hash function to deduplicate states during
+    /// determinization.
+    ///
+    /// Each DFA state is looked up by the set of NFA states it is made of,
+    /// which means hashing that set every time a transition is computed. For
+    /// large NFAs, such as those for big alternations of literals, this
+    /// hashing can account for a significant share of build time. By
+    /// default, the standard library's hash function is used, which is
+    /// designed to resist denial of service attacks. When enabled, a much
+    /// simpler non-cryptographic hash function (in the style of FxHash) is
+    /// used instead.
+    ///
+    /// This has no effect on the DFA produced, only on how long it takes to
+    /// build. Since determinization can already take exponential time on
+    /// adversarial patterns (see
+    /// [`dfa_size_limit`](struct.Builder.html#method.dfa_size_limit)),
+    /// giving up resistance to hash flooding is usually a good trade.
+    ///
+    /// This is disabled by default.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// let pattern = "foo|bar|baz|quux";
+    /// let fast = dense::Builder::new()
+    ///     .determinize_fast_hash(true)
+    ///     .build(pattern)?;
+    /// let default = dense::Builder::new().build(pattern)?;
+    /// assert_eq!(
+    ///     fast.to_bytes_native_endian()?,
+    ///     default.to_bytes_native_endian()?,
+    /// );
+    /// assert_eq!(Some(6), fast.find(b"xyzbar"));
+    /// # Ok::<(), regex_automata::Error>(())
+    /// ```
+    pub fn determinize_fast_hash(&mut self, yes: bool) -> &mut Builder {
+        self.determinize_fast_hash = yes;
+        self
+    }
+
+    /// Set the bytes that searches should step over without matching them.
+    ///
+    /// When a search reaches a skip byte, it moves past it without following
+    /// a transition, as if the byte were not present in the haystack. For
+    /// example, with a skip set of `b" "`, the pattern `abc` matches
+    /// `a b  c`. This is useful when scanning for tokens that may be
+    /// separated by insignificant padding.
+    ///
+    /// Skipping is done by the search loop, which checks each byte against a
+    /// 256-bit table before transitioning on it. The DFA itself is built
+    /// exactly as it would be without skip bytes. There are a few
+    /// consequences worth noting:
+    ///
+    /// * Skip bytes are stepped over in every state, including the start
+    ///   state. This is meant for unanchored searches, where leading skip
+    ///   bytes would be consumed by the implicit `.*?` prefix anyway. An
+    ///   anchored search steps over them too, so its match may begin after
+    ///   leading skip bytes.
+    /// * A match never ends with a skip byte. Skip bytes immediately
+    ///   following a match aren't part of it, and a reverse search doesn't
+    ///   include the skip bytes immediately preceding the start it reports.
+    /// * A pattern can never match a skip byte, even if it mentions one
+    ///   explicitly, since the search never transitions on it.
+    /// * Only the `is_match`, `shortest_match`, `find` and `rfind` routines
+    ///   (and their `_at` variants) of [`DenseDFA`](../enum.DenseDFA.html)
+    ///   use the skip set. Other search routines, and walking the DFA
+    ///   manually with `next_state`, see every byte.
+    /// * The [`prefilter`](struct.Builder.html#method.prefilter) and
+    ///   [`reverse_suffix_prefilter`](struct.Builder.html#method.reverse_suffix_prefilter)
+    ///   are never used with a skip set, since a skip byte may interrupt
+    ///   their literal in the haystack.
+    /// * The skip set is serialized with the DFA. Sparse DFAs don't support
+    ///   one, so converting a DFA with a skip set to a sparse DFA, or calling
+    ///   [`build_many_sparse`](struct.Builder.html#method.build_many_sparse)
+    ///   with one, returns an error.
+    /// * It has no effect when
+    ///   [`full_match`](struct.Builder.html#method.full_match) is enabled.
+    ///
+    /// Each call replaces the previous skip set. By default, no bytes are
+    /// skipped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().skip_bytes(b" ").build("ab|x[ y]z")?;
+    /// // Spaces are skipped anywhere, but a match never ends with one.
+    /// assert_eq!(Some(3), dfa.find(b"a b  x"));
+    /// // A space in the pattern can never be matched.
+    /// assert_eq!(None, dfa.find(b"x z"));
+    /// assert_eq!(Some(4), dfa.find(b"x yz"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn skip_bytes(&mut self, bytes: &[u8]) -> &mut Builder {
+        self.skip_bytes = bytes.to_vec();
+        self
+    }
+
+    /// Set a limit on the number of NFA states used to compile a pattern.
+    ///
+    /// A pattern is first compiled to an NFA, which is then determinized into
+    /// a DFA. While the DFA is usually what gets big, some patterns produce
+    /// an enormous NFA on their own. For example, a bounded repetition such
+    /// as `a{1000000}` copies its sub-expression once per repetition. When
+    /// compiling untrusted patterns, this limit rejects such patterns with an
+    /// [`NFATooBig`](../enum.ErrorKind.html#variant.NFATooBig) error before
+    /// the memory is spent and before determinization begins.
+    ///
+    /// The limit is approximate: the NFA may briefly exceed it by the size of
+    /// a single sub-expression before the error is detected.
+    ///
+    /// By default, there is no limit.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, ErrorKind};
+    ///
+    /// let result = dense::Builder::new()
+    ///     .nfa_size_limit(1000)
+    ///     .build("a{5000}");
+    /// match *result.unwrap_err().kind() {
+    ///     ErrorKind::NFATooBig { limit } => assert_eq!(1000, limit),
+    ///     ref kind => panic!("unexpected error: {:?}", kind),
+    /// }
+    /// ```
+    pub fn nfa_size_limit(&mut self, limit: usize) -> &mut Builder {
+        self.nfa.size_limit(Some(limit));
+        self
+    }
+
+    /// Set a limit on the number of states in the DFA produced by
+    /// determinization.
+    ///
+    /// Determinization can take exponential time and space in the size of
+    /// the NFA, even when the NFA itself is small. For example, `[01]*1[01]{20}`
+    /// compiles to a small NFA but its DFA has millions of states. When
+    /// compiling untrusted patterns, this limit stops determinization with a
+    /// [`DeterminizeTooBig`](../enum.ErrorKind.html#variant.DeterminizeTooBig)
+    /// error as soon as the DFA would exceed it.
+    ///
+    /// The limit counts every state added during determinization, including
+    /// the dead state and any duplicates introduced by
+    /// [`determinize_cache_limit`](struct.Builder.html#method.determinize_cache_limit).
+    /// It is checked before minimization, so a DFA whose minimal form fits
+    /// within the limit may still be rejected.
+    ///
+    /// By default, there is no limit.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, ErrorKind};
+    ///
+    /// let result = dense::Builder::new()
+    ///     .dfa_size_limit(1000)
+    ///     .build("[01]*1[01]{20}");
+    /// match *result.unwrap_err().kind() {
+    ///     ErrorKind::DeterminizeTooBig { limit } => assert_eq!(1000, limit),
+    ///     ref kind => panic!("unexpected error: {:?}", kind),
+    /// }
+    /// ```
+    pub fn dfa_size_limit(&mut self, limit: usize) -> &mut Builder {
+        self.dfa_size_limit = Some(limit);
+        self
+    }
+
+    /// Set a limit on the number of copies that counted repetitions in a
+    /// pattern may expand to.
+    ///
+    /// A counted repetition such as `a{5000}` or `a{0,5000}` is compiled by
+    /// copying its sub-expression once per repetition, so a short pattern can
+    /// produce a very large NFA. This limit is checked right after parsing,
+    /// before any automaton is built, and fails with a
+    /// [`RepetitionTooBig`](../enum.ErrorKind.html#variant.RepetitionTooBig)
+    /// error, which pins the problem on counted repetitions rather than on
+    /// the pattern as a whole (as
+    /// [`nfa_size_limit`](struct.Builder.html#method.nfa_size_limit) does).
+    ///
+    /// The count of a pattern is the sum of the upper bounds of its counted
+    /// repetitions (or the lower bound, for `{n,}`), where a repetition
+    /// nested inside another is multiplied by the outer bound. For example,
+    /// `a{10}b{2,5}` counts `15` and `(a{10}){3}` counts `30`. The operators
+    /// `*`, `+` and `?` don't count, since they never copy anything.
+    ///
+    /// By default, there is no limit.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, ErrorKind};
+    ///
+    /// let mut builder = dense::Builder::new();
+    /// builder.repetition_limit(100);
+    /// assert!(builder.build("[0-9]{3}-[0-9]{4}").is_ok());
+    /// match *builder.build("a{0,5000}").unwrap_err().kind() {
+    ///     ErrorKind::RepetitionTooBig { limit } => assert_eq!(100, limit),
+    ///     ref kind => panic!("unexpected error: {:?}", kind),
+    /// }
+    /// ```
+    pub fn repetition_limit(&mut self, limit: usize) -> &mut Builder {
+        self.repetition_limit = Some(limit);
+        self
+    }
+
+    /// Apply best effort heuristics to shrink the NFA at the expense of more
+    /// time/memory.
+    ///
+    /// This may be exposed in the future, but for now is exported for use in
+    /// the `regex-automata-debug` tool.
+    #[doc(hidden)]
+    pub fn shrink(&mut self, yes: bool) -> &mut Builder {
+        self.nfa.shrink(yes);
+        self
+    }
 }
 
-/// The following methods implement mutable routines on the internal
-/// representation of a DFA. As such, we must fix the first type parameter to
-/// a `Vec<S>` since a generic `T: AsRef<[S]>` does not permit mutation. We
-/// can get away with this because these methods are internal to the crate and
-/// are exclusively used during construction of the DFA.
 #[cfg(feature = "std")]
-impl<S: StateID> Repr<Vec<S>, S> {
-    pub fn premultiply(&mut self) -> Result<()> {
-        if self.premultiplied || self.state_count <= 1 {
-            return Ok(());
+impl Default for Builder {
+    fn default() -> Builder {
+        Builder::new()
+    }
+}
+
+/// The priority given to matches when more than one is possible at the same
+/// time, as configured by
+/// [`Builder::match_priority`](struct.Builder.html#method.match_priority).
+///
+/// During determinization, each DFA state is built from an ordered set of
+/// NFA states, where earlier NFA states correspond to higher priority
+/// threads of the search. When a match state of the NFA appears in that set,
+/// the DFA state becomes a match state, and the priority decides which of
+/// the other NFA states in the set survive.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MatchPriority {
+    /// Prefer the match that a backtracking engine would report, which
+    /// depends on the order of alternations and on whether repetitions are
+    /// greedy. Every NFA state following the NFA match state is dropped,
+    /// since those correspond to lower priority threads, while the states
+    /// preceding it are kept since they may still produce a preferred,
+    /// longer match.
+    ///
+    /// For example, `a|ab|abc` matches `a` in `abc`, while `abc|ab|a`
+    /// matches `abc`.
+    ///
+    /// This is the default.
+    LeftmostFirst,
+    /// Prefer the longest match. Every NFA state in the set is kept, so the
+    /// search continues for as long as any thread can still match, and the
+    /// order of alternations and greediness make no difference.
+    ///
+    /// This is only supported for anchored DFAs, since the threads of an
+    /// unanchored search that begin later in the haystack can't be told
+    /// apart from the ones that began earlier. Building an unanchored DFA
+    /// with this priority returns an error.
+    ///
+    /// For example, both `a|ab|abc` and `abc|ab|a` match `abc` in `abc`.
+    LeftmostLongest,
+    /// Prefer the match that ends first. Every NFA state in the set is
+    /// dropped, so each match state leads only to the dead state, and a
+    /// search stops as soon as a match is seen. The end of a match is then
+    /// the same as the one reported by
+    /// [`DFA::shortest_match`](../trait.DFA.html#method.shortest_match).
+    ///
+    /// For example, both `a|ab|abc` and `abc|ab|a` match `a` in `abc`.
+    ShortestFirst,
+}
+
+/// The layout of a dense DFA's transition table, as configured by
+/// [`Builder::layout`](struct.Builder.html#method.layout).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Layout {
+    /// The transitions of each state are contiguous, ordered by equivalence
+    /// class.
+    ///
+    /// This is the default.
+    RowMajor,
+    /// The transitions of every state on the same equivalence class are
+    /// contiguous, ordered by state. State identifiers are never
+    /// premultiplied.
+    ColumnMajor,
+}
+
+/// Scratch space that can be reused across many DFA builds.
+///
+/// Determinization needs a few buffers that grow with the size of the DFA
+/// being built, most notably the map used to find DFA states that were
+/// already built. Passing the same scratch space to
+/// [`Builder::build_reusing`](struct.Builder.html#method.build_reusing) for
+/// every build keeps the capacity of these buffers around, which avoids
+/// much of the allocation churn when compiling a large number of patterns.
+/// The NFA and the DFA's transition table are still allocated anew for
+/// every build, since the transition table is owned by the DFA returned.
+///
+/// The contents of the scratch space never affect the DFA built.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct BuildScratch {
+    det: determinize::Scratch<usize>,
+}
+
+#[cfg(feature = "std")]
+impl BuildScratch {
+    /// Create empty scratch space.
+    pub fn new() -> BuildScratch {
+        BuildScratch { det: determinize::Scratch::new() }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for BuildScratch {
+    fn default() -> BuildScratch {
+        BuildScratch::new()
+    }
+}
+
+/// Statistics about the construction of a single dense DFA.
+///
+/// These are returned by
+/// [`Builder::build_with_stats`](struct.Builder.html#method.build_with_stats).
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct BuildStats {
+    nfa_states: usize,
+    determinized_states: usize,
+    minimized_states: Option<usize>,
+    alphabet_len: usize,
+    transition_table_bytes: usize,
+    premultiplied: bool,
+    provenance: Option<Vec<Vec<nfa::StateID>>>,
+}
+
+#[cfg(feature = "std")]
+impl BuildStats {
+    /// The number of states in the NFA that the DFA was built from.
+    pub fn nfa_states(&self) -> usize {
+        self.nfa_states
+    }
+
+    /// The number of states in the DFA immediately after determinization,
+    /// including the dead state.
+    pub fn determinized_states(&self) -> usize {
+        self.determinized_states
+    }
+
+    /// The number of states in the DFA after minimization, including the
+    /// dead state. If minimization was not enabled, then this returns
+    /// `None`.
+    pub fn minimized_states(&self) -> Option<usize> {
+        self.minimized_states
+    }
+
+    /// The number of elements in the DFA's alphabet. This is `256` when byte
+    /// classes are disabled, and is otherwise the number of equivalence
+    /// classes.
+    pub fn alphabet_len(&self) -> usize {
+        self.alphabet_len
+    }
+
+    /// The size, in bytes, of the largest transition table that was held in
+    /// memory during construction. Since minimization only ever removes
+    /// states, this is the size of the table after determinization.
+    pub fn transition_table_bytes(&self) -> usize {
+        self.transition_table_bytes
+    }
+
+    /// The NFA states that the given state of the DFA was built from, in
+    /// ascending order. If provenance wasn't recorded (see
+    /// [`Builder::record_provenance`](struct.Builder.html#method.record_provenance)),
+    /// then this returns `None`.
+    ///
+    /// Only NFA states that consume input are included, so the set is empty
+    /// for the dead state and for match states that can't be extended. When
+    /// minimization is enabled, a state represents the union of the NFA
+    /// states of every state merged into it.
+    ///
+    /// The identifier given must refer to a state of the DFA as it was
+    /// returned by the builder. This panics if it doesn't refer to a state.
+    pub fn provenance<S: StateID>(&self, id: S) -> Option<&[nfa::StateID]> {
+        let provenance = match self.provenance {
+            None => return None,
+            Some(ref provenance) => provenance,
+        };
+        let index = if self.premultiplied {
+            id.to_usize() / self.alphabet_len
+        } else {
+            id.to_usize()
+        };
+        Some(&provenance[index])
+    }
+
+    /// Label each state of the DFA with the set of NFA states it was built
+    /// from, e.g., `nfa {3, 5}`. If provenance wasn't recorded, then this
+    /// returns `None`.
+    ///
+    /// The labels can be shown alongside the DFA with
+    /// [`DenseDFA::with_labels`](enum.DenseDFA.html#method.with_labels) and
+    /// serialized with
+    /// [`DenseDFA::to_bytes_with_labels_little_endian`](enum.DenseDFA.html#method.to_bytes_with_labels_little_endian).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let (dfa, stats) = dense::Builder::new()
+    ///     .record_provenance(true)
+    ///     .build_with_stats("[0-9]+")?;
+    /// let labels = stats.state_labels().unwrap();
+    /// assert!(labels.get(dfa.start_state()).unwrap().starts_with("nfa {"));
+    /// assert!(dfa.with_labels(&labels).to_dot().contains("nfa {"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn state_labels(&self) -> Option<StateLabels> {
+        let provenance = match self.provenance {
+            None => return None,
+            Some(ref provenance) => provenance,
+        };
+        let mut labels = StateLabels::new();
+        for (index, nfa_states) in provenance.iter().enumerate() {
+            let id = if self.premultiplied {
+                index * self.alphabet_len
+            } else {
+                index
+            };
+            let nfa_states: Vec<String> =
+                nfa_states.iter().map(|id| id.to_string()).collect();
+            let label = format!("nfa {{{}}}", nfa_states.join(", "));
+            labels.labels.insert(id, label);
         }
+        Some(labels)
+    }
+}
+
+/// Return the given byte as its escaped string form.
+#[cfg(feature = "std")]
+fn escape(b: u8) -> String {
+    use std::ascii;
+
+    String::from_utf8(ascii::escape_default(b).collect::<Vec<_>>()).unwrap()
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use error::ErrorKind;
+
+    #[test]
+    fn errors_when_converting_to_smaller_dfa() {
+        let pattern = r"\w{10}";
+        let dfa = Builder::new()
+            .byte_classes(false)
+            .anchored(true)
+            .premultiply(false)
+            .build_with_size::<u16>(pattern)
+            .unwrap();
+        assert!(dfa.to_u8().is_err());
+    }
+
+    #[test]
+    fn errors_when_determinization_would_overflow() {
+        let pattern = r"\w{10}";
+
+        let mut builder = Builder::new();
+        builder.byte_classes(false).anchored(true).premultiply(false);
+        // using u16 is fine
+        assert!(builder.build_with_size::<u16>(pattern).is_ok());
+        // // ... but u8 results in overflow (because there are >256 states)
+        assert!(builder.build_with_size::<u8>(pattern).is_err());
+    }
 
-        let alpha_len = self.alphabet_len();
-        premultiply_overflow_error(
-            S::from_usize(self.state_count - 1),
-            alpha_len,
-        )?;
+    #[test]
+    fn errors_when_premultiply_would_overflow() {
+        let pattern = r"[a-z]";
 
-        for id in (0..self.state_count).map(S::from_usize) {
-            for (_, next) in self.get_state_mut(id).iter_mut() {
-                *next = S::from_usize(next.to_usize() * alpha_len);
+        let mut builder = Builder::new();
+        builder.byte_classes(false).anchored(true).premultiply(false);
+        // without premultiplication is OK
+        assert!(builder.build_with_size::<u8>(pattern).is_ok());
+        // ... but with premultiplication overflows u8
+        builder.premultiply(true);
+        let err = builder.build_with_size::<u8>(pattern).unwrap_err();
+        match *err.kind() {
+            ErrorKind::PremultiplyOverflow { max, requested_max } => {
+                assert_eq!(255, max);
+                assert!(requested_max > 255 && requested_max <= 65535);
             }
+            ref kind => panic!("unexpected error kind: {:?}", kind),
         }
-        self.premultiplied = true;
-        self.start = S::from_usize(self.start.to_usize() * alpha_len);
-        self.max_match = S::from_usize(self.max_match.to_usize() * alpha_len);
-        Ok(())
+        assert!(err.to_string().contains("u16 or larger"));
     }
 
-    /// Minimize this DFA using Hopcroft's algorithm.
-    ///
-    /// This cannot be called on a premultiplied DFA.
-    pub fn minimize(&mut self) {
-        assert!(!self.premultiplied, "can't minimize premultiplied DFA");
+    #[test]
+    fn structural_equality() {
+        use std::collections::HashSet;
 
-        Minimizer::new(self).run();
+        let mut builder = Builder::new();
+        builder.anchored(true).minimize(true).byte_classes(false);
+        let dfa1 = builder.build("a|b").unwrap();
+        let dfa2 = builder.build("[ab]").unwrap();
+        let dfa3 = builder.build("[ab]c").unwrap();
+        assert_eq!(dfa1, dfa2);
+        assert_ne!(dfa1, dfa3);
+        assert_eq!(dfa1.as_ref(), dfa2.as_ref());
+
+        let set: HashSet<_> = vec![dfa1, dfa2, dfa3].into_iter().collect();
+        assert_eq!(2, set.len());
     }
 
-    /// Set the start state of this DFA.
-    ///
-    /// Note that a start state cannot be set on a premultiplied DFA. Instead,
-    /// DFAs should first be completely constructed and then premultiplied.
-    pub fn set_start_state(&mut self, start: S) {
-        assert!(!self.premultiplied, "can't set start on premultiplied DFA");
-        assert!(start.to_usize() < self.state_count, "invalid start state");
+    #[test]
+    fn iter_accepted_respects_length_bound() {
+        let dfa = Builder::new()
+            .anchored(true)
+            .allow_invalid_utf8(true)
+            .build(r"[ab]c*|(?-u:\xFF)")
+            .unwrap();
+        let got: Vec<Vec<u8>> = dfa.iter_accepted(2).collect();
+        let expected: Vec<&[u8]> = vec![b"a", b"ac", b"b", b"bc", b"\xFF"];
+        assert_eq!(expected, got);
+        let sparse = dfa.to_sparse().unwrap();
+        let got: Vec<Vec<u8>> = sparse.iter_accepted(2).collect();
+        assert_eq!(expected, got);
+
+        assert_eq!(0, dfa.iter_accepted(0).count());
+        let empty = Builder::new().anchored(true).build(r"a*").unwrap();
+        let got: Vec<Vec<u8>> = empty.iter_accepted(0).collect();
+        assert_eq!(vec![Vec::<u8>::new()], got);
+        assert_eq!(4, empty.iter_accepted(3).count());
+    }
 
-        self.start = start;
+    #[test]
+    fn skip_bytes_are_ignored_by_search() {
+        for &(classes, accelerate) in
+            &[(true, true), (true, false), (false, true), (false, false)]
+        {
+            let mut builder = Builder::new();
+            builder
+                .byte_classes(classes)
+                .accelerate(accelerate)
+                .skip_bytes(b" -");
+            let dfa = builder.build("abc|x y").unwrap();
+            assert_eq!(Some(8), dfa.find(b"za b-  cz"));
+            assert_eq!(Some(8), dfa.find(b"za b-  c -z"));
+            assert_eq!(Some(8), dfa.shortest_match(b"za b-  c -z"));
+            assert!(dfa.is_match(b"- a-b-c -"));
+            assert_eq!(None, dfa.find(b"x y"));
+            assert!(!dfa.is_match(b"xy"));
+
+            let rev = builder.reverse(true).build("abc").unwrap();
+            assert_eq!(Some(1), rev.rfind(b"za b-  c -z"));
+            assert_eq!(None, rev.rfind(b"a  c"));
+
+            match dfa.to_sparse() {
+                Err(ref err) => match *err.kind() {
+                    ErrorKind::Unsupported(_) => {}
+                    ref kind => panic!("unexpected error: {:?}", kind),
+                },
+                Ok(_) => panic!("sparse DFA with skip bytes"),
+            }
+            assert!(builder.build_many_sparse(&["abc"]).is_err());
+        }
     }
 
-    /// Set the maximum state identifier that could possible correspond to a
-    /// match state.
-    ///
-    /// Callers must uphold the invariant that any state identifier less than
-    /// or equal to the identifier given is either a match state or the special
-    /// dead state (which always has identifier 0 and whose transitions all
-    /// lead back to itself).
-    ///
-    /// This cannot be called on a premultiplied DFA.
-    pub fn set_max_match_state(&mut self, id: S) {
-        assert!(!self.premultiplied, "can't set match on premultiplied DFA");
-        assert!(id.to_usize() < self.state_count, "invalid max match state");
+    #[test]
+    fn skip_bytes_in_pattern_are_never_matched() {
+        for &classes in &[true, false] {
+            let mut builder = Builder::new();
+            builder.byte_classes(classes).skip_bytes(b" ");
+            let dfa = builder.build("a b|c[ d]e| +").unwrap();
+            assert!(!dfa.is_match(b"a b"));
+            assert!(!dfa.is_match(b"   "));
+            assert!(!dfa.is_match(b"c e"));
+            assert_eq!(Some(4), dfa.find(b"c de"));
+            assert_eq!(Some(3), dfa.find(b"cde  "));
+
+            let rev =
+                builder.anchored(true).reverse(true).build("cde").unwrap();
+            assert_eq!(Some(2), rev.rfind(b"  cde"));
+            assert_eq!(Some(2), rev.rfind(b"  c d e"));
+        }
+    }
 
-        self.max_match = id;
+    #[test]
+    fn skip_bytes_survive_serialization() {
+        let dfa = Builder::new().skip_bytes(b" ").build("ab|x[ y]z").unwrap();
+        let bytes = dfa.to_bytes_native_endian().unwrap();
+        assert!(DFAHeader::from_bytes(&bytes).unwrap().has_skip_bytes());
+        let loaded: DenseDFA<&[usize], usize> =
+            unsafe { DenseDFA::from_bytes(&bytes) };
+        assert_eq!(dfa.as_ref(), loaded);
+        assert_eq!(Some(3), loaded.find(b"a b  x"));
+        assert_eq!(None, loaded.find(b"x z"));
+
+        let rle = dfa.to_bytes_rle_native_endian().unwrap();
+        let loaded = DenseDFA::<Vec<usize>, usize>::from_bytes_rle(&rle);
+        assert_eq!(Some(3), loaded.unwrap().find(b"a b  x"));
+
+        let plain = Builder::new().build("ab").unwrap();
+        let bytes = plain.to_bytes_native_endian().unwrap();
+        assert!(!DFAHeader::from_bytes(&bytes).unwrap().has_skip_bytes());
     }
 
-    /// Add the given transition to this DFA. Both the `from` and `to` states
-    /// must already exist.
-    ///
-    /// This cannot be called on a premultiplied DFA.
-    pub fn add_transition(&mut self, from: S, byte: u8, to: S) {
-        assert!(!self.premultiplied, "can't add trans to premultiplied DFA");
-        assert!(from.to_usize() < self.state_count, "invalid from state");
-        assert!(to.to_usize() < self.state_count, "invalid to state");
+    #[test]
+    fn is_match_from_start_state_agrees_with_is_match() {
+        let haystacks: &[&[u8]] =
+            &[b"", b"a", b"xyz", b"foo1", b"zzfoo99bar", b"fo"];
+        for &(pattern, anchored) in
+            &[("foo[0-9]+", false), ("foo[0-9]+", true), ("a+|z*", true)]
+        {
+            let dfa =
+                Builder::new().anchored(anchored).build(pattern).unwrap();
+            let sparse = dfa.to_sparse().unwrap();
+            for &h in haystacks {
+                let start = dfa.start_state();
+                assert_eq!(dfa.is_match(h), dfa.is_match_from(h, start));
+                let start = sparse.start_state();
+                assert_eq!(sparse.is_match(h), sparse.is_match_from(h, start));
+            }
+        }
+    }
 
-        let class = self.byte_classes().get(byte);
-        let offset = from.to_usize() * self.alphabet_len() + class as usize;
-        self.trans[offset] = to;
+    #[test]
+    fn find_or_dead_agrees_with_find_at() {
+        use dfa::SearchResult;
+
+        let dfa = DenseDFA::new("foo[0-9]+").unwrap();
+        for haystack in &["foo12", "xfoo1y", "fo", "", "foo"] {
+            let haystack = haystack.as_bytes();
+            match dfa.find_or_dead(haystack, 0) {
+                SearchResult::Match(end) => {
+                    assert_eq!(Some(end), dfa.find_at(haystack, 0))
+                }
+                _ => assert_eq!(None, dfa.find_at(haystack, 0)),
+            }
+        }
+        // An unanchored DFA only dies after a match, so a failed search
+        // always exhausts the input.
+        assert_eq!(SearchResult::NoMatchExhausted, dfa.find_or_dead(b"fo", 0));
+
+        let dfa = DenseDFA::<Vec<usize>, usize>::empty();
+        assert_eq!(SearchResult::NoMatchDead(1), dfa.find_or_dead(b"ab", 1));
     }
 
-    /// An an empty state (a state where all transitions lead to a dead state)
-    /// and return its identifier. The identifier returned is guaranteed to
-    /// not point to any other existing state.
-    ///
-    /// If adding a state would exhaust the state identifier space (given by
-    /// `S`), then this returns an error. In practice, this means that the
-    /// state identifier representation chosen is too small.
-    ///
-    /// This cannot be called on a premultiplied DFA.
-    pub fn add_empty_state(&mut self) -> Result<S> {
-        assert!(!self.premultiplied, "can't add state to premultiplied DFA");
+    #[test]
+    fn reverse_suffix_prefilter_agrees_with_unfiltered_search() {
+        let patterns = &["[a-z]+ing", "foo|barfoo", "x[0-9]*yz", "[a-c]", "a"];
+        let haystacks: &[&[u8]] = &[
+            b"",
+            b"singing!",
+            b"abarfoo foo",
+            b"x12yzq",
+            b"zzz",
+            b"xyz",
+            // The reverse DFA dies on invalid UTF-8 before it reaches the
+            // literal, so the prefilter must not skip over it.
+            b"acb\xFFc\n",
+            b"xyz\xCE\xB2\xCE",
+            b"sing\xE2\x82",
+        ];
+        for pattern in patterns {
+            let plain = Builder::new().reverse(true).build(pattern).unwrap();
+            let filtered = Builder::new()
+                .reverse(true)
+                .reverse_suffix_prefilter(true)
+                .build(pattern)
+                .unwrap();
+            assert!(filtered.literal_prefix().is_empty());
+            for haystack in haystacks {
+                for end in 0..haystack.len() + 1 {
+                    assert_eq!(
+                        plain.rfind_at(haystack, end),
+                        filtered.rfind_at(haystack, end),
+                        "pattern {:?}, haystack {:?}, end {}",
+                        pattern,
+                        haystack,
+                        end,
+                    );
+                }
+            }
+        }
 
-        let id = if self.state_count == 0 {
-            S::from_usize(0)
-        } else {
-            next_state_id(S::from_usize(self.state_count - 1))?
+        let build = |allow_invalid_utf8: bool| {
+            Builder::new()
+                .reverse(true)
+                .reverse_suffix_prefilter(true)
+                .allow_invalid_utf8(allow_invalid_utf8)
+                .build("a")
+                .unwrap()
         };
-        let alphabet_len = self.alphabet_len();
-        self.trans.extend(iter::repeat(dead_id::<S>()).take(alphabet_len));
-        // This should never panic, since state_count is a usize. The
-        // transition table size would have run out of room long ago.
-        self.state_count = self.state_count.checked_add(1).unwrap();
-        Ok(id)
+        assert_eq!(None, build(false).rfind(b"acb\xFFc\n"));
+        assert_eq!(Some(0), build(true).rfind(b"acb\xFFc\n"));
     }
 
-    /// Return a mutable representation of the state corresponding to the given
-    /// id. This is useful for implementing routines that manipulate DFA states
-    /// (e.g., swapping states).
-    ///
-    /// This cannot be called on a premultiplied DFA.
-    pub fn get_state_mut(&mut self, id: S) -> StateMut<S> {
-        assert!(!self.premultiplied, "can't get state in premultiplied DFA");
-
-        let alphabet_len = self.alphabet_len();
-        let offset = id.to_usize() * alphabet_len;
-        StateMut {
-            transitions: &mut self.trans[offset..offset + alphabet_len],
+    #[test]
+    fn utf8_search_agrees_with_fast_paths() {
+        let patterns = &["a(?-u:\\xCE)?", "(?-u:.)", "a(?-u:.)+", "β+x?"];
+        let haystacks = &["", "aβ", "xaβββ", "ββx", "zaβa"];
+        for pattern in patterns {
+            let build = |reverse: bool, fast: bool| {
+                Builder::new()
+                    .allow_invalid_utf8(true)
+                    .reverse(reverse)
+                    .prefilter(fast)
+                    .reverse_suffix_prefilter(fast)
+                    .accelerate(fast)
+                    .build(pattern)
+                    .unwrap()
+            };
+            let (fwd, fast_fwd) = (build(false, false), build(false, true));
+            let (rev, fast_rev) = (build(true, false), build(true, true));
+            for haystack in haystacks {
+                let end = fwd.find_utf8(haystack);
+                assert_eq!(end, fast_fwd.find_utf8(haystack), "{}", pattern);
+                if let Some(i) = end {
+                    assert!(haystack.is_char_boundary(i));
+                }
+                let start = rev.rfind_utf8(haystack);
+                assert_eq!(
+                    start,
+                    fast_rev.rfind_utf8(haystack),
+                    "{}",
+                    pattern
+                );
+                if let Some(i) = start {
+                    assert!(haystack.is_char_boundary(i));
+                }
+            }
         }
-    }
+        let dfa = Builder::new()
+            .allow_invalid_utf8(true)
+            .prefilter(true)
+            .build("a(?-u:\\xCE)?")
+            .unwrap();
+        assert_eq!(Some(3), dfa.find("xaβ".as_bytes()));
+        assert_eq!(Some(2), dfa.find_utf8("xaβ"));
 
-    /// Swap the two states given in the transition table.
-    ///
-    /// This routine does not do anything to check the correctness of this
-    /// swap. Callers must ensure that other states pointing to id1 and id2 are
-    /// updated appropriately.
-    ///
-    /// This cannot be called on a premultiplied DFA.
-    pub fn swap_states(&mut self, id1: S, id2: S) {
-        assert!(!self.premultiplied, "can't swap states in premultiplied DFA");
+        // Every match here splits a character, which must not cause the
+        // haystack to be searched again for each of them.
+        let haystack = "β".repeat(20_000);
+        let dfa = Builder::new()
+            .allow_invalid_utf8(true)
+            .build(r"(?-u:[\x00-\xFF])*(?-u:\xCE)")
+            .unwrap();
+        assert_eq!(None, dfa.find_utf8(&haystack));
+        let dfa = Builder::new()
+            .allow_invalid_utf8(true)
+            .reverse(true)
+            .build(r"(?-u:\xB2)(?-u:[\x00-\xFF])*")
+            .unwrap();
+        assert_eq!(None, dfa.rfind_utf8(&haystack));
+    }
 
-        let o1 = id1.to_usize() * self.alphabet_len();
-        let o2 = id2.to_usize() * self.alphabet_len();
-        for b in 0..self.alphabet_len() {
-            self.trans.swap(o1 + b, o2 + b);
+    #[test]
+    fn is_minimal_after_minimize() {
+        for pattern in &["a|b|ab", "foo[0-9]+|bar[0-9]+", "(ab)*|(ba)*"] {
+            let minimal =
+                Builder::new().minimize(true).build(pattern).unwrap();
+            assert!(minimal.is_minimal(), "pattern: {:?}", pattern);
         }
+        // Determinization alone leaves two equivalent states for `[ab]`
+        // after `x` and `y`.
+        let dfa = Builder::new()
+            .minimize(false)
+            .premultiply(false)
+            .anchored(true)
+            .build("x[ab]|y[ab]")
+            .unwrap();
+        assert!(!dfa.is_minimal());
+        let premultiplied =
+            Builder::new().anchored(true).build("x[ab]|y[ab]").unwrap();
+        assert!(!premultiplied.is_minimal());
     }
 
-    /// Truncate the states in this DFA to the given count.
-    ///
-    /// This routine does not do anything to check the correctness of this
-    /// truncation. Callers must ensure that other states pointing to truncated
-    /// states are updated appropriately.
-    ///
-    /// This cannot be called on a premultiplied DFA.
-    pub fn truncate_states(&mut self, count: usize) {
-        assert!(!self.premultiplied, "can't truncate in premultiplied DFA");
+    #[test]
+    fn state_partition_matches_minimized_size() {
+        for pattern in &["a|b|ab", "foo[0-9]+|bar[0-9]+", "x[ab]|y[ab]"] {
+            let dfa = Builder::new().build(pattern).unwrap();
+            let minimal =
+                Builder::new().minimize(true).build(pattern).unwrap();
+            let partition = dfa.state_partition();
+            assert_eq!(minimal.repr().state_count(), partition.len());
+
+            let mut ids: Vec<usize> =
+                partition.into_iter().flat_map(|g| g).collect();
+            ids.sort();
+            let all: Vec<usize> =
+                dfa.repr().states().map(|(id, _)| id).collect();
+            assert_eq!(all, ids);
+        }
+    }
 
-        let alphabet_len = self.alphabet_len();
-        self.trans.truncate(count * alphabet_len);
-        self.state_count = count;
+    #[test]
+    fn build_literals_agrees_with_alternation() {
+        let sets: &[&[&str]] = &[
+            &["foo", "bar", "quux"],
+            &["a", "ab", "abc"],
+            &["abc", "ab", "a"],
+            &["sam", "samwise", "sa", "sam"],
+            &["", "a"],
+            &[],
+        ];
+        let haystacks: &[&[u8]] =
+            &[b"", b"a", b"abc", b"xxsamwise", b"fobarfoo", b"qua", b"zab"];
+        for literals in sets {
+            let alternation = literals.join("|");
+            for &anchored in &[false, true] {
+                for &reverse in &[false, true] {
+                    let mut builder = Builder::new();
+                    builder.anchored(anchored).reverse(reverse);
+                    // regex-syntax doesn't permit an empty alternation.
+                    let expected = if literals.is_empty() {
+                        DenseDFA::empty()
+                    } else {
+                        builder.build(&alternation).unwrap()
+                    };
+                    let got = builder.build_literals(literals).unwrap();
+                    for haystack in haystacks {
+                        let (e, g) = if reverse {
+                            (expected.rfind(haystack), got.rfind(haystack))
+                        } else {
+                            (expected.find(haystack), got.find(haystack))
+                        };
+                        assert_eq!(
+                            e, g,
+                            "literals: {:?}, anchored: {}, reverse: {}, \
+                             haystack: {:?}",
+                            literals, anchored, reverse, haystack,
+                        );
+                    }
+                }
+            }
+        }
     }
 
-    /// This routine shuffles all match states in this DFA---according to the
-    /// given map---to the beginning of the DFA such that every non-match state
-    /// appears after every match state. (With one exception: the special dead
-    /// state remains as the first state.) The given map should have length
-    /// exactly equivalent to the number of states in this DFA.
-    ///
-    /// The purpose of doing this shuffling is to avoid the need to store
-    /// additional state to determine whether a state is a match state or not.
-    /// It also enables a single conditional in the core matching loop instead
-    /// of two.
-    ///
-    /// This updates `self.max_match` to point to the last matching state as
-    /// well as `self.start` if the starting state was moved.
-    pub fn shuffle_match_states(&mut self, is_match: &[bool]) {
-        assert!(
-            !self.premultiplied,
-            "cannot shuffle match states of premultiplied DFA"
-        );
-        assert_eq!(self.state_count, is_match.len());
+    #[test]
+    fn build_literals_sets_prefilter() {
+        let dfa = Builder::new()
+            .prefilter(true)
+            .build_literals(&["foobar", "foobaz", "food"])
+            .unwrap();
+        assert_eq!(b"foo", dfa.literal_prefix());
+        assert_eq!(Some(7), dfa.find(b"xx foods"));
+    }
 
-        if self.state_count <= 1 {
-            return;
+    #[test]
+    fn determinize_cache_limit_preserves_matches() {
+        let pattern = r"[a-z]+[0-9]*(foo|bar)+[a-z0-9_]{2}";
+        let full = Builder::new().build(pattern).unwrap();
+        let limited =
+            Builder::new().determinize_cache_limit(1).build(pattern).unwrap();
+        assert!(limited.repr().state_count() > full.repr().state_count());
+        for haystack in &["abc12foobarxy", "foo", "zfoo9", "a1barbarZZ!"] {
+            let haystack = haystack.as_bytes();
+            assert_eq!(full.find(haystack), limited.find(haystack));
         }
 
-        let mut first_non_match = 1;
-        while first_non_match < self.state_count && is_match[first_non_match] {
-            first_non_match += 1;
-        }
+        let minimal = Builder::new().minimize(true).build(pattern).unwrap();
+        let limited = Builder::new()
+            .minimize(true)
+            .determinize_cache_limit(1)
+            .build(pattern)
+            .unwrap();
+        assert_eq!(minimal.repr().state_count(), limited.repr().state_count());
+    }
 
-        let mut swaps: Vec<S> = vec![dead_id(); self.state_count];
-        let mut cur = self.state_count - 1;
-        while cur > first_non_match {
-            if is_match[cur] {
-                self.swap_states(
-                    S::from_usize(cur),
-                    S::from_usize(first_non_match),
-                );
-                swaps[cur] = S::from_usize(first_non_match);
-                swaps[first_non_match] = S::from_usize(cur);
+    #[test]
+    fn simulate_reports_every_prefix() {
+        use dfa::MatchStatus::*;
 
-                first_non_match += 1;
-                while first_non_match < cur && is_match[first_non_match] {
-                    first_non_match += 1;
-                }
-            }
-            cur -= 1;
-        }
-        for id in (0..self.state_count).map(S::from_usize) {
-            for (_, next) in self.get_state_mut(id).iter_mut() {
-                if swaps[next.to_usize()] != dead_id() {
-                    *next = swaps[next.to_usize()];
-                }
-            }
+        let dfa =
+            Builder::new().anchored(true).build(r"[a-z]+[0-9]?").unwrap();
+        let statuses = dfa.simulate(b"abc1d2");
+        assert_eq!(
+            vec![NoMatch, Match, Match, Match, Match, Dead, Dead],
+            statuses
+        );
+        for (i, &status) in statuses.iter().enumerate() {
+            let prefix_matches = dfa.find(&b"abc1d2"[..i]) == Some(i);
+            assert_eq!(prefix_matches, status == Match);
         }
-        if swaps[self.start.to_usize()] != dead_id() {
-            self.start = swaps[self.start.to_usize()];
+
+        // Leftmost-first semantics stop looking for matches once the
+        // leftmost match can't be extended, even in an unanchored DFA.
+        let dfa = Builder::new().build(r"[0-9]").unwrap();
+        assert_eq!(vec![NoMatch, NoMatch, Match, Dead], dfa.simulate(b"x1y"));
+    }
+
+    #[test]
+    fn determinize_fast_hash_builds_identical_dfas() {
+        let mut scratch = BuildScratch::new();
+        let mut fast = Builder::new();
+        fast.determinize_fast_hash(true);
+        let patterns = &[r"\w+\s+\d{3}", "foo|bar|quux", r"[01]*1[01]{5}"];
+        for &pattern in patterns {
+            let expected = Builder::new().build(pattern).unwrap();
+            assert_eq!(expected, fast.build(pattern).unwrap());
+            // Alternate hash functions on the same scratch space, which
+            // must not reuse a cache keyed by the other function.
+            let reused = fast.build_reusing(&mut scratch, pattern).unwrap();
+            assert_eq!(expected, reused);
+            let reused =
+                Builder::new().build_reusing(&mut scratch, pattern).unwrap();
+            assert_eq!(expected, reused);
         }
-        self.max_match = S::from_usize(first_non_match - 1);
     }
-}
 
-#[cfg(feature = "std")]
-impl<T: AsRef<[S]>, S: StateID> fmt::Debug for Repr<T, S> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fn state_status<T: AsRef<[S]>, S: StateID>(
-            dfa: &Repr<T, S>,
-            id: S,
-        ) -> &'static str {
-            if id == dead_id() {
-                if dfa.is_match_state(id) {
-                    "D*"
-                } else {
-                    "D "
-                }
-            } else if id == dfa.start_state() {
-                if dfa.is_match_state(id) {
-                    ">*"
-                } else {
-                    "> "
+    #[test]
+    fn build_both_matches_separate_builds() {
+        let mut builder = Builder::new();
+        builder.anchored(true).accelerate(true);
+        for &pattern in &["a+b|c+b", r"\w+\s", "foo|bar|baz", ""] {
+            let (determinized, minimized) =
+                builder.build_both(pattern).unwrap();
+            assert_eq!(
+                builder.minimize(false).build(pattern).unwrap(),
+                determinized
+            );
+            assert_eq!(
+                builder.minimize(true).build(pattern).unwrap(),
+                minimized
+            );
+        }
+    }
+
+    #[test]
+    fn build_reusing_matches_build() {
+        let mut scratch = BuildScratch::new();
+        let mut builder = Builder::new();
+        builder.dfa_size_limit(20);
+        let patterns = &["[a-z]+[0-9]", r"[01]*1[01]{5}", "foo|bar", "", "x*"];
+        for &pattern in patterns {
+            match builder.build(pattern) {
+                Ok(dfa) => {
+                    let reused =
+                        builder.build_reusing(&mut scratch, pattern).unwrap();
+                    assert_eq!(dfa, reused, "{}", pattern);
                 }
-            } else {
-                if dfa.is_match_state(id) {
-                    " *"
-                } else {
-                    "  "
+                Err(_) => {
+                    assert!(builder
+                        .build_reusing(&mut scratch, pattern)
+                        .is_err());
                 }
             }
         }
+    }
 
-        writeln!(f, "DenseDFA(")?;
-        for (id, state) in self.states() {
-            let status = state_status(self, id);
-            writeln!(f, "{}{:06}: {:?}", status, id.to_usize(), state)?;
+    #[test]
+    fn rle_round_trip() {
+        for &(classes, premultiply) in
+            &[(true, true), (true, false), (false, true), (false, false)]
+        {
+            let dfa = Builder::new()
+                .byte_classes(classes)
+                .premultiply(premultiply)
+                .build_with_size::<u16>("foo[0-9]+|[a-z]{2}bar")
+                .unwrap();
+            let bytes = dfa.to_bytes_rle_native_endian().unwrap();
+            let got =
+                DenseDFA::<Vec<u16>, u16>::from_bytes_rle(&bytes).unwrap();
+            assert_eq!(dfa, got);
+
+            // Truncated input is an error rather than a panic.
+            let short = &bytes[..bytes.len() - 1];
+            assert!(DenseDFA::<Vec<u16>, u16>::from_bytes_rle(short).is_err());
         }
-        writeln!(f, ")")?;
-        Ok(())
     }
-}
 
-/// An iterator over all states in a DFA.
-///
-/// This iterator yields a tuple for each state. The first element of the
-/// tuple corresponds to a state's identifier, and the second element
-/// corresponds to the state itself (comprised of its transitions).
-///
-/// If this DFA is premultiplied, then the state identifiers are in turn
-/// premultiplied as well, making them usable without additional modification.
-///
-/// `'a` corresponding to the lifetime of original DFA, `T` corresponds to
-/// the type of the transition table itself and `S` corresponds to the state
-/// identifier representation.
-#[cfg(feature = "std")]
-pub(crate) struct StateIter<'a, T: 'a, S: 'a> {
-    dfa: &'a Repr<T, S>,
-    it: iter::Enumerate<slice::Chunks<'a, S>>,
-}
+    #[test]
+    fn builder_keeps_no_state_between_builds() {
+        let mut builder = Builder::new();
+        builder.minimize(true).byte_classes(false);
+        let fresh = |pattern| {
+            Builder::new()
+                .minimize(true)
+                .byte_classes(false)
+                .build(pattern)
+                .unwrap()
+        };
 
-#[cfg(feature = "std")]
-impl<'a, T: AsRef<[S]>, S: StateID> Iterator for StateIter<'a, T, S> {
-    type Item = (S, State<'a, S>);
+        let unicode = builder.build(r"\w+\s+\p{Greek}").unwrap();
+        let literal = builder.build("z").unwrap();
+        assert_eq!(fresh("z"), literal);
+        assert_eq!(fresh(r"\w+\s+\p{Greek}"), unicode);
+        // A failed build doesn't leave anything behind either.
+        assert!(builder.build("(").is_err());
+        assert_eq!(literal, builder.build("z").unwrap());
+    }
 
-    fn next(&mut self) -> Option<(S, State<'a, S>)> {
-        self.it.next().map(|(id, chunk)| {
-            let state = State { transitions: chunk };
-            let id = if self.dfa.premultiplied {
-                id * self.dfa.alphabet_len()
-            } else {
-                id
-            };
-            (S::from_usize(id), state)
-        })
+    #[test]
+    fn varint_round_trip() {
+        for &premultiply in &[true, false] {
+            // Enough states that some indices need more than one byte.
+            let dfa = Builder::new()
+                .premultiply(premultiply)
+                .build_with_size::<u32>(r"\w{3}[0-9]")
+                .unwrap();
+            assert!(dfa.repr().state_count() > 128);
+            let bytes = dfa.to_bytes_varint_native_endian().unwrap();
+            assert!(bytes.len() < dfa.to_bytes_native_endian().unwrap().len());
+            let got =
+                DenseDFA::<Vec<u32>, u32>::from_bytes_varint(&bytes).unwrap();
+            assert_eq!(dfa, got);
+
+            let short = &bytes[..bytes.len() - 1];
+            assert!(
+                DenseDFA::<Vec<u32>, u32>::from_bytes_varint(short).is_err()
+            );
+            assert!(DenseDFA::<Vec<u32>, u32>::from_bytes_rle(&bytes).is_err());
+        }
     }
-}
 
-/// An immutable representation of a single DFA state.
-///
-/// `'a` correspondings to the lifetime of a DFA's transition table and `S`
-/// corresponds to the state identifier representation.
-#[cfg(feature = "std")]
-pub(crate) struct State<'a, S: 'a> {
-    transitions: &'a [S],
-}
+    /// A 3 byte state identifier, stored big endian so that its derived
+    /// ordering agrees with its numeric value.
+    #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+    struct U24([u8; 3]);
 
-#[cfg(feature = "std")]
-impl<'a, S: StateID> State<'a, S> {
-    /// Return an iterator over all transitions in this state. This yields
-    /// a number of transitions equivalent to the alphabet length of the
-    /// corresponding DFA.
-    ///
-    /// Each transition is represented by a tuple. The first element is
-    /// the input byte for that transition and the second element is the
-    /// transitions itself.
-    pub fn transitions(&self) -> StateTransitionIter<S> {
-        StateTransitionIter { it: self.transitions.iter().enumerate() }
+    unsafe impl StateID for U24 {
+        fn from_usize(n: usize) -> U24 {
+            U24([(n >> 16) as u8, (n >> 8) as u8, n as u8])
+        }
+
+        fn to_usize(self) -> usize {
+            let U24([a, b, c]) = self;
+            (a as usize) << 16 | (b as usize) << 8 | c as usize
+        }
+
+        fn max_id() -> usize {
+            (1 << 24) - 1
+        }
+
+        fn read_bytes(slice: &[u8]) -> U24 {
+            U24([slice[0], slice[1], slice[2]])
+        }
+
+        fn write_bytes(self, slice: &mut [u8]) {
+            slice[..3].copy_from_slice(&self.0);
+        }
     }
 
-    /// Return an iterator over a sparse representation of the transitions in
-    /// this state. Only non-dead transitions are returned.
-    ///
-    /// The "sparse" representation in this case corresponds to a sequence of
-    /// triples. The first two elements of the triple comprise an inclusive
-    /// byte range while the last element corresponds to the transition taken
-    /// for all bytes in the range.
-    ///
-    /// This is somewhat more condensed than the classical sparse
-    /// representation (where you have an element for every non-dead
-    /// transition), but in practice, checking if a byte is in a range is very
-    /// cheap and using ranges tends to conserve quite a bit more space.
-    pub fn sparse_transitions(&self) -> StateSparseTransitionIter<S> {
-        StateSparseTransitionIter { dense: self.transitions(), cur: None }
+    #[test]
+    fn odd_state_sizes_are_widened_when_serialized() {
+        let dfa = DenseDFA::new(r"\w+[0-9]").unwrap();
+        let odd = dfa.to_sized::<U24>().unwrap();
+        assert_eq!(Some(3), odd.find(b"ab1 x"));
+
+        let bytes = odd.to_bytes_native_endian().unwrap();
+        assert_eq!(
+            dfa.to_u32().unwrap().to_bytes_native_endian().unwrap(),
+            bytes
+        );
+        let widened: DenseDFA<&[u32], u32> =
+            unsafe { DenseDFA::from_bytes(&bytes) };
+        assert_eq!(Some(3), widened.find(b"ab1 x"));
+
+        let rle = odd.to_bytes_rle_native_endian().unwrap();
+        let loaded = DenseDFA::<Vec<U24>, U24>::from_bytes_rle(&rle).unwrap();
+        assert_eq!(odd, loaded);
+        assert!(odd.to_rust_source("ODD").is_err());
     }
-}
 
-#[cfg(feature = "std")]
-impl<'a, S: StateID> fmt::Debug for State<'a, S> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut transitions = vec![];
-        for (start, end, next_id) in self.sparse_transitions() {
-            let line = if start == end {
-                format!("{} => {}", escape(start), next_id.to_usize())
-            } else {
-                format!(
-                    "{}-{} => {}",
-                    escape(start),
-                    escape(end),
-                    next_id.to_usize(),
-                )
-            };
-            transitions.push(line);
+    #[test]
+    fn rle_rejects_mismatched_state_size() {
+        let dfa = DenseDFA::new("foo").unwrap().to_u16().unwrap();
+        let bytes = dfa.to_bytes_rle_native_endian().unwrap();
+        let err =
+            DenseDFA::<Vec<u32>, u32>::from_bytes_rle(&bytes).unwrap_err();
+        match *err.kind() {
+            ErrorKind::Deserialize(_) => {}
+            ref kind => panic!("unexpected error: {:?}", kind),
         }
-        write!(f, "{}", transitions.join(", "))?;
-        Ok(())
     }
-}
 
-/// An iterator over all transitions in a single DFA state. This yields
-/// a number of transitions equivalent to the alphabet length of the
-/// corresponding DFA.
-///
-/// Each transition is represented by a tuple. The first element is the input
-/// byte for that transition and the second element is the transitions itself.
-#[cfg(feature = "std")]
-#[derive(Debug)]
-pub(crate) struct StateTransitionIter<'a, S: 'a> {
-    it: iter::Enumerate<slice::Iter<'a, S>>,
-}
+    #[test]
+    fn serialize_without_classes_round_trip() {
+        let dfa = DenseDFA::new("foo[0-9]+|bar").unwrap().to_u32().unwrap();
+        let bytes = dfa.to_bytes_without_classes_native_endian().unwrap();
+        let with = dfa.to_bytes_native_endian().unwrap();
+        assert_eq!(with.len() - 248, bytes.len());
+
+        let classes = ByteClasses::from_bytes(&dfa.byte_classes().to_bytes());
+        let got: DenseDFA<&[u32], u32> =
+            unsafe { DenseDFA::from_bytes_with_classes(&bytes, classes) };
+        assert_eq!(dfa.as_ref(), got);
+    }
 
-#[cfg(feature = "std")]
-impl<'a, S: StateID> Iterator for StateTransitionIter<'a, S> {
-    type Item = (u8, S);
+    #[test]
+    #[should_panic]
+    fn from_bytes_rejects_omitted_classes() {
+        let dfa = DenseDFA::new("foo[0-9]+").unwrap().to_u32().unwrap();
+        let bytes = dfa.to_bytes_without_classes_native_endian().unwrap();
+        let _: DenseDFA<&[u32], u32> = unsafe { DenseDFA::from_bytes(&bytes) };
+    }
 
-    fn next(&mut self) -> Option<(u8, S)> {
-        self.it.next().map(|(i, &id)| (i as u8, id))
+    #[test]
+    fn from_bytes_accepts_trailing_bytes() {
+        let dfa = DenseDFA::new("foo[0-9]+").unwrap().to_u16().unwrap();
+        let mut bytes = dfa.to_bytes_native_endian().unwrap();
+        bytes.extend_from_slice(&[0; 16]);
+        let loaded: DenseDFA<&[u16], u16> =
+            unsafe { DenseDFA::from_bytes(&bytes) };
+        assert_eq!(Some(8), loaded.find(b"foo12345"));
     }
-}
 
-/// An iterator over all transitions in a single DFA state using a sparse
-/// representation.
-///
-/// Each transition is represented by a triple. The first two elements of the
-/// triple comprise an inclusive byte range while the last element corresponds
-/// to the transition taken for all bytes in the range.
-#[cfg(feature = "std")]
-#[derive(Debug)]
-pub(crate) struct StateSparseTransitionIter<'a, S: 'a> {
-    dense: StateTransitionIter<'a, S>,
-    cur: Option<(u8, u8, S)>,
-}
+    #[test]
+    #[should_panic(expected = "insufficient transition table bytes")]
+    fn from_bytes_rejects_truncated_transitions() {
+        let dfa = DenseDFA::new("foo[0-9]+").unwrap().to_u16().unwrap();
+        let mut bytes = dfa.to_bytes_native_endian().unwrap();
+        let len = bytes.len();
+        bytes.truncate(len - 2);
+        let _: DenseDFA<&[u16], u16> = unsafe { DenseDFA::from_bytes(&bytes) };
+    }
 
-#[cfg(feature = "std")]
-impl<'a, S: StateID> Iterator for StateSparseTransitionIter<'a, S> {
-    type Item = (u8, u8, S);
+    #[test]
+    fn validate_rejects_corrupt_transitions() {
+        for &premultiply in &[false, true] {
+            let dfa = Builder::new()
+                .premultiply(premultiply)
+                .build_with_size::<u16>("foo[0-9]+")
+                .unwrap();
+            assert!(dfa.validate().is_ok());
+
+            let mut bytes = dfa.to_bytes_native_endian().unwrap();
+            // The transition table is at the end of the serialized bytes, so
+            // this points the last transition at a state that doesn't exist.
+            let last = bytes.len() - 2;
+            NativeEndian::write_u16(&mut bytes[last..], 0xFFFF);
+            let dfa: DenseDFA<&[u16], u16> =
+                unsafe { DenseDFA::from_bytes(&bytes) };
+            let err = dfa.validate().unwrap_err();
+            let msg = err.to_string();
+            assert!(msg.contains("invalid state 65535"), "{}", msg);
+            match *Error::from(err).kind() {
+                ErrorKind::Deserialize(ref got) => assert_eq!(&msg, got),
+                ref kind => panic!("unexpected error kind: {:?}", kind),
+            }
+        }
+    }
 
-    fn next(&mut self) -> Option<(u8, u8, S)> {
-        while let Some((b, next)) = self.dense.next() {
-            let (prev_start, prev_end, prev_next) = match self.cur {
-                Some(t) => t,
-                None => {
-                    self.cur = Some((b, b, next));
-                    continue;
+    #[test]
+    fn shared_dfa_clones_share_transitions() {
+        let dfa = DenseDFA::new("foo[0-9]+").unwrap();
+        let expected = dfa.clone();
+        let shared = dfa.into_shared();
+        let copy = shared.clone();
+        assert!(Arc::ptr_eq(&shared.repr().trans, &copy.repr().trans));
+        assert_eq!(expected.as_ref(), copy.as_ref());
+        assert_eq!(Some(8), copy.find(b"foo12345"));
+    }
+
+    #[test]
+    fn prefilter_agrees_with_unfiltered_search() {
+        let patterns = &["foo[0-9]+", "abc|abd", "(?i)ab", "a|b", "x*"];
+        let haystacks: &[&[u8]] = &[
+            b"",
+            b"fo",
+            b"xxfoo1",
+            b"fofoo12foo3",
+            b"zabd",
+            b"AB",
+            b"ccb",
+            // The unanchored DFA dies on invalid UTF-8 before it reaches the
+            // literal, so the prefilter must not skip over it.
+            b"\xFFfoo1",
+            b"foo1\xFFfoo2",
+            b"\xCE\xB2abd\xCEab",
+            b"a\xE2\x82b",
+        ];
+        for &pattern in patterns {
+            let plain = Builder::new().build(pattern).unwrap();
+            let filtered =
+                Builder::new().prefilter(true).build(pattern).unwrap();
+            for &h in haystacks {
+                for start in 0..h.len() + 1 {
+                    assert_eq!(
+                        plain.find_at(h, start),
+                        filtered.find_at(h, start),
+                        "pattern {:?}, haystack {:?}, start {}",
+                        pattern,
+                        h,
+                        start,
+                    );
+                    assert_eq!(
+                        plain.is_match_at(h, start),
+                        filtered.is_match_at(h, start)
+                    );
+                    assert_eq!(
+                        plain.shortest_match_at(h, start),
+                        filtered.shortest_match_at(h, start)
+                    );
                 }
-            };
-            if prev_next == next {
-                self.cur = Some((prev_start, b, prev_next));
-            } else {
-                self.cur = Some((b, b, next));
-                if prev_next != dead_id() {
-                    return Some((prev_start, prev_end, prev_next));
+            }
+        }
+
+        let prefix = |pattern: &str| {
+            let dfa = Builder::new().prefilter(true).build(pattern).unwrap();
+            dfa.literal_prefix().to_vec()
+        };
+        assert_eq!(b"foo".to_vec(), prefix("foo[0-9]+"));
+        assert_eq!(b"ab".to_vec(), prefix("abc|abd"));
+        assert_eq!(Vec::<u8>::new(), prefix("(?i)ab"));
+        assert_eq!(Vec::<u8>::new(), prefix("x*"));
+
+        let dfa = Builder::new().prefilter(true).build("foo").unwrap();
+        assert_eq!(None, dfa.find(b"\xFFfoo"));
+        assert!(!dfa.is_match(b"\xFFfoo"));
+        let dfa = Builder::new()
+            .allow_invalid_utf8(true)
+            .prefilter(true)
+            .build("foo")
+            .unwrap();
+        assert_eq!(Some(4), dfa.find(b"\xFFfoo"));
+    }
+
+    #[test]
+    fn find_bounded_agrees_with_find() {
+        use dfa::SearchOutcome;
+
+        let dfa = DenseDFA::new(r"[a-z]+[0-9]").unwrap();
+        let haystack = b"12 ab3 cd";
+        for limit in 0..haystack.len() + 2 {
+            match dfa.find_bounded(haystack, limit) {
+                SearchOutcome::Match(end) => {
+                    assert_eq!(Some(end), dfa.find(haystack))
                 }
+                SearchOutcome::NoMatch => panic!("limit {}", limit),
+                // The match is decided once the DFA dies after `3`.
+                SearchOutcome::Incomplete => assert!(limit < 7),
             }
         }
-        if let Some((start, end, next)) = self.cur.take() {
-            if next != dead_id() {
-                return Some((start, end, next));
+        assert_eq!(SearchOutcome::NoMatch, dfa.find_bounded(b"12", 2));
+        assert_eq!(SearchOutcome::Incomplete, dfa.find_bounded(b"12", 1));
+    }
+
+    #[test]
+    fn find_utf16_agrees_with_transcoding() {
+        let dfa = DenseDFA::new(r"\w+\s").unwrap();
+        let haystacks = &["", "abc ", "!!δέλτα ", "💩x💩 y", "   ", "ab"];
+        for haystack in haystacks {
+            // Convert the byte offset of a UTF-8 match to code units.
+            let expected = dfa
+                .find(haystack.as_bytes())
+                .map(|end| haystack[..end].encode_utf16().count());
+            let units: Vec<u16> = haystack.encode_utf16().collect();
+            assert_eq!(expected, dfa.find_utf16(&units), "{:?}", haystack);
+        }
+
+        // An unpaired surrogate is treated as U+FFFD.
+        let dfa = DenseDFA::new("a\u{FFFD}+b").unwrap();
+        assert_eq!(Some(3), dfa.find_utf16(&[0x61, 0xD800, 0x62]));
+        assert_eq!(Some(4), dfa.find_utf16(&[0x61, 0xDC00, 0xD800, 0x62]));
+        // But a valid surrogate pair is not.
+        assert_eq!(None, dfa.find_utf16(&[0x61, 0xD800, 0xDC00, 0x62]));
+    }
+
+    #[test]
+    fn state_agrees_with_states() {
+        for &premultiply in &[false, true] {
+            let dfa = Builder::new()
+                .premultiply(premultiply)
+                .build(r"[a-c]+[0-9]")
+                .unwrap();
+            let r = dfa.repr();
+            for (id, state) in r.states() {
+                let got: Vec<_> = r.state(id).transitions().collect();
+                let expected: Vec<_> = state.transitions().collect();
+                assert_eq!(expected, got);
+
+                let view = dfa.state(id);
+                assert_eq!(id, view.id());
+                assert_eq!(dfa.is_match_state(id), view.is_match());
+                assert_eq!(id == dead_id(), view.is_dead());
+                let mut next = 0;
+                for (start, end, to) in view.transitions() {
+                    assert_eq!(next, start as usize);
+                    for b in start..=end {
+                        assert_eq!(dfa.next_state(id, b), to);
+                    }
+                    next = end as usize + 1;
+                }
+                assert_eq!(256, next);
             }
         }
-        None
     }
-}
 
-/// A mutable representation of a single DFA state.
-///
-/// `'a` correspondings to the lifetime of a DFA's transition table and `S`
-/// corresponds to the state identifier representation.
-#[cfg(feature = "std")]
-pub(crate) struct StateMut<'a, S: 'a> {
-    transitions: &'a mut [S],
-}
+    #[test]
+    fn accepts_empty_agrees_with_searching() {
+        let patterns = &["a*", "a+", "(?:ab)?", "a|", "[0-9]{0,3}x?", "b"];
+        for &pattern in patterns {
+            for &reverse in &[false, true] {
+                let dfa = Builder::new()
+                    .anchored(true)
+                    .reverse(reverse)
+                    .build(pattern)
+                    .unwrap();
+                let expected = if reverse {
+                    dfa.rfind(b"").is_some()
+                } else {
+                    dfa.find(b"").is_some()
+                };
+                assert_eq!(expected, dfa.accepts_empty(), "{}", pattern);
+                let sparse = dfa.to_sparse().unwrap();
+                assert_eq!(expected, sparse.accepts_empty(), "{}", pattern);
+            }
+        }
+    }
 
-#[cfg(feature = "std")]
-impl<'a, S: StateID> StateMut<'a, S> {
-    /// Return an iterator over all transitions in this state. This yields
-    /// a number of transitions equivalent to the alphabet length of the
-    /// corresponding DFA.
-    ///
-    /// Each transition is represented by a tuple. The first element is the
-    /// input byte for that transition and the second element is a mutable
-    /// reference to the transition itself.
-    pub fn iter_mut(&mut self) -> StateTransitionIterMut<S> {
-        StateTransitionIterMut { it: self.transitions.iter_mut().enumerate() }
+    #[test]
+    fn reverse_language_agrees_with_reversed_input() {
+        let patterns = &["ab+c|d", "a*", "[a-c]{2}b?", "(?:ab|ba)+c"];
+        let mut inputs = vec![vec![]];
+        for len in 1..5 {
+            let mut input = vec![b'a'; len];
+            // Enumerate every string over `abcd` of this length.
+            for n in 0..4usize.pow(len as u32) {
+                for (i, b) in input.iter_mut().enumerate() {
+                    *b = b'a' + (n >> (2 * i) & 3) as u8;
+                }
+                inputs.push(input.clone());
+            }
+        }
+        for &pattern in patterns {
+            for &premultiply in &[false, true] {
+                let dfa = Builder::new()
+                    .full_match(true)
+                    .premultiply(premultiply)
+                    .build(pattern)
+                    .unwrap();
+                let rev = dfa.reverse_language().unwrap();
+                assert!(rev.is_anchored());
+                let double = rev.reverse_language().unwrap();
+                for input in &inputs {
+                    let reversed: Vec<u8> =
+                        input.iter().rev().cloned().collect();
+                    let expected = dfa.is_full_match(&reversed);
+                    assert_eq!(expected, rev.is_full_match(input));
+                    assert_eq!(
+                        dfa.is_full_match(input),
+                        double.is_full_match(input)
+                    );
+                }
+            }
+        }
     }
-}
 
-#[cfg(feature = "std")]
-impl<'a, S: StateID> fmt::Debug for StateMut<'a, S> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Debug::fmt(&State { transitions: self.transitions }, f)
+    #[test]
+    fn full_match_applies_through_every_search_routine() {
+        use dfa::{SearchOutcome, SearchResult};
+
+        fn check<D: DFA>(dfa: D) {
+            assert!(dfa.requires_full_match());
+            assert_eq!(None, dfa.find(b"123a"));
+            assert_eq!(Some(3), dfa.find(b"123"));
+            assert_eq!(None, dfa.find_at(b"a123", 1));
+            assert!(!dfa.is_match_at(b"a123", 1));
+            assert_eq!(None, dfa.shortest_match(b"123a"));
+            assert!(!dfa.is_match_from(b"123a", dfa.start_state()));
+            assert!(dfa.is_match_from(b"123", dfa.start_state()));
+            let units: Vec<u16> = "123a".encode_utf16().collect();
+            assert_eq!(None, dfa.find_utf16(&units));
+            assert_eq!(Some(3), dfa.find_utf16(&units[..3]));
+            assert!(!dfa.is_match_chunks(&[b"12", b"3a"]));
+            assert!(dfa.is_match_chunks(&[b"12", b"", b"3"]));
+            assert_eq!(None, dfa.find_chunks(&[b"12", b"3a"]));
+            assert_eq!(Some(3), dfa.find_chunks(&[b"12", b"3"]));
+            assert_eq!(
+                SearchResult::NoMatchDead(4),
+                dfa.find_or_dead(b"123a", 0)
+            );
+            assert_eq!(SearchResult::Match(3), dfa.find_or_dead(b"123", 0));
+            assert_eq!(
+                SearchResult::NoMatchExhausted,
+                dfa.find_or_dead(b"", 0)
+            );
+            assert_eq!(SearchOutcome::NoMatch, dfa.find_bounded(b"123a", 10));
+            assert_eq!(
+                SearchOutcome::Incomplete,
+                dfa.find_bounded(b"123a", 2)
+            );
+            assert_eq!(SearchOutcome::Match(3), dfa.find_bounded(b"123", 3));
+            assert_eq!(None, dfa.find_with_wildcard(b"12?a", b'?'));
+            assert_eq!(Some(3), dfa.find_with_wildcard(b"12?", b'?'));
+        }
+
+        let dfa = Builder::new().full_match(true).build(r"\d+").unwrap();
+        check(&dfa);
+        check(&&dfa);
+        check(dfa.to_sparse().unwrap());
+        match dfa {
+            DenseDFA::PremultipliedByteClass(ref r) => check(r),
+            _ => unreachable!(),
+        }
+        assert_eq!(Some(3), DFA::find(&&dfa, b"123"));
+        assert_eq!(None, DFA::find(&&dfa, b"123a"));
+        check(dfa);
     }
-}
 
-/// A mutable iterator over all transitions in a DFA state.
-///
-/// Each transition is represented by a tuple. The first element is the
-/// input byte for that transition and the second element is a mutable
-/// reference to the transition itself.
-#[cfg(feature = "std")]
-#[derive(Debug)]
-pub(crate) struct StateTransitionIterMut<'a, S: 'a> {
-    it: iter::Enumerate<slice::IterMut<'a, S>>,
-}
+    #[test]
+    fn full_match_requires_the_entire_input() {
+        let dfa = Builder::new().full_match(true).build(r"\d+").unwrap();
+        assert!(dfa.requires_full_match());
+        assert!(dfa.is_full_match(b"123"));
+        assert!(!dfa.is_full_match(b"123a"));
+        assert!(!dfa.is_full_match(b"a123"));
+        assert!(!dfa.is_full_match(b""));
+        let sparse = dfa.to_sparse().unwrap();
+        let bytes = dfa.to_bytes_native_endian().unwrap();
+        let loaded: DenseDFA<&[usize], usize> =
+            unsafe { DenseDFA::from_bytes(&bytes) };
+        for &(input, expected) in
+            &[(&b"123"[..], Some(3)), (b"123a", None), (b"a123", None)]
+        {
+            assert_eq!(expected.is_some(), dfa.is_match(input));
+            assert_eq!(expected, dfa.find(input));
+            assert_eq!(expected, dfa.shortest_match(input));
+            assert_eq!(expected, sparse.find(input));
+            assert_eq!(expected, loaded.find(input));
+        }
+        assert!(sparse.requires_full_match());
+        assert!(loaded.requires_full_match());
+
+        // The end anchor applies to reverse searches too.
+        let rev = Builder::new()
+            .full_match(true)
+            .reverse(true)
+            .build(r"\d+")
+            .unwrap();
+        assert_eq!(Some(0), rev.rfind(b"123"));
+        assert_eq!(None, rev.rfind(b"a123"));
+        assert_eq!(None, rev.rfind(b"123a"));
 
-#[cfg(feature = "std")]
-impl<'a, S: StateID> Iterator for StateTransitionIterMut<'a, S> {
-    type Item = (u8, &'a mut S);
+        // Disabling full matches restores the other settings.
+        let mut builder = Builder::new();
+        builder.anchored(true).longest_match(true);
+        builder.full_match(true).full_match(false);
+        let dfa = builder.build("a|ab").unwrap();
+        assert!(!dfa.requires_full_match());
+        assert!(dfa.is_anchored());
+        assert_eq!(Some(2), dfa.find(b"abc"));
+        let dfa = Builder::new()
+            .full_match(true)
+            .full_match(false)
+            .build("a")
+            .unwrap();
+        assert!(!dfa.is_anchored());
+        assert_eq!(Some(2), dfa.find(b"ba"));
 
-    fn next(&mut self) -> Option<(u8, &'a mut S)> {
-        self.it.next().map(|(i, id)| (i as u8, id))
+        // Leftmost-first semantics would drop `ab` once `a` has matched.
+        let dfa = Builder::new().full_match(true).build("a|ab|abc").unwrap();
+        for &input in &[&b"a"[..], b"ab", b"abc"] {
+            assert!(dfa.is_full_match(input), "{:?}", input);
+        }
+        assert!(!dfa.is_full_match(b"abcd"));
+        let first = Builder::new().anchored(true).build("a|ab").unwrap();
+        assert!(!first.is_full_match(b"ab"));
     }
-}
 
-/// A builder for constructing a deterministic finite automaton from regular
-/// expressions.
-///
-/// This builder permits configuring several aspects of the construction
-/// process such as case insensitivity, Unicode support and various options
-/// that impact the size of the generated DFA. In some cases, options (like
-/// performing DFA minimization) can come with a substantial additional cost.
-///
-/// This builder always constructs a *single* DFA. As such, this builder can
-/// only be used to construct regexes that either detect the presence of a
-/// match or find the end location of a match. A single DFA cannot produce both
-/// the start and end of a match. For that information, use a
-/// [`Regex`](struct.Regex.html), which can be similarly configured using
-/// [`RegexBuilder`](struct.RegexBuilder.html).
-#[cfg(feature = "std")]
-#[derive(Clone, Debug)]
-pub struct Builder {
-    parser: ParserBuilder,
-    nfa: nfa::Builder,
-    anchored: bool,
-    minimize: bool,
-    premultiply: bool,
-    byte_classes: bool,
-    reverse: bool,
-    longest_match: bool,
-}
+    #[test]
+    fn find_with_wildcard_agrees_with_substitution() {
+        let dfa = DenseDFA::new("ab[0-9]c|b{3}").unwrap();
+        let haystacks: &[&[u8]] =
+            &[b"?", b"a??c", b"xa?1?", b"??b", b"ab?", b"ab?c?b"];
+        for &haystack in haystacks {
+            let wildcards: Vec<usize> = haystack
+                .iter()
+                .enumerate()
+                .filter(|&(_, &b)| b == b'?')
+                .map(|(i, _)| i)
+                .collect();
+            // Try every replacement of the wildcards by brute force.
+            let mut expected = None;
+            let mut input = haystack.to_vec();
+            for n in 0..256usize.pow(wildcards.len() as u32) {
+                for (k, &i) in wildcards.iter().enumerate() {
+                    input[i] = (n >> (8 * k)) as u8;
+                }
+                expected = cmp::max(expected, dfa.find(&input));
+            }
+            assert_eq!(
+                expected,
+                dfa.find_with_wildcard(haystack, b'?'),
+                "{:?}",
+                haystack
+            );
+        }
+    }
 
-#[cfg(feature = "std")]
-impl Builder {
-    /// Create a new DenseDFA builder with the default configuration.
-    pub fn new() -> Builder {
-        let mut nfa = nfa::Builder::new();
-        // This is enabled by default, but we set it here anyway. Since we're
-        // building a DFA, shrinking the NFA is always a good idea.
-        nfa.shrink(true);
-        Builder {
-            parser: ParserBuilder::new(),
-            nfa,
-            anchored: false,
-            minimize: false,
-            premultiply: true,
-            byte_classes: true,
-            reverse: false,
-            longest_match: false,
+    #[test]
+    fn compact_alphabet_merges_identical_columns() {
+        use classes::ByteClassSet;
+
+        // Build a DFA by hand where `a` and `b` are in separate classes but
+        // always lead to the same place, while `c` does not.
+        let mut classes = ByteClassSet::new();
+        classes.set_range(b'a', b'a');
+        classes.set_range(b'b', b'b');
+        classes.set_range(b'c', b'c');
+        let mut repr: Repr<Vec<usize>, usize> =
+            Repr::empty_with_byte_classes(classes.byte_classes());
+        let start = repr.add_empty_state().unwrap();
+        let matched = repr.add_empty_state().unwrap();
+        for &b in b"ab" {
+            repr.add_transition(start, b, matched);
+            repr.add_transition(matched, b, matched);
+        }
+        repr.add_transition(start, b'c', start);
+        repr.set_start_state(start);
+        repr.shuffle_match_states(&[false, false, true]);
+        assert_eq!(5, repr.alphabet_len());
+
+        for &premultiply in &[false, true] {
+            let mut compacted = repr.clone();
+            if premultiply {
+                compacted.premultiply().unwrap();
+            }
+            let before = compacted.clone().into_dense_dfa();
+            let mut after = compacted.into_dense_dfa();
+            after.compact_alphabet();
+            assert_eq!(4, after.byte_classes().alphabet_len());
+            let classes = after.byte_classes();
+            assert_eq!(classes.get(b'a'), classes.get(b'b'));
+            assert_ne!(classes.get(b'b'), classes.get(b'c'));
+            for haystack in &["a", "cb", "ccab", "cc", "d", "ac", ""] {
+                let haystack = haystack.as_bytes();
+                assert_eq!(before.find(haystack), after.find(haystack));
+            }
+
+            let rle = after.to_bytes_rle_native_endian().unwrap();
+            assert_eq!(after, DenseDFA::from_bytes_rle(&rle).unwrap());
         }
     }
 
-    /// Build a DFA from the given pattern.
-    ///
-    /// If there was a problem parsing or compiling the pattern, then an error
-    /// is returned.
-    pub fn build(&self, pattern: &str) -> Result<DenseDFA<Vec<usize>, usize>> {
-        self.build_with_size::<usize>(pattern)
+    #[test]
+    fn trap_states_finds_unreachable_matches() {
+        // Build a DFA by hand with a non-dead state that loops forever.
+        let mut repr: Repr<Vec<usize>, usize> = Repr::empty();
+        let start = repr.add_empty_state().unwrap();
+        let matched = repr.add_empty_state().unwrap();
+        let trap = repr.add_empty_state().unwrap();
+        repr.add_transition(start, b'a', matched);
+        repr.add_transition(start, b'b', trap);
+        repr.add_transition(trap, b'b', trap);
+        repr.set_start_state(start);
+        repr.shuffle_match_states(&[false, false, true, false]);
+        let dfa = repr.into_dense_dfa();
+
+        let traps: Vec<usize> = dfa.trap_states();
+        assert_eq!(2, traps.len());
+        assert!(traps.contains(&0));
+        assert!(!dfa.never_matches());
+        assert!(traps.contains(&dfa.next_state(dfa.start_state(), b'b')));
+        assert!(!traps.contains(&dfa.start_state()));
     }
 
-    /// Build a DFA from the given pattern using a specific representation for
-    /// the DFA's state IDs.
-    ///
-    /// If there was a problem parsing or compiling the pattern, then an error
-    /// is returned.
-    ///
-    /// The representation of state IDs is determined by the `S` type
-    /// parameter. In general, `S` is usually one of `u8`, `u16`, `u32`, `u64`
-    /// or `usize`, where `usize` is the default used for `build`. The purpose
-    /// of specifying a representation for state IDs is to reduce the memory
-    /// footprint of a DFA.
-    ///
-    /// When using this routine, the chosen state ID representation will be
-    /// used throughout determinization and minimization, if minimization
-    /// was requested. Even if the minimized DFA can fit into the chosen
-    /// state ID representation but the initial determinized DFA cannot,
-    /// then this will still return an error. To get a minimized DFA with a
-    /// smaller state ID representation, first build it with a bigger state ID
-    /// representation, and then shrink the size of the DFA using one of its
-    /// conversion routines, such as
-    /// [`DenseDFA::to_u16`](enum.DenseDFA.html#method.to_u16).
-    pub fn build_with_size<S: StateID>(
-        &self,
-        pattern: &str,
-    ) -> Result<DenseDFA<Vec<S>, S>> {
-        self.build_from_nfa(&self.build_nfa(pattern)?)
+    #[test]
+    fn unshuffled_match_states_agree_with_shuffled() {
+        let patterns = &["a|b+c", "[a-z]+[0-9]", "foo|foobar|bar", ""];
+        let haystacks: &[&[u8]] =
+            &[b"", b"a", b"bbbc", b"xyz9", b"foobar", b"zzfoo", b"bc"];
+        for &pattern in patterns {
+            for &anchored in &[false, true] {
+                for &(minimize, premultiply) in &[
+                    (false, false),
+                    (false, true),
+                    (true, false),
+                    (true, true),
+                ] {
+                    let mut builder = Builder::new();
+                    builder
+                        .anchored(anchored)
+                        .minimize(minimize)
+                        .premultiply(premultiply)
+                        .accelerate(true);
+                    let shuffled = builder.build(pattern).unwrap();
+                    let unshuffled = builder
+                        .shuffle_match_states(false)
+                        .build(pattern)
+                        .unwrap();
+                    assert_eq!(
+                        shuffled.repr().state_count(),
+                        unshuffled.repr().state_count()
+                    );
+                    let sparse = unshuffled.to_sparse().unwrap();
+                    let mut remapped = unshuffled.clone();
+                    remapped.remap_for_locality();
+                    let mut compacted = unshuffled.clone();
+                    compacted.compact_alphabet();
+
+                    let bytes = remapped.to_bytes_native_endian().unwrap();
+                    let header = DFAHeader::from_bytes(&bytes).unwrap();
+                    assert!(header.has_match_set());
+                    let loaded: DenseDFA<&[usize], usize> =
+                        unsafe { DenseDFA::from_bytes(&bytes) };
+                    assert_eq!(remapped.as_ref(), loaded);
+                    let rle = compacted.to_bytes_rle_native_endian().unwrap();
+                    assert_eq!(
+                        compacted,
+                        DenseDFA::from_bytes_rle(&rle).unwrap()
+                    );
+                    let varint =
+                        unshuffled.to_bytes_varint_native_endian().unwrap();
+                    assert_eq!(
+                        unshuffled,
+                        DenseDFA::from_bytes_varint(&varint).unwrap()
+                    );
+                    let rev = unshuffled.reverse_language().unwrap();
+                    let expected_rev = shuffled.reverse_language().unwrap();
+                    for &h in haystacks {
+                        let expected = shuffled.find(h);
+                        assert_eq!(expected, unshuffled.find(h));
+                        assert_eq!(
+                            shuffled.shortest_match(h),
+                            unshuffled.shortest_match(h)
+                        );
+                        assert_eq!(expected, sparse.find(h));
+                        assert_eq!(expected, remapped.find(h));
+                        assert_eq!(expected, loaded.find(h));
+                        assert_eq!(expected, compacted.find(h));
+                        assert_eq!(expected_rev.rfind(h), rev.rfind(h));
+                    }
+                    if anchored {
+                        assert_eq!(
+                            shuffled.finite_language(100),
+                            unshuffled.finite_language(100)
+                        );
+                        let expected = shuffled.to_unanchored().unwrap();
+                        let got = unshuffled.to_unanchored().unwrap();
+                        for &h in haystacks {
+                            assert_eq!(expected.find(h), got.find(h));
+                        }
+                    }
+                }
+            }
+        }
+
+        // States keep the order in which they were built, so the state
+        // reached on `a` precedes the match state reached on `b`.
+        let dfa = Builder::new()
+            .anchored(true)
+            .premultiply(false)
+            .shuffle_match_states(false)
+            .build("ab|b")
+            .unwrap();
+        assert_eq!(1, dfa.start_state());
+        let (after_a, after_b) =
+            (dfa.next_state(1, b'a'), dfa.next_state(1, b'b'));
+        assert!(after_a < after_b);
+        assert!(!dfa.is_match_state(after_a));
+        assert!(dfa.is_match_state(after_b));
+        assert!(dfa.state(after_b).is_match());
+        assert!(dfa.is_match_state(dfa.next_state(after_a, b'b')));
+
+        // The match set sits between the byte classes and the transitions,
+        // so labels must be found after it.
+        let mut labels = StateLabels::new();
+        labels.set(after_b, "matched b");
+        let bytes = dfa.to_bytes_with_labels_native_endian(&labels).unwrap();
+        assert_eq!(labels, StateLabels::from_dfa_bytes(&bytes).unwrap());
     }
 
-    /// An internal only (for now) API for building a dense DFA directly from
-    /// an NFA.
-    pub(crate) fn build_from_nfa<S: StateID>(
-        &self,
-        nfa: &NFA,
-    ) -> Result<DenseDFA<Vec<S>, S>> {
-        if self.longest_match && !self.anchored {
-            return Err(Error::unsupported_longest_match());
+    #[test]
+    fn states_capacity_hint_does_not_change_dfa() {
+        let pattern = "foo[0-9]+|bar|baz";
+        let plain = Builder::new().build(pattern).unwrap();
+        for &hint in &[0, 1, 1000] {
+            let hinted =
+                Builder::new().states_capacity_hint(hint).build(pattern);
+            assert_eq!(plain, hinted.unwrap());
         }
+    }
 
-        let mut dfa = if self.byte_classes {
-            Determinizer::new(nfa)
-                .with_byte_classes()
-                .longest_match(self.longest_match)
-                .build()
-        } else {
-            Determinizer::new(nfa).longest_match(self.longest_match).build()
-        }?;
-        if self.minimize {
-            dfa.minimize();
+    #[test]
+    fn remap_for_locality_preserves_matches() {
+        let haystacks: &[&[u8]] =
+            &[b"", b"foo1", b"xbarfoo99", b"ba", b"zzzbar", b"\xCE\xB2b"];
+        for &premultiply in &[false, true] {
+            for &anchored in &[false, true] {
+                let dfa = Builder::new()
+                    .premultiply(premultiply)
+                    .anchored(anchored)
+                    .build(r"foo[0-9]+|bar|\w")
+                    .unwrap();
+                let mut remapped = dfa.clone();
+                remapped.remap_for_locality();
+                assert!(remapped.validate().is_ok());
+                assert!(remapped.is_match_state(
+                    remapped.next_state(remapped.start_state(), b'b',)
+                ));
+                for &h in haystacks {
+                    assert_eq!(dfa.find(h), remapped.find(h));
+                    assert_eq!(
+                        dfa.shortest_match(h),
+                        remapped.shortest_match(h)
+                    );
+                }
+            }
         }
-        if self.premultiply {
-            dfa.premultiply()?;
+    }
+
+    #[test]
+    fn chunked_search_agrees_with_contiguous_search() {
+        let dfa = DenseDFA::new(r"foo[0-9]+|\w+z").unwrap();
+        let haystack = b"ab foo123 wz";
+        for i in 0..haystack.len() + 1 {
+            for j in i..haystack.len() + 1 {
+                let chunks: &[&[u8]] =
+                    &[&haystack[..i], &haystack[i..j], &haystack[j..]];
+                assert_eq!(dfa.find(haystack), dfa.find_chunks(chunks));
+                assert_eq!(
+                    dfa.is_match(haystack),
+                    dfa.is_match_chunks(chunks)
+                );
+            }
         }
-        Ok(dfa.into_dense_dfa())
+        assert_eq!(None, dfa.find_chunks(&[]));
     }
 
-    /// Builds an NFA from the given pattern.
-    pub(crate) fn build_nfa(&self, pattern: &str) -> Result<NFA> {
-        let hir = self.parser.build().parse(pattern).map_err(Error::syntax)?;
-        Ok(self.nfa.build(&hir)?)
+    #[test]
+    fn from_conversions_round_trip() {
+        fn search<'a, D: Into<DenseDFA<&'a [usize], usize>>>(
+            dfa: D,
+        ) -> Option<usize> {
+            dfa.into().find(b"foo123")
+        }
+
+        let owned = DenseDFA::new("foo[0-9]+").unwrap();
+        assert_eq!(Some(6), search(&owned));
+
+        let borrowed: DenseDFA<&[usize], usize> = (&owned).into();
+        let copied: DenseDFA<Vec<usize>, usize> = borrowed.into();
+        assert_eq!(owned, copied);
     }
 
-    /// Set whether matching must be anchored at the beginning of the input.
-    ///
-    /// When enabled, a match must begin at the start of the input. When
-    /// disabled, the DFA will act as if the pattern started with a `.*?`,
-    /// which enables a match to appear anywhere.
-    ///
-    /// By default this is disabled.
-    pub fn anchored(&mut self, yes: bool) -> &mut Builder {
-        self.anchored = yes;
-        self.nfa.anchored(yes);
-        self
+    #[test]
+    fn anchored_matches_agrees_with_is_match() {
+        let dfa = Builder::new().anchored(true).build(r"[0-9]+|x").unwrap();
+        let haystack = b"a1x 23";
+        let got = dfa.anchored_matches(haystack);
+        assert_eq!(haystack.len() + 1, got.len());
+        for (i, &matched) in got.iter().enumerate() {
+            assert_eq!(dfa.is_match(&haystack[i..]), matched, "offset {}", i);
+        }
+        assert_eq!(vec![false, true, true, false, true, true, false], got,);
+
+        let empty = Builder::new().anchored(true).build(r"a*").unwrap();
+        assert_eq!(vec![true, true], empty.anchored_matches(b"b"));
+        assert_eq!(2, empty.anchored_matches_iter(b"b").size_hint().0);
     }
 
-    /// Enable or disable the case insensitive flag by default.
-    ///
-    /// By default this is disabled. It may alternatively be selectively
-    /// enabled in the regular expression itself via the `i` flag.
-    pub fn case_insensitive(&mut self, yes: bool) -> &mut Builder {
-        self.parser.case_insensitive(yes);
-        self
+    #[test]
+    fn prefix_search_agrees_with_naive_search() {
+        fn naive_find(lit: &[u8], h: &[u8], start: usize) -> Option<usize> {
+            (start..h.len() + 1).find(|&i| h[i..].starts_with(lit))
+        }
+        fn naive_rfind(lit: &[u8], h: &[u8], end: usize) -> Option<usize> {
+            (0..end + 1).rev().find(|&i| h[..i].ends_with(lit))
+        }
+
+        let literals: &[&[u8]] =
+            &[b"a", b"aab", b"abab", b"abaabab", b"aaaaaaaaaaaaaaab"];
+        let haystacks: &[&[u8]] = &[
+            b"",
+            b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaab",
+            b"baaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+            b"abaababaabababababaab",
+            b"xxabababxx",
+        ];
+        for &lit in literals {
+            let (prefix, suffix) =
+                (Prefix::new(lit, false), Prefix::new_reverse(lit, false));
+            for &h in haystacks {
+                for i in 0..h.len() + 1 {
+                    assert_eq!(naive_find(lit, h, i), prefix.find(h, i));
+                    assert_eq!(naive_rfind(lit, h, i), suffix.rfind(h, i));
+                }
+            }
+        }
     }
 
-    /// Enable verbose mode in the regular expression.
-    ///
-    /// When enabled, verbose mode permits insigificant whitespace in many
-    /// places in the regular expression, as well as comments. Comments are
-    /// started using `#` and continue until the end of the line.
-    ///
-    /// By default, this is disabled. It may be selectively enabled in the
-    /// regular expression by using the `x` flag regardless of this setting.
-    pub fn ignore_whitespace(&mut self, yes: bool) -> &mut Builder {
-        self.parser.ignore_whitespace(yes);
-        self
+    #[test]
+    fn serialization_drops_prefilter() {
+        let dfa = Builder::new().prefilter(true).build("foo[a-z]").unwrap();
+        assert_eq!(b"foo", dfa.literal_prefix());
+        let bytes = dfa.to_bytes_native_endian().unwrap();
+        let loaded: DenseDFA<&[usize], usize> =
+            unsafe { DenseDFA::from_bytes(&bytes) };
+        assert!(loaded.literal_prefix().is_empty());
+        assert_eq!(None, loaded.find(b"9 foo"));
+        assert_eq!(Some(6), loaded.find(b"9 foox"));
     }
 
-    /// Enable or disable the "dot matches any character" flag by default.
-    ///
-    /// By default this is disabled. It may alternatively be selectively
-    /// enabled in the regular expression itself via the `s` flag.
-    pub fn dot_matches_new_line(&mut self, yes: bool) -> &mut Builder {
-        self.parser.dot_matches_new_line(yes);
-        self
+    #[test]
+    fn project_to_bytes_drops_prefilter() {
+        let allowed: Vec<u8> = (b'a'..=b'z').collect();
+        let dfa = Builder::new().prefilter(true).build("foo[a-z]").unwrap();
+        assert_eq!(b"foo", dfa.literal_prefix());
+        let projected = dfa.project_to_bytes(&allowed).unwrap();
+        assert!(projected.literal_prefix().is_empty());
+        assert_eq!(None, projected.find(b"9 foox"));
+        assert_eq!(Some(4), projected.find(b"foox"));
     }
 
-    /// Enable or disable the "swap greed" flag by default.
-    ///
-    /// By default this is disabled. It may alternatively be selectively
-    /// enabled in the regular expression itself via the `U` flag.
-    pub fn swap_greed(&mut self, yes: bool) -> &mut Builder {
-        self.parser.swap_greed(yes);
-        self
+    #[test]
+    fn project_to_bytes_truncates_search_at_disallowed_byte() {
+        let allowed: Vec<u8> = (b'a'..=b'z').chain(Some(b' ')).collect();
+        let haystacks: &[&[u8]] =
+            &[b"foo bar", b"foo9bar", b"quux", b"9foo", b"", b"ba\xFFr"];
+        for &(premultiply, byte_classes) in
+            &[(false, false), (false, true), (true, false), (true, true)]
+        {
+            let dfa = Builder::new()
+                .premultiply(premultiply)
+                .byte_classes(byte_classes)
+                .build(r"[a-z0-9]+r|9")
+                .unwrap();
+            let projected = dfa.project_to_bytes(&allowed).unwrap();
+            projected.validate().unwrap();
+            assert_eq!(
+                projected.repr().state_count(),
+                dfa.repr().state_count()
+            );
+            for &h in haystacks {
+                let end = h
+                    .iter()
+                    .position(|b| !allowed.contains(b))
+                    .unwrap_or(h.len());
+                assert_eq!(dfa.find(&h[..end]), projected.find(h), "{:?}", h);
+            }
+        }
     }
 
-    /// Enable or disable the Unicode flag (`u`) by default.
-    ///
-    /// By default this is **enabled**. It may alternatively be selectively
-    /// disabled in the regular expression itself via the `u` flag.
-    ///
-    /// Note that unless `allow_invalid_utf8` is enabled (it's disabled by
-    /// default), a regular expression will fail to parse if Unicode mode is
-    /// disabled and a sub-expression could possibly match invalid UTF-8.
-    pub fn unicode(&mut self, yes: bool) -> &mut Builder {
-        self.parser.unicode(yes);
-        self
+    #[test]
+    fn accelerate_agrees_with_plain_search() {
+        let haystacks: &[&[u8]] =
+            &[b"", b"ab", b"xx9 y42z", b"qqqqqqy", b"\xFFx1", b"z0z"];
+        for &(premultiply, byte_classes) in
+            &[(false, false), (false, true), (true, false), (true, true)]
+        {
+            let mut builder = Builder::new();
+            builder.premultiply(premultiply).byte_classes(byte_classes);
+            let plain = builder.build(r"[xyz][0-9]+").unwrap();
+            let dfa = builder.accelerate(true).build(r"[xyz][0-9]+").unwrap();
+            assert!(dfa.is_accelerated());
+
+            let bytes = dfa.to_bytes_native_endian().unwrap();
+            let loaded: DenseDFA<&[usize], usize> =
+                unsafe { DenseDFA::from_bytes(&bytes) };
+            assert!(loaded.is_accelerated());
+            let rle = dfa.to_bytes_rle_native_endian().unwrap();
+            let rle: DenseDFA<Vec<usize>, usize> =
+                DenseDFA::from_bytes_rle(&rle).unwrap();
+            assert!(rle.is_accelerated());
+            let projected = dfa.project_to_bytes(b"xz0123").unwrap();
+            let plain_projected = plain.project_to_bytes(b"xz0123").unwrap();
+
+            for &h in haystacks {
+                assert_eq!(plain.find(h), dfa.find(h), "{:?}", h);
+                assert_eq!(plain.find(h), loaded.find(h), "{:?}", h);
+                assert_eq!(plain.find(h), rle.find(h), "{:?}", h);
+                assert_eq!(plain.is_match(h), dfa.is_match(h), "{:?}", h);
+                assert_eq!(
+                    plain.shortest_match(h),
+                    dfa.shortest_match(h),
+                    "{:?}",
+                    h
+                );
+                assert_eq!(
+                    plain_projected.find(h),
+                    projected.find(h),
+                    "{:?}",
+                    h
+                );
+            }
+        }
+
+        let anchored =
+            Builder::new().anchored(true).accelerate(true).build("x").unwrap();
+        assert!(!anchored.is_accelerated());
     }
 
-    /// When enabled, the builder will permit the construction of a regular
-    /// expression that may match invalid UTF-8.
-    ///
-    /// When disabled (the default), the builder is guaranteed to produce a
-    /// regex that will only ever match valid UTF-8 (otherwise, the builder
-    /// will return an error).
-    pub fn allow_invalid_utf8(&mut self, yes: bool) -> &mut Builder {
-        self.parser.allow_invalid_utf8(yes);
-        self.nfa.allow_invalid_utf8(yes);
-        self
+    #[test]
+    fn relabel_byte_classes_preserves_matches() {
+        let haystacks: &[&[u8]] = &[b"fa9bar", b"xyz", b"", b"a0z9", b"zafe1"];
+        for &premultiply in &[false, true] {
+            let dfa = Builder::new()
+                .premultiply(premultiply)
+                .prefilter(true)
+                .build(r"a[a-f]*[0-9]")
+                .unwrap();
+            assert_eq!(b"a", dfa.literal_prefix());
+            let other = Builder::new().build(r"[c-x]").unwrap();
+            let classes = dfa.byte_classes().refine(other.byte_classes());
+            for classes in &[classes, ByteClasses::singletons()] {
+                let relabeled = dfa.relabel_byte_classes(classes).unwrap();
+                relabeled.validate().unwrap();
+                assert!(relabeled.literal_prefix().is_empty());
+                assert_eq!(
+                    classes.alphabet_len(),
+                    relabeled.byte_classes().alphabet_len()
+                );
+                for &h in haystacks {
+                    assert_eq!(dfa.find(h), relabeled.find(h), "{:?}", h);
+                }
+            }
+
+            let coarse = Builder::new().build(r"[a-z]").unwrap();
+            match *dfa
+                .relabel_byte_classes(coarse.byte_classes())
+                .unwrap_err()
+                .kind()
+            {
+                ErrorKind::IncompatibleByteClasses => {}
+                ref kind => panic!("unexpected error: {:?}", kind),
+            }
+        }
     }
 
-    /// Set the nesting limit used for the regular expression parser.
-    ///
-    /// The nesting limit controls how deep the abstract syntax tree is allowed
-    /// to be. If the AST exceeds the given limit (e.g., with too many nested
-    /// groups), then an error is returned by the parser.
-    ///
-    /// The purpose of this limit is to act as a heuristic to prevent stack
-    /// overflow when building a finite automaton from a regular expression's
-    /// abstract syntax tree. In particular, construction currently uses
-    /// recursion. In the future, the implementation may stop using recursion
-    /// and this option will no longer be necessary.
-    ///
-    /// This limit is not checked until the entire AST is parsed. Therefore,
-    /// if callers want to put a limit on the amount of heap space used, then
-    /// they should impose a limit on the length, in bytes, of the concrete
-    /// pattern string. In particular, this is viable since the parser will
-    /// limit itself to heap space proportional to the lenth of the pattern
-    /// string.
-    ///
-    /// Note that a nest limit of `0` will return a nest limit error for most
-    /// patterns but not all. For example, a nest limit of `0` permits `a` but
-    /// not `ab`, since `ab` requires a concatenation AST item, which results
-    /// in a nest depth of `1`. In general, a nest limit is not something that
-    /// manifests in an obvious way in the concrete syntax, therefore, it
-    /// should not be used in a granular way.
-    pub fn nest_limit(&mut self, limit: u32) -> &mut Builder {
-        self.parser.nest_limit(limit);
-        self
+    #[test]
+    fn match_priority_on_alternation() {
+        let find = |priority, anchored, haystack: &[u8]| {
+            Builder::new()
+                .match_priority(priority)
+                .anchored(anchored)
+                .build("a|ab|abc")
+                .unwrap()
+                .find(haystack)
+        };
+        assert_eq!(Some(1), find(MatchPriority::LeftmostFirst, true, b"abc"));
+        assert_eq!(
+            Some(3),
+            find(MatchPriority::LeftmostLongest, true, b"abc")
+        );
+        assert_eq!(Some(1), find(MatchPriority::ShortestFirst, true, b"abc"));
+        assert_eq!(
+            Some(3),
+            find(MatchPriority::LeftmostFirst, false, b"xxabc")
+        );
+        assert_eq!(
+            Some(3),
+            find(MatchPriority::ShortestFirst, false, b"xxabc")
+        );
+        assert_eq!(None, find(MatchPriority::ShortestFirst, true, b"xab"));
+
+        let err = Builder::new()
+            .match_priority(MatchPriority::LeftmostLongest)
+            .build("a|ab|abc")
+            .unwrap_err();
+        match *err.kind() {
+            ErrorKind::Unsupported(_) => {}
+            ref kind => panic!("unexpected error: {:?}", kind),
+        }
     }
 
-    /// Minimize the DFA.
-    ///
-    /// When enabled, the DFA built will be minimized such that it is as small
-    /// as possible.
-    ///
-    /// Whether one enables minimization or not depends on the types of costs
-    /// you're willing to pay and how much you care about its benefits. In
-    /// particular, minimization has worst case `O(n*k*logn)` time and `O(k*n)`
-    /// space, where `n` is the number of DFA states and `k` is the alphabet
-    /// size. In practice, minimization can be quite costly in terms of both
-    /// space and time, so it should only be done if you're willing to wait
-    /// longer to produce a DFA. In general, you might want a minimal DFA in
-    /// the following circumstances:
-    ///
-    /// 1. You would like to optimize for the size of the automaton. This can
-    ///    manifest in one of two ways. Firstly, if you're converting the
-    ///    DFA into Rust code (or a table embedded in the code), then a minimal
-    ///    DFA will translate into a corresponding reduction in code  size, and
-    ///    thus, also the final compiled binary size. Secondly, if you are
-    ///    building many DFAs and putting them on the heap, you'll be able to
-    ///    fit more if they are smaller. Note though that building a minimal
-    ///    DFA itself requires additional space; you only realize the space
-    ///    savings once the minimal DFA is constructed (at which point, the
-    ///    space used for minimization is freed).
-    /// 2. You've observed that a smaller DFA results in faster match
-    ///    performance. Naively, this isn't guaranteed since there is no
-    ///    inherent difference between matching with a bigger-than-minimal
-    ///    DFA and a minimal DFA. However, a smaller DFA may make use of your
-    ///    CPU's cache more efficiently.
-    /// 3. You are trying to establish an equivalence between regular
-    ///    languages. The standard method for this is to build a minimal DFA
-    ///    for each language and then compare them. If the DFAs are equivalent
-    ///    (up to state renaming), then the languages are equivalent.
-    ///
-    /// This option is disabled by default.
-    pub fn minimize(&mut self, yes: bool) -> &mut Builder {
-        self.minimize = yes;
-        self
+    #[test]
+    fn nfa_size_limit() {
+        let err = Builder::new().nfa_size_limit(500).build(r"(?:ab){1000}");
+        match *err.unwrap_err().kind() {
+            ErrorKind::NFATooBig { limit } => assert_eq!(500, limit),
+            ref kind => panic!("unexpected error: {:?}", kind),
+        }
+
+        let dfa = Builder::new().nfa_size_limit(500).build(r"(?:ab){10}");
+        assert_eq!(Some(20), dfa.unwrap().find(&b"ab".repeat(10)));
     }
 
-    /// Premultiply state identifiers in the DFA's transition table.
-    ///
-    /// When enabled, state identifiers are premultiplied to point to their
-    /// corresponding row in the DFA's transition table. That is, given the
-    /// `i`th state, its corresponding premultiplied identifier is `i * k`
-    /// where `k` is the alphabet size of the DFA. (The alphabet size is at
-    /// most 256, but is in practice smaller if byte classes is enabled.)
-    ///
-    /// When state identifiers are not premultiplied, then the identifier of
-    /// the `i`th state is `i`.
-    ///
-    /// The advantage of premultiplying state identifiers is that is saves
-    /// a multiplication instruction per byte when searching with the DFA.
-    /// This has been observed to lead to a 20% performance benefit in
-    /// micro-benchmarks.
-    ///
-    /// The primary disadvantage of premultiplying state identifiers is
-    /// that they require a larger integer size to represent. For example,
-    /// if your DFA has 200 states, then its premultiplied form requires
-    /// 16 bits to represent every possible state identifier, where as its
-    /// non-premultiplied form only requires 8 bits.
-    ///
-    /// This option is enabled by default.
-    pub fn premultiply(&mut self, yes: bool) -> &mut Builder {
-        self.premultiply = yes;
-        self
+    #[test]
+    fn dfa_size_limit() {
+        let dfa = Builder::new().build(r"[01]*1[01]{5}").unwrap();
+        let states = dfa.repr().state_count();
+
+        let err =
+            Builder::new().dfa_size_limit(states - 1).build(r"[01]*1[01]{5}");
+        match *err.unwrap_err().kind() {
+            ErrorKind::DeterminizeTooBig { limit } => {
+                assert_eq!(states - 1, limit)
+            }
+            ref kind => panic!("unexpected error: {:?}", kind),
+        }
+        let limited =
+            Builder::new().dfa_size_limit(states).build(r"[01]*1[01]{5}");
+        assert_eq!(dfa, limited.unwrap());
     }
 
-    /// Shrink the size of the DFA's alphabet by mapping bytes to their
-    /// equivalence classes.
-    ///
-    /// When enabled, each DFA will use a map from all possible bytes to their
-    /// corresponding equivalence class. Each equivalence class represents a
-    /// set of bytes that does not discriminate between a match and a non-match
-    /// in the DFA. For example, the pattern `[ab]+` has at least two
-    /// equivalence classes: a set containing `a` and `b` and a set containing
-    /// every byte except for `a` and `b`. `a` and `b` are in the same
-    /// equivalence classes because they never discriminate between a match
-    /// and a non-match.
-    ///
-    /// The advantage of this map is that the size of the transition table can
-    /// be reduced drastically from `#states * 256 * sizeof(id)` to
-    /// `#states * k * sizeof(id)` where `k` is the number of equivalence
-    /// classes. As a result, total space usage can decrease substantially.
-    /// Moreover, since a smaller alphabet is used, compilation becomes faster
-    /// as well.
-    ///
-    /// The disadvantage of this map is that every byte searched must be
-    /// passed through this map before it can be used to determine the next
-    /// transition. This has a small match time performance cost.
-    ///
-    /// This option is enabled by default.
-    pub fn byte_classes(&mut self, yes: bool) -> &mut Builder {
-        self.byte_classes = yes;
-        self
+    #[test]
+    fn repetition_count_multiplies_nested_bounds() {
+        let cases = &[
+            ("abc", 0),
+            ("a*b+c?", 0),
+            ("a{10}b{2,5}", 15),
+            ("(a{10}){3}", 30),
+            ("(ab){3,}|c", 3),
+            ("((a{2}b{3}){4})*", 20),
+        ];
+        for &(pattern, count) in cases {
+            let hir = Builder::new().build_hir(pattern).unwrap();
+            assert_eq!(count, repetition_count(&hir), "{:?}", pattern);
+
+            let mut builder = Builder::new();
+            assert!(builder.repetition_limit(count).build(pattern).is_ok());
+            if count > 0 {
+                let err = builder.repetition_limit(count - 1).build(pattern);
+                match *err.unwrap_err().kind() {
+                    ErrorKind::RepetitionTooBig { .. } => {}
+                    ref kind => panic!("unexpected error: {:?}", kind),
+                }
+            }
+        }
     }
 
-    /// Reverse the DFA.
-    ///
-    /// A DFA reversal is performed by reversing all of the concatenated
-    /// sub-expressions in the original pattern, recursively. The resulting
-    /// DFA can be used to match the pattern starting from the end of a string
-    /// instead of the beginning of a string.
-    ///
-    /// Generally speaking, a reversed DFA is most useful for finding the start
-    /// of a match, since a single forward DFA is only capable of finding the
-    /// end of a match. This start of match handling is done for you
-    /// automatically if you build a [`Regex`](struct.Regex.html).
-    pub fn reverse(&mut self, yes: bool) -> &mut Builder {
-        self.reverse = yes;
-        self.nfa.reverse(yes);
-        self
+    #[test]
+    fn invalid_utf8_leads_to_dead_state() {
+        fn is_valid_prefix(bytes: &[u8]) -> bool {
+            match str::from_utf8(bytes) {
+                Ok(_) => true,
+                Err(err) => err.error_len().is_none(),
+            }
+        }
+
+        // None of these contain a match, so an unanchored DFA stays alive
+        // for exactly as long as the input is a prefix of valid UTF-8.
+        let haystacks: &[&[u8]] = &[
+            b"ab\xFFcd",
+            b"\xE2\x28\xA1",
+            b"x\xC0\x80",
+            b"\xED\xA0\x80",
+            b"\xF4\x90\x80\x80",
+            b"\xE2\x82\xAC\x80",
+            b"caf\xC3",
+        ];
+        for pattern in &["zzz", r"\w+q", "\u{2603}"] {
+            for &minimize in &[false, true] {
+                let dfa =
+                    Builder::new().minimize(minimize).build(pattern).unwrap();
+                for &h in haystacks {
+                    let mut state = dfa.start_state();
+                    for i in 0..h.len() {
+                        state = dfa.next_state(state, h[i]);
+                        assert_eq!(
+                            !is_valid_prefix(&h[..i + 1]),
+                            dfa.is_dead_state(state),
+                            "{:?} on {:?} at {}",
+                            pattern,
+                            h,
+                            i,
+                        );
+                    }
+                }
+            }
+        }
     }
 
-    /// Find the longest possible match.
-    ///
-    /// This is distinct from the default leftmost-first match semantics in
-    /// that it treats all NFA states as having equivalent priority. In other
-    /// words, the longest possible match is always found and it is not
-    /// possible to implement non-greedy match semantics when this is set. That
-    /// is, `a+` and `a+?` are equivalent when this is enabled.
-    ///
-    /// In particular, a practical issue with this option at the moment is that
-    /// it prevents unanchored searches from working correctly, since
-    /// unanchored searches are implemented by prepending an non-greedy `.*?`
-    /// to the beginning of the pattern. As stated above, non-greedy match
-    /// semantics aren't supported. Therefore, if this option is enabled and
-    /// an unanchored search is requested, then building a DFA will return an
-    /// error.
-    ///
-    /// This option is principally useful when building a reverse DFA for
-    /// finding the start of a match. If you are building a regex with
-    /// [`RegexBuilder`](struct.RegexBuilder.html), then this is handled for
-    /// you automatically. The reason why this is necessary for start of match
-    /// handling is because we want to find the earliest possible starting
-    /// position of a match to satisfy leftmost-first match semantics. When
-    /// matching in reverse, this means finding the longest possible match,
-    /// hence, this option.
-    ///
-    /// By default this is disabled.
-    pub fn longest_match(&mut self, yes: bool) -> &mut Builder {
-        // There is prior art in RE2 that shows how this can support unanchored
-        // searches. Instead of treating all NFA states as having equivalent
-        // priority, we instead group NFA states into sets, and treat members
-        // of each set as having equivalent priority, but having greater
-        // priority than all following members of different sets. We then
-        // essentially assign a higher priority to everything over the prefix
-        // `.*?`.
-        self.longest_match = yes;
-        self
+    #[test]
+    fn fits_in_agrees_with_to_sized() {
+        for &premultiply in &[false, true] {
+            for pattern in &["a", "[a-z]{3}", r"\w{2}", "(?i)abcdefghijklmnop"]
+            {
+                let dfa = Builder::new()
+                    .premultiply(premultiply)
+                    .build(pattern)
+                    .unwrap();
+                assert_eq!(dfa.fits_in::<u8>(), dfa.to_u8().is_ok());
+                assert_eq!(dfa.fits_in::<u16>(), dfa.to_u16().is_ok());
+                assert!(dfa.fits_in::<usize>());
+
+                let bits = dfa.state_id_bits();
+                assert_eq!(bits <= 8, dfa.fits_in::<u8>(), "{:?}", pattern);
+                assert_eq!(bits <= 16, dfa.fits_in::<u16>(), "{:?}", pattern);
+            }
+        }
     }
 
-    /// Apply best effort heuristics to shrink the NFA at the expense of more
-    /// time/memory.
-    ///
-    /// This may be exposed in the future, but for now is exported for use in
-    /// the `regex-automata-debug` tool.
-    #[doc(hidden)]
-    pub fn shrink(&mut self, yes: bool) -> &mut Builder {
-        self.nfa.shrink(yes);
-        self
+    #[test]
+    fn syntax_error_has_source() {
+        use std::error::Error;
+
+        let err = Builder::new().build("a(").unwrap_err();
+        match *err.kind() {
+            ErrorKind::Syntax(_) => {}
+            ref kind => panic!("unexpected error: {:?}", kind),
+        }
+        let source = err.source().unwrap();
+        assert!(source.downcast_ref::<::regex_syntax::Error>().is_some());
+        assert!(Builder::new()
+            .nfa_size_limit(1)
+            .build("a+")
+            .unwrap_err()
+            .source()
+            .is_none());
     }
-}
 
-#[cfg(feature = "std")]
-impl Default for Builder {
-    fn default() -> Builder {
-        Builder::new()
+    #[test]
+    fn to_unanchored_agrees_with_unanchored_build() {
+        let patterns =
+            &["a", "abc|a", "a*", "(?:ab)+c?", "[0-9]+x|[0-9]", "foo|oof", ""];
+        let haystacks: &[&[u8]] = &[
+            b"",
+            b"zzabcz",
+            b"aaa",
+            b"xababc",
+            b"12y3x",
+            b"ofoof",
+            b"b",
+            b"\xFFa",
+            b"ab\xFF\xFEabc",
+            b"1\xC02x",
+        ];
+        for &p in patterns {
+            for &premultiply in &[false, true] {
+                let expected =
+                    Builder::new().allow_invalid_utf8(true).build(p).unwrap();
+                let got = Builder::new()
+                    .anchored(true)
+                    .premultiply(premultiply)
+                    .build(p)
+                    .unwrap()
+                    .to_unanchored()
+                    .unwrap();
+                assert!(!got.is_anchored());
+                if p == "foo|oof" {
+                    assert!(got.is_accelerated());
+                }
+                got.validate().unwrap();
+                for &h in haystacks {
+                    let msg = format!("{:?} on {:?}", p, h);
+                    assert_eq!(expected.find(h), got.find(h), "{}", msg);
+                    assert_eq!(
+                        expected.shortest_match(h),
+                        got.shortest_match(h),
+                        "{}",
+                        msg
+                    );
+                }
+            }
+        }
     }
-}
 
-/// Return the given byte as its escaped string form.
-#[cfg(feature = "std")]
-fn escape(b: u8) -> String {
-    use std::ascii;
+    #[test]
+    fn prefault_keeps_dfa_usable() {
+        let dfa = Builder::new().build(r"(?-u:\w+@\w+)").unwrap();
+        let bytes = dfa.to_u16().unwrap().to_bytes_native_endian().unwrap();
+        let dfa: DenseDFA<&[u16], u16> =
+            unsafe { DenseDFA::from_bytes(&bytes) };
+        dfa.prefault();
+        assert_eq!(Some(5), dfa.find(b"a@bcd"));
+
+        let sparse = dfa.to_sparse().unwrap();
+        sparse.prefault();
+        assert_eq!(Some(5), sparse.find(b"a@bcd"));
+    }
 
-    String::from_utf8(ascii::escape_default(b).collect::<Vec<_>>()).unwrap()
-}
+    #[test]
+    fn fingerprint_ignores_state_id_representation() {
+        let dfa = Builder::new().build(r"(?-u:\w+@\w+)").unwrap();
+        let fp = dfa.fingerprint();
+        assert_eq!(fp, dfa.to_u16().unwrap().fingerprint());
+        assert_eq!(fp, dfa.to_u32().unwrap().fingerprint());
+
+        let bytes = dfa.to_u16().unwrap().to_bytes_native_endian().unwrap();
+        let loaded: DenseDFA<&[u16], u16> =
+            unsafe { DenseDFA::from_bytes(&bytes) };
+        assert_eq!(fp, loaded.fingerprint());
+
+        let anchored =
+            Builder::new().anchored(true).build(r"(?-u:\w+@\w+)").unwrap();
+        assert_ne!(fp, anchored.fingerprint());
+        let plain = Builder::new().premultiply(false).build(r"(?-u:\w+@\w+)");
+        assert_ne!(fp, plain.unwrap().fingerprint());
+    }
 
-#[cfg(all(test, feature = "std"))]
-mod tests {
-    use super::*;
+    #[test]
+    fn from_parts_checks_table() {
+        for &premultiply in &[false, true] {
+            let dfa =
+                Builder::new().premultiply(premultiply).build("a+b").unwrap();
+            let r = dfa.repr();
+            let from = |trans| {
+                DenseDFA::from_parts(
+                    trans,
+                    *r.byte_classes(),
+                    r.start,
+                    r.max_match,
+                    r.anchored,
+                    r.premultiplied,
+                )
+            };
+            let borrowed = from(r.trans()).unwrap();
+            assert_eq!(Some(4), borrowed.find(b"xaab"));
+            assert_eq!(dfa.fingerprint(), borrowed.fingerprint());
+
+            assert!(from(&r.trans()[1..]).is_err());
+            let mut bad = r.trans().to_vec();
+            bad[0] = usize::max_value();
+            assert!(from(&bad).is_err());
+        }
+    }
 
     #[test]
-    fn errors_when_converting_to_smaller_dfa() {
-        let pattern = r"\w{10}";
+    fn column_major_layout_agrees_with_row_major() {
+        let haystacks: &[&[u8]] =
+            &[b"", b"abc7", b"zzxyzq", b"fa9e1", b"\xFFab0", b"xy"];
+        for &byte_classes in &[false, true] {
+            for &anchored in &[false, true] {
+                let mut builder = Builder::new();
+                builder
+                    .premultiply(false)
+                    .byte_classes(byte_classes)
+                    .anchored(anchored)
+                    .accelerate(true);
+                let row = builder.build(r"[a-f]+[0-9]|xyz").unwrap();
+                let col = builder
+                    .layout(Layout::ColumnMajor)
+                    .build(r"[a-f]+[0-9]|xyz")
+                    .unwrap();
+                assert_eq!(Layout::RowMajor, row.layout());
+                assert_eq!(Layout::ColumnMajor, col.layout());
+                assert_eq!(row.is_accelerated(), col.is_accelerated());
+                assert_eq!(
+                    format!("{:?}", row.repr()),
+                    format!("{:?}", col.repr())
+                );
+                for (id, _) in row.repr().states() {
+                    for b in 0..256 {
+                        let b = b as u8;
+                        assert_eq!(
+                            row.next_state(id, b),
+                            col.next_state(id, b)
+                        );
+                    }
+                }
+                for &haystack in haystacks {
+                    assert_eq!(row.find(haystack), col.find(haystack));
+                    assert_eq!(row.rfind(haystack), col.rfind(haystack));
+                    assert_eq!(row.is_match(haystack), col.is_match(haystack));
+                }
+
+                let bytes = col.to_bytes_native_endian().unwrap();
+                assert!(DFAHeader::from_bytes(&bytes)
+                    .unwrap()
+                    .is_column_major());
+                let loaded: DenseDFA<&[usize], usize> =
+                    unsafe { DenseDFA::from_bytes(&bytes) };
+                loaded.validate().unwrap();
+                assert_eq!(col.as_ref(), loaded);
+                let rle = col.to_bytes_rle_native_endian().unwrap();
+                assert_eq!(col, DenseDFA::from_bytes_rle(&rle).unwrap());
+
+                let small = col.to_u8().unwrap();
+                assert_eq!(Layout::ColumnMajor, small.layout());
+                assert_eq!(row.find(b"abc7"), small.find(b"abc7"));
+                let sparse = col.to_sparse().unwrap();
+                assert_eq!(row.find(b"abc7"), sparse.find(b"abc7"));
+
+                let rev = col.reverse_language().unwrap();
+                assert_eq!(Layout::ColumnMajor, rev.layout());
+                let projected = col.project_to_bytes(b"abcxyz7").unwrap();
+                assert_eq!(Layout::ColumnMajor, projected.layout());
+                let expected = row.project_to_bytes(b"abcxyz7").unwrap();
+                let mut remapped = col.clone();
+                remapped.remap_for_locality();
+                assert_eq!(Layout::ColumnMajor, remapped.layout());
+                for &haystack in haystacks {
+                    assert_eq!(
+                        row.reverse_language().unwrap().rfind(haystack),
+                        rev.rfind(haystack)
+                    );
+                    assert_eq!(
+                        expected.find(haystack),
+                        projected.find(haystack)
+                    );
+                    assert_eq!(row.find(haystack), remapped.find(haystack));
+                }
+                if anchored {
+                    let unanchored = col.to_unanchored().unwrap();
+                    assert_eq!(Layout::ColumnMajor, unanchored.layout());
+                    let expected = row.to_unanchored().unwrap();
+                    for &haystack in haystacks {
+                        assert_eq!(
+                            expected.find(haystack),
+                            unanchored.find(haystack)
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn column_major_layout_is_never_premultiplied() {
+        let row =
+            Builder::new().premultiply(false).build("x[ab]|y[ab]").unwrap();
+        let mut col = Builder::new()
+            .layout(Layout::ColumnMajor)
+            .build("x[ab]|y[ab]")
+            .unwrap();
+        assert_eq!(row.state_partition(), col.state_partition());
+        assert_eq!(row.trap_states(), col.trap_states());
+        assert_eq!(row.to_dot(), col.to_dot());
+        col.repr_mut().premultiplied = true;
+        assert!(col.validate().is_err());
+    }
+
+    #[test]
+    fn finite_language_agrees_with_iter_accepted() {
+        for &(pattern, count) in
+            &[("", 1), ("[a-c]{1,3}x?", 78), ("(?-u:[\\x00-\\xFF])", 256)]
+        {
+            let dfa = Builder::new()
+                .anchored(true)
+                .minimize(false)
+                .allow_invalid_utf8(true)
+                .build(pattern)
+                .unwrap();
+            let expected: Vec<Vec<u8>> = dfa.iter_accepted(10).collect();
+            assert_eq!(count, expected.len(), "{:?}", pattern);
+            assert_eq!(Some(expected), dfa.finite_language(count));
+            assert_eq!(None, dfa.finite_language(count - 1));
+        }
+        for pattern in &["a*", "(ab)+c", "x|y(z+)w"] {
+            let dfa = Builder::new().anchored(true).build(pattern).unwrap();
+            assert_eq!(None, dfa.finite_language(usize::max_value()));
+        }
+        let dfa = Builder::new().build("a").unwrap();
+        assert_eq!(None, dfa.finite_language(usize::max_value()));
+
+        // 256^16 strings can't be counted in a usize.
         let dfa = Builder::new()
-            .byte_classes(false)
             .anchored(true)
-            .premultiply(false)
-            .build_with_size::<u16>(pattern)
+            .allow_invalid_utf8(true)
+            .build("(?-u:[\\x00-\\xFF]){16}")
             .unwrap();
-        assert!(dfa.to_u8().is_err());
+        assert_eq!(None, dfa.finite_language(usize::max_value()));
     }
 
     #[test]
-    fn errors_when_determinization_would_overflow() {
-        let pattern = r"\w{10}";
+    fn provenance_covers_every_state() {
+        let pattern = r"[a-c]+x|b[0-9]*";
+        let (_, stats) = Builder::new().build_with_stats(pattern).unwrap();
+        assert_eq!(None, stats.provenance(0usize));
+
+        for &(minimize, premultiply) in
+            &[(false, false), (true, false), (false, true), (true, true)]
+        {
+            let mut builder = Builder::new();
+            builder
+                .anchored(true)
+                .minimize(minimize)
+                .premultiply(premultiply)
+                .determinize_cache_limit(1)
+                .record_provenance(true);
+            let (dfa, stats) = builder.build_with_stats(pattern).unwrap();
+            let nfa = builder.build_nfa(pattern).unwrap();
+            assert!(stats.provenance(dead_id::<usize>()).unwrap().is_empty());
+            for (id, _) in dfa.repr().states() {
+                let nfa_states = stats.provenance(id).unwrap();
+                assert!(nfa_states.windows(2).all(|w| w[0] < w[1]));
+                assert!(nfa_states.iter().all(|&s| s < nfa.len()));
+                // Every byte leaving a live state is consumed by one of the
+                // NFA states it was built from.
+                for b in 0..256 {
+                    let next = dfa.next_state(id, b as u8);
+                    if dfa.is_dead_state(next) {
+                        continue;
+                    }
+                    assert!(nfa_states.iter().any(|&s| nfa
+                        .state(s)
+                        .transitions()
+                        .iter()
+                        .any(|t| t.start <= b as u8 && b as u8 <= t.end)));
+                }
+            }
+        }
+    }
 
-        let mut builder = Builder::new();
-        builder.byte_classes(false).anchored(true).premultiply(false);
-        // using u16 is fine
-        assert!(builder.build_with_size::<u16>(pattern).is_ok());
-        // // ... but u8 results in overflow (because there are >256 states)
-        assert!(builder.build_with_size::<u8>(pattern).is_err());
+    #[test]
+    fn ascii_patterns_have_tiny_alphabets() {
+        // Every non-ASCII byte must end up in the same class.
+        for &(pattern, len) in &[("[a-z]+", 3), ("[a-z]+[0-9]", 5)] {
+            let dfa = Builder::new().anchored(true).build(pattern).unwrap();
+            let classes = dfa.byte_classes();
+            assert_eq!(len, classes.alphabet_len(), "{:?}", pattern);
+            assert!((0x80..=0xFF).all(|b| classes.get(b) == classes.get(0x80)));
+
+            let dfa = Builder::new()
+                .allow_invalid_utf8(true)
+                .build(pattern)
+                .unwrap();
+            assert_eq!(len, dfa.byte_classes().alphabet_len());
+        }
     }
 
     #[test]
-    fn errors_when_premultiply_would_overflow() {
-        let pattern = r"[a-z]";
+    fn state_labels_roundtrip() {
+        let dfa = Builder::new().anchored(true).build("ab+").unwrap();
+        let mut labels = StateLabels::new();
+        labels.set(dfa.start_state(), "start");
+        labels.set(dfa.next_state(dfa.start_state(), b'a'), "after a");
+
+        let plain = dfa.to_bytes_big_endian().unwrap();
+        let bytes = dfa.to_bytes_with_labels_big_endian(&labels).unwrap();
+        assert_eq!(plain[..30], bytes[..30]);
+        assert_eq!(plain[32..], bytes[32..plain.len()]);
+        assert_eq!(labels, StateLabels::from_dfa_bytes(&bytes).unwrap());
+        assert!(StateLabels::from_dfa_bytes(&plain).unwrap().is_empty());
+
+        let truncated = &bytes[..bytes.len() - 1];
+        assert!(StateLabels::from_dfa_bytes(truncated).is_err());
+        let rle = dfa.to_bytes_rle_native_endian().unwrap();
+        assert!(StateLabels::from_dfa_bytes(&rle).is_err());
+
+        let out = format!("{:?}", dfa.with_labels(&labels));
+        assert!(out.contains("(after a):"));
+        let unlabeled = format!("{:?}", dfa.with_labels(&StateLabels::new()));
+        assert_eq!(format!("{:?}", dfa.repr()), unlabeled);
+
+        for end in &[0, 40, 300, 400, plain.len() - 1] {
+            assert!(StateLabels::from_dfa_bytes(&bytes[..*end]).is_err());
+        }
+        let mut bogus = bytes.clone();
+        let count = plain.len();
+        BigEndian::write_u64(&mut bogus[count..], 1);
+        BigEndian::write_u64(&mut bogus[count + 16..], !0);
+        assert!(StateLabels::from_dfa_bytes(&bogus).is_err());
+    }
 
-        let mut builder = Builder::new();
-        builder.byte_classes(false).anchored(true).premultiply(false);
-        // without premultiplication is OK
-        assert!(builder.build_with_size::<u8>(pattern).is_ok());
-        // ... but with premultiplication overflows u8
-        builder.premultiply(true);
-        assert!(builder.build_with_size::<u8>(pattern).is_err());
+    #[test]
+    fn state_labels_from_provenance() {
+        for &premultiply in &[false, true] {
+            let (dfa, stats) = Builder::new()
+                .anchored(true)
+                .premultiply(premultiply)
+                .record_provenance(true)
+                .build_with_stats("ab+")
+                .unwrap();
+            let labels = stats.state_labels().unwrap();
+            assert_eq!(dfa.repr().state_count(), labels.len());
+            let start = dfa.start_state();
+            assert!(labels.get(start).unwrap().starts_with("nfa {"));
+
+            let dot = dfa.with_labels(&labels).to_dot();
+            assert!(dot.contains(labels.get(start).unwrap()));
+            assert!(!dfa.to_dot().contains("nfa {"));
+        }
+        let (_, stats) = Builder::new().build_with_stats("ab+").unwrap();
+        assert!(stats.state_labels().is_none());
     }
 
     // let data = ::std::fs::read_to_string("/usr/share/dict/words").unwrap();
@@ -1,15 +1,24 @@
+use std::collections::hash_map::{DefaultHasher, RandomState};
 use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
 use std::mem;
 use std::rc::Rc;
 
-use dense;
-use error::Result;
+use dense::{self, MatchPriority};
+use error::{Error, Result};
+use multi::PatternID;
 use nfa::{self, NFA};
 use sparse_set::SparseSet;
 use state_id::{dead_id, StateID};
 
 type DFARepr<S> = dense::Repr<Vec<S>, S>;
 
+/// The NFA states making up each DFA state, indexed by DFA state index.
+type Provenance = Vec<Vec<nfa::StateID>>;
+
+/// A determinized DFA along with its provenance, if it was recorded.
+pub(crate) type Determinized<S> = (DFARepr<S>, Option<Provenance>);
+
 /// A determinizer converts an NFA to a DFA.
 ///
 /// This determinizer follows the typical powerset construction, where each
@@ -30,30 +39,171 @@ pub(crate) struct Determinizer<'a, S: StateID> {
     /// The DFA we're building.
     dfa: DFARepr<S>,
     /// Each DFA state being built is defined as an *ordered* set of NFA
-    /// states, along with a flag indicating whether the state is a match
-    /// state or not.
+    /// states, along with the patterns that match in that state.
     ///
     /// This is never empty. The first state is always a dummy state such that
     /// a state id == 0 corresponds to a dead state.
     builder_states: Vec<Rc<State>>,
     /// A cache of DFA states that already exist and can be easily looked up
     /// via ordered sets of NFA states.
-    cache: HashMap<Rc<State>, S>,
+    cache: HashMap<Rc<State>, S, StateHasher>,
+    /// Whether the cache should use a fast non-cryptographic hash function
+    /// instead of the standard library's default.
+    fast_hash: bool,
+    /// The number of entries the cache may hold before it is cleared, if
+    /// any. This doubles each time the cache is cleared.
+    cache_limit: Option<usize>,
+    /// The maximum number of states the DFA may have, if any.
+    state_limit: Option<usize>,
     /// Scratch space for a stack of NFA states to visit, for depth first
     /// visiting without recursion.
     stack: Vec<nfa::StateID>,
     /// Scratch space for storing an ordered sequence of NFA states, for
     /// amortizing allocation.
     scratch_nfa_states: Vec<nfa::StateID>,
-    /// Whether to build a DFA that finds the longest possible match.
-    longest_match: bool,
+    /// Which NFA states to keep in a DFA state once an NFA match state has
+    /// been seen.
+    match_priority: MatchPriority,
+    /// Whether to report the NFA states making up each DFA state once
+    /// determinization is done. When enabled, clearing the cache no longer
+    /// frees the NFA states of compiled DFA states.
+    record_provenance: bool,
+    /// Whether to shuffle match states to the front of the DFA once
+    /// determinization is done, instead of recording them in a set.
+    shuffle_match_states: bool,
+}
+
+/// Allocations used by determinization that can be reused across builds.
+///
+/// The buffers here are emptied before each use, but keep their capacity,
+/// so that building many DFAs in a row doesn't repeatedly grow them from
+/// nothing.
+#[derive(Debug)]
+pub(crate) struct Scratch<S> {
+    builder_states: Vec<Rc<State>>,
+    cache: HashMap<Rc<State>, S, StateHasher>,
+    stack: Vec<nfa::StateID>,
+    scratch_nfa_states: Vec<nfa::StateID>,
+}
+
+impl<S: StateID> Scratch<S> {
+    /// Create empty scratch space.
+    pub fn new() -> Scratch<S> {
+        Scratch {
+            builder_states: vec![],
+            cache: HashMap::with_hasher(StateHasher::new(false)),
+            stack: vec![],
+            scratch_nfa_states: vec![],
+        }
+    }
+}
+
+/// The hash function used by the cache that deduplicates DFA states.
+///
+/// This is chosen at runtime rather than via a type parameter, so that the
+/// choice can be stored on a builder (and in reusable scratch space) without
+/// changing any types.
+#[derive(Clone, Debug)]
+pub(crate) enum StateHasher {
+    /// The standard library's default, randomly keyed, hash function.
+    Default(RandomState),
+    /// A fast non-cryptographic hash function, in the style of the FxHash
+    /// function used by rustc. Its output is fully determined by its input.
+    Fast,
+}
+
+impl StateHasher {
+    fn new(fast: bool) -> StateHasher {
+        if fast {
+            StateHasher::Fast
+        } else {
+            StateHasher::Default(RandomState::new())
+        }
+    }
+
+    fn is_fast(&self) -> bool {
+        match *self {
+            StateHasher::Default(_) => false,
+            StateHasher::Fast => true,
+        }
+    }
+}
+
+impl BuildHasher for StateHasher {
+    type Hasher = StateHash;
+
+    fn build_hasher(&self) -> StateHash {
+        match *self {
+            StateHasher::Default(ref random) => {
+                StateHash::Default(random.build_hasher())
+            }
+            StateHasher::Fast => StateHash::Fast(0),
+        }
+    }
+}
+
+/// The hasher produced by a `StateHasher`.
+#[derive(Debug)]
+pub(crate) enum StateHash {
+    Default(DefaultHasher),
+    Fast(u64),
+}
+
+impl StateHash {
+    /// The multiplier used by FxHash on 64-bit targets.
+    const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+    #[inline(always)]
+    fn add_to_hash(hash: &mut u64, word: u64) {
+        *hash = (hash.rotate_left(5) ^ word).wrapping_mul(StateHash::SEED);
+    }
+}
+
+impl Hasher for StateHash {
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        let hash = match *self {
+            StateHash::Default(ref mut h) => return h.write(bytes),
+            StateHash::Fast(ref mut hash) => hash,
+        };
+        // The NFA state sets being hashed are slices of integers, which are
+        // written here all at once, so consume them a word at a time.
+        let mut chunks = bytes.chunks_exact(8);
+        for chunk in &mut chunks {
+            let mut word = [0; 8];
+            word.copy_from_slice(chunk);
+            StateHash::add_to_hash(hash, u64::from_le_bytes(word));
+        }
+        for &b in chunks.remainder() {
+            StateHash::add_to_hash(hash, b as u64);
+        }
+    }
+
+    #[inline]
+    fn write_usize(&mut self, n: usize) {
+        match *self {
+            StateHash::Default(ref mut h) => h.write_usize(n),
+            StateHash::Fast(ref mut hash) => {
+                StateHash::add_to_hash(hash, n as u64)
+            }
+        }
+    }
+
+    #[inline]
+    fn finish(&self) -> u64 {
+        match *self {
+            StateHash::Default(ref h) => h.finish(),
+            StateHash::Fast(hash) => hash,
+        }
+    }
 }
 
 /// An intermediate representation for a DFA state during determinization.
 #[derive(Debug, Eq, Hash, PartialEq)]
 struct State {
-    /// Whether this state is a match state or not.
-    is_match: bool,
+    /// The patterns that match in this state, in ascending order. This is
+    /// empty if and only if this state is not a match state.
+    matches: Vec<PatternID>,
     /// An ordered sequence of NFA states that make up this DFA state.
     nfa_states: Vec<nfa::StateID>,
 }
@@ -62,7 +212,7 @@ impl<'a, S: StateID> Determinizer<'a, S> {
     /// Create a new determinizer for converting the given NFA to a DFA.
     pub fn new(nfa: &'a NFA) -> Determinizer<'a, S> {
         let dead = Rc::new(State::dead());
-        let mut cache = HashMap::default();
+        let mut cache = HashMap::with_hasher(StateHasher::new(false));
         cache.insert(dead.clone(), dead_id());
 
         Determinizer {
@@ -70,9 +220,14 @@ impl<'a, S: StateID> Determinizer<'a, S> {
             dfa: DFARepr::empty().anchored(nfa.is_anchored()),
             builder_states: vec![dead],
             cache,
+            fast_hash: false,
+            cache_limit: None,
+            state_limit: None,
             stack: vec![],
             scratch_nfa_states: vec![],
-            longest_match: false,
+            match_priority: MatchPriority::LeftmostFirst,
+            record_provenance: false,
+            shuffle_match_states: true,
         }
     }
 
@@ -85,18 +240,171 @@ impl<'a, S: StateID> Determinizer<'a, S> {
         self
     }
 
-    /// Instruct the determinizer to build a DFA that recognizes the longest
-    /// possible match instead of the leftmost first match. This is useful when
-    /// constructing reverse DFAs for finding the start of a match.
-    pub fn longest_match(mut self, yes: bool) -> Determinizer<'a, S> {
-        self.longest_match = yes;
+    /// Reserve room for the given number of states in the DFA's transition
+    /// table up front.
+    ///
+    /// This must be called after the alphabet has been fixed, i.e., after
+    /// `with_byte_classes`.
+    pub fn states_capacity_hint(
+        mut self,
+        states: usize,
+    ) -> Determinizer<'a, S> {
+        self.dfa.reserve_states(states);
         self
     }
 
-    /// Build the DFA. If there was a problem constructing the DFA (e.g., if
-    /// the chosen state identifier representation is too small), then an error
-    /// is returned.
-    pub fn build(mut self) -> Result<DFARepr<S>> {
+    /// Bound the number of entries in the cache used to deduplicate DFA
+    /// states.
+    ///
+    /// Once the cache exceeds the limit, it is cleared of every state that
+    /// has already been compiled, and the NFA states backing them are freed.
+    /// Later occurrences of a cleared state become new (duplicate) DFA
+    /// states. To guarantee that determinization terminates, the limit
+    /// doubles each time the cache is cleared.
+    pub fn cache_limit(mut self, limit: Option<usize>) -> Determinizer<'a, S> {
+        self.cache_limit = limit.map(|limit| limit.max(1));
+        self
+    }
+
+    /// Use a fast non-cryptographic hash function for the cache used to
+    /// deduplicate DFA states, instead of the standard library's default
+    /// (SipHash).
+    pub fn fast_hash(mut self, yes: bool) -> Determinizer<'a, S> {
+        if yes != self.fast_hash {
+            let mut cache = HashMap::with_hasher(StateHasher::new(yes));
+            cache.extend(self.cache.drain());
+            self.cache = cache;
+            self.fast_hash = yes;
+        }
+        self
+    }
+
+    /// Fail determinization once the DFA would need more than the given
+    /// number of states, including its dead state.
+    pub fn state_limit(mut self, limit: Option<usize>) -> Determinizer<'a, S> {
+        self.state_limit = limit;
+        self
+    }
+
+    /// Instruct the determinizer to prioritize matches as given. By default,
+    /// this builds a DFA that recognizes the leftmost first match.
+    ///
+    /// Recognizing the longest possible match is useful when constructing
+    /// reverse DFAs for finding the start of a match.
+    pub fn match_priority(
+        mut self,
+        priority: MatchPriority,
+    ) -> Determinizer<'a, S> {
+        self.match_priority = priority;
+        self
+    }
+
+    /// Record the NFA states that make up each DFA state, which are then
+    /// returned by `build_with_provenance`.
+    pub fn record_provenance(mut self, yes: bool) -> Determinizer<'a, S> {
+        self.record_provenance = yes;
+        self
+    }
+
+    /// Shuffle match states to the front of the DFA once determinization is
+    /// done. When disabled, states keep the order in which they were built
+    /// and the match states are recorded in a set instead. This is enabled
+    /// by default.
+    pub fn shuffle_match_states(mut self, yes: bool) -> Determinizer<'a, S> {
+        self.shuffle_match_states = yes;
+        self
+    }
+
+    /// Build the DFA, along with the NFA states that make up each of its
+    /// states if provenance recording is enabled. If there was a problem
+    /// constructing the DFA (e.g., if the chosen state identifier
+    /// representation is too small), then an error is returned.
+    ///
+    /// The NFA states are indexed by DFA state index, and only include the
+    /// NFA states that consume input.
+    ///
+    /// If scratch space is given, then its buffers are used instead of
+    /// allocating new ones. The buffers are handed back (emptied of this
+    /// build's states) when done, even if an error occurs.
+    pub fn build_with_provenance(
+        mut self,
+        scratch: Option<&mut Scratch<S>>,
+    ) -> Result<Determinized<S>> {
+        let scratch = match scratch {
+            None => {
+                let provenance = self.determinize_with_provenance()?;
+                return Ok((self.dfa, provenance));
+            }
+            Some(scratch) => scratch,
+        };
+        self.swap_scratch(scratch);
+        self.builder_states.clear();
+        if self.cache.hasher().is_fast() == self.fast_hash {
+            self.cache.clear();
+        } else {
+            // The scratch space was last used with a different hash
+            // function, so its cache can't be reused.
+            self.cache =
+                HashMap::with_hasher(StateHasher::new(self.fast_hash));
+        }
+        let dead = Rc::new(State::dead());
+        self.builder_states.push(dead.clone());
+        self.cache.insert(dead, dead_id());
+
+        let result = self.determinize_with_provenance();
+        self.swap_scratch(scratch);
+        scratch.builder_states.clear();
+        scratch.cache.clear();
+        result.map(|provenance| (self.dfa, provenance))
+    }
+
+    /// Run the powerset construction, and return the NFA states making up
+    /// each DFA state if provenance recording is enabled.
+    fn determinize_with_provenance(&mut self) -> Result<Option<Provenance>> {
+        let remap = self.determinize()?;
+        if !self.record_provenance {
+            return Ok(None);
+        }
+        let mut provenance = vec![vec![]; remap.len()];
+        for (old, state) in self.builder_states.iter().enumerate() {
+            provenance[remap[old]] = state.nfa_states.clone();
+        }
+        Ok(Some(provenance))
+    }
+
+    /// Exchange this determinizer's buffers with those of the given scratch
+    /// space.
+    fn swap_scratch(&mut self, scratch: &mut Scratch<S>) {
+        mem::swap(&mut self.builder_states, &mut scratch.builder_states);
+        mem::swap(&mut self.cache, &mut scratch.cache);
+        mem::swap(&mut self.stack, &mut scratch.stack);
+        mem::swap(
+            &mut self.scratch_nfa_states,
+            &mut scratch.scratch_nfa_states,
+        );
+    }
+
+    /// Build the DFA, along with the patterns that match in each of its
+    /// states. The patterns are indexed by DFA state index, and are empty for
+    /// every state that isn't a match state.
+    ///
+    /// Note that unless the match priority is `LeftmostLongest`, at most one
+    /// pattern is reported for each state, since the NFA states following a
+    /// match state are dropped.
+    pub fn build_with_patterns(
+        mut self,
+    ) -> Result<(DFARepr<S>, Vec<Vec<PatternID>>)> {
+        let remap = self.determinize()?;
+        let mut patterns = vec![vec![]; remap.len()];
+        for (old, state) in self.builder_states.iter().enumerate() {
+            patterns[remap[old]] = state.matches.clone();
+        }
+        Ok((self.dfa, patterns))
+    }
+
+    /// Run the powerset construction, and return a map from the index that
+    /// each DFA state was built with to its final index.
+    fn determinize(&mut self) -> Result<Vec<usize>> {
         let representative_bytes: Vec<u8> =
             self.dfa.byte_classes().representatives().collect();
         let mut sparse = self.new_sparse_set();
@@ -110,6 +418,13 @@ impl<'a, S: StateID> Determinizer<'a, S> {
                     uncompiled.push(next_dfa_id);
                 }
             }
+            let over_limit = match self.cache_limit {
+                None => false,
+                Some(limit) => self.cache.len() > limit,
+            };
+            if over_limit {
+                self.clear_cache(&uncompiled);
+            }
         }
 
         // At this point, we shuffle the matching states in the final DFA to
@@ -117,9 +432,12 @@ impl<'a, S: StateID> Determinizer<'a, S> {
         // condition by merely inspecting the current state's identifier, and
         // avoids the need for any additional auxiliary storage.
         let is_match: Vec<bool> =
-            self.builder_states.iter().map(|s| s.is_match).collect();
-        self.dfa.shuffle_match_states(&is_match);
-        Ok(self.dfa)
+            self.builder_states.iter().map(|s| s.is_match()).collect();
+        if !self.shuffle_match_states {
+            self.dfa.set_match_states(&is_match);
+            return Ok((0..is_match.len()).collect());
+        }
+        Ok(self.dfa.shuffle_match_states(&is_match))
     }
 
     /// Return the identifier for the next DFA state given an existing DFA
@@ -164,7 +482,7 @@ impl<'a, S: StateID> Determinizer<'a, S> {
             match *self.nfa.state(nfa_id) {
                 nfa::State::Union { .. }
                 | nfa::State::Fail
-                | nfa::State::Match => {}
+                | nfa::State::Match { .. } => {}
                 nfa::State::Range { range: ref r } => {
                     if r.start <= b && b <= r.end {
                         self.epsilon_closure(r.next, next_nfa_states);
@@ -187,7 +505,11 @@ impl<'a, S: StateID> Determinizer<'a, S> {
     /// Compute the epsilon closure for the given NFA state.
     fn epsilon_closure(&mut self, start: nfa::StateID, set: &mut SparseSet) {
         if !self.nfa.state(start).is_epsilon() {
-            set.insert(start);
+            // Distinct transitions may lead to the same state, which must
+            // only be added once.
+            if !set.contains(start) {
+                set.insert(start);
+            }
             return;
         }
 
@@ -202,7 +524,7 @@ impl<'a, S: StateID> Determinizer<'a, S> {
                     nfa::State::Range { .. }
                     | nfa::State::Sparse { .. }
                     | nfa::State::Fail
-                    | nfa::State::Match => break,
+                    | nfa::State::Match { .. } => break,
                     nfa::State::Union { ref alternates } => {
                         id = match alternates.get(0) {
                             None => break,
@@ -228,11 +550,49 @@ impl<'a, S: StateID> Determinizer<'a, S> {
         Ok(id)
     }
 
+    /// Clear the cache of every state except for the dead state and the
+    /// given uncompiled states, and double the cache limit.
+    ///
+    /// Compiled states are never consulted again except to determine which
+    /// patterns match in them, so their NFA states are dropped (unless
+    /// provenance is being recorded).
+    fn clear_cache(&mut self, uncompiled: &[S]) {
+        let mut keep = vec![false; self.builder_states.len()];
+        keep[dead_id::<S>().to_usize()] = true;
+        for &id in uncompiled {
+            keep[id.to_usize()] = true;
+        }
+        let compiled = Rc::new(State::dead());
+
+        self.cache.clear();
+        for (id, state) in self.builder_states.iter_mut().enumerate() {
+            if keep[id] {
+                self.cache.insert(state.clone(), S::from_usize(id));
+            } else if self.record_provenance {
+                // The NFA states are still needed, so only the cache entry
+                // is dropped.
+            } else if state.is_match() {
+                let matches = state.matches.clone();
+                *state = Rc::new(State { matches, nfa_states: vec![] });
+            } else {
+                *state = compiled.clone();
+            }
+        }
+        if let Some(ref mut limit) = self.cache_limit {
+            *limit = limit.saturating_mul(2);
+        }
+    }
+
     /// Add the given state to the DFA and make it available in the cache.
     ///
     /// The state initially has no transitions. That is, it transitions to the
     /// dead state for all possible inputs.
     fn add_state(&mut self, state: State) -> Result<S> {
+        if let Some(limit) = self.state_limit {
+            if self.dfa.state_count() >= limit {
+                return Err(Error::determinize_too_big(limit));
+            }
+        }
         let id = self.dfa.add_empty_state()?;
         let rstate = Rc::new(state);
         self.builder_states.push(rstate.clone());
@@ -243,7 +603,7 @@ impl<'a, S: StateID> Determinizer<'a, S> {
     /// Convert the given set of ordered NFA states to a DFA state.
     fn new_state(&mut self, set: &SparseSet) -> State {
         let mut state = State {
-            is_match: false,
+            matches: vec![],
             nfa_states: mem::replace(&mut self.scratch_nfa_states, vec![]),
         };
         state.nfa_states.clear();
@@ -259,15 +619,21 @@ impl<'a, S: StateID> Determinizer<'a, S> {
                 nfa::State::Fail => {
                     break;
                 }
-                nfa::State::Match => {
-                    state.is_match = true;
-                    if !self.longest_match {
-                        break;
+                nfa::State::Match { pattern_id } => {
+                    state.matches.push(pattern_id);
+                    match self.match_priority {
+                        MatchPriority::LeftmostLongest => {}
+                        MatchPriority::ShortestFirst => {
+                            state.nfa_states.clear();
+                            break;
+                        }
+                        MatchPriority::LeftmostFirst => break,
                     }
                 }
                 nfa::State::Union { .. } => {}
             }
         }
+        state.matches.sort();
         state
     }
 
@@ -280,6 +646,11 @@ impl<'a, S: StateID> Determinizer<'a, S> {
 impl State {
     /// Create a new empty dead state.
     fn dead() -> State {
-        State { nfa_states: vec![], is_match: false }
+        State { nfa_states: vec![], matches: vec![] }
+    }
+
+    /// Returns true if and only if this is a match state.
+    fn is_match(&self) -> bool {
+        !self.matches.is_empty()
     }
 }
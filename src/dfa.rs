@@ -1,11 +1,55 @@
-use state_id::StateID;
+use core::char;
+use core::ptr;
+
+use state_id::{dead_id, StateID};
+
+/// The outcome of a search performed by
+/// [`DFA::find_or_dead`](trait.DFA.html#method.find_or_dead).
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum SearchResult {
+    /// A match was found, ending at the given offset.
+    Match(usize),
+    /// No match was found, and the DFA entered its dead state at the given
+    /// offset. No match can be found by reading further input.
+    NoMatchDead(usize),
+    /// No match was found, but all of the input was read without entering
+    /// the dead state. A match may still be found if more input follows.
+    NoMatchExhausted,
+}
+
+/// The outcome of a search performed by
+/// [`DFA::find_bounded`](trait.DFA.html#method.find_bounded).
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum SearchOutcome {
+    /// A match was found, ending at the given offset.
+    Match(usize),
+    /// There is no match anywhere in the input.
+    NoMatch,
+    /// The search read as many bytes as it was permitted to without
+    /// determining where the leftmost match ends or whether one exists.
+    Incomplete,
+}
+
+/// Whether a DFA accepts a prefix of its input, as reported by
+/// [`DFA::simulate`](trait.DFA.html#method.simulate).
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum MatchStatus {
+    /// The DFA is in its dead state, so neither this prefix nor any longer
+    /// one can match.
+    Dead,
+    /// The DFA is not in a match state, but a longer prefix may still match.
+    NoMatch,
+    /// The DFA is in a match state, so a match ends at this prefix.
+    Match,
+}
 
 /// A trait describing the interface of a deterministic finite automaton (DFA).
 ///
 /// Every DFA has exactly one start state and at least one dead state (which
 /// may be the same, as in the case of an empty DFA). In all cases, a state
 /// identifier of `0` must be a dead state such that `DFA::is_dead_state(0)`
-/// always returns `true`.
+/// always returns `true`. This state is never a match state, and is also
+/// returned by `DFA::dead_state_id`.
 ///
 /// Every DFA also has zero or more match states, such that
 /// `DFA::is_match_state(id)` returns `true` if and only if `id` corresponds to
@@ -23,6 +67,48 @@ pub trait DFA {
     type ID: StateID;
 
     /// Return the identifier of this DFA's start state.
+    ///
+    /// Together with [`next_state`](trait.DFA.html#method.next_state), this
+    /// is all that is needed to walk a DFA by hand, which makes it possible
+    /// to interleave DFA transitions with custom logic while reusing the
+    /// compiled transition table. A manual walk bypasses everything the
+    /// search routines do on top of the transition table: nothing stops at
+    /// the first match or dead state, and optimizations such as prefix
+    /// scanning and start state acceleration are not applied. Callers must
+    /// therefore check `is_match_state` and `is_dead_state` themselves
+    /// (`is_match_or_dead_state` checks for both at once). This includes the
+    /// start state, which is a match state when the DFA matches the empty
+    /// string and a dead state when it never matches anything.
+    ///
+    /// # Example
+    ///
+    /// This finds the end of the leftmost-first match like `find` does, but
+    /// counts the number of transitions taken along the way.
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = DenseDFA::new("[a-z]*")?;
+    /// let mut state = dfa.start_state();
+    /// // The empty string matches, so the start state is a match state.
+    /// assert!(dfa.is_match_state(state));
+    ///
+    /// let (mut last_match, mut steps) = (Some(0), 0);
+    /// for (i, &b) in b"abc123".iter().enumerate() {
+    ///     state = dfa.next_state(state, b);
+    ///     steps += 1;
+    ///     if dfa.is_dead_state(state) {
+    ///         break;
+    ///     } else if dfa.is_match_state(state) {
+    ///         last_match = Some(i + 1);
+    ///     }
+    /// }
+    /// assert_eq!(dfa.find(b"abc123"), last_match);
+    /// assert_eq!(Some(3), last_match);
+    /// assert_eq!(4, steps);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
     fn start_state(&self) -> Self::ID;
 
     /// Returns true if and only if the given identifier corresponds to a match
@@ -34,6 +120,67 @@ pub trait DFA {
     /// thus can never lead to a match.
     fn is_dead_state(&self, id: Self::ID) -> bool;
 
+    /// Return the identifier of this DFA's canonical dead state.
+    ///
+    /// This is always `0`, in every DFA and for every state identifier
+    /// representation, and this is guaranteed not to change. The state it
+    /// identifies is never a match state, and every one of its transitions
+    /// leads back to itself. Algorithms built on top of this trait, such as
+    /// products or complements of DFAs, may rely on this instead of
+    /// hardcoding `0`.
+    ///
+    /// The DFAs in this crate report only this state as dead. Other
+    /// implementations of this trait are permitted to have more than one
+    /// dead state, so `is_dead_state` remains the right way to test whether
+    /// an arbitrary state is dead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = DenseDFA::new("foo")?.to_u16()?;
+    /// let dead = dfa.dead_state_id();
+    /// assert_eq!(0u16, dead);
+    /// assert!(dfa.is_dead_state(dead));
+    /// assert!(!dfa.is_match_state(dead));
+    /// assert_eq!(dead, dfa.next_state(dead, b'f'));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[inline]
+    fn dead_state_id(&self) -> Self::ID {
+        dead_id()
+    }
+
+    /// Returns true if and only if this DFA matches the empty string.
+    ///
+    /// This is the case precisely when the start state is a match state, so
+    /// this takes constant time. It is also precisely when a search can
+    /// report a zero-width match, such as `a*` does at every position of
+    /// `bbb`. Code that iterates over matches must take care to make
+    /// progress after an empty match, or else it will find the same match
+    /// forever. [`Regex::find_iter`](struct.Regex.html#method.find_iter)
+    /// does this by starting its next search one byte further along.
+    /// Checking this up front lets a caller skip that handling, or warn
+    /// about a pattern that matches everywhere.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// assert!(DenseDFA::new("a*")?.accepts_empty());
+    /// assert!(DenseDFA::new("a|")?.accepts_empty());
+    /// assert!(!DenseDFA::new("a+")?.accepts_empty());
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[inline]
+    fn accepts_empty(&self) -> bool {
+        self.is_match_state(self.start_state())
+    }
+
     /// Returns true if and only if the given identifier corresponds to either
     /// a dead state or a match state, such that one of `is_match_state(id)`
     /// or `is_dead_state(id)` must return true.
@@ -50,9 +197,69 @@ pub trait DFA {
     /// start at index `0`.
     fn is_anchored(&self) -> bool;
 
+    /// Returns true if and only if this DFA only reports matches that span
+    /// the entire haystack.
+    ///
+    /// Such a DFA is anchored, and every search routine provided by this
+    /// trait only reports a match when the DFA is in a match state after
+    /// consuming all of the input, as if the pattern ended with `\z`. For
+    /// example, `find` returns either `None` or `Some(bytes.len())`.
+    ///
+    /// The default implementation returns false. See
+    /// [`dense::Builder::full_match`](dense/struct.Builder.html#method.full_match)
+    /// for more details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().full_match(true).build(r"\d+")?;
+    /// assert!(dfa.requires_full_match());
+    /// assert_eq!(Some(3), dfa.find(b"123"));
+    /// assert_eq!(None, dfa.find(b"123a"));
+    /// assert!(!dfa.is_match_chunks(&[b"12", b"3a"]));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[inline]
+    fn requires_full_match(&self) -> bool {
+        false
+    }
+
     /// Given the current state that this DFA is in and the next input byte,
     /// this method returns the identifier of the next state. The identifier
     /// returned is always valid, but it may correspond to a dead state.
+    ///
+    /// Any internal details of the transition table, such as byte classes
+    /// or premultiplied state identifiers, are handled by this method.
+    /// Callers should treat state identifiers as opaque values that are only
+    /// meaningful to the DFA that produced them.
+    ///
+    /// # Example
+    ///
+    /// This example shows how to write a custom search loop that reports
+    /// whether the DFA is in a match state after every byte of input. It is
+    /// equivalent to the inner loop used by `is_match`, except it does not
+    /// stop at the first match.
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = DenseDFA::new("[0-9]{2}")?;
+    /// let mut state = dfa.start_state();
+    /// let mut matched = vec![];
+    /// for &b in b"a12".iter() {
+    ///     state = dfa.next_state(state, b);
+    ///     if dfa.is_dead_state(state) {
+    ///         break;
+    ///     }
+    ///     matched.push(dfa.is_match_state(state));
+    /// }
+    /// assert_eq!(matched, vec![false, false, true]);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
     fn next_state(&self, current: Self::ID, input: u8) -> Self::ID;
 
     /// Like `next_state`, but its implementation may look up the next state
@@ -211,12 +418,76 @@ pub trait DFA {
         if self.is_anchored() && start > 0 {
             return false;
         }
+        if self.requires_full_match() {
+            return full_match(self, &bytes[start..], false);
+        }
 
         let mut state = self.start_state();
         if self.is_match_or_dead_state(state) {
             return self.is_match_state(state);
         }
-        for &b in bytes[start..].iter() {
+        match run_to_match_or_dead(self, &mut state, bytes, start) {
+            None => false,
+            Some(_) => self.is_match_state(state),
+        }
+    }
+
+    /// Returns the same as `is_match`, but begins the search in the given
+    /// state instead of this DFA's start state.
+    ///
+    /// This makes it possible to resume a search that was suspended at a
+    /// known state, or to measure the cost of the matching loop without the
+    /// cost of computing a start state. Calling this with `start_state()`
+    /// returns precisely the same result as `is_match`.
+    ///
+    /// Note that an anchored DFA is not treated specially by this routine,
+    /// since there is no notion of a starting offset.
+    ///
+    /// # Panics
+    ///
+    /// This may panic if `state` is not a valid state identifier for this
+    /// DFA. It will never cause undefined behavior.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = DenseDFA::new("foo[0-9]+bar")?;
+    /// let start = dfa.start_state();
+    /// assert!(dfa.is_match_from(b"foo123bar", start));
+    ///
+    /// // Resume a search after feeding a prefix of the input manually.
+    /// let mut state = start;
+    /// for &b in b"foo12".iter() {
+    ///     state = dfa.next_state(state, b);
+    /// }
+    /// assert!(dfa.is_match_from(b"3bar", state));
+    /// assert!(!dfa.is_match_from(b"bar", start));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[inline]
+    fn is_match_from(&self, bytes: &[u8], state: Self::ID) -> bool {
+        if self.requires_full_match() {
+            return full_match_from(self, state, bytes.iter());
+        }
+        let mut state = state;
+        if self.is_match_or_dead_state(state) {
+            return self.is_match_state(state);
+        }
+        let (first, rest) = match bytes.split_first() {
+            None => return false,
+            Some((&first, rest)) => (first, rest),
+        };
+        // The caller's state hasn't been validated yet, so the first
+        // transition uses bounds checks. Every state after that was produced
+        // by this DFA and is therefore valid.
+        state = self.next_state(state, first);
+        if self.is_match_or_dead_state(state) {
+            return self.is_match_state(state);
+        }
+        for &b in rest.iter() {
             state = unsafe { self.next_state_unchecked(state, b) };
             if self.is_match_or_dead_state(state) {
                 return self.is_match_state(state);
@@ -236,22 +507,21 @@ pub trait DFA {
         if self.is_anchored() && start > 0 {
             return None;
         }
+        if self.requires_full_match() {
+            let yes = full_match(self, &bytes[start..], false);
+            return if yes { Some(bytes.len()) } else { None };
+        }
 
         let mut state = self.start_state();
         if self.is_match_or_dead_state(state) {
             return if self.is_dead_state(state) { None } else { Some(start) };
         }
-        for (i, &b) in bytes[start..].iter().enumerate() {
-            state = unsafe { self.next_state_unchecked(state, b) };
-            if self.is_match_or_dead_state(state) {
-                return if self.is_dead_state(state) {
-                    None
-                } else {
-                    Some(start + i + 1)
-                };
-            }
+        let end = run_to_match_or_dead(self, &mut state, bytes, start)?;
+        if self.is_dead_state(state) {
+            None
+        } else {
+            Some(end)
         }
-        None
     }
 
     /// Returns the same as `find`, but starts the search at the given
@@ -260,11 +530,33 @@ pub trait DFA {
     /// The significance of the starting point is that it takes the surrounding
     /// context into consideration. For example, if the DFA is anchored, then
     /// a match can only occur when `start == 0`.
+    ///
+    /// The offset returned is always relative to the beginning of `bytes`,
+    /// not relative to `start`. This makes it possible to resume a search
+    /// without re-slicing the input.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = DenseDFA::new("foo[0-9]+")?;
+    /// let haystack = b"foo1 foo123";
+    /// assert_eq!(Some(4), dfa.find_at(haystack, 0));
+    /// assert_eq!(Some(11), dfa.find_at(haystack, 4));
+    /// assert_eq!(None, dfa.find_at(haystack, 11));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
     #[inline]
     fn find_at(&self, bytes: &[u8], start: usize) -> Option<usize> {
         if self.is_anchored() && start > 0 {
             return None;
         }
+        if self.requires_full_match() {
+            let yes = full_match(self, &bytes[start..], false);
+            return if yes { Some(bytes.len()) } else { None };
+        }
 
         let mut state = self.start_state();
         let mut last_match = if self.is_dead_state(state) {
@@ -274,14 +566,14 @@ pub trait DFA {
         } else {
             None
         };
-        for (i, &b) in bytes[start..].iter().enumerate() {
-            state = unsafe { self.next_state_unchecked(state, b) };
-            if self.is_match_or_dead_state(state) {
-                if self.is_dead_state(state) {
-                    return last_match;
-                }
-                last_match = Some(start + i + 1);
+        let mut at = start;
+        while let Some(end) = run_to_match_or_dead(self, &mut state, bytes, at)
+        {
+            if self.is_dead_state(state) {
+                return last_match;
             }
+            last_match = Some(end);
+            at = end;
         }
         last_match
     }
@@ -297,6 +589,10 @@ pub trait DFA {
         if self.is_anchored() && start < bytes.len() {
             return None;
         }
+        if self.requires_full_match() {
+            let yes = full_match(self, &bytes[..start], true);
+            return if yes { Some(0) } else { None };
+        }
 
         let mut state = self.start_state();
         let mut last_match = if self.is_dead_state(state) {
@@ -317,47 +613,1263 @@ pub trait DFA {
         }
         last_match
     }
-}
-
-impl<'a, T: DFA> DFA for &'a T {
-    type ID = T::ID;
 
+    /// Returns the same as `is_match`, but searches a string.
+    ///
+    /// This is equivalent to `is_match(s.as_bytes())`. It exists so that
+    /// code searching text can say so, and so that a faster path specific to
+    /// valid UTF-8 can be added later without changing callers.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = DenseDFA::new(r"\p{Greek}+")?;
+    /// assert!(dfa.is_match_str("alpha is α"));
+    /// assert!(!dfa.is_match_str("alpha is a"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
     #[inline]
-    fn start_state(&self) -> Self::ID {
-        (**self).start_state()
+    fn is_match_str(&self, s: &str) -> bool {
+        self.is_match(s.as_bytes())
     }
 
+    /// Returns the same as `find`, but searches a string.
+    ///
+    /// When the DFA was built with
+    /// [`allow_invalid_utf8`](dense/struct.Builder.html#method.allow_invalid_utf8)
+    /// disabled (the default), the offset returned is guaranteed to fall on
+    /// a character boundary of `s`, and so can always be used to slice it.
+    /// This holds because such a DFA only matches complete UTF-8 encodings
+    /// of codepoints, and an unanchored search only starts matching at the
+    /// beginning of an encoding.
+    ///
+    /// If invalid UTF-8 is allowed, then a match may end in the middle of a
+    /// character. Use [`find_utf8`](trait.DFA.html#method.find_utf8) to
+    /// only report matches that end on a boundary regardless of how the DFA
+    /// was built.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = DenseDFA::new(r"\p{Greek}+")?;
+    /// let s = "alpha is αβγ!";
+    /// let end = dfa.find_str(s).unwrap();
+    /// assert_eq!("alpha is αβγ", &s[..end]);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
     #[inline]
-    fn is_match_state(&self, id: Self::ID) -> bool {
-        (**self).is_match_state(id)
+    fn find_str(&self, s: &str) -> Option<usize> {
+        self.find(s.as_bytes())
     }
 
+    /// Returns the same as `find`, but with `base` added to the offset
+    /// returned.
+    ///
+    /// This is useful when `bytes` is a window into some larger input (for
+    /// example, a chunk of a stream) that begins at offset `base`, since the
+    /// offset returned is then relative to the beginning of the larger
+    /// input. When there is no match, `None` is returned regardless of
+    /// `base`.
+    ///
+    /// Note that this is not the same as
+    /// [`find_at`](trait.DFA.html#method.find_at): the search still treats
+    /// the beginning of `bytes` as the beginning of the input. In
+    /// particular, an anchored DFA can match at the beginning of `bytes`
+    /// even when `base` is not zero.
+    ///
+    /// # Panics
+    ///
+    /// This panics if adding `base` to the end of a match overflows `usize`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = DenseDFA::new("foo[0-9]+")?;
+    /// let stream = b"xxxxxxxx foo12 foo3";
+    /// let window = &stream[8..];
+    /// assert_eq!(Some(14), dfa.find_with_base(window, 8));
+    /// assert_eq!(None, dfa.find_with_base(b"bar", 8));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
     #[inline]
-    fn is_match_or_dead_state(&self, id: Self::ID) -> bool {
-        (**self).is_match_or_dead_state(id)
+    fn find_with_base(&self, bytes: &[u8], base: usize) -> Option<usize> {
+        self.find(bytes).map(|end| {
+            base.checked_add(end).expect("match offset overflows usize")
+        })
     }
 
+    /// Returns the same as `find`, but only reports match offsets that fall
+    /// on a UTF-8 character boundary in `s`.
+    ///
+    /// When a DFA is built with the default configuration (where
+    /// [`allow_invalid_utf8`](dense/struct.Builder.html#method.allow_invalid_utf8)
+    /// is disabled), every match of a non-empty pattern already ends on a
+    /// character boundary, so this returns the same as `find`. When invalid
+    /// UTF-8 is permitted, a pattern like `(?-u:.)` may match a single byte
+    /// of a multi-byte encoding. Such match positions are skipped by this
+    /// routine: the offset returned is the end of the longest match (subject
+    /// to leftmost-first semantics) that ends on a character boundary, or
+    /// `None` if there is no such match. Consequently, the offset returned
+    /// is always safe to use when slicing `s`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new()
+    ///     .allow_invalid_utf8(true)
+    ///     .build("(?-u:.)+")?;
+    /// // The shortest match ends after the first byte of `β`, while
+    /// // `find_utf8` only reports the end of the encoding.
+    /// assert_eq!(Some(1), dfa.shortest_match("β".as_bytes()));
+    /// assert_eq!(Some(2), dfa.find_utf8("β"));
+    ///
+    /// let dfa = dense::Builder::new()
+    ///     .allow_invalid_utf8(true)
+    ///     .build("(?-u:.)")?;
+    /// assert_eq!(Some(1), dfa.find("β".as_bytes()));
+    /// assert_eq!(None, dfa.find_utf8("β"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
     #[inline]
-    fn is_dead_state(&self, id: Self::ID) -> bool {
-        (**self).is_dead_state(id)
+    fn find_utf8(&self, s: &str) -> Option<usize> {
+        let bytes = s.as_bytes();
+        let end = self.find_at(bytes, 0)?;
+        if s.is_char_boundary(end) {
+            return Some(end);
+        }
+        // The longest match splits a character, so walk the DFA once more
+        // up to `end`, remembering the last match that ends on a character
+        // boundary. This is a single pass, since every match ending on a
+        // boundary ends before `end`.
+        let mut state = self.start_state();
+        let mut last_match =
+            if self.is_match_state(state) { Some(0) } else { None };
+        for (i, &b) in bytes[..end].iter().enumerate() {
+            state = unsafe { self.next_state_unchecked(state, b) };
+            if self.is_match_or_dead_state(state) {
+                if self.is_dead_state(state) {
+                    return last_match;
+                }
+                if s.is_char_boundary(i + 1) {
+                    last_match = Some(i + 1);
+                }
+            }
+        }
+        last_match
     }
 
+    /// Returns the same as `rfind`, but only reports match offsets that fall
+    /// on a UTF-8 character boundary in `s`.
+    ///
+    /// This is the reverse counterpart to `find_utf8`, and is typically used
+    /// with a DFA built with the
+    /// [`dense::Builder::reverse`](dense/struct.Builder.html#method.reverse)
+    /// configuration knob to find the start of a match. When invalid UTF-8
+    /// is permitted, `rfind` may report a start offset in the middle of a
+    /// character's encoding, and slicing `s` at such an offset panics. This
+    /// routine instead returns the start of the longest reverse match that
+    /// begins on a character boundary, or `None` if there is no such match.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new()
+    ///     .reverse(true)
+    ///     .allow_invalid_utf8(true)
+    ///     .build("(?-u:.)")?;
+    /// assert_eq!(Some(1), dfa.rfind("β".as_bytes()));
+    /// assert_eq!(None, dfa.rfind_utf8("β"));
+    /// assert_eq!(Some(2), dfa.rfind_utf8("βa"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
     #[inline]
-    fn is_anchored(&self) -> bool {
-        (**self).is_anchored()
+    fn rfind_utf8(&self, s: &str) -> Option<usize> {
+        let bytes = s.as_bytes();
+        let start = self.rfind_at(bytes, bytes.len())?;
+        if s.is_char_boundary(start) {
+            return Some(start);
+        }
+        // This mirrors `find_utf8`: walk back from the end once more, down
+        // to `start`, remembering the last match that starts on a boundary.
+        let mut state = self.start_state();
+        let mut last_match =
+            if self.is_match_state(state) { Some(bytes.len()) } else { None };
+        for i in (start..bytes.len()).rev() {
+            state = unsafe { self.next_state_unchecked(state, bytes[i]) };
+            if self.is_match_or_dead_state(state) {
+                if self.is_dead_state(state) {
+                    return last_match;
+                }
+                if s.is_char_boundary(i) {
+                    last_match = Some(i);
+                }
+            }
+        }
+        last_match
     }
 
+    /// Returns the same as `find`, but searches a sequence of UTF-16 code
+    /// units instead of bytes.
+    ///
+    /// This DFA still operates on bytes. Each character decoded from `units`
+    /// is encoded as UTF-8 on the fly and fed to the DFA, so no intermediate
+    /// buffer is allocated and the same DFA can search both UTF-8 and UTF-16
+    /// text. Unpaired surrogates are searched as if they were
+    /// `U+FFFD REPLACEMENT CHARACTER`.
+    ///
+    /// The offset returned is measured in code units, i.e., it is an index
+    /// into `units`, and the match is `units[..end]` for some start. As with
+    /// `find_utf8`, a match is only reported if it ends on a character
+    /// boundary, which means a surrogate pair is never split. This only makes
+    /// a difference for DFAs that can match part of a character's UTF-8
+    /// encoding, such as those built with
+    /// [`allow_invalid_utf8`](dense/struct.Builder.html#method.allow_invalid_utf8)
+    /// enabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = DenseDFA::new("δ+x")?;
+    /// // `💩` is encoded as a surrogate pair, i.e., two code units.
+    /// let units: Vec<u16> = "💩δδx".encode_utf16().collect();
+    /// assert_eq!(Some(5), dfa.find_utf16(&units));
+    /// assert_eq!(Some(9), dfa.find("💩δδx".as_bytes()));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
     #[inline]
-    fn next_state(&self, current: Self::ID, input: u8) -> Self::ID {
-        (**self).next_state(current, input)
+    fn find_utf16(&self, units: &[u16]) -> Option<usize> {
+        let mut state = self.start_state();
+        let mut last_match = if self.is_dead_state(state) {
+            return None;
+        } else if self.is_match_state(state) {
+            Some(0)
+        } else {
+            None
+        };
+        let mut at = 0;
+        let mut buf = [0; 4];
+        'search: for result in char::decode_utf16(units.iter().cloned()) {
+            // An unpaired surrogate always occupies exactly one code unit, as
+            // does its replacement.
+            let ch = result.unwrap_or(char::REPLACEMENT_CHARACTER);
+            at += ch.len_utf16();
+            for &b in ch.encode_utf8(&mut buf).as_bytes() {
+                state = unsafe { self.next_state_unchecked(state, b) };
+                if self.is_dead_state(state) {
+                    break 'search;
+                }
+            }
+            if self.is_match_state(state) {
+                last_match = Some(at);
+            }
+        }
+        if self.requires_full_match() {
+            // The last match ends at the end of the input if and only if the
+            // DFA consumed all of it and ended in a match state.
+            last_match.filter(|&end| end == units.len())
+        } else {
+            last_match
+        }
     }
 
-    #[inline]
-    unsafe fn next_state_unchecked(
-        &self,
-        current: Self::ID,
-        input: u8,
-    ) -> Self::ID {
-        (**self).next_state_unchecked(current, input)
+    /// Returns the same as `find_at`, but also reports why the search failed
+    /// when no match is found.
+    ///
+    /// A search without a match ends in one of two ways. Either the DFA
+    /// entered its dead state, in which case no amount of additional input
+    /// can produce a match, or it read all
+    /// of the input without doing so, in which case more input could still
+    /// produce a match. Callers processing a stream can use this to stop
+    /// early, or to decide whether to wait for more input.
+    ///
+    /// The offset in `NoMatchDead` is the position at which the DFA was in
+    /// its dead state. That is, the dead state was entered after reading the
+    /// byte just before it. If the start state itself is dead (or the DFA is
+    /// anchored and `start > 0`), then this is `start`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA, SearchResult};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new()
+    ///     .anchored(true)
+    ///     .build("foo[0-9]+")?;
+    /// assert_eq!(SearchResult::Match(5), dfa.find_or_dead(b"foo12x", 0));
+    /// assert_eq!(SearchResult::NoMatchDead(2), dfa.find_or_dead(b"fxo1", 0));
+    /// assert_eq!(SearchResult::NoMatchExhausted, dfa.find_or_dead(b"foo", 0));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[inline]
+    fn find_or_dead(&self, bytes: &[u8], start: usize) -> SearchResult {
+        if self.is_anchored() && start > 0 {
+            return SearchResult::NoMatchDead(start);
+        }
+        if self.requires_full_match() {
+            // Only the state after the last byte matters, but the search can
+            // still stop as soon as the DFA dies.
+            let mut state = self.start_state();
+            for (i, &b) in bytes[start..].iter().enumerate() {
+                if self.is_dead_state(state) {
+                    return SearchResult::NoMatchDead(start + i);
+                }
+                state = self.next_state(state, b);
+            }
+            return if self.is_match_state(state) {
+                SearchResult::Match(bytes.len())
+            } else if self.is_dead_state(state) {
+                SearchResult::NoMatchDead(bytes.len())
+            } else {
+                SearchResult::NoMatchExhausted
+            };
+        }
+
+        let mut state = self.start_state();
+        let mut last_match = if self.is_dead_state(state) {
+            return SearchResult::NoMatchDead(start);
+        } else if self.is_match_state(state) {
+            Some(start)
+        } else {
+            None
+        };
+        for (i, &b) in bytes[start..].iter().enumerate() {
+            state = unsafe { self.next_state_unchecked(state, b) };
+            if self.is_match_or_dead_state(state) {
+                if self.is_dead_state(state) {
+                    return match last_match {
+                        None => SearchResult::NoMatchDead(start + i + 1),
+                        Some(end) => SearchResult::Match(end),
+                    };
+                }
+                last_match = Some(start + i + 1);
+            }
+        }
+        match last_match {
+            None => SearchResult::NoMatchExhausted,
+            Some(end) => SearchResult::Match(end),
+        }
+    }
+
+    /// Returns the end of the match found by this DFA alongside the end of
+    /// the match found by `longest`, which is meant to be a DFA for the same
+    /// pattern compiled with leftmost-longest semantics.
+    ///
+    /// This is a diagnostic for checking whether adopting POSIX-style
+    /// leftmost-longest semantics would change the results of a search. Run
+    /// it over a corpus, and any input for which the two offsets differ is
+    /// one where the semantics diverge. For example, `Sam|Samwise` reports
+    /// `Sam` under leftmost-first semantics but `Samwise` under
+    /// leftmost-longest semantics.
+    ///
+    /// The second DFA should be built with
+    /// [`longest_match`](dense/struct.Builder.html#method.longest_match)
+    /// enabled. Since leftmost-longest DFAs must currently be anchored (see
+    /// that option for why), this DFA should be anchored too, or else the two
+    /// searches disagree about where a match may start.
+    ///
+    /// This makes two full passes over `bytes`, one with each DFA, so it
+    /// costs about as much as calling `find` on each of them. Neither DFA
+    /// can be used to speed up the other's search.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let pattern = "Sam|Samwise";
+    /// let first = dense::Builder::new().anchored(true).build(pattern)?;
+    /// let longest = dense::Builder::new()
+    ///     .anchored(true)
+    ///     .longest_match(true)
+    ///     .build(pattern)?;
+    /// assert_eq!((Some(3), Some(7)), first.find_both(&longest, b"Samwise"));
+    /// assert_eq!((Some(3), Some(3)), first.find_both(&longest, b"Sam I am"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[inline]
+    fn find_both<L: DFA>(
+        &self,
+        longest: &L,
+        bytes: &[u8],
+    ) -> (Option<usize>, Option<usize>)
+    where
+        Self: Sized,
+    {
+        (self.find(bytes), longest.find(bytes))
+    }
+
+    /// Returns true if and only if this DFA matches all of `bytes`.
+    ///
+    /// This walks the DFA over every byte of the input and reports whether
+    /// it ends in a match state, which is what checking a pattern against an
+    /// entire input (as if it were wrapped in `^...$`) requires. Unlike
+    /// `is_match`, it never stops at the first match state, although it
+    /// does stop early once the DFA enters its dead state.
+    ///
+    /// The answer is only meaningful for anchored DFAs using leftmost-longest
+    /// semantics, such as those built with
+    /// [`full_match`](dense/struct.Builder.html#method.full_match) enabled.
+    /// Leftmost-longest semantics ensure that determinization never drops a
+    /// lower priority alternative that could still reach the end of the
+    /// input. For example, a leftmost-first DFA for `a|ab` stops in its dead
+    /// state on `ab`. For DFAs built with `full_match`, this is the same
+    /// check that `is_match` performs.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().full_match(true).build(r"[0-9]+")?;
+    /// assert!(dfa.is_full_match(b"123"));
+    /// assert!(!dfa.is_full_match(b"123a"));
+    /// assert!(!dfa.is_full_match(b""));
+    ///
+    /// let dfa = dense::Builder::new()
+    ///     .anchored(true)
+    ///     .longest_match(true)
+    ///     .build(r"[0-9]+")?;
+    /// assert!(!dfa.is_full_match(b"123a"));
+    /// // is_match succeeds as soon as a prefix matches.
+    /// assert!(dfa.is_match(b"123a"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[inline]
+    fn is_full_match(&self, bytes: &[u8]) -> bool {
+        let mut state = self.start_state();
+        for &b in bytes {
+            if self.is_dead_state(state) {
+                return false;
+            }
+            state = self.next_state(state, b);
+        }
+        self.is_match_state(state)
+    }
+
+    /// Returns the same as `find`, but gives up after reading `limit` bytes.
+    ///
+    /// This bounds the work done by a single search, which protects latency
+    /// sensitive code from inputs that force a long scan, such as a huge
+    /// haystack in which a required suffix never appears. If the DFA
+    /// reaches a decision within the first `limit` bytes, by entering its
+    /// dead state or by reading the entire input, then the result is the
+    /// same as `find`. Otherwise, `SearchOutcome::Incomplete` is returned.
+    ///
+    /// Note that a search that has already seen a match can still be
+    /// incomplete: under leftmost-first semantics, the match may extend past
+    /// the limit, so its end isn't known until the DFA dies or the input
+    /// ends. Callers that are happy with any match, not necessarily the one
+    /// `find` would report, can instead run `shortest_match` on
+    /// `&bytes[..limit]`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA, SearchOutcome};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = DenseDFA::new("foo[0-9]+")?;
+    /// let haystack = b"xxxxfoo123 foo4";
+    /// assert_eq!(SearchOutcome::Match(10), dfa.find_bounded(haystack, 11));
+    /// assert_eq!(SearchOutcome::Incomplete, dfa.find_bounded(haystack, 9));
+    /// assert_eq!(SearchOutcome::Incomplete, dfa.find_bounded(haystack, 3));
+    /// assert_eq!(SearchOutcome::NoMatch, dfa.find_bounded(b"xxxx", 4));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[inline]
+    fn find_bounded(&self, bytes: &[u8], limit: usize) -> SearchOutcome {
+        let truncated = bytes.len() > limit;
+        let bytes = if truncated { &bytes[..limit] } else { bytes };
+        let decided = |last_match: Option<usize>| match last_match {
+            None => SearchOutcome::NoMatch,
+            Some(end) => SearchOutcome::Match(end),
+        };
+
+        if self.requires_full_match() {
+            let mut state = self.start_state();
+            for &b in bytes {
+                if self.is_dead_state(state) {
+                    return SearchOutcome::NoMatch;
+                }
+                state = self.next_state(state, b);
+            }
+            // If the DFA is still alive, then the rest of the input decides.
+            return if self.is_dead_state(state) {
+                SearchOutcome::NoMatch
+            } else if truncated {
+                SearchOutcome::Incomplete
+            } else if self.is_match_state(state) {
+                SearchOutcome::Match(bytes.len())
+            } else {
+                SearchOutcome::NoMatch
+            };
+        }
+
+        let mut state = self.start_state();
+        let mut last_match = if self.is_dead_state(state) {
+            return SearchOutcome::NoMatch;
+        } else if self.is_match_state(state) {
+            Some(0)
+        } else {
+            None
+        };
+        let mut at = 0;
+        while let Some(end) = run_to_match_or_dead(self, &mut state, bytes, at)
+        {
+            if self.is_dead_state(state) {
+                return decided(last_match);
+            }
+            last_match = Some(end);
+            at = end;
+        }
+        if truncated {
+            SearchOutcome::Incomplete
+        } else {
+            decided(last_match)
+        }
+    }
+
+    /// Returns an iterator that runs `find` on each of the given inputs, in
+    /// order.
+    ///
+    /// This is meant for running one DFA over a large number of small,
+    /// independent inputs, such as the lines of a log file. Each search
+    /// starts afresh from the start state. The iterator is lazy, so inputs
+    /// are only searched as results are consumed.
+    ///
+    /// Currently, this is equivalent to calling `find` in a loop. Searching
+    /// a batch through this routine permits optimizations that span inputs
+    /// (such as prefetching the start of the next input) to be added later
+    /// without any changes by callers.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = DenseDFA::new("[0-9]+ms")?;
+    /// let lines: &[&[u8]] = &[b"ok in 12ms", b"timeout", b"took 350ms"];
+    /// let ends: Vec<Option<usize>> =
+    ///     dfa.find_batch(lines.iter().cloned()).collect();
+    /// assert_eq!(vec![Some(10), None, Some(10)], ends);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[inline]
+    fn find_batch<'a, I>(&self, inputs: I) -> FindBatch<'_, Self, I::IntoIter>
+    where
+        Self: Sized,
+        I: IntoIterator<Item = &'a [u8]>,
+    {
+        FindBatch { dfa: self, inputs: inputs.into_iter() }
+    }
+
+    /// Returns the same as `is_match`, but searches the logical
+    /// concatenation of the given chunks without copying them into a single
+    /// buffer.
+    ///
+    /// This is useful when the haystack is split across multiple buffers,
+    /// such as those filled by a vectored read. The DFA's state is carried
+    /// from the end of one chunk to the beginning of the next, so a match
+    /// may span any number of chunk boundaries. Empty chunks are permitted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = DenseDFA::new("foo[0-9]+bar")?;
+    /// assert!(dfa.is_match_chunks(&[b"xfo", b"o12", b"", b"3bar"]));
+    /// assert!(!dfa.is_match_chunks(&[b"foo", b"bar"]));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[inline]
+    fn is_match_chunks(&self, chunks: &[&[u8]]) -> bool {
+        if self.requires_full_match() {
+            let bytes = chunks.iter().flat_map(|chunk| chunk.iter());
+            return full_match_from(self, self.start_state(), bytes);
+        }
+        let mut state = self.start_state();
+        if self.is_match_or_dead_state(state) {
+            return self.is_match_state(state);
+        }
+        for chunk in chunks {
+            for &b in chunk.iter() {
+                state = unsafe { self.next_state_unchecked(state, b) };
+                if self.is_match_or_dead_state(state) {
+                    return self.is_match_state(state);
+                }
+            }
+        }
+        false
+    }
+
+    /// Returns the same as `find`, but searches the logical concatenation of
+    /// the given chunks without copying them into a single buffer.
+    ///
+    /// The offset returned is relative to the beginning of the first chunk,
+    /// as if all of the chunks had been concatenated together. That is, a
+    /// match ending at offset `i` in the `n`th chunk is reported as `i` plus
+    /// the sum of the lengths of all preceding chunks.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = DenseDFA::new("foo[0-9]+")?;
+    /// assert_eq!(Some(7), dfa.find_chunks(&[b"xfo", b"o12", b"3bar"]));
+    /// assert_eq!(None, dfa.find_chunks(&[b"fo", b"bar"]));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[inline]
+    fn find_chunks(&self, chunks: &[&[u8]]) -> Option<usize> {
+        if self.requires_full_match() {
+            let bytes = chunks.iter().flat_map(|chunk| chunk.iter());
+            let yes = full_match_from(self, self.start_state(), bytes);
+            let len = chunks.iter().map(|chunk| chunk.len()).sum();
+            return if yes { Some(len) } else { None };
+        }
+        let mut state = self.start_state();
+        let mut last_match = if self.is_dead_state(state) {
+            return None;
+        } else if self.is_match_state(state) {
+            Some(0)
+        } else {
+            None
+        };
+        let mut offset = 0;
+        for chunk in chunks {
+            for (i, &b) in chunk.iter().enumerate() {
+                state = unsafe { self.next_state_unchecked(state, b) };
+                if self.is_match_or_dead_state(state) {
+                    if self.is_dead_state(state) {
+                        return last_match;
+                    }
+                    last_match = Some(offset + i + 1);
+                }
+            }
+            offset += chunk.len();
+        }
+        last_match
+    }
+
+    /// Returns the states this DFA visits while searching the given bytes,
+    /// for debugging.
+    ///
+    /// Each element is the offset of a byte in `bytes` paired with the state
+    /// the DFA transitioned into after reading it. The start state is not
+    /// included, since it is always `start_state()`.
+    ///
+    /// By default, the trace follows the rules of `find`: it continues
+    /// past match states and ends with the transition into the dead state,
+    /// if there is one. When `keep_going` is true, every byte is traced
+    /// regardless, which will repeat the dead state once it is entered.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().anchored(true).build("ab+")?;
+    ///
+    /// let trace = dfa.trace(b"abbxy", false);
+    /// let offsets: Vec<usize> = trace.iter().map(|&(i, _)| i).collect();
+    /// assert_eq!(vec![0, 1, 2, 3], offsets);
+    /// assert!(!dfa.is_match_state(trace[0].1));
+    /// assert!(dfa.is_match_state(trace[1].1));
+    /// assert_eq!(trace[1].1, trace[2].1);
+    /// assert!(dfa.is_dead_state(trace[3].1));
+    ///
+    /// assert_eq!(5, dfa.trace(b"abbxy", true).len());
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[cfg(feature = "std")]
+    fn trace(&self, bytes: &[u8], keep_going: bool) -> Vec<(usize, Self::ID)> {
+        let mut trace = vec![];
+        let mut state = self.start_state();
+        if self.is_dead_state(state) && !keep_going {
+            return trace;
+        }
+        for (i, &b) in bytes.iter().enumerate() {
+            state = self.next_state(state, b);
+            trace.push((i, state));
+            if self.is_dead_state(state) && !keep_going {
+                break;
+            }
+        }
+        trace
+    }
+
+    /// Returns whether this DFA is in a match state after reading each prefix
+    /// of the given bytes.
+    ///
+    /// The returned vector has `bytes.len() + 1` elements, where the element
+    /// at index `i` describes the state reached after reading `bytes[..i]`.
+    /// In particular, the first element describes the start state. Unlike
+    /// the search routines, this never stops early: once the dead state is
+    /// entered, every remaining element is `MatchStatus::Dead`.
+    ///
+    /// This is like [`trace`](trait.DFA.html#method.trace), but reports
+    /// acceptance instead of state identifiers, which is convenient for
+    /// showing how a match develops as input is typed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, MatchStatus, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().anchored(true).build("ab+")?;
+    ///
+    /// use regex_automata::MatchStatus::*;
+    /// assert_eq!(
+    ///     vec![NoMatch, NoMatch, Match, Match, Dead, Dead],
+    ///     dfa.simulate(b"abbxy"),
+    /// );
+    /// assert_eq!(vec![MatchStatus::NoMatch], dfa.simulate(b""));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[cfg(feature = "std")]
+    fn simulate(&self, bytes: &[u8]) -> Vec<MatchStatus> {
+        let status = |id| {
+            if self.is_dead_state(id) {
+                MatchStatus::Dead
+            } else if self.is_match_state(id) {
+                MatchStatus::Match
+            } else {
+                MatchStatus::NoMatch
+            }
+        };
+        let mut state = self.start_state();
+        let mut statuses = Vec::with_capacity(bytes.len() + 1);
+        statuses.push(status(state));
+        for &b in bytes {
+            state = self.next_state(state, b);
+            statuses.push(status(state));
+        }
+        statuses
+    }
+
+    /// Returns an iterator over every string of length at most `max_len`
+    /// whose walk through this DFA, beginning at its start state, ends in a
+    /// match state.
+    ///
+    /// Strings are yielded in lexicographic order by performing a bounded
+    /// depth first traversal of the DFA. Every byte is tried from each state,
+    /// so a DFA that groups bytes into equivalence classes has each of its
+    /// transitions expanded back into the concrete bytes of the class.
+    ///
+    /// Note that an unanchored DFA accepts every string that has a match as a
+    /// prefix of some suffix (since it behaves as if the pattern started with
+    /// `.*?`), so this is usually only interesting for anchored DFAs.
+    ///
+    /// # Explosion
+    ///
+    /// The number of strings yielded can grow exponentially with `max_len`.
+    /// For example, `[a-z]*` has 26<sup>n</sup> accepted strings of length
+    /// `n`, and an unanchored DFA may consider up to 256<sup>n</sup> strings.
+    /// Callers should keep `max_len` small or stop consuming the iterator
+    /// early.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = dense::Builder::new().anchored(true).build("a|bc?")?;
+    /// let strings: Vec<Vec<u8>> = dfa.iter_accepted(5).collect();
+    /// assert_eq!(strings, vec![
+    ///     b"a".to_vec(), b"b".to_vec(), b"bc".to_vec(),
+    /// ]);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[cfg(feature = "std")]
+    fn iter_accepted(&self, max_len: usize) -> AcceptedIter<'_, Self>
+    where
+        Self: Sized,
+    {
+        AcceptedIter {
+            dfa: self,
+            max_len,
+            started: false,
+            stack: vec![],
+            bytes: vec![],
+        }
+    }
+
+    /// Returns the same as `find`, except every occurrence of `wildcard` in
+    /// `bytes` may stand for any byte at all.
+    ///
+    /// This is useful for matching templates in which a sentinel byte marks
+    /// positions whose contents are unknown. The offset returned is the
+    /// greatest one that `find` would return for any way of replacing the
+    /// wildcards in `bytes` with concrete bytes, or `None` if no replacement
+    /// produces a match.
+    ///
+    /// Since a wildcard may lead to many different states, this search
+    /// simulates a set of DFA states rather than a single one. Each
+    /// wildcard replaces every state in the set with all of its successors,
+    /// and each other byte moves every state in the set along its
+    /// transition. Duplicate and dead states are removed after each step, so
+    /// the set shrinks back to a single state whenever its members converge
+    /// (or all but one die).
+    ///
+    /// # Performance
+    ///
+    /// This is much slower than `find`. Every wildcard costs 256 transitions
+    /// for each state in the set, and every other byte costs one transition
+    /// for each state in the set along with the cost of deduplicating it. In
+    /// the worst case, the set contains every state in the DFA, making the
+    /// search take `O(n * m)` time where `n` is the length of the input and
+    /// `m` is the number of DFA states. Inputs without wildcards are still
+    /// searched one state at a time, but without the optimizations used by
+    /// `find`, such as prefilters and acceleration. This routine also
+    /// allocates.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = DenseDFA::new("foo[0-9]+")?;
+    /// assert_eq!(Some(5), dfa.find_with_wildcard(b"f?o12", b'?'));
+    /// assert_eq!(Some(5), dfa.find_with_wildcard(b"foo??x", b'?'));
+    /// assert_eq!(None, dfa.find_with_wildcard(b"f?x12", b'?'));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[cfg(feature = "std")]
+    fn find_with_wildcard(&self, bytes: &[u8], wildcard: u8) -> Option<usize> {
+        let start = self.start_state();
+        let mut last_match =
+            if self.is_match_state(start) { Some(0) } else { None };
+        let mut states = vec![start];
+        let mut next = vec![];
+        for (i, &b) in bytes.iter().enumerate() {
+            next.clear();
+            for &state in &states {
+                if b == wildcard {
+                    next.extend(
+                        (0..256)
+                            .map(|input| self.next_state(state, input as u8)),
+                    );
+                } else {
+                    next.push(self.next_state(state, b));
+                }
+            }
+            next.sort();
+            next.dedup();
+            next.retain(|&id| !self.is_dead_state(id));
+            core::mem::swap(&mut states, &mut next);
+            if states.is_empty() {
+                break;
+            }
+            if states.iter().any(|&id| self.is_match_state(id)) {
+                last_match = Some(i + 1);
+            }
+        }
+        if self.requires_full_match() {
+            last_match.filter(|&end| end == bytes.len())
+        } else {
+            last_match
+        }
+    }
+
+    /// Run `find` on the given bytes `iters` times and return the total time
+    /// taken.
+    ///
+    /// This is a canonical way to measure this crate's search performance on
+    /// a corpus of one's own, e.g., to catch regressions when upgrading. The
+    /// input and every result pass through an optimization barrier, so the
+    /// compiler can neither hoist the search out of the loop nor discard it.
+    /// The measurement includes only the searches themselves.
+    ///
+    /// The barrier is implemented with a volatile read, which adds a small
+    /// constant cost to each iteration. This is negligible unless the input
+    /// is tiny.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{DFA, DenseDFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let dfa = DenseDFA::new("foo[0-9]+")?;
+    /// let haystack = "abc ".repeat(100) + "foo123";
+    /// let elapsed = dfa.bench_find(haystack.as_bytes(), 10);
+    /// println!("{:?} per search", elapsed / 10);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    #[cfg(feature = "std")]
+    #[inline(never)]
+    fn bench_find(&self, bytes: &[u8], iters: usize) -> std::time::Duration {
+        let start = std::time::Instant::now();
+        for _ in 0..iters {
+            black_box(self.find(black_box(bytes)));
+        }
+        start.elapsed()
+    }
+
+    /// Touch every page of this DFA's transition table so that it is resident
+    /// in memory before a search begins.
+    ///
+    /// This is useful when a DFA is loaded from a memory mapped file (e.g.,
+    /// via `DenseDFA::from_bytes`), where the first search would otherwise
+    /// pay for page faults as it visits new parts of the transition table.
+    /// Calling this before a latency sensitive search moves that cost up
+    /// front. Reads are performed sequentially, one byte per page.
+    ///
+    /// This never changes the behavior of a search. The default
+    /// implementation does nothing.
+    fn prefault(&self) {}
+
+    /// Hint that the transition out of `current` on `input` will be looked
+    /// up soon. This is only called by the search loop when the `prefetch`
+    /// feature is enabled. The default implementation does nothing.
+    #[cfg(feature = "prefetch")]
+    #[doc(hidden)]
+    #[inline(always)]
+    fn prefetch_transition(&self, _current: Self::ID, _input: u8) {}
+}
+
+/// An iterator over the results of searching many inputs with one DFA.
+///
+/// This is created by [`DFA::find_batch`](trait.DFA.html#method.find_batch).
+/// It yields the result of `find` for each input, in order.
+///
+/// `'d` is the lifetime of the DFA, `D` is its type and `I` is the type of
+/// the iterator over the inputs.
+#[derive(Clone, Debug)]
+pub struct FindBatch<'d, D: 'd, I> {
+    dfa: &'d D,
+    inputs: I,
+}
+
+impl<'a, 'd, D: DFA, I: Iterator<Item = &'a [u8]>> Iterator
+    for FindBatch<'d, D, I>
+{
+    type Item = Option<usize>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Option<usize>> {
+        self.inputs.next().map(|input| self.dfa.find(input))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inputs.size_hint()
+    }
+}
+
+/// An iterator over the strings accepted by a DFA, up to a length bound.
+///
+/// This iterator is created by
+/// [`DFA::iter_accepted`](trait.DFA.html#method.iter_accepted).
+///
+/// `'d` is the lifetime of the DFA being enumerated and `D` is its type.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct AcceptedIter<'d, D: 'd + DFA> {
+    dfa: &'d D,
+    max_len: usize,
+    started: bool,
+    /// The states on the current path, each paired with the next byte to
+    /// try from that state. A next byte of 256 means the state is exhausted.
+    stack: Vec<(D::ID, u16)>,
+    /// The bytes on the current path. There is always one fewer byte than
+    /// there are states on the stack.
+    bytes: Vec<u8>,
+}
+
+#[cfg(feature = "std")]
+impl<'d, D: DFA> Iterator for AcceptedIter<'d, D> {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Vec<u8>> {
+        if !self.started {
+            self.started = true;
+            let start = self.dfa.start_state();
+            if self.dfa.is_dead_state(start) {
+                return None;
+            }
+            self.stack.push((start, 0));
+            if self.dfa.is_match_state(start) {
+                return Some(vec![]);
+            }
+        }
+        loop {
+            let at_max_len = self.bytes.len() >= self.max_len;
+            let next_byte = match self.stack.last_mut() {
+                None => return None,
+                Some(top) => {
+                    if at_max_len || top.1 > 255 {
+                        None
+                    } else {
+                        top.1 += 1;
+                        Some((top.0, (top.1 - 1) as u8))
+                    }
+                }
+            };
+            let (id, byte) = match next_byte {
+                Some(next_byte) => next_byte,
+                None => {
+                    // Every byte from the current state has been tried (or
+                    // we can't go any deeper), so backtrack.
+                    self.stack.pop();
+                    self.bytes.pop();
+                    continue;
+                }
+            };
+            let next = self.dfa.next_state(id, byte);
+            if self.dfa.is_dead_state(next) {
+                continue;
+            }
+            self.bytes.push(byte);
+            self.stack.push((next, 0));
+            if self.dfa.is_match_state(next) {
+                return Some(self.bytes.clone());
+            }
+        }
+    }
+}
+
+/// Return the given value unchanged, while preventing the compiler from
+/// making any assumptions about it.
+///
+/// `std::hint::black_box` would be preferable, but it requires a newer Rust
+/// than this crate supports.
+#[cfg(feature = "std")]
+#[inline(always)]
+fn black_box<T>(value: T) -> T {
+    unsafe {
+        let copy = ptr::read_volatile(&value);
+        core::mem::forget(value);
+        copy
+    }
+}
+
+/// Advance the given DFA from `state` over `bytes[at..]`, stopping as soon as
+/// it enters a match or dead state.
+///
+/// If such a state is entered, then `state` is set to it and the offset
+/// immediately following the byte that led to it is returned. Otherwise,
+/// `state` is set to the state reached at the end of the input and `None` is
+/// returned. This is the inner loop shared by the forward search routines,
+/// which only differ in what they do once it stops.
+#[inline(always)]
+fn run_to_match_or_dead<D: DFA + ?Sized>(
+    dfa: &D,
+    state: &mut D::ID,
+    bytes: &[u8],
+    at: usize,
+) -> Option<usize> {
+    run_to_match_or_dead_skipping(dfa, state, bytes, at, |_, at| at)
+}
+
+/// Like `run_to_match_or_dead`, but before each transition, `skip` is given
+/// the current state and offset and returns the offset of the next byte to
+/// transition on, which may be `bytes.len()` to end the search.
+///
+/// This lets accelerated dense DFAs skip over bytes that leave their start
+/// state in place while sharing the same loop. When `skip` returns its
+/// offset unchanged, it compiles away.
+#[inline(always)]
+pub(crate) fn run_to_match_or_dead_skipping<D, F>(
+    dfa: &D,
+    state: &mut D::ID,
+    bytes: &[u8],
+    mut at: usize,
+    skip: F,
+) -> Option<usize>
+where
+    D: DFA + ?Sized,
+    F: Fn(D::ID, usize) -> usize,
+{
+    // Working on a local copy lets the state stay in a register.
+    let mut cur = *state;
+    loop {
+        at = skip(cur, at);
+        if at >= bytes.len() {
+            break;
+        }
+        cur = unsafe { dfa.next_state_unchecked(cur, bytes[at]) };
+        at += 1;
+        // Each transition can only be looked up once the previous one has
+        // been read, so the earliest a prefetch of the next transition can
+        // be issued is right here, immediately before the load it would be
+        // hiding. This is why prefetching is off by default: on a 12MB
+        // transition table, it made no measurable difference (see the
+        // `prefetch` benchmarks).
+        #[cfg(feature = "prefetch")]
+        {
+            if at < bytes.len() {
+                dfa.prefetch_transition(cur, bytes[at]);
+            }
+        }
+        if dfa.is_match_or_dead_state(cur) {
+            *state = cur;
+            return Some(at);
+        }
+    }
+    *state = cur;
+    None
+}
+
+/// Returns true if and only if walking the given DFA over all of `bytes`
+/// ends in a match state. The bytes are walked back to front when `reverse`
+/// is true.
+///
+/// This is how DFAs built with
+/// [`full_match`](dense/struct.Builder.html#method.full_match) enabled
+/// search, since a match must consume the entire haystack.
+#[inline]
+fn full_match<D: DFA + ?Sized>(dfa: &D, bytes: &[u8], reverse: bool) -> bool {
+    let start = dfa.start_state();
+    if reverse {
+        full_match_from(dfa, start, bytes.iter().rev())
+    } else {
+        full_match_from(dfa, start, bytes.iter())
+    }
+}
+
+/// Returns true if and only if walking the given DFA from `state` over all
+/// of `bytes` ends in a match state.
+#[inline]
+fn full_match_from<'a, D: DFA + ?Sized, I: Iterator<Item = &'a u8>>(
+    dfa: &D,
+    mut state: D::ID,
+    bytes: I,
+) -> bool {
+    for &b in bytes {
+        if dfa.is_dead_state(state) {
+            return false;
+        }
+        state = dfa.next_state(state, b);
+    }
+    dfa.is_match_state(state)
+}
+
+/// Read one byte from every page of the given memory. The reads are volatile
+/// so that the compiler cannot optimize them away.
+pub(crate) fn prefault_bytes(bytes: &[u8]) {
+    /// A conservative guess at the page size. Using a smaller stride than
+    /// the real page size only costs a few redundant reads.
+    const PAGE_SIZE: usize = 4096;
+
+    let ptr = bytes.as_ptr();
+    for offset in (0..bytes.len()).step_by(PAGE_SIZE) {
+        // This is safe because `offset` is in bounds of `bytes`, and any
+        // byte may be read as a `u8`.
+        unsafe {
+            ptr::read_volatile(ptr.add(offset));
+        }
+    }
+}
+
+impl<'a, T: DFA> DFA for &'a T {
+    type ID = T::ID;
+
+    #[inline]
+    fn start_state(&self) -> Self::ID {
+        (**self).start_state()
+    }
+
+    #[inline]
+    fn is_match_state(&self, id: Self::ID) -> bool {
+        (**self).is_match_state(id)
+    }
+
+    #[inline]
+    fn is_match_or_dead_state(&self, id: Self::ID) -> bool {
+        (**self).is_match_or_dead_state(id)
+    }
+
+    #[inline]
+    fn is_dead_state(&self, id: Self::ID) -> bool {
+        (**self).is_dead_state(id)
+    }
+
+    #[inline]
+    fn is_anchored(&self) -> bool {
+        (**self).is_anchored()
+    }
+
+    #[inline]
+    fn requires_full_match(&self) -> bool {
+        (**self).requires_full_match()
+    }
+
+    #[inline]
+    fn next_state(&self, current: Self::ID, input: u8) -> Self::ID {
+        (**self).next_state(current, input)
+    }
+
+    #[inline]
+    unsafe fn next_state_unchecked(
+        &self,
+        current: Self::ID,
+        input: u8,
+    ) -> Self::ID {
+        (**self).next_state_unchecked(current, input)
+    }
+
+    // The following are forwarded so that a reference to a DFA gets the
+    // same specialized search routines (and thus the same prefilters and
+    // acceleration) as the DFA itself.
+
+    #[inline]
+    fn is_match_at(&self, bytes: &[u8], start: usize) -> bool {
+        (**self).is_match_at(bytes, start)
+    }
+
+    #[inline]
+    fn shortest_match_at(&self, bytes: &[u8], start: usize) -> Option<usize> {
+        (**self).shortest_match_at(bytes, start)
+    }
+
+    #[inline]
+    fn find_at(&self, bytes: &[u8], start: usize) -> Option<usize> {
+        (**self).find_at(bytes, start)
+    }
+
+    #[inline]
+    fn rfind_at(&self, bytes: &[u8], start: usize) -> Option<usize> {
+        (**self).rfind_at(bytes, start)
+    }
+
+    #[inline]
+    fn prefault(&self) {
+        (**self).prefault()
+    }
+
+    #[cfg(feature = "prefetch")]
+    #[inline(always)]
+    fn prefetch_transition(&self, current: Self::ID, input: u8) {
+        (**self).prefetch_transition(current, input)
     }
 }
@@ -7,9 +7,16 @@ use regex_syntax;
 pub type Result<T> = result::Result<T, Error>;
 
 /// An error that occurred during the construction of a DFA.
+///
+/// The cause of an error can be inspected with
+/// [`kind`](struct.Error.html#method.kind). When the error was caused by a
+/// pattern that failed to parse, the parser's error is also available via
+/// `std::error::Error::source`.
 #[derive(Clone, Debug)]
 pub struct Error {
     kind: ErrorKind,
+    /// The parse error that caused a syntax error, if any.
+    syntax: Option<Box<regex_syntax::Error>>,
 }
 
 /// The kind of error that occurred.
@@ -18,6 +25,8 @@ pub enum ErrorKind {
     /// An error that occurred while parsing a regular expression. Note that
     /// this error may be printed over multiple lines, and is generally
     /// intended to be end user readable on its own.
+    ///
+    /// The underlying parse error is available as the error's `source`.
     Syntax(String),
     /// An error that occurred because an unsupported regex feature was used.
     /// The message string describes which unsupported feature was used.
@@ -28,6 +37,9 @@ pub enum ErrorKind {
     Unsupported(String),
     /// An error that occurred when attempting to serialize a DFA to bytes.
     Serialize(String),
+    /// An error that occurred when validating a deserialized DFA. The message
+    /// string describes which invariant of the DFA was violated.
+    Deserialize(String),
     /// An error that occurs when constructing a DFA would require the use of
     /// a state ID that overflows the chosen state ID representation. For
     /// example, if one is using `u8` for state IDs and builds a DFA with
@@ -45,12 +57,48 @@ pub enum ErrorKind {
     /// but doing so would overflow the chosen state ID representation.
     ///
     /// When `max == requested_max`, then the state ID would overflow `usize`.
+    /// Otherwise, `requested_max` is the largest premultiplied state ID that
+    /// the DFA needs, which can be used to pick a larger state ID
+    /// representation. For example, if `requested_max` is `300`, then `u16`
+    /// or larger is sufficient.
     PremultiplyOverflow {
         /// The maximum possible state id.
         max: usize,
         /// The maximum ID required by premultiplication.
         requested_max: usize,
     },
+    /// An error that occurs when compiling a regular expression would
+    /// require an NFA with more states than the configured limit. This is
+    /// reported before determinization begins.
+    NFATooBig {
+        /// The maximum number of NFA states permitted.
+        limit: usize,
+    },
+    /// An error that occurs when a DFA is relabeled with byte classes that
+    /// merge bytes the DFA distinguishes, i.e., classes that aren't a
+    /// refinement of the DFA's own.
+    IncompatibleByteClasses,
+    /// An error that occurs when determinizing an NFA would produce a DFA
+    /// with more states than the configured limit. This is reported as soon
+    /// as the limit is exceeded, so that no more time or memory is spent.
+    DeterminizeTooBig {
+        /// The maximum number of DFA states permitted.
+        limit: usize,
+    },
+    /// An error that occurs when the counted repetitions in a pattern, such
+    /// as `a{1000}`, expand to more copies than the configured limit. This
+    /// is reported right after parsing.
+    RepetitionTooBig {
+        /// The maximum number of copies permitted.
+        limit: usize,
+    },
+    /// Hints that destructuring should not be exhaustive.
+    ///
+    /// This enum may grow additional variants, so this makes sure clients
+    /// don't count on exhaustive matching. (Otherwise, adding a new variant
+    /// could break existing code.)
+    #[doc(hidden)]
+    __Nonexhaustive,
 }
 
 impl Error {
@@ -59,39 +107,71 @@ impl Error {
         &self.kind
     }
 
+    fn new(kind: ErrorKind) -> Error {
+        Error { kind, syntax: None }
+    }
+
     pub(crate) fn syntax(err: regex_syntax::Error) -> Error {
-        Error { kind: ErrorKind::Syntax(err.to_string()) }
+        Error {
+            kind: ErrorKind::Syntax(err.to_string()),
+            syntax: Some(Box::new(err)),
+        }
     }
 
     pub(crate) fn unsupported_anchor() -> Error {
         let msg = r"anchors such as ^, $, \A and \z are not supported";
-        Error { kind: ErrorKind::Unsupported(msg.to_string()) }
+        Error::new(ErrorKind::Unsupported(msg.to_string()))
     }
 
     pub(crate) fn unsupported_word() -> Error {
         let msg = r"word boundary assertions (\b and \B) are not supported";
-        Error { kind: ErrorKind::Unsupported(msg.to_string()) }
+        Error::new(ErrorKind::Unsupported(msg.to_string()))
     }
 
     pub(crate) fn unsupported_longest_match() -> Error {
         let msg = "unachored searches with longest match \
                    semantics are not supported";
-        Error { kind: ErrorKind::Unsupported(msg.to_string()) }
+        Error::new(ErrorKind::Unsupported(msg.to_string()))
+    }
+
+    pub(crate) fn unsupported_sparse_skip_bytes() -> Error {
+        let msg = "skip bytes are not supported by sparse DFAs";
+        Error::new(ErrorKind::Unsupported(msg.to_string()))
     }
 
     pub(crate) fn serialize(message: &str) -> Error {
-        Error { kind: ErrorKind::Serialize(message.to_string()) }
+        Error::new(ErrorKind::Serialize(message.to_string()))
+    }
+
+    pub(crate) fn deserialize(message: &str) -> Error {
+        Error::new(ErrorKind::Deserialize(message.to_string()))
     }
 
     pub(crate) fn state_id_overflow(max: usize) -> Error {
-        Error { kind: ErrorKind::StateIDOverflow { max } }
+        Error::new(ErrorKind::StateIDOverflow { max })
+    }
+
+    pub(crate) fn nfa_too_big(limit: usize) -> Error {
+        Error::new(ErrorKind::NFATooBig { limit })
+    }
+
+    pub(crate) fn determinize_too_big(limit: usize) -> Error {
+        Error::new(ErrorKind::DeterminizeTooBig { limit })
+    }
+
+    pub(crate) fn repetition_too_big(limit: usize) -> Error {
+        Error::new(ErrorKind::RepetitionTooBig { limit })
+    }
+
+    pub(crate) fn incompatible_byte_classes() -> Error {
+        Error::new(ErrorKind::IncompatibleByteClasses)
     }
 
     pub(crate) fn premultiply_overflow(
         max: usize,
         requested_max: usize,
     ) -> Error {
-        Error { kind: ErrorKind::PremultiplyOverflow { max, requested_max } }
+        Error::new(ErrorKind::PremultiplyOverflow { max, requested_max })
     }
 }
 
@@ -101,14 +181,26 @@ impl error::Error for Error {
             ErrorKind::Syntax(_) => "syntax error",
             ErrorKind::Unsupported(_) => "unsupported syntax",
             ErrorKind::Serialize(_) => "serialization error",
+            ErrorKind::Deserialize(_) => "invalid deserialized DFA",
             ErrorKind::StateIDOverflow { .. } => {
                 "state id representation too small"
             }
             ErrorKind::PremultiplyOverflow { .. } => {
                 "state id representation too small for premultiplication"
             }
+            ErrorKind::NFATooBig { .. } => "NFA exceeds size limit",
+            ErrorKind::IncompatibleByteClasses => "incompatible byte classes",
+            ErrorKind::DeterminizeTooBig { .. } => "DFA exceeds size limit",
+            ErrorKind::RepetitionTooBig { .. } => {
+                "counted repetitions exceed limit"
+            }
+            ErrorKind::__Nonexhaustive => unreachable!(),
         }
     }
+
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        self.syntax.as_ref().map(|err| &**err as &(dyn error::Error + 'static))
+    }
 }
 
 impl fmt::Display for Error {
@@ -119,6 +211,9 @@ impl fmt::Display for Error {
             ErrorKind::Serialize(ref msg) => {
                 write!(f, "DFA serialization error: {}", msg)
             }
+            ErrorKind::Deserialize(ref msg) => {
+                write!(f, "invalid deserialized DFA: {}", msg)
+            }
             ErrorKind::StateIDOverflow { max } => write!(
                 f,
                 "building the DFA failed because it required building \
@@ -140,11 +235,52 @@ impl fmt::Display for Error {
                         f,
                         "premultiplication of states requires the ability to \
                          represent at least a state ID of {}, but the chosen \
-                         representation only permits a maximum state ID of {}",
-                        requested_max, max,
+                         representation only permits a maximum state ID of {} \
+                         (a representation of {} or larger is required)",
+                        requested_max,
+                        max,
+                        smallest_repr(requested_max),
                     )
                 }
             }
+            ErrorKind::NFATooBig { limit } => write!(
+                f,
+                "compiling the regex requires an NFA with more than {} \
+                 states, which exceeds the configured limit",
+                limit,
+            ),
+            ErrorKind::IncompatibleByteClasses => write!(
+                f,
+                "the byte classes given put bytes that the DFA \
+                 distinguishes in the same class",
+            ),
+            ErrorKind::DeterminizeTooBig { limit } => write!(
+                f,
+                "determinizing the NFA requires a DFA with more than {} \
+                 states, which exceeds the configured limit",
+                limit,
+            ),
+            ErrorKind::RepetitionTooBig { limit } => write!(
+                f,
+                "the counted repetitions in the regex expand to more than \
+                 {} copies, which exceeds the configured limit",
+                limit,
+            ),
+            ErrorKind::__Nonexhaustive => unreachable!(),
         }
     }
 }
+
+/// Return the name of the smallest state ID representation provided by this
+/// crate that can represent the given state ID.
+fn smallest_repr(id: usize) -> &'static str {
+    if id <= 0xFF {
+        "u8"
+    } else if id <= 0xFFFF {
+        "u16"
+    } else if id as u64 <= 0xFFFF_FFFF {
+        "u32"
+    } else {
+        "u64"
+    }
+}
@@ -0,0 +1,347 @@
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+
+use dense;
+use error::{Error, Result};
+use sparse;
+
+/// The kind of DFA described by a [`DFAHeader`](struct.DFAHeader.html).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum DFAKind {
+    /// A dense DFA, as written by
+    /// [`DenseDFA::to_bytes_native_endian`](enum.DenseDFA.html#method.to_bytes_native_endian)
+    /// and related routines.
+    Dense,
+    /// A sparse DFA, as written by
+    /// [`SparseDFA::to_bytes_native_endian`](enum.SparseDFA.html#method.to_bytes_native_endian)
+    /// and related routines.
+    Sparse,
+}
+
+/// The metadata at the beginning of a serialized DFA.
+///
+/// Reading a header only looks at the first few hundred bytes of a
+/// serialized DFA, and never touches its transition table, so it takes
+/// constant time. This makes it possible to inspect a collection of
+/// serialized DFAs (for example, to pick the one with a particular state
+/// identifier size) before deserializing any of them.
+///
+/// Unlike deserialization, reading a header works regardless of the
+/// endianness the DFA was written with. Whether it matches the current
+/// platform is reported by
+/// [`is_native_endian`](struct.DFAHeader.html#method.is_native_endian).
+///
+/// # Example
+///
+/// ```
+/// use regex_automata::{DFAHeader, DFAKind, DenseDFA};
+///
+/// # fn example() -> Result<(), regex_automata::Error> {
+/// let dfa = DenseDFA::new("foo[0-9]+")?.to_u16()?;
+/// let bytes = dfa.to_bytes_native_endian()?;
+///
+/// let header = DFAHeader::from_bytes(&bytes)?;
+/// assert_eq!(DFAKind::Dense, header.kind());
+/// assert!(header.is_native_endian());
+/// assert_eq!(2, header.state_id_size());
+/// assert!(!header.is_anchored());
+/// assert_eq!(dfa.byte_classes().alphabet_len(), header.alphabet_len());
+/// # Ok(()) }; example().unwrap()
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DFAHeader {
+    kind: DFAKind,
+    big_endian: bool,
+    version: u16,
+    state_id_size: usize,
+    options: u16,
+    start_state: usize,
+    state_count: usize,
+    max_match_state: usize,
+    alphabet_len: usize,
+}
+
+impl DFAHeader {
+    /// Read the header at the beginning of a serialized dense or sparse DFA.
+    ///
+    /// An error is returned if the bytes don't start with the label of a
+    /// serialized DFA, if they're too short to contain a complete header, or
+    /// if the DFA was written with an unsupported format version.
+    pub fn from_bytes(buf: &[u8]) -> Result<DFAHeader> {
+        let (kind, rest) = if buf.starts_with(dense::LABEL) {
+            (DFAKind::Dense, &buf[dense::LABEL.len()..])
+        } else if buf.starts_with(sparse::LABEL) {
+            (DFAKind::Sparse, &buf[sparse::LABEL.len()..])
+        } else {
+            return Err(Error::deserialize("could not find DFA label"));
+        };
+        if rest.len() < 32 {
+            return Err(Error::deserialize("DFA header is truncated"));
+        }
+        match LittleEndian::read_u16(rest) {
+            0xFEFF => DFAHeader::read::<LittleEndian>(kind, false, rest),
+            0xFFFE => DFAHeader::read::<BigEndian>(kind, true, rest),
+            x => Err(Error::deserialize(&format!(
+                "invalid endianness check 0x{:X} in DFA header",
+                x
+            ))),
+        }
+    }
+
+    /// Read the remainder of a header, starting at the endianness check.
+    fn read<A: ByteOrder>(
+        kind: DFAKind,
+        big_endian: bool,
+        buf: &[u8],
+    ) -> Result<DFAHeader> {
+        let version = A::read_u16(&buf[2..]);
+        match version {
+            1 | 2 => {}
+            _ => {
+                return Err(Error::deserialize(&format!(
+                    "unsupported version {}",
+                    version
+                )))
+            }
+        }
+        let options = A::read_u16(&buf[6..]);
+        if let Some(msg) = dense::check_version(version, options) {
+            return Err(Error::deserialize(msg));
+        }
+        let classes = &buf[32..];
+        let alphabet_len = if options & dense::MASK_CLASSES_OMITTED > 0 {
+            if classes.len() < 8 {
+                return Err(Error::deserialize("DFA header is truncated"));
+            }
+            A::read_u64(classes) as usize
+        } else {
+            if classes.len() < 256 {
+                return Err(Error::deserialize("DFA header is truncated"));
+            }
+            classes[..256].iter().map(|&c| c as usize + 1).max().unwrap()
+        };
+        Ok(DFAHeader {
+            kind,
+            big_endian,
+            version,
+            state_id_size: A::read_u16(&buf[4..]) as usize,
+            options,
+            start_state: A::read_u64(&buf[8..]) as usize,
+            state_count: A::read_u64(&buf[16..]) as usize,
+            max_match_state: A::read_u64(&buf[24..]) as usize,
+            alphabet_len,
+        })
+    }
+
+    /// Returns whether the header belongs to a dense or a sparse DFA.
+    pub fn kind(&self) -> DFAKind {
+        self.kind
+    }
+
+    /// Returns true if and only if the DFA was serialized with the
+    /// endianness of the current platform, which is required in order to
+    /// deserialize it with `from_bytes`.
+    pub fn is_native_endian(&self) -> bool {
+        self.big_endian == cfg!(target_endian = "big")
+    }
+
+    /// Returns the version of the serialization format.
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    /// Returns the size, in bytes, of the DFA's state identifiers. This is
+    /// the size of the `S` type parameter that the DFA must be deserialized
+    /// with. It is always 1, 2, 4 or 8.
+    pub fn state_id_size(&self) -> usize {
+        self.state_id_size
+    }
+
+    /// Returns true if and only if the DFA is anchored.
+    pub fn is_anchored(&self) -> bool {
+        self.options & dense::MASK_ANCHORED > 0
+    }
+
+    /// Returns true if and only if the DFA's state identifiers are
+    /// premultiplied. This is always false for sparse DFAs.
+    pub fn is_premultiplied(&self) -> bool {
+        self.options & dense::MASK_PREMULTIPLIED > 0
+    }
+
+    /// Returns true if and only if the DFA's transitions are run length
+    /// encoded, in which case it must be deserialized with
+    /// [`DenseDFA::from_bytes_rle`](enum.DenseDFA.html#method.from_bytes_rle).
+    pub fn is_run_length_encoded(&self) -> bool {
+        self.options & dense::MASK_RLE > 0
+    }
+
+    /// Returns true if and only if the DFA's transitions are varint encoded,
+    /// in which case it must be deserialized with
+    /// [`DenseDFA::from_bytes_varint`](enum.DenseDFA.html#method.from_bytes_varint).
+    pub fn is_varint_encoded(&self) -> bool {
+        self.options & dense::MASK_VARINT > 0
+    }
+
+    /// Returns true if and only if the DFA was serialized without its byte
+    /// class map, in which case it must be deserialized with
+    /// [`DenseDFA::from_bytes_with_classes`](enum.DenseDFA.html#method.from_bytes_with_classes).
+    pub fn is_classes_omitted(&self) -> bool {
+        self.options & dense::MASK_CLASSES_OMITTED > 0
+    }
+
+    /// Returns true if and only if the DFA uses acceleration in its start
+    /// state. See
+    /// [`dense::Builder::accelerate`](dense/struct.Builder.html#method.accelerate).
+    pub fn is_accelerated(&self) -> bool {
+        self.options & dense::MASK_ACCEL > 0
+    }
+
+    /// Returns true if and only if the DFA's transition table is stored in
+    /// column-major order. See
+    /// [`dense::Builder::layout`](dense/struct.Builder.html#method.layout).
+    pub fn is_column_major(&self) -> bool {
+        self.options & dense::MASK_COLUMN_MAJOR > 0
+    }
+
+    /// Returns true if and only if the DFA skips bytes while searching. See
+    /// [`dense::Builder::skip_bytes`](dense/struct.Builder.html#method.skip_bytes).
+    pub fn has_skip_bytes(&self) -> bool {
+        self.options & dense::MASK_SKIP_BYTES > 0
+    }
+
+    /// Returns true if and only if the DFA's match states weren't shuffled
+    /// to the front of its transition table, in which case they are recorded
+    /// in a bitset following the byte class map. See
+    /// [`dense::Builder::shuffle_match_states`](dense/struct.Builder.html#method.shuffle_match_states).
+    pub fn has_match_set(&self) -> bool {
+        self.options & dense::MASK_MATCH_SET > 0
+    }
+
+    /// Returns true if and only if state labels follow the DFA's transition
+    /// table. They can be read with
+    /// [`StateLabels::from_dfa_bytes`](dense/struct.StateLabels.html#method.from_dfa_bytes).
+    pub fn has_labels(&self) -> bool {
+        self.options & dense::MASK_LABELS > 0
+    }
+
+    /// Returns the identifier of the DFA's start state.
+    pub fn start_state(&self) -> usize {
+        self.start_state
+    }
+
+    /// Returns the number of states in the DFA, including its dead state.
+    pub fn state_count(&self) -> usize {
+        self.state_count
+    }
+
+    /// Returns the identifier of the DFA's last match state.
+    pub fn max_match_state(&self) -> usize {
+        self.max_match_state
+    }
+
+    /// Returns the number of elements in the DFA's alphabet, i.e., the
+    /// number of its byte classes.
+    pub fn alphabet_len(&self) -> usize {
+        self.alphabet_len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {DenseDFA, SparseDFA};
+
+    #[test]
+    fn reads_every_format() {
+        let dfa = DenseDFA::new("[a-z]+[0-9]").unwrap().to_u32().unwrap();
+        let little =
+            DFAHeader::from_bytes(&dfa.to_bytes_little_endian().unwrap())
+                .unwrap();
+        let big = DFAHeader::from_bytes(&dfa.to_bytes_big_endian().unwrap())
+            .unwrap();
+        assert_ne!(little.is_native_endian(), big.is_native_endian());
+        assert_eq!(little.state_count(), big.state_count());
+        assert_eq!(little.alphabet_len(), big.alphabet_len());
+        assert_eq!(4, big.state_id_size());
+        assert!(little.is_premultiplied());
+
+        let bytes = dfa.to_bytes_without_classes_native_endian().unwrap();
+        let omitted = DFAHeader::from_bytes(&bytes).unwrap();
+        assert!(omitted.is_classes_omitted());
+        assert_eq!(little.alphabet_len(), omitted.alphabet_len());
+
+        let bytes = dfa.to_bytes_rle_native_endian().unwrap();
+        assert!(DFAHeader::from_bytes(&bytes)
+            .unwrap()
+            .is_run_length_encoded());
+        let bytes = dfa.to_bytes_varint_native_endian().unwrap();
+        assert!(DFAHeader::from_bytes(&bytes).unwrap().is_varint_encoded());
+
+        let sparse = SparseDFA::new("[a-z]+[0-9]").unwrap();
+        let bytes = sparse.to_bytes_native_endian().unwrap();
+        let header = DFAHeader::from_bytes(&bytes).unwrap();
+        assert_eq!(DFAKind::Sparse, header.kind());
+        assert_eq!(little.alphabet_len(), header.alphabet_len());
+        assert!(!header.is_premultiplied());
+    }
+
+    #[test]
+    fn rejects_invalid_headers() {
+        let bytes = DenseDFA::new("a").unwrap().to_bytes_native_endian();
+        let bytes = bytes.unwrap();
+        assert!(DFAHeader::from_bytes(&bytes[..40]).is_err());
+        assert!(DFAHeader::from_bytes(&bytes[1..]).is_err());
+        assert!(DFAHeader::from_bytes(b"").is_err());
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let dfa = DenseDFA::new("a").unwrap();
+        let header =
+            DFAHeader::from_bytes(&dfa.to_bytes_native_endian().unwrap());
+        assert_eq!(1, header.unwrap().version());
+        let header =
+            DFAHeader::from_bytes(&dfa.to_bytes_rle_native_endian().unwrap());
+        assert_eq!(dense::VERSION, header.unwrap().version());
+
+        let newer = (dense::VERSION + 1).to_ne_bytes();
+        let at = dense::LABEL.len() + 2;
+        for mut bytes in vec![
+            dfa.to_bytes_native_endian().unwrap(),
+            dfa.to_bytes_rle_native_endian().unwrap(),
+        ] {
+            bytes[at..at + 2].copy_from_slice(&newer);
+            let err = DFAHeader::from_bytes(&bytes).unwrap_err();
+            let expected =
+                format!("unsupported version {}", dense::VERSION + 1);
+            assert!(err.to_string().contains(&expected), "{}", err);
+        }
+
+        let mut bytes = dfa.to_bytes_rle_native_endian().unwrap();
+        bytes[at..at + 2].copy_from_slice(&newer);
+        assert!(DenseDFA::<Vec<usize>, usize>::from_bytes_rle(&bytes).is_err());
+    }
+
+    #[test]
+    fn rejects_options_unknown_to_the_version() {
+        let dfa = DenseDFA::new("a").unwrap();
+        let opts_at = dense::LABEL.len() + 6;
+        let set_opts = |bytes: &mut Vec<u8>, opts: u16| {
+            let old = u16::from_ne_bytes([bytes[opts_at], bytes[opts_at + 1]]);
+            let new = (old | opts).to_ne_bytes();
+            bytes[opts_at..opts_at + 2].copy_from_slice(&new);
+        };
+
+        // Version 1 loaders would misread an encoded DFA, so a version 1
+        // header can't claim one.
+        let mut bytes = dfa.to_bytes_native_endian().unwrap();
+        set_opts(&mut bytes, dense::MASK_RLE);
+        let err = DFAHeader::from_bytes(&bytes).unwrap_err();
+        assert!(err.to_string().contains("version 2"), "{}", err);
+
+        let mut bytes = dfa.to_bytes_rle_native_endian().unwrap();
+        set_opts(&mut bytes, 0x8000);
+        let err = DFAHeader::from_bytes(&bytes).unwrap_err();
+        assert!(err.to_string().contains("unknown options"), "{}", err);
+        assert!(DenseDFA::<Vec<usize>, usize>::from_bytes_rle(&bytes).is_err());
+    }
+}
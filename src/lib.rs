@@ -19,6 +19,10 @@ This section gives a brief overview of the primary types in this crate:
   but uses a sparse representation (uses less space, but slower matching).
 * A [`DFA`](trait.DFA.html) trait that defines an interface that all DFAs must
   implement.
+* A [`MultiDFA`](struct.MultiDFA.html) searches for many patterns at once
+  and reports which of them matched. A
+  [`MultiRegex`](struct.MultiRegex.html) does the same, but also reports the
+  span of each match.
 * Both dense DFAs and sparse DFAs support
   [serialization to raw bytes](enum.DenseDFA.html#method.to_bytes_little_endian)
   and
@@ -164,6 +168,34 @@ Note that unlike dense DFAs, sparse DFAs have no alignment requirements.
 Conversely, dense DFAs must be be aligned to the same alignment as their
 state identifier representation.
 
+# Sharing across threads
+
+Searching never mutates a DFA or a regex. There are no caches or other
+interior mutability, so every DFA and regex type in this crate is `Send` and
+`Sync` whenever its transition table is. In practice, that means a single
+compiled regex can be shared by reference (or via an `Arc`) among any number
+of threads, each of which can search with it concurrently without any
+synchronization. No separate thread safe wrapper is needed.
+
+```
+use std::sync::Arc;
+use std::thread;
+
+use regex_automata::Regex;
+
+let re = Arc::new(Regex::new(r"[0-9]{4}-[0-9]{2}-[0-9]{2}").unwrap());
+let handles: Vec<_> = (0..4).map(|i| {
+    let re = Arc::clone(&re);
+    thread::spawn(move || {
+        let text = format!("day {}: 2018-12-2{}", i, i);
+        re.find(text.as_bytes())
+    })
+}).collect();
+for handle in handles {
+    assert_eq!(Some((7, 17)), handle.join().unwrap());
+}
+```
+
 # Support for `no_std`
 
 This crate comes with a `std` feature that is enabled by default. When the
@@ -204,6 +236,23 @@ includes:
 * Anchors such as `^`, `$`, `\A` and `\z`.
 * Word boundary assertions such as `\b` and `\B`.
 
+These assertions are never silently ignored. If one appears anywhere in a
+pattern, including inside a sub-expression that is only one branch of an
+alternation, then building a DFA or regex fails with an
+[`ErrorKind::Unsupported`](enum.ErrorKind.html#variant.Unsupported) error.
+Consequently, a DFA produced by this crate never matches as if an assertion
+were absent.
+
+```
+use regex_automata::{ErrorKind, Regex};
+
+let err = Regex::new(r"foo|\Abar").unwrap_err();
+match *err.kind() {
+    ErrorKind::Unsupported(_) => {}
+    ref kind => panic!("unexpected error: {:?}", kind),
+}
+```
+
 It is possible to run a search that is anchored at the beginning of the input.
 To do that, set the
 [`RegexBuilder::anchored`](struct.RegexBuilder.html#method.anchored)
@@ -296,16 +345,33 @@ extern crate fst;
 #[cfg(feature = "std")]
 extern crate regex_syntax;
 
+#[cfg(feature = "std")]
+pub use archive::DFAArchive;
+pub use archive::{ArchiveError, DFAArchiveIter, DFAArchiveRef};
 pub use dense::DenseDFA;
-pub use dfa::DFA;
+#[cfg(feature = "std")]
+pub use dfa::AcceptedIter;
+pub use dfa::{FindBatch, MatchStatus, SearchOutcome, SearchResult, DFA};
 #[cfg(feature = "std")]
 pub use error::{Error, ErrorKind};
-pub use regex::Regex;
 #[cfg(feature = "std")]
-pub use regex::RegexBuilder;
+pub use header::{DFAHeader, DFAKind};
+#[cfg(feature = "std")]
+pub use multi::{MultiDFA, MultiDFABuilder, OverlappingMatches, PatternID};
+#[cfg(feature = "std")]
+pub use nfa::NFA;
+pub use regex::{LineTerminator, Match, Regex};
+#[cfg(feature = "std")]
+pub use regex::{MultiMatches, MultiRegex, RegexBuilder};
 pub use sparse::SparseDFA;
 pub use state_id::StateID;
+#[cfg(feature = "std")]
+pub use state_id::{
+    next_state_id, premultiply_overflow_error, premultiply_state_id,
+    usize_to_state_id,
+};
 
+mod archive;
 mod classes;
 #[path = "dense.rs"]
 mod dense_imp;
@@ -315,8 +381,12 @@ mod dfa;
 #[cfg(feature = "std")]
 mod error;
 #[cfg(feature = "std")]
+mod header;
+#[cfg(feature = "std")]
 mod minimize;
 #[cfg(feature = "std")]
+mod multi;
+#[cfg(feature = "std")]
 #[doc(hidden)]
 pub mod nfa;
 mod regex;
@@ -39,17 +39,21 @@ type DFARepr<S> = dense::Repr<Vec<S>, S>;
 ///    point during NFA compilation via the algorithm described in the
 ///    "Incremental Construction of MinimalAcyclic Finite-State Automata"
 ///    paper.)
-pub(crate) struct Minimizer<'a, S: 'a> {
-    dfa: &'a mut DFARepr<S>,
+///
+/// Partitions are computed over state indices rather than state identifiers,
+/// which permits checking whether a premultiplied DFA is minimal. Only DFAs
+/// that aren't premultiplied can be minimized though.
+pub(crate) struct Minimizer<S> {
+    alphabet_len: usize,
     in_transitions: Vec<Vec<Vec<S>>>,
     partitions: Vec<StateSet<S>>,
     waiting: Vec<StateSet<S>>,
 }
 
-impl<'a, S: StateID> fmt::Debug for Minimizer<'a, S> {
+impl<S: StateID> fmt::Debug for Minimizer<S> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("Minimizer")
-            .field("dfa", &self.dfa)
+            .field("alphabet_len", &self.alphabet_len)
             .field("in_transitions", &self.in_transitions)
             .field("partitions", &self.partitions)
             .field("waiting", &self.waiting)
@@ -72,60 +76,41 @@ impl<'a, S: StateID> fmt::Debug for Minimizer<'a, S> {
 #[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
 struct StateSet<S>(Rc<RefCell<Vec<S>>>);
 
-impl<'a, S: StateID> Minimizer<'a, S> {
-    pub fn new(dfa: &'a mut DFARepr<S>) -> Minimizer<'a, S> {
+impl<S: StateID> Minimizer<S> {
+    pub fn new<T: AsRef<[S]>>(dfa: &dense::Repr<T, S>) -> Minimizer<S> {
+        let alphabet_len = dfa.alphabet_len();
         let in_transitions = Minimizer::incoming_transitions(dfa);
         let partitions = Minimizer::initial_partitions(dfa);
         let waiting = vec![partitions[0].clone()];
 
-        Minimizer { dfa, in_transitions, partitions, waiting }
+        Minimizer { alphabet_len, in_transitions, partitions, waiting }
     }
 
-    pub fn run(mut self) {
-        let mut incoming = StateSet::empty();
-        let mut scratch1 = StateSet::empty();
-        let mut scratch2 = StateSet::empty();
-        let mut newparts = vec![];
-
-        while let Some(set) = self.waiting.pop() {
-            for b in (0..self.dfa.alphabet_len()).map(|b| b as u8) {
-                self.find_incoming_to(b, &set, &mut incoming);
+    /// Returns true if and only if the DFA given to `new` is already
+    /// minimal. That is, no two of its states are equivalent.
+    pub fn is_minimal(&mut self) -> bool {
+        self.refine();
+        self.partitions.iter().all(|p| p.len() == 1)
+    }
 
-                for p in 0..self.partitions.len() {
-                    self.partitions[p].intersection(&incoming, &mut scratch1);
-                    if scratch1.is_empty() {
-                        newparts.push(self.partitions[p].clone());
-                        continue;
-                    }
+    /// Returns the equivalence classes of the states of the DFA given to
+    /// `new`. Each class is a list of state indices in ascending order, and
+    /// the classes are ordered by their smallest index.
+    pub fn partition(mut self) -> Vec<Vec<S>> {
+        self.refine();
+        let mut classes: Vec<Vec<S>> =
+            self.partitions.iter().map(|p| p.0.borrow().clone()).collect();
+        classes.sort();
+        classes
+    }
 
-                    self.partitions[p].subtract(&incoming, &mut scratch2);
-                    if scratch2.is_empty() {
-                        newparts.push(self.partitions[p].clone());
-                        continue;
-                    }
-
-                    let (x, y) =
-                        (scratch1.deep_clone(), scratch2.deep_clone());
-                    newparts.push(x.clone());
-                    newparts.push(y.clone());
-                    match self.find_waiting(&self.partitions[p]) {
-                        Some(i) => {
-                            self.waiting[i] = x;
-                            self.waiting.push(y);
-                        }
-                        None => {
-                            if x.len() <= y.len() {
-                                self.waiting.push(x);
-                            } else {
-                                self.waiting.push(y);
-                            }
-                        }
-                    }
-                }
-                newparts = mem::replace(&mut self.partitions, newparts);
-                newparts.clear();
-            }
-        }
+    /// Minimize the given DFA in place. The DFA must be the same one given to
+    /// `new`, and it must not be premultiplied.
+    ///
+    /// This returns a map from the index of every state in the original DFA
+    /// to the index of the state it was merged into.
+    pub fn run(mut self, dfa: &mut DFARepr<S>) -> Vec<usize> {
+        self.refine();
 
         // At this point, we now have a minimal partitioning of states, where
         // each partition is an equivalence class of DFA states. Now we need to
@@ -135,7 +120,7 @@ impl<'a, S: StateID> Minimizer<'a, S> {
         // Create a map from DFA state ID to the representative ID of the
         // equivalence class to which it belongs. The representative ID of an
         // equivalence class of states is the minimum ID in that class.
-        let mut state_to_part = vec![dead_id(); self.dfa.state_count()];
+        let mut state_to_part = vec![dead_id(); dfa.state_count()];
         for p in &self.partitions {
             p.iter(|id| state_to_part[id.to_usize()] = p.min());
         }
@@ -144,9 +129,9 @@ impl<'a, S: StateID> Minimizer<'a, S> {
         // create a map from equivalence IDs to the new IDs. Thus, the new
         // minimal ID of *any* state in the unminimized DFA can be obtained
         // with minimals_ids[state_to_part[old_id]].
-        let mut minimal_ids = vec![dead_id(); self.dfa.state_count()];
+        let mut minimal_ids = vec![dead_id(); dfa.state_count()];
         let mut new_id = S::from_usize(0);
-        for (id, _) in self.dfa.states() {
+        for (id, _) in dfa.states() {
             if state_to_part[id.to_usize()] == id {
                 minimal_ids[id.to_usize()] = new_id;
                 new_id = S::from_usize(new_id.to_usize() + 1);
@@ -157,28 +142,28 @@ impl<'a, S: StateID> Minimizer<'a, S> {
 
         // Re-map this DFA in place such that the only states remaining
         // correspond to the representative states of every equivalence class.
-        for id in (0..self.dfa.state_count()).map(S::from_usize) {
+        for id in (0..dfa.state_count()).map(S::from_usize) {
             // If this state isn't a representative for an equivalence class,
             // then we skip it since it won't appear in the minimal DFA.
             if state_to_part[id.to_usize()] != id {
                 continue;
             }
-            for (_, next) in self.dfa.get_state_mut(id).iter_mut() {
+            for (_, next) in dfa.get_state_mut(id).iter_mut() {
                 *next = minimal_ids[state_to_part[next.to_usize()].to_usize()];
             }
-            self.dfa.swap_states(id, minimal_ids[id.to_usize()]);
+            dfa.swap_states(id, minimal_ids[id.to_usize()]);
         }
         // Trim off all unused states from the pre-minimized DFA. This
         // represents all states that were merged into a non-singleton
         // equivalence class of states, and appeared after the first state
         // in each such class. (Because the state with the smallest ID in each
         // equivalence class is its representative ID.)
-        self.dfa.truncate_states(minimal_count);
+        dfa.truncate_states(minimal_count);
 
         // Update the new start state, which is now just the minimal ID of
         // whatever state the old start state was collapsed into.
-        let old_start = self.dfa.start_state();
-        self.dfa.set_start_state(
+        let old_start = dfa.start_state();
+        dfa.set_start_state(
             minimal_ids[state_to_part[old_start.to_usize()].to_usize()],
         );
 
@@ -189,13 +174,66 @@ impl<'a, S: StateID> Minimizer<'a, S> {
         // earlier match state. Therefore, to find the new max match state,
         // we iterate over all previous match states, find their corresponding
         // new minimal ID, and take the maximum of those.
-        let old_max = self.dfa.max_match_state();
-        self.dfa.set_max_match_state(dead_id());
+        let old_max = dfa.max_match_state();
+        dfa.set_max_match_state(dead_id());
         for id in (0..(old_max.to_usize() + 1)).map(S::from_usize) {
             let part = state_to_part[id.to_usize()];
             let new_id = minimal_ids[part.to_usize()];
-            if new_id > self.dfa.max_match_state() {
-                self.dfa.set_max_match_state(new_id);
+            if new_id > dfa.max_match_state() {
+                dfa.set_max_match_state(new_id);
+            }
+        }
+        state_to_part
+            .iter()
+            .map(|part| minimal_ids[part.to_usize()].to_usize())
+            .collect()
+    }
+
+    /// Refine the partitions of states until no partition can be split any
+    /// further, at which point each partition is an equivalence class.
+    fn refine(&mut self) {
+        let mut incoming = StateSet::empty();
+        let mut scratch1 = StateSet::empty();
+        let mut scratch2 = StateSet::empty();
+        let mut newparts = vec![];
+
+        while let Some(set) = self.waiting.pop() {
+            for b in (0..self.alphabet_len).map(|b| b as u8) {
+                self.find_incoming_to(b, &set, &mut incoming);
+
+                for p in 0..self.partitions.len() {
+                    self.partitions[p].intersection(&incoming, &mut scratch1);
+                    if scratch1.is_empty() {
+                        newparts.push(self.partitions[p].clone());
+                        continue;
+                    }
+
+                    self.partitions[p].subtract(&incoming, &mut scratch2);
+                    if scratch2.is_empty() {
+                        newparts.push(self.partitions[p].clone());
+                        continue;
+                    }
+
+                    let (x, y) =
+                        (scratch1.deep_clone(), scratch2.deep_clone());
+                    newparts.push(x.clone());
+                    newparts.push(y.clone());
+                    match self.find_waiting(&self.partitions[p]) {
+                        Some(i) => {
+                            self.waiting[i] = x;
+                            self.waiting.push(y);
+                        }
+                        None => {
+                            if x.len() <= y.len() {
+                                self.waiting.push(x);
+                            } else {
+                                self.waiting.push(y);
+                            }
+                        }
+                    }
+                }
+                newparts = mem::replace(&mut self.partitions, newparts);
+                newparts.clear();
             }
         }
     }
@@ -219,14 +257,17 @@ impl<'a, S: StateID> Minimizer<'a, S> {
         incoming.canonicalize();
     }
 
-    fn initial_partitions(dfa: &DFARepr<S>) -> Vec<StateSet<S>> {
+    fn initial_partitions<T: AsRef<[S]>>(
+        dfa: &dense::Repr<T, S>,
+    ) -> Vec<StateSet<S>> {
         let mut is_match = StateSet::empty();
         let mut no_match = StateSet::empty();
         for (id, _) in dfa.states() {
+            let index = S::from_usize(dfa.state_id_to_index(id));
             if dfa.is_match_state(id) {
-                is_match.add(id);
+                is_match.add(index);
             } else {
-                no_match.add(id);
+                no_match.add(index);
             }
         }
 
@@ -238,14 +279,17 @@ impl<'a, S: StateID> Minimizer<'a, S> {
         sets
     }
 
-    fn incoming_transitions(dfa: &DFARepr<S>) -> Vec<Vec<Vec<S>>> {
+    fn incoming_transitions<T: AsRef<[S]>>(
+        dfa: &dense::Repr<T, S>,
+    ) -> Vec<Vec<Vec<S>>> {
         let mut incoming = vec![];
         for _ in dfa.states() {
             incoming.push(vec![vec![]; dfa.alphabet_len()]);
         }
         for (id, state) in dfa.states() {
+            let index = S::from_usize(dfa.state_id_to_index(id));
             for (b, next) in state.transitions() {
-                incoming[next.to_usize()][b as usize].push(id);
+                incoming[dfa.state_id_to_index(next)][b as usize].push(index);
             }
         }
         incoming
@@ -0,0 +1,372 @@
+use regex_syntax::hir::Hir;
+
+use dense;
+use dfa::DFA;
+use error::Result;
+use sparse::SparseDFA;
+use state_id::StateID;
+
+/// The identifier of a pattern in a multi-pattern DFA.
+///
+/// Patterns are numbered in the order in which they were given to the
+/// builder, starting at `0`.
+pub type PatternID = usize;
+
+/// A DFA that matches any of several patterns and reports which of them
+/// matched.
+///
+/// A multi-pattern DFA is a single DFA built from the alternation of every
+/// pattern, in which each match state remembers the patterns whose match it
+/// represents. Searching therefore takes time proportional to the length of
+/// the input, regardless of the number of patterns.
+///
+/// Unlike a DFA built from a single pattern, matches are not prioritized:
+/// a match state reports *every* pattern with a match ending at the current
+/// position, and the search continues past a match of one pattern in order
+/// to find matches of the others. As a result, the searches provided here
+/// report the end of a match as soon as it is seen, similar to
+/// [`DFA::shortest_match`](trait.DFA.html#method.shortest_match), rather
+/// than using leftmost first semantics.
+///
+/// The type parameter `D` is the underlying DFA, which is accessible via
+/// [`dfa`](struct.MultiDFA.html#method.dfa). Note that the `DFA` trait
+/// routines on the underlying DFA only report whether *some* pattern
+/// matched. A multi-pattern DFA is built with
+/// [`dense::Builder::build_many_sparse`](dense/struct.Builder.html#method.build_many_sparse),
+/// or with a [`MultiDFABuilder`](struct.MultiDFABuilder.html) when patterns
+/// are added one at a time.
+///
+/// # Example
+///
+/// ```
+/// use regex_automata::dense;
+///
+/// # fn example() -> Result<(), regex_automata::Error> {
+/// let rules = &[r"error", r"[0-9]+ms", r"timeout|error"];
+/// let dfa = dense::Builder::new().build_many_sparse(rules)?;
+///
+/// let line = b"request failed after 350ms: error";
+/// assert_eq!(Some((26, vec![1])), dfa.shortest_match(line));
+///
+/// let all: Vec<(usize, Vec<usize>)> = dfa
+///     .overlapping_matches(line)
+///     .map(|(end, ids)| (end, ids.to_vec()))
+///     .collect();
+/// assert_eq!(vec![(26, vec![1]), (33, vec![0, 2])], all);
+/// # Ok(()) }; example().unwrap()
+/// ```
+#[derive(Clone, Debug)]
+pub struct MultiDFA<D: DFA> {
+    /// The DFA matching the alternation of every pattern.
+    dfa: D,
+    /// Every match state of `dfa`, sorted by identifier, along with the
+    /// patterns that match in it (in ascending order).
+    matches: Vec<(D::ID, Box<[PatternID]>)>,
+    /// The number of patterns this DFA was built from.
+    pattern_count: usize,
+}
+
+impl<D: DFA> MultiDFA<D> {
+    /// Create a multi-pattern DFA from its parts. The matches given must be
+    /// sorted by state identifier.
+    pub(crate) fn new(
+        dfa: D,
+        matches: Vec<(D::ID, Box<[PatternID]>)>,
+        pattern_count: usize,
+    ) -> MultiDFA<D> {
+        debug_assert!(matches.windows(2).all(|w| w[0].0 < w[1].0));
+        MultiDFA { dfa, matches, pattern_count }
+    }
+
+    /// Return the underlying DFA.
+    pub fn dfa(&self) -> &D {
+        &self.dfa
+    }
+
+    /// Return the number of patterns this DFA was built from.
+    pub fn pattern_count(&self) -> usize {
+        self.pattern_count
+    }
+
+    /// Return the patterns that match in the given state of the underlying
+    /// DFA, in ascending order. This is empty if and only if the state is not
+    /// a match state.
+    pub fn patterns(&self, id: D::ID) -> &[PatternID] {
+        if !self.dfa.is_match_state(id) {
+            return &[];
+        }
+        match self.matches.binary_search_by_key(&id, |&(id, _)| id) {
+            Ok(i) => &self.matches[i].1,
+            Err(_) => &[],
+        }
+    }
+
+    /// Returns the end offset of the earliest match in the given bytes, along
+    /// with every pattern that has a match ending there. If no pattern
+    /// matches, then `None` is returned.
+    ///
+    /// The patterns are returned in ascending order.
+    pub fn shortest_match(
+        &self,
+        bytes: &[u8],
+    ) -> Option<(usize, Vec<PatternID>)> {
+        self.shortest_match_at(bytes, 0)
+    }
+
+    /// Returns the same as `shortest_match`, but starts the search at the
+    /// given offset.
+    ///
+    /// The significance of the starting point is that it takes the surrounding
+    /// context into consideration. For example, if the DFA is anchored, then
+    /// a match can only occur when `start == 0`.
+    pub fn shortest_match_at(
+        &self,
+        bytes: &[u8],
+        start: usize,
+    ) -> Option<(usize, Vec<PatternID>)> {
+        let mut it = OverlappingMatches::new(self, bytes, start);
+        it.next().map(|(end, ids)| (end, ids.to_vec()))
+    }
+
+    /// Returns an iterator over the end offset of every match in the given
+    /// bytes, in ascending order, along with the patterns that have a match
+    /// ending at each offset.
+    ///
+    /// Matches may overlap, and a single pattern may be reported at several
+    /// consecutive offsets. For example, `[0-9]+` is reported after every
+    /// digit in `123`.
+    pub fn overlapping_matches<'m, 't>(
+        &'m self,
+        bytes: &'t [u8],
+    ) -> OverlappingMatches<'m, 't, D> {
+        OverlappingMatches::new(self, bytes, 0)
+    }
+}
+
+/// A builder for a multi-pattern DFA whose patterns are added one at a time.
+///
+/// This is useful for maintaining a growing rule set. Each pattern is parsed
+/// as soon as it is added, so syntax errors are reported right away, and a
+/// [`MultiDFA`](struct.MultiDFA.html) for every pattern added so far can be
+/// built at any point. The patterns are compiled with the configuration of
+/// the [`dense::Builder`](dense/struct.Builder.html) given to
+/// [`new`](struct.MultiDFABuilder.html#method.new).
+///
+/// Currently, each call to `build` determinizes the union of every pattern,
+/// so it takes roughly as long as building the whole rule set from scratch
+/// (minus parsing the patterns). Determinizing only the part of the DFA
+/// affected by a new pattern is a possible future optimization that
+/// wouldn't change this API.
+///
+/// # Example
+///
+/// ```
+/// use regex_automata::{dense, MultiDFABuilder};
+///
+/// # fn example() -> Result<(), regex_automata::Error> {
+/// let mut builder = MultiDFABuilder::new(&dense::Builder::new());
+/// assert_eq!(0, builder.add_pattern("foo")?);
+/// let dfa = builder.build()?;
+/// assert_eq!(None, dfa.shortest_match(b"bar"));
+///
+/// assert_eq!(1, builder.add_pattern("ba[rz]")?);
+/// let dfa = builder.build()?;
+/// assert_eq!(Some((3, vec![1])), dfa.shortest_match(b"bar"));
+/// assert_eq!(Some((3, vec![0])), dfa.shortest_match(b"foo"));
+/// # Ok(()) }; example().unwrap()
+/// ```
+#[derive(Clone, Debug)]
+pub struct MultiDFABuilder {
+    builder: dense::Builder,
+    /// Every pattern added so far, indexed by pattern identifier.
+    exprs: Vec<Hir>,
+}
+
+impl MultiDFABuilder {
+    /// Create a builder with no patterns that compiles patterns with the
+    /// given dense DFA builder's configuration.
+    pub fn new(builder: &dense::Builder) -> MultiDFABuilder {
+        MultiDFABuilder { builder: builder.clone(), exprs: vec![] }
+    }
+
+    /// Add a pattern to this builder and return its identifier.
+    ///
+    /// The pattern is assigned the next unused identifier. Identifiers of
+    /// existing patterns do not change. If the pattern fails to parse, then
+    /// an error is returned and this builder is left unchanged.
+    pub fn add_pattern(&mut self, pattern: &str) -> Result<PatternID> {
+        let id = self.exprs.len();
+        self.exprs.push(self.builder.build_hir(pattern)?);
+        Ok(id)
+    }
+
+    /// Return the number of patterns added to this builder.
+    pub fn pattern_count(&self) -> usize {
+        self.exprs.len()
+    }
+
+    /// Build a sparse multi-pattern DFA from every pattern added so far.
+    ///
+    /// This is equivalent to calling
+    /// [`build_many_sparse`](dense/struct.Builder.html#method.build_many_sparse)
+    /// with the same patterns.
+    pub fn build(&self) -> Result<MultiDFA<SparseDFA<Vec<u8>, usize>>> {
+        self.build_with_size::<usize>()
+    }
+
+    /// Build a sparse multi-pattern DFA using a specific representation for
+    /// the DFA's state IDs.
+    ///
+    /// See
+    /// [`dense::Builder::build_with_size`](dense/struct.Builder.html#method.build_with_size)
+    /// for more details.
+    pub fn build_with_size<S: StateID>(
+        &self,
+    ) -> Result<MultiDFA<SparseDFA<Vec<u8>, S>>> {
+        self.builder.build_many_from_hirs(&self.exprs)
+    }
+}
+
+/// An iterator over the end offsets of all matches of a multi-pattern DFA,
+/// along with the patterns matching at each.
+///
+/// `'m` is the lifetime of the multi-pattern DFA and `'t` is the lifetime of
+/// the bytes being searched.
+#[derive(Clone, Debug)]
+pub struct OverlappingMatches<'m, 't, D: DFA + 'm> {
+    dfa: &'m MultiDFA<D>,
+    text: &'t [u8],
+    /// The offset of the next byte to read.
+    at: usize,
+    /// The current state, or `None` if the search hasn't started yet.
+    state: Option<D::ID>,
+}
+
+impl<'m, 't, D: DFA> OverlappingMatches<'m, 't, D> {
+    fn new(
+        dfa: &'m MultiDFA<D>,
+        text: &'t [u8],
+        start: usize,
+    ) -> OverlappingMatches<'m, 't, D> {
+        OverlappingMatches { dfa, text, at: start, state: None }
+    }
+}
+
+impl<'m, 't, D: DFA> Iterator for OverlappingMatches<'m, 't, D> {
+    type Item = (usize, &'m [PatternID]);
+
+    fn next(&mut self) -> Option<(usize, &'m [PatternID])> {
+        let dfa = &self.dfa.dfa;
+        let mut state = match self.state {
+            Some(state) => state,
+            None => {
+                let state = dfa.start_state();
+                self.state = Some(state);
+                if dfa.is_anchored() && self.at > 0 {
+                    self.at = self.text.len();
+                    return None;
+                }
+                if dfa.is_match_state(state) {
+                    return Some((self.at, self.dfa.patterns(state)));
+                }
+                state
+            }
+        };
+        while self.at < self.text.len() && !dfa.is_dead_state(state) {
+            state =
+                unsafe { dfa.next_state_unchecked(state, self.text[self.at]) };
+            self.at += 1;
+            if dfa.is_match_state(state) {
+                self.state = Some(state);
+                return Some((self.at, self.dfa.patterns(state)));
+            }
+        }
+        self.state = Some(state);
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MultiDFABuilder;
+    use dense;
+    use dfa::DFA;
+
+    /// Returns true if the anchored DFA given matches all of `bytes`.
+    fn full_match<D: DFA>(dfa: &D, bytes: &[u8]) -> bool {
+        let mut state = dfa.start_state();
+        for &b in bytes {
+            state = dfa.next_state(state, b);
+        }
+        dfa.is_match_state(state)
+    }
+
+    #[test]
+    fn overlapping_matches_agree_with_each_pattern() {
+        let patterns = &["a+", "ab|b", "[0-9]*", "xyz", "[^a]b"];
+        let multi = dense::Builder::new().build_many_sparse(patterns).unwrap();
+        let anchored: Vec<_> = patterns
+            .iter()
+            .map(|p| dense::Builder::new().anchored(true).build(p).unwrap())
+            .collect();
+
+        let haystacks: &[&[u8]] = &[b"", b"aab", b"xxyz9b", b"b1ab", b"zzz"];
+        for &h in haystacks {
+            let mut expected = vec![];
+            for end in 0..=h.len() {
+                let ids: Vec<usize> = (0..patterns.len())
+                    .filter(|&i| {
+                        (0..=end).any(|s| full_match(&anchored[i], &h[s..end]))
+                    })
+                    .collect();
+                if !ids.is_empty() {
+                    expected.push((end, ids));
+                }
+            }
+            let got: Vec<(usize, Vec<usize>)> = multi
+                .overlapping_matches(h)
+                .map(|(end, ids)| (end, ids.to_vec()))
+                .collect();
+            assert_eq!(expected, got, "{:?}", h);
+            assert_eq!(expected.first().cloned(), multi.shortest_match(h));
+        }
+    }
+
+    #[test]
+    fn anchored_and_empty() {
+        let multi = dense::Builder::new()
+            .anchored(true)
+            .build_many_sparse(&["foo", "fo+"])
+            .unwrap();
+        assert_eq!(Some((2, vec![1])), multi.shortest_match(b"foo"));
+        assert_eq!(3, multi.overlapping_matches(b"fooo").count());
+        assert_eq!(None, multi.shortest_match_at(b"xfoo", 1));
+
+        let none: &[&str] = &[];
+        let multi = dense::Builder::new().build_many_sparse(none).unwrap();
+        assert_eq!(0, multi.pattern_count());
+        assert_eq!(None, multi.shortest_match(b"anything"));
+    }
+
+    #[test]
+    fn incremental_build_matches_batch_build() {
+        let patterns = &["a+", "ab|b", "[0-9]*", "xyz"];
+        let batch = dense::Builder::new().build_many_sparse(patterns).unwrap();
+
+        let mut builder = MultiDFABuilder::new(&dense::Builder::new());
+        assert_eq!(0, builder.build().unwrap().pattern_count());
+        for (i, p) in patterns.iter().enumerate() {
+            assert_eq!(i, builder.add_pattern(p).unwrap());
+        }
+        assert!(builder.add_pattern("(").is_err());
+        assert_eq!(patterns.len(), builder.pattern_count());
+
+        let multi = builder.build().unwrap();
+        assert_eq!(patterns.len(), multi.pattern_count());
+        for &h in &[&b"aab"[..], b"xxyz9b", b"b1ab"] {
+            let got: Vec<_> = multi.overlapping_matches(h).collect();
+            let expected: Vec<_> = batch.overlapping_matches(h).collect();
+            assert_eq!(expected, got);
+        }
+    }
+}
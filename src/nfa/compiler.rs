@@ -39,6 +39,7 @@ use regex_syntax::utf8::{Utf8Range, Utf8Sequences};
 
 use classes::ByteClassSet;
 use error::{Error, Result};
+use multi::PatternID;
 use nfa::map::{Utf8BoundedMap, Utf8SuffixKey, Utf8SuffixMap};
 use nfa::range_trie::RangeTrie;
 use nfa::{State, StateID, Transition, NFA};
@@ -51,6 +52,7 @@ struct Config {
     allow_invalid_utf8: bool,
     reverse: bool,
     shrink: bool,
+    size_limit: Option<usize>,
 }
 
 impl Default for Config {
@@ -60,6 +62,7 @@ impl Default for Config {
             allow_invalid_utf8: false,
             reverse: false,
             shrink: true,
+            size_limit: None,
         }
     }
 }
@@ -112,6 +115,36 @@ impl Builder {
         compiler.compile(nfa, expr)
     }
 
+    /// Compile an NFA that matches any of the given expressions, where each
+    /// expression has its own match state labeled with its index in `exprs`.
+    ///
+    /// The expressions are compiled into a single alternation, in order, so
+    /// that one search with a DFA built from this NFA can report matches for
+    /// all of them.
+    pub fn build_many(&self, exprs: &[Hir]) -> Result<NFA> {
+        let mut nfa = NFA::always_match();
+        let mut compiler = Compiler::new();
+        compiler.configure(self.config);
+        compiler.compile_many(&mut nfa, exprs)?;
+        Ok(nfa)
+    }
+
+    /// Compile an NFA that matches any one of the given literals, as if
+    /// they were joined together in an alternation, in order.
+    ///
+    /// The literals are arranged into a trie directly instead of going
+    /// through a regex's high level intermediate representation, which is
+    /// substantially faster for large sets of literals. Literals that can
+    /// never match under leftmost first semantics (because an earlier
+    /// literal is a prefix of them) are dropped.
+    pub fn build_literals<B: AsRef<[u8]>>(&self, literals: &[B]) -> NFA {
+        let mut nfa = NFA::always_match();
+        let mut compiler = Compiler::new();
+        compiler.configure(self.config);
+        compiler.compile_literals(&mut nfa, literals);
+        nfa
+    }
+
     /// Set whether matching must be anchored at the beginning of the input.
     ///
     /// When enabled, a match must begin at the start of the input. When
@@ -164,6 +197,21 @@ impl Builder {
         self.config.shrink = yes;
         self
     }
+
+    /// Set an approximate limit on the number of states in the NFA.
+    ///
+    /// If compiling a regex would require more states than this, then
+    /// compilation stops and an error is returned. The limit is checked
+    /// between sub-expressions, so the NFA may briefly exceed the limit by
+    /// the size of a single sub-expression. It applies to the states created
+    /// during compilation, before empty states are removed, so the final
+    /// NFA is usually somewhat smaller.
+    ///
+    /// By default, there is no limit.
+    pub fn size_limit(&mut self, limit: Option<usize>) -> &mut Builder {
+        self.config.size_limit = limit;
+        self
+    }
 }
 
 /// A compiler that converts a regex abstract syntax to an NFA via Thompson's
@@ -235,9 +283,9 @@ enum CState {
     /// into one Union type of state, where the latter has its epsilon
     /// transitions reversed to reflect the priority inversion.
     UnionReverse { alternates: Vec<StateID> },
-    /// A match state. There is exactly one such occurrence of this state in
-    /// an NFA.
-    Match,
+    /// A match state for the pattern with the given identifier. There is
+    /// exactly one such occurrence of this state for each pattern in an NFA.
+    Match { pattern_id: PatternID },
 }
 
 /// A value that represents the result of compiling a sub-expression of a
@@ -284,26 +332,66 @@ impl Compiler {
 
     /// Convert the current intermediate NFA to its final compiled form.
     fn compile(&self, nfa: &mut NFA, expr: &Hir) -> Result<()> {
-        nfa.anchored = self.config.anchored;
+        self.compile_with(nfa, || self.c(&expr))
+    }
 
-        let mut start = self.add_empty();
-        if !nfa.anchored {
-            let compiled = if self.config.allow_invalid_utf8 {
-                self.c_unanchored_prefix_invalid_utf8()?
-            } else {
-                self.c_unanchored_prefix_valid_utf8()?
-            };
-            self.patch(start, compiled.start);
-            start = compiled.end;
-        }
-        let compiled = self.c(&expr)?;
-        let match_id = self.add_match();
+    /// Compile an NFA for an alternation of the given literals.
+    fn compile_literals<B: AsRef<[u8]>>(&self, nfa: &mut NFA, literals: &[B]) {
+        self.compile_with(nfa, || Ok(self.c_literals(literals)))
+            .expect("compiling literals never fails")
+    }
+
+    /// Compile an NFA whose body is produced by the given closure, surrounding
+    /// it with an unanchored prefix (if necessary) and a match state.
+    fn compile_with<F>(&self, nfa: &mut NFA, body: F) -> Result<()>
+    where
+        F: FnOnce() -> Result<ThompsonRef>,
+    {
+        let start = self.c_start(nfa)?;
+        let compiled = body()?;
+        let match_id = self.add_match(0);
         self.patch(start, compiled.start);
         self.patch(compiled.end, match_id);
         self.finish(nfa);
         Ok(())
     }
 
+    /// Compile an NFA that matches any of the given expressions, where each
+    /// expression leads to its own match state. The identifier of each
+    /// pattern is its index in `exprs`.
+    fn compile_many(&self, nfa: &mut NFA, exprs: &[Hir]) -> Result<()> {
+        let start = self.c_start(nfa)?;
+        let union = self.add_union();
+        self.patch(start, union);
+        for (pattern_id, expr) in exprs.iter().enumerate() {
+            let compiled = self.c(expr)?;
+            let match_id = self.add_match(pattern_id);
+            self.patch(union, compiled.start);
+            self.patch(compiled.end, match_id);
+        }
+        self.finish(nfa);
+        Ok(())
+    }
+
+    /// Compile the start of an NFA, which includes the unanchored prefix if
+    /// the NFA isn't anchored, and return the state from which the body of
+    /// the NFA should continue.
+    fn c_start(&self, nfa: &mut NFA) -> Result<StateID> {
+        nfa.anchored = self.config.anchored;
+
+        let start = self.add_empty();
+        if nfa.anchored {
+            return Ok(start);
+        }
+        let compiled = if self.config.allow_invalid_utf8 {
+            self.c_unanchored_prefix_invalid_utf8()?
+        } else {
+            self.c_unanchored_prefix_valid_utf8()?
+        };
+        self.patch(start, compiled.start);
+        Ok(compiled.end)
+    }
+
     /// Finishes the compilation process and populates the provide NFA with
     /// the final graph.
     fn finish(&self, nfa: &mut NFA) {
@@ -364,9 +452,9 @@ impl Compiler {
                         alternates: alternates.into_boxed_slice(),
                     });
                 }
-                CState::Match => {
+                CState::Match { pattern_id } => {
                     remap[id] = nfa.states.len();
-                    nfa.states.push(State::Match);
+                    nfa.states.push(State::Match { pattern_id });
                 }
             }
         }
@@ -390,6 +478,24 @@ impl Compiler {
     }
 
     fn c(&self, expr: &Hir) -> Result<ThompsonRef> {
+        self.check_size()?;
+        let compiled = self.c_expr(expr)?;
+        self.check_size()?;
+        Ok(compiled)
+    }
+
+    /// Returns an error if the number of states compiled so far exceeds the
+    /// configured size limit.
+    fn check_size(&self) -> Result<()> {
+        match self.config.size_limit {
+            Some(limit) if self.states.borrow().len() > limit => {
+                Err(Error::nfa_too_big(limit))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn c_expr(&self, expr: &Hir) -> Result<ThompsonRef> {
         match *expr.kind() {
             HirKind::Empty => {
                 let id = self.add_empty();
@@ -424,6 +530,80 @@ impl Compiler {
         }
     }
 
+    /// Compile the given literals into a trie, where every node at which a
+    /// literal ends has an epsilon transition to the end of the trie.
+    ///
+    /// In an alternation of literals with leftmost first semantics, a literal
+    /// that has an earlier literal as a prefix can never match, since the
+    /// earlier literal always matches first. Such literals are skipped, which
+    /// guarantees that a node at which a literal ends never gains children
+    /// added by a later literal. As a result, the edge to the end of the trie
+    /// always has the lowest priority at each node. The children of a node
+    /// are all on distinct bytes, so their relative priority doesn't matter.
+    fn c_literals<B: AsRef<[u8]>>(&self, literals: &[B]) -> ThompsonRef {
+        struct Node {
+            is_match: bool,
+            children: Vec<(u8, usize)>,
+        }
+
+        let mut trie = vec![Node { is_match: false, children: vec![] }];
+        'literals: for lit in literals {
+            let lit = lit.as_ref();
+            let mut node = 0;
+            for i in 0..lit.len() {
+                if trie[node].is_match {
+                    continue 'literals;
+                }
+                let b = if self.config.reverse {
+                    lit[lit.len() - i - 1]
+                } else {
+                    lit[i]
+                };
+                node = match trie[node]
+                    .children
+                    .binary_search_by_key(&b, |&(b, _)| b)
+                {
+                    Ok(i) => trie[node].children[i].1,
+                    Err(i) => {
+                        let child = trie.len();
+                        trie.push(Node { is_match: false, children: vec![] });
+                        trie[node].children.insert(i, (b, child));
+                        child
+                    }
+                };
+            }
+            trie[node].is_match = true;
+        }
+
+        // Every node is created after its parent, so compiling nodes in
+        // reverse order guarantees that each node's children have already
+        // been compiled.
+        let end = self.add_empty();
+        let mut ids = vec![0; trie.len()];
+        for node in (0..trie.len()).rev() {
+            let ranges: Vec<Transition> = trie[node]
+                .children
+                .iter()
+                .map(|&(b, child)| Transition {
+                    start: b,
+                    end: b,
+                    next: ids[child],
+                })
+                .collect();
+            ids[node] = match (trie[node].is_match, ranges.is_empty()) {
+                (false, _) => self.add_sparse(ranges),
+                (true, true) => end,
+                (true, false) => {
+                    let union = self.add_union();
+                    self.patch(union, self.add_sparse(ranges));
+                    self.patch(union, end);
+                    union
+                }
+            };
+        }
+        ThompsonRef { start: ids[0], end }
+    }
+
     fn c_concat<I>(&self, mut it: I) -> Result<ThompsonRef>
     where
         I: DoubleEndedIterator<Item = Result<ThompsonRef>>,
@@ -520,7 +700,7 @@ impl Compiler {
         //     000005: 61 => 06
         //     000006: alt(07, 08)
         //     000007: 61 => 08
-        //     000008: MATCH
+        //     000008: MATCH(0)
         //
         // And effectively, once you hit state 2, the epsilon closure will
         // include states 3, 5, 5, 6, 7 and 8, which is quite a bit. It is
@@ -534,7 +714,7 @@ impl Compiler {
         //      000005: 61 => 06
         //      000006: alt(07, 08)
         //      000007: 61 => 08
-        //      000008: MATCH
+        //      000008: MATCH(0)
         //
         // So that the epsilon closure of state 2 is now just 3 and 8.
         let empty = self.add_empty();
@@ -799,7 +979,7 @@ impl Compiler {
             CState::UnionReverse { ref mut alternates } => {
                 alternates.push(to);
             }
-            CState::Match => {}
+            CState::Match { .. } => {}
         }
     }
 
@@ -844,9 +1024,9 @@ impl Compiler {
         id
     }
 
-    fn add_match(&self) -> StateID {
+    fn add_match(&self, pattern_id: PatternID) -> StateID {
         let id = self.states.borrow().len();
-        self.states.borrow_mut().push(CState::Match);
+        self.states.borrow_mut().push(CState::Match { pattern_id });
         id
     }
 }
@@ -1035,7 +1215,7 @@ mod tests {
     }
 
     fn s_match() -> State {
-        State::Match
+        State::Match { pattern_id: 0 }
     }
 
     #[test]
@@ -1,6 +1,12 @@
-use std::fmt;
+use std::fmt::{self, Write};
+use std::iter;
+use std::slice;
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian, NativeEndian};
 
 use classes::ByteClasses;
+use error::{Error, Result};
+use multi::PatternID;
 pub use nfa::compiler::Builder;
 
 mod compiler;
@@ -10,8 +16,40 @@ mod range_trie;
 /// The representation for an NFA state identifier.
 pub type StateID = usize;
 
+/// The label at the beginning of every serialized NFA.
+const LABEL: &[u8] = b"rust-regex-automata-nfa\x00";
+
+/// The version of the NFA serialization format. This is independent of the
+/// version used for DFAs.
+const VERSION: u16 = 1;
+
+/// Masks used in serialization of NFAs.
+const MASK_ANCHORED: u16 = 0b0000_0000_0000_0001;
+
+/// The tags identifying each kind of state in a serialized NFA.
+const TAG_RANGE: u8 = 0;
+const TAG_SPARSE: u8 = 1;
+const TAG_UNION: u8 = 2;
+const TAG_FAIL: u8 = 3;
+const TAG_MATCH: u8 = 4;
+
 /// A final compiled NFA.
 ///
+/// Every DFA in this crate is built by first compiling a pattern to a
+/// Thompson NFA, which is then determinized. The NFA for a pattern can be
+/// obtained with
+/// [`dense::Builder::build_nfa`](dense/struct.Builder.html#method.build_nfa)
+/// and inspected with its read-only accessors, for example by rendering it
+/// with [`to_dot`](struct.NFA.html#method.to_dot). An NFA can also be
+/// serialized, loaded elsewhere with
+/// [`from_bytes`](struct.NFA.html#method.from_bytes) and then determinized
+/// with
+/// [`dense::Builder::build_from_nfa`](dense/struct.Builder.html#method.build_from_nfa).
+///
+/// The NFA is not meant to be used for searching. Its precise shape, such as
+/// how many states it has, is not part of this crate's API and may change
+/// between releases.
+///
 /// The states of the NFA are indexed by state IDs, which are how transitions
 /// are expressed.
 #[derive(Clone)]
@@ -25,7 +63,7 @@ pub struct NFA {
     start: StateID,
     /// The state list. This list is guaranteed to be indexable by the starting
     /// state ID, and it is also guaranteed to contain exactly one `Match`
-    /// state.
+    /// state for each pattern.
     states: Vec<State>,
     /// A mapping from any byte value to its corresponding equivalence class
     /// identifier. Two bytes in the same equivalence class cannot discriminate
@@ -47,7 +85,7 @@ impl NFA {
         NFA {
             anchored: false,
             start: 0,
-            states: vec![State::Match],
+            states: vec![State::Match { pattern_id: 0 }],
             byte_classes: ByteClasses::empty(),
         }
     }
@@ -82,12 +120,358 @@ impl NFA {
         &self.states[id]
     }
 
+    /// Returns an iterator over every state in this NFA, in order of their
+    /// identifiers. Each item is a state's identifier paired with the state
+    /// itself.
+    pub fn states(&self) -> StateIter<'_> {
+        StateIter { it: self.states.iter().enumerate() }
+    }
+
+    /// Returns the epsilon closure of the given state, which is the set of
+    /// states reachable from it without consuming any input, including the
+    /// state itself.
+    ///
+    /// The states are returned in priority order. That is, when two states
+    /// in the closure can both lead to a match, the one that appears first
+    /// corresponds to the match preferred by leftmost first semantics.
+    pub fn epsilon_closure(&self, id: StateID) -> Vec<StateID> {
+        let mut seen = vec![false; self.len()];
+        let mut closure = vec![];
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            if seen[id] {
+                continue;
+            }
+            seen[id] = true;
+            closure.push(id);
+            stack.extend(self.state(id).epsilons().iter().rev());
+        }
+        closure
+    }
+
+    /// Render this NFA in the DOT language, for visualization with a tool
+    /// like Graphviz.
+    ///
+    /// Byte transitions are drawn as solid edges labeled with their byte
+    /// range. Epsilon transitions are drawn as dashed edges labeled with
+    /// their priority, where lower numbers take precedence. Match states are
+    /// drawn with a double circle and labeled with their pattern identifier,
+    /// while fail states are drawn as boxes.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        // Writing to a String never fails.
+        writeln!(dot, "digraph nfa {{").unwrap();
+        writeln!(dot, "    rankdir=LR;").unwrap();
+        writeln!(dot, "    node [shape=circle];").unwrap();
+        writeln!(dot, "    start [shape=point];").unwrap();
+        writeln!(dot, "    start -> {};", self.start).unwrap();
+        for (id, state) in self.states() {
+            match *state {
+                State::Match { pattern_id } => writeln!(
+                    dot,
+                    "    {} [shape=doublecircle, label=\"{}\\n#{}\"];",
+                    id, id, pattern_id,
+                )
+                .unwrap(),
+                State::Fail => {
+                    writeln!(dot, "    {} [shape=box];", id).unwrap()
+                }
+                _ => {}
+            }
+            for t in state.transitions() {
+                writeln!(
+                    dot,
+                    "    {} -> {} [label=\"{}\"];",
+                    id,
+                    t.next,
+                    dot_escape(&t.range_string()),
+                )
+                .unwrap();
+            }
+            for (priority, &next) in state.epsilons().iter().enumerate() {
+                writeln!(
+                    dot,
+                    "    {} -> {} [style=dashed, label=\"{}\"];",
+                    id, next, priority,
+                )
+                .unwrap();
+            }
+        }
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+
     /// Return the set of equivalence classes for this NFA. The slice returned
     /// always has length 256 and maps each possible byte value to its
     /// corresponding equivalence class ID (which is never more than 255).
     pub fn byte_classes(&self) -> &ByteClasses {
         &self.byte_classes
     }
+
+    /// Serialize this NFA to raw bytes in little endian format.
+    ///
+    /// This makes it possible to compile a pattern once and determinize it
+    /// elsewhere, or to cache an NFA for building DFAs with several
+    /// different configurations via
+    /// [`dense::Builder::build_from_nfa`](../dense/struct.Builder.html#method.build_from_nfa).
+    /// Options that are applied while determinizing, such as minimization,
+    /// aren't part of the NFA. But options that shape the NFA itself, such
+    /// as anchoring, matching in reverse or the syntax flags, are fixed when
+    /// it's built.
+    ///
+    /// The format begins with a label, an endianness check and a version
+    /// number, like the one used for DFAs, followed by the NFA's anchoring,
+    /// start state, byte class map and states. Each state is a tag byte
+    /// followed by its transitions, with every state identifier written as
+    /// a `u64`. Unlike a DFA, an NFA can't be used in place, so it must be
+    /// loaded with [`from_bytes`](struct.NFA.html#method.from_bytes).
+    pub fn to_bytes_little_endian(&self) -> Vec<u8> {
+        self.to_bytes::<LittleEndian>()
+    }
+
+    /// Serialize this NFA to raw bytes in big endian format.
+    ///
+    /// See
+    /// [`to_bytes_little_endian`](struct.NFA.html#method.to_bytes_little_endian)
+    /// for more details.
+    pub fn to_bytes_big_endian(&self) -> Vec<u8> {
+        self.to_bytes::<BigEndian>()
+    }
+
+    /// Serialize this NFA to raw bytes in native endian format.
+    ///
+    /// See
+    /// [`to_bytes_little_endian`](struct.NFA.html#method.to_bytes_little_endian)
+    /// for more details.
+    pub fn to_bytes_native_endian(&self) -> Vec<u8> {
+        self.to_bytes::<NativeEndian>()
+    }
+
+    fn to_bytes<A: ByteOrder>(&self) -> Vec<u8> {
+        fn write_u64<A: ByteOrder>(buf: &mut Vec<u8>, n: usize) {
+            let mut bytes = [0; 8];
+            A::write_u64(&mut bytes, n as u64);
+            buf.extend_from_slice(&bytes);
+        }
+        fn write_transition<A: ByteOrder>(buf: &mut Vec<u8>, t: &Transition) {
+            buf.push(t.start);
+            buf.push(t.end);
+            write_u64::<A>(buf, t.next);
+        }
+
+        let mut buf = LABEL.to_vec();
+        let mut header = [0; 6];
+        A::write_u16(&mut header[0..], 0xFEFF);
+        A::write_u16(&mut header[2..], VERSION);
+        A::write_u16(
+            &mut header[4..],
+            if self.anchored { MASK_ANCHORED } else { 0 },
+        );
+        buf.extend_from_slice(&header);
+        write_u64::<A>(&mut buf, self.start);
+        write_u64::<A>(&mut buf, self.states.len());
+        buf.extend_from_slice(&self.byte_classes.to_bytes());
+        for state in &self.states {
+            match *state {
+                State::Range { ref range } => {
+                    buf.push(TAG_RANGE);
+                    write_transition::<A>(&mut buf, range);
+                }
+                State::Sparse { ref ranges } => {
+                    buf.push(TAG_SPARSE);
+                    write_u64::<A>(&mut buf, ranges.len());
+                    for t in ranges.iter() {
+                        write_transition::<A>(&mut buf, t);
+                    }
+                }
+                State::Union { ref alternates } => {
+                    buf.push(TAG_UNION);
+                    write_u64::<A>(&mut buf, alternates.len());
+                    for &alt in alternates.iter() {
+                        write_u64::<A>(&mut buf, alt);
+                    }
+                }
+                State::Fail => buf.push(TAG_FAIL),
+                State::Match { pattern_id } => {
+                    buf.push(TAG_MATCH);
+                    write_u64::<A>(&mut buf, pattern_id);
+                }
+            }
+        }
+        buf
+    }
+
+    /// Deserialize an NFA written by one of the `to_bytes` routines, such as
+    /// [`to_bytes_native_endian`](struct.NFA.html#method.to_bytes_native_endian).
+    ///
+    /// The NFA is copied into freshly allocated memory and checked for
+    /// consistency, so this is safe to call on untrusted input: every
+    /// problem is reported as an error rather than a panic, and an NFA that
+    /// is loaded successfully can always be determinized.
+    ///
+    /// An error is returned if the bytes are not a valid serialized NFA, or
+    /// if they were written with a different endianness than this
+    /// platform's.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{dense, DFA, NFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let builder = dense::Builder::new();
+    /// let bytes = builder.build_nfa("foo[0-9]+")?.to_bytes_native_endian();
+    ///
+    /// // Perhaps on another machine...
+    /// let nfa = NFA::from_bytes(&bytes)?;
+    /// let dfa = dense::Builder::new()
+    ///     .minimize(true)
+    ///     .build_from_nfa::<usize>(&nfa)?;
+    /// assert_eq!(Some(8), dfa.find(b"xxfoo123"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn from_bytes(buf: &[u8]) -> Result<NFA> {
+        fn take<'b>(buf: &mut &'b [u8], len: usize) -> Result<&'b [u8]> {
+            if buf.len() < len {
+                return Err(Error::deserialize("unexpected end of input"));
+            }
+            let (head, tail) = buf.split_at(len);
+            *buf = tail;
+            Ok(head)
+        }
+        fn read_u64(buf: &mut &[u8]) -> Result<usize> {
+            let n = NativeEndian::read_u64(take(buf, 8)?);
+            if n as usize as u64 != n {
+                return Err(Error::deserialize("NFA is too big"));
+            }
+            Ok(n as usize)
+        }
+        fn read_transition(buf: &mut &[u8]) -> Result<Transition> {
+            let range = take(buf, 2)?;
+            let (start, end) = (range[0], range[1]);
+            if start > end {
+                return Err(Error::deserialize(&format!(
+                    "invalid transition range {}-{}",
+                    start, end,
+                )));
+            }
+            Ok(Transition { start, end, next: read_u64(buf)? })
+        }
+
+        let mut buf = buf;
+        if take(&mut buf, LABEL.len())? != LABEL {
+            return Err(Error::deserialize("could not find NFA label"));
+        }
+        if NativeEndian::read_u16(take(&mut buf, 2)?) != 0xFEFF {
+            return Err(Error::deserialize(
+                "endianness mismatch, NFA was serialized with a \
+                 different endianness",
+            ));
+        }
+        let version = NativeEndian::read_u16(take(&mut buf, 2)?);
+        if version != VERSION {
+            return Err(Error::deserialize(&format!(
+                "unsupported NFA version {}",
+                version,
+            )));
+        }
+        let opts = NativeEndian::read_u16(take(&mut buf, 2)?);
+        let start = read_u64(&mut buf)?;
+        let len = read_u64(&mut buf)?;
+        let classes = take(&mut buf, 256)?;
+        if classes[0] != 0
+            || (1..256).any(|b| {
+                let (prev, cur) = (classes[b - 1], classes[b]);
+                cur != prev && cur as usize != prev as usize + 1
+            })
+        {
+            return Err(Error::deserialize("invalid byte class map"));
+        }
+        let byte_classes = ByteClasses::from_slice(classes);
+
+        // Every state takes at least one byte, which bounds the allocation.
+        let mut states = Vec::with_capacity(::std::cmp::min(len, buf.len()));
+        for _ in 0..len {
+            let tag = take(&mut buf, 1)?[0];
+            let state = match tag {
+                TAG_RANGE => {
+                    State::Range { range: read_transition(&mut buf)? }
+                }
+                TAG_SPARSE => {
+                    let count = read_u64(&mut buf)?;
+                    let mut ranges: Vec<Transition> = vec![];
+                    for _ in 0..count {
+                        let t = read_transition(&mut buf)?;
+                        match ranges.last() {
+                            Some(prev) if prev.end >= t.start => {
+                                return Err(Error::deserialize(
+                                    "sparse transitions are not sorted",
+                                ));
+                            }
+                            _ => ranges.push(t),
+                        }
+                    }
+                    State::Sparse { ranges: ranges.into_boxed_slice() }
+                }
+                TAG_UNION => {
+                    let count = read_u64(&mut buf)?;
+                    let mut alternates = vec![];
+                    for _ in 0..count {
+                        alternates.push(read_u64(&mut buf)?);
+                    }
+                    State::Union { alternates: alternates.into_boxed_slice() }
+                }
+                TAG_FAIL => State::Fail,
+                TAG_MATCH => State::Match { pattern_id: read_u64(&mut buf)? },
+                _ => {
+                    return Err(Error::deserialize(&format!(
+                        "unknown NFA state tag {}",
+                        tag,
+                    )))
+                }
+            };
+            states.push(state);
+        }
+        if !buf.is_empty() {
+            return Err(Error::deserialize("trailing bytes after NFA states"));
+        }
+
+        let is_boundary =
+            |b: u8| b == 0 || byte_classes.get(b) != byte_classes.get(b - 1);
+        if start >= states.len() {
+            return Err(Error::deserialize("invalid NFA start state"));
+        }
+        for state in &states {
+            for t in state.transitions() {
+                if t.next >= states.len() {
+                    return Err(Error::deserialize(&format!(
+                        "transition to invalid NFA state {}",
+                        t.next,
+                    )));
+                }
+                // The determinizer only looks at one byte per class, so the
+                // classes must not split any range.
+                if !is_boundary(t.start)
+                    || (t.end < 255 && !is_boundary(t.end + 1))
+                {
+                    return Err(Error::deserialize(
+                        "transition range splits a byte class",
+                    ));
+                }
+            }
+            if state.epsilons().iter().any(|&alt| alt >= states.len()) {
+                return Err(Error::deserialize(
+                    "epsilon transition to invalid NFA state",
+                ));
+            }
+        }
+        Ok(NFA {
+            anchored: opts & MASK_ANCHORED > 0,
+            start,
+            states,
+            byte_classes,
+        })
+    }
 }
 
 impl fmt::Debug for NFA {
@@ -108,35 +492,91 @@ pub enum State {
     ///
     /// This is a special case of Sparse in that it encodes only one transition
     /// (and therefore avoids the allocation).
-    Range { range: Transition },
+    Range {
+        /// The single transition out of this state.
+        range: Transition,
+    },
     /// A state with possibly many transitions, represented in a sparse
     /// fashion. Transitions are ordered lexicographically by input range.
     /// As such, this may only be used when every transition has equal
     /// priority. (In practice, this is only used for encoding large UTF-8
     /// automata.)
-    Sparse { ranges: Box<[Transition]> },
+    Sparse {
+        /// The transitions out of this state, ordered by input range.
+        ranges: Box<[Transition]>,
+    },
     /// An alternation such that there exists an epsilon transition to all
     /// states in `alternates`, where matches found via earlier transitions
     /// are preferred over later transitions.
-    Union { alternates: Box<[StateID]> },
+    Union {
+        /// The targets of the epsilon transitions, in priority order.
+        alternates: Box<[StateID]>,
+    },
     /// A fail state. When encountered, the automaton is guaranteed to never
     /// reach a match state.
     Fail,
-    /// A match state. There is exactly one such occurrence of this state in
-    /// an NFA.
-    Match,
+    /// A match state for the pattern with the given identifier. There is
+    /// exactly one such occurrence of this state for each pattern in an NFA.
+    /// An NFA compiled from a single pattern has one match state, whose
+    /// pattern identifier is `0`.
+    Match {
+        /// The identifier of the pattern that matches in this state.
+        pattern_id: PatternID,
+    },
 }
 
 /// A transition to another state, only if the given byte falls in the
 /// inclusive range specified.
 #[derive(Clone, Copy, Eq, Hash, PartialEq)]
 pub struct Transition {
+    /// The first byte in this transition's range.
     pub start: u8,
+    /// The last byte in this transition's range.
     pub end: u8,
+    /// The state this transition leads to.
     pub next: StateID,
 }
 
+/// An iterator over the states of an NFA, along with their identifiers.
+///
+/// `'a` is the lifetime of the NFA.
+#[derive(Debug)]
+pub struct StateIter<'a> {
+    it: iter::Enumerate<slice::Iter<'a, State>>,
+}
+
+impl<'a> Iterator for StateIter<'a> {
+    type Item = (StateID, &'a State);
+
+    fn next(&mut self) -> Option<(StateID, &'a State)> {
+        self.it.next()
+    }
+}
+
 impl State {
+    /// Returns the transitions out of this state that consume a byte of
+    /// input, ordered by byte range.
+    ///
+    /// This is empty for union, fail and match states.
+    pub fn transitions(&self) -> &[Transition] {
+        match *self {
+            State::Range { ref range } => slice::from_ref(range),
+            State::Sparse { ref ranges } => ranges,
+            State::Union { .. } | State::Fail | State::Match { .. } => &[],
+        }
+    }
+
+    /// Returns the targets of the epsilon transitions out of this state, in
+    /// priority order, where earlier targets are preferred.
+    ///
+    /// This is empty for every state other than a union state.
+    pub fn epsilons(&self) -> &[StateID] {
+        match *self {
+            State::Union { ref alternates } => alternates,
+            _ => &[],
+        }
+    }
+
     /// Returns true if and only if this state contains one or more epsilon
     /// transitions.
     pub fn is_epsilon(&self) -> bool {
@@ -144,7 +584,7 @@ impl State {
             State::Range { .. }
             | State::Sparse { .. }
             | State::Fail
-            | State::Match => false,
+            | State::Match { .. } => false,
             State::Union { .. } => true,
         }
     }
@@ -169,7 +609,7 @@ impl State {
                 }
             }
             State::Fail => {}
-            State::Match => {}
+            State::Match { .. } => {}
         }
     }
 }
@@ -195,22 +635,33 @@ impl fmt::Debug for State {
                 write!(f, "alt({})", alts)
             }
             State::Fail => write!(f, "FAIL"),
-            State::Match => write!(f, "MATCH"),
+            State::Match { pattern_id } => write!(f, "MATCH({})", pattern_id),
         }
     }
 }
 
-impl fmt::Debug for Transition {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let Transition { start, end, next } = *self;
+impl Transition {
+    /// Returns the byte range of this transition in escaped form.
+    fn range_string(&self) -> String {
         if self.start == self.end {
-            write!(f, "{} => {}", escape(start), next)
+            escape(self.start)
         } else {
-            write!(f, "{}-{} => {}", escape(start), escape(end), next)
+            format!("{}-{}", escape(self.start), escape(self.end))
         }
     }
 }
 
+impl fmt::Debug for Transition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} => {}", self.range_string(), self.next)
+    }
+}
+
+/// Escape the given string for use in a quoted DOT identifier.
+pub(crate) fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
 /// Return the given byte as its escaped string form.
 fn escape(b: u8) -> String {
     use std::ascii;
@@ -249,4 +700,83 @@ mod tests {
         assert_eq!(None, dfa.find_at(b"ab", 1));
         assert_eq!(None, dfa.find_at(b"ab", 2));
     }
+
+    #[test]
+    fn epsilon_closure_is_in_priority_order() {
+        let nfa =
+            dense::Builder::new().anchored(true).build_nfa("a|b|").unwrap();
+        let closure = nfa.epsilon_closure(nfa.start());
+        assert_eq!(nfa.start(), closure[0]);
+
+        let firsts: Vec<u8> = closure
+            .iter()
+            .flat_map(|&id| nfa.state(id).transitions())
+            .map(|t| t.start)
+            .collect();
+        assert_eq!(vec![b'a', b'b'], firsts);
+        match *nfa.state(*closure.last().unwrap()) {
+            State::Match { pattern_id: 0 } => {}
+            ref state => panic!("unexpected state: {:?}", state),
+        }
+        assert_eq!(nfa.len(), nfa.states().count());
+    }
+
+    #[test]
+    fn serialize_round_trip() {
+        let patterns = &[r"\w+[0-9]", "a|b|", "(?i)foo.*bar"];
+        for &pattern in patterns {
+            let nfa = dense::Builder::new().build_nfa(pattern).unwrap();
+            let bytes = nfa.to_bytes_native_endian();
+            let got = NFA::from_bytes(&bytes).unwrap();
+            assert_eq!(nfa.is_anchored(), got.is_anchored());
+            assert_eq!(nfa.start(), got.start());
+            assert_eq!(nfa.byte_classes(), got.byte_classes());
+            assert!(nfa.states().eq(got.states()));
+
+            let builder = dense::Builder::new();
+            assert_eq!(
+                builder.build_from_nfa::<usize>(&nfa).unwrap(),
+                builder.build_from_nfa::<usize>(&got).unwrap(),
+            );
+            for end in 0..bytes.len() {
+                assert!(NFA::from_bytes(&bytes[..end]).is_err());
+            }
+        }
+
+        let nfa = dense::Builder::new().anchored(true).build_nfa("a").unwrap();
+        let got = NFA::from_bytes(&nfa.to_bytes_native_endian()).unwrap();
+        assert!(got.is_anchored());
+    }
+
+    #[test]
+    fn deserialize_rejects_invalid_states() {
+        let nfa = dense::Builder::new().anchored(true).build_nfa("a").unwrap();
+        let mut bytes = nfa.to_bytes_native_endian();
+        let foreign = if cfg!(target_endian = "little") {
+            nfa.to_bytes_big_endian()
+        } else {
+            nfa.to_bytes_little_endian()
+        };
+        assert!(NFA::from_bytes(&foreign).is_err());
+
+        // Point the start state past the end of the NFA.
+        let start = LABEL.len() + 6;
+        NativeEndian::write_u64(&mut bytes[start..], nfa.len() as u64);
+        assert!(NFA::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn to_dot_has_an_edge_per_transition() {
+        let nfa =
+            dense::Builder::new().anchored(true).build_nfa("\"|a*").unwrap();
+        let dot = nfa.to_dot();
+        let edges: usize = nfa
+            .states()
+            .map(|(_, s)| s.transitions().len() + s.epsilons().len())
+            .sum();
+        // One more edge comes from the start marker.
+        assert_eq!(edges + 1, dot.matches("->").count());
+        assert!(dot.contains(r#"[label="\\\""]"#));
+        assert!(dot.contains("shape=doublecircle"));
+    }
 }
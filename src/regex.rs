@@ -1,9 +1,13 @@
+use core::ops::Range;
+
 #[cfg(feature = "std")]
 use dense::{self, DenseDFA};
 use dfa::DFA;
 #[cfg(feature = "std")]
 use error::Result;
 #[cfg(feature = "std")]
+use multi::PatternID;
+#[cfg(feature = "std")]
 use sparse::SparseDFA;
 #[cfg(feature = "std")]
 use state_id::StateID;
@@ -58,6 +62,7 @@ use state_id::StateID;
 pub struct Regex<D: DFA = DenseDFA<Vec<usize>, usize>> {
     forward: D,
     reverse: D,
+    allow_empty_matches: bool,
 }
 
 /// A regular expression that uses deterministic finite automata for fast
@@ -109,6 +114,7 @@ pub struct Regex<D: DFA = DenseDFA<Vec<usize>, usize>> {
 pub struct Regex<D> {
     forward: D,
     reverse: D,
+    allow_empty_matches: bool,
 }
 
 #[cfg(feature = "std")]
@@ -177,6 +183,10 @@ impl<D: DFA> Regex<D> {
     /// DFA enters a match state or a dead state, then this routine will return
     /// `true` or `false`, respectively, without inspecting any future input.
     ///
+    /// Only the forward DFA is used. Since the start of the match is never
+    /// computed, this is cheaper than `find` whenever the position of the
+    /// match isn't needed.
+    ///
     /// # Example
     ///
     /// ```
@@ -199,6 +209,9 @@ impl<D: DFA> Regex<D> {
     /// position at which it stopped scanning input if and only if a match
     /// was found. If no match is found, then `None` is returned.
     ///
+    /// Like `is_match`, this only runs the forward DFA and skips the reverse
+    /// scan that `find` uses to locate the start of a match.
+    ///
     /// # Example
     ///
     /// ```
@@ -253,6 +266,92 @@ impl<D: DFA> Regex<D> {
         self.find_at(input, 0)
     }
 
+    /// Returns the start and end offset of a match that ends as early as
+    /// possible. If no match exists, then `None` is returned.
+    ///
+    /// The end offset is the one reported by `shortest_match`, and the start
+    /// offset is the leftmost position from which a match ends there. The
+    /// span returned may therefore differ from the one reported by `find`.
+    ///
+    /// Like `find`, this runs two passes: the forward DFA scans for the end
+    /// of the match, and then the reverse DFA scans backwards from the end to
+    /// find its start. The difference is that the forward pass stops at the
+    /// first match state instead of continuing to look for the leftmost
+    /// first end, which can be much further along. The reverse pass only
+    /// covers the bytes between the search start and the match end. When all
+    /// that's needed is some match and where it lies, this does strictly less
+    /// work than `find`.
+    ///
+    /// A DFA state cannot record the offset at which a match began, so
+    /// recovering the start in the forward pass alone would require tagged
+    /// transitions that update position registers as the search proceeds.
+    /// The DFAs in this crate have no such registers, which is why the
+    /// reverse scan is always needed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::Regex;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = Regex::new("[a-z]+ing")?;
+    /// assert_eq!(Some((3, 7)), re.find_earliest(b"zz singing"));
+    /// assert_eq!(Some((3, 10)), re.find(b"zz singing"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn find_earliest(&self, input: &[u8]) -> Option<(usize, usize)> {
+        self.find_earliest_at(input, 0)
+    }
+
+    /// Returns the bytes of the leftmost first match in the given input. If
+    /// no match exists, then `None` is returned.
+    ///
+    /// This finds the same match as `find`, but returns the matched
+    /// sub-slice of `input` instead of its offsets. The slice returned
+    /// borrows from `input` (not from this regex), so it lives as long as the
+    /// input does.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::Regex;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = Regex::new("[0-9]{4}-[0-9]{2}-[0-9]{2}")?;
+    /// let line = b"released on 2020-06-29, tagged later";
+    /// assert_eq!(Some(&b"2020-06-29"[..]), re.find_slice(line));
+    /// assert_eq!(None, re.find_slice(b"not yet released"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn find_slice<'t>(&self, input: &'t [u8]) -> Option<&'t [u8]> {
+        self.find(input).map(|(start, end)| &input[start..end])
+    }
+
+    /// Returns the leftmost first match in the given input as a
+    /// [`Match`](struct.Match.html). If no match exists, then `None` is
+    /// returned.
+    ///
+    /// This finds the same match as `find`, which remains the lower level
+    /// routine returning a bare `(start, end)` pair.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::Regex;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = Regex::new("[0-9]+")?;
+    /// let haystack = b"order 1337 shipped";
+    /// let m = re.find_match(haystack).unwrap();
+    /// assert_eq!(6..10, m.range());
+    /// assert_eq!(4, m.len());
+    /// assert_eq!(b"1337", m.as_slice(haystack));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn find_match(&self, input: &[u8]) -> Option<Match> {
+        self.find_match_at(input, 0)
+    }
+
     /// Returns the same as `is_match`, but starts the search at the given
     /// offset.
     ///
@@ -283,6 +382,28 @@ impl<D: DFA> Regex<D> {
     /// The significance of the starting point is that it takes the surrounding
     /// context into consideration. For example, if the DFA is anchored, then
     /// a match can only occur when `start == 0`.
+    ///
+    /// The offsets returned are relative to the beginning of `input`, not to
+    /// `start`. The match always begins at or after `start`: the forward
+    /// search starts reading at `start`, and the reverse search that finds
+    /// the beginning of the match only looks at `input[start..end]`, so it
+    /// never reaches back into the bytes before `start`, even if the pattern
+    /// could match them. This makes `find_at` the primitive behind
+    /// `find_iter`, which resumes each search where the previous match ended.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::Regex;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = Regex::new("[a-z]+")?;
+    /// assert_eq!(Some((0, 6)), re.find_at(b"foobar 42", 0));
+    /// // The match is cut short at `start` instead of extending backwards.
+    /// assert_eq!(Some((3, 6)), re.find_at(b"foobar 42", 3));
+    /// assert_eq!(None, re.find_at(b"foobar 42", 6));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
     pub fn find_at(
         &self,
         input: &[u8],
@@ -300,6 +421,36 @@ impl<D: DFA> Regex<D> {
         Some((start, end))
     }
 
+    /// Returns the same as `find_match`, but starts the search at the given
+    /// offset.
+    ///
+    /// The significance of the starting point is that it takes the surrounding
+    /// context into consideration. For example, if the DFA is anchored, then
+    /// a match can only occur when `start == 0`.
+    pub fn find_match_at(&self, input: &[u8], start: usize) -> Option<Match> {
+        self.find_at(input, start).map(|(start, end)| Match::new(start, end))
+    }
+
+    /// Returns the same as `find_earliest`, but starts the search at the
+    /// given offset.
+    ///
+    /// The significance of the starting point is that it takes the surrounding
+    /// context into consideration. For example, if the DFA is anchored, then
+    /// a match can only occur when `start == 0`.
+    pub fn find_earliest_at(
+        &self,
+        input: &[u8],
+        start: usize,
+    ) -> Option<(usize, usize)> {
+        let end = self.forward().shortest_match_at(input, start)?;
+        let start = self
+            .reverse()
+            .rfind(&input[start..end])
+            .map(|i| start + i)
+            .expect("reverse search must match if forward search does");
+        Some((start, end))
+    }
+
     /// Returns an iterator over all non-overlapping leftmost first matches
     /// in the given bytes. If no match exists, then the iterator yields no
     /// elements.
@@ -323,10 +474,175 @@ impl<D: DFA> Regex<D> {
     /// assert_eq!(matches, vec![(0, 4), (5, 10), (11, 17)]);
     /// # Ok(()) }; example().unwrap()
     /// ```
+    ///
+    /// When the regex can match the empty string, the iterator always makes
+    /// progress. After an empty match, the next search begins one byte later,
+    /// and an empty match that immediately follows the end of the previous
+    /// match is skipped:
+    ///
+    /// ```
+    /// use regex_automata::Regex;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = Regex::new("a*")?;
+    /// let matches: Vec<(usize, usize)> = re.find_iter(b"baaab").collect();
+    /// assert_eq!(matches, vec![(0, 0), (1, 4), (5, 5)]);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    ///
+    /// If the regex is anchored, then it can only match at the beginning of
+    /// the input, so the iterator yields at most one match:
+    ///
+    /// ```
+    /// use regex_automata::RegexBuilder;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = RegexBuilder::new().anchored(true).build("abc")?;
+    /// let matches: Vec<(usize, usize)> = re.find_iter(b"abcabc").collect();
+    /// assert_eq!(matches, vec![(0, 3)]);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
     pub fn find_iter<'r, 't>(&'r self, input: &'t [u8]) -> Matches<'r, 't, D> {
         Matches::new(self, input)
     }
 
+    /// Calls `f` with the start and end of each match that `find_iter`
+    /// would yield, in order, until `f` returns `false`.
+    ///
+    /// This suits consumers that handle each match as it's found, such as
+    /// ones that write matches out or count them, and makes stopping early
+    /// as simple as returning `false`. It visits exactly the same matches as
+    /// `find_iter`, including its handling of empty matches, and allocates
+    /// nothing.
+    ///
+    /// This lives on `Regex` rather than on the [`DFA`](trait.DFA.html)
+    /// trait, since a single DFA only reports where a match ends. Finding
+    /// where it starts needs the reverse DFA.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::Regex;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = Regex::new("[0-9]+")?;
+    /// let mut total = 0;
+    /// re.for_each_match(b"1 22 333 4444", |start, end| {
+    ///     total += end - start;
+    ///     // Stop once the longest number so far is 3 digits long.
+    ///     end - start < 3
+    /// });
+    /// assert_eq!(6, total);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn for_each_match<F: FnMut(usize, usize) -> bool>(
+        &self,
+        input: &[u8],
+        mut f: F,
+    ) {
+        for (start, end) in self.find_iter(input) {
+            if !f(start, end) {
+                break;
+            }
+        }
+    }
+
+    /// Returns an iterator over all non-overlapping leftmost first matches
+    /// in each line of the given bytes.
+    ///
+    /// The input is split into lines at each occurrence of the given
+    /// terminator, and each line is searched separately as if by
+    /// `find_iter`. So a match never spans more than one line and never
+    /// includes a line terminator, and an anchored regex can match at the
+    /// start of every line. The offsets reported are still offsets into the
+    /// entire input, not into the line.
+    ///
+    /// The last line of the input need not end with a terminator. But if it
+    /// does, then there is no empty line after it, which is consistent with
+    /// how `str::lines` works. Empty lines elsewhere are searched like any
+    /// other line.
+    ///
+    /// With [`LineTerminator::CrLf`](enum.LineTerminator.html#variant.CrLf),
+    /// a `\r` immediately preceding a `\n` is part of the terminator and so
+    /// is excluded from the line. A `\r` anywhere else, including at the very
+    /// end of the input, is an ordinary byte.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{LineTerminator, RegexBuilder};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = RegexBuilder::new().anchored(true).build(r"[a-z]+\s*")?;
+    /// let text = b"foo \r\n1\r\nbar\r";
+    /// let matches: Vec<(usize, usize)> =
+    ///     re.find_iter_lines(text, LineTerminator::CrLf).collect();
+    /// assert_eq!(matches, vec![(0, 4), (9, 13)]);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn find_iter_lines<'r, 't>(
+        &'r self,
+        input: &'t [u8],
+        terminator: LineTerminator,
+    ) -> LineMatches<'r, 't, D> {
+        LineMatches::new(self, input, terminator)
+    }
+
+    /// Returns the `n`th match (counting from zero) among the matches
+    /// yielded by `find_iter`. If there are `n` or fewer matches, then `None`
+    /// is returned.
+    ///
+    /// This advances through the earlier matches exactly as `find_iter`
+    /// does, including its handling of empty matches, but nothing is
+    /// allocated and only the position of each match is kept.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::Regex;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = Regex::new("[0-9]+")?;
+    /// let text = b"1 22 333 4444";
+    /// assert_eq!(Some((0, 1)), re.nth_match(text, 0));
+    /// assert_eq!(Some((5, 8)), re.nth_match(text, 2));
+    /// assert_eq!(None, re.nth_match(text, 4));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn nth_match(&self, input: &[u8], n: usize) -> Option<(usize, usize)> {
+        self.find_iter(input).nth(n)
+    }
+
+    /// Returns the last match among the matches yielded by `find_iter`. If
+    /// no match exists, then `None` is returned.
+    ///
+    /// Matches are found exactly as `find_iter` finds them, scanning forward
+    /// through the input, so this reports the last of a sequence of
+    /// non-overlapping leftmost first matches. This isn't necessarily the
+    /// match that a backwards search would find first: for example, the last
+    /// match of `aa` in `aaa` is `(0, 2)`. Only the most recent match is
+    /// kept, so nothing is allocated.
+    ///
+    /// Since an anchored regex matches at most once, this returns the same
+    /// match as `find` for anchored regexes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::Regex;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = Regex::new("[a-z]+")?;
+    /// assert_eq!(Some((8, 12)), re.last_match(b"foo bar quux 42"));
+    ///
+    /// let re = Regex::new("aa")?;
+    /// assert_eq!(Some((0, 2)), re.last_match(b"aaa"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn last_match(&self, input: &[u8]) -> Option<(usize, usize)> {
+        self.find_iter(input).last()
+    }
+
     /// Build a new regex from its constituent forward and reverse DFAs.
     ///
     /// This is useful when deserializing a regex from some arbitrary
@@ -386,7 +702,7 @@ impl<D: DFA> Regex<D> {
     /// # Ok(()) }; example().unwrap()
     /// ```
     pub fn from_dfas(forward: D, reverse: D) -> Regex<D> {
-        Regex { forward, reverse }
+        Regex { forward, reverse, allow_empty_matches: true }
     }
 
     /// Return the underlying DFA responsible for forward matching.
@@ -398,6 +714,90 @@ impl<D: DFA> Regex<D> {
     pub fn reverse(&self) -> &D {
         &self.reverse
     }
+
+    /// Consume this regex and return its forward and reverse DFAs, in that
+    /// order.
+    ///
+    /// This is the inverse of
+    /// [`Regex::from_dfas`](struct.Regex.html#method.from_dfas), and is
+    /// useful when one wants to take ownership of the DFAs without cloning
+    /// them, for example, to serialize them or to convert them into a
+    /// different representation.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::Regex;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = Regex::new("foo[0-9]+")?;
+    /// let (fwd, rev) = re.into_dfas();
+    /// let re = Regex::from_dfas(fwd.to_u16()?, rev.to_u16()?);
+    /// assert_eq!(Some((0, 6)), re.find(b"foo123"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn into_dfas(self) -> (D, D) {
+        (self.forward, self.reverse)
+    }
+}
+
+/// The span of a single match in a haystack.
+///
+/// The start offset is inclusive and the end offset is exclusive, so an empty
+/// match has equal start and end offsets. A match doesn't borrow the
+/// haystack it was found in, but
+/// [`as_slice`](struct.Match.html#method.as_slice) can recover the matched
+/// bytes from it.
+///
+/// This is returned by
+/// [`Regex::find_match`](struct.Regex.html#method.find_match).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Match {
+    start: usize,
+    end: usize,
+}
+
+impl Match {
+    /// Create a match from its start and end offsets.
+    ///
+    /// This panics if `start > end`.
+    pub fn new(start: usize, end: usize) -> Match {
+        assert!(start <= end, "match start {} exceeds end {}", start, end);
+        Match { start, end }
+    }
+
+    /// Returns the offset at which this match begins.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Returns the offset immediately following the end of this match.
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// Returns the offsets of this match as a range.
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// Returns the number of bytes in this match.
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Returns true if and only if this match is empty.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Returns the bytes of the given haystack covered by this match.
+    ///
+    /// The haystack should be the one this match was found in. This panics
+    /// if the match lies outside of the haystack.
+    pub fn as_slice<'t>(&self, haystack: &'t [u8]) -> &'t [u8] {
+        &haystack[self.range()]
+    }
 }
 
 /// An iterator over all non-overlapping matches for a particular search.
@@ -429,28 +829,346 @@ impl<'r, 't, D: DFA> Iterator for Matches<'r, 't, D> {
     type Item = (usize, usize);
 
     fn next(&mut self) -> Option<(usize, usize)> {
-        if self.last_end > self.text.len() {
+        loop {
+            if self.last_end > self.text.len() {
+                return None;
+            }
+            let (s, e) = match self.re.find_at(self.text, self.last_end) {
+                None => return None,
+                Some((s, e)) => (s, e),
+            };
+            if s == e {
+                // This is an empty match. To ensure we make progress, start
+                // the next search at the smallest possible starting position
+                // of the next match following this one.
+                self.last_end = e + 1;
+                // Don't accept empty matches immediately following a match,
+                // or at all if the regex was configured to skip them. Just
+                // move on to the next match.
+                if Some(e) == self.last_match || !self.re.allow_empty_matches {
+                    continue;
+                }
+            } else {
+                self.last_end = e;
+            }
+            if self.re.forward().is_anchored() {
+                // An anchored regex only matches at the beginning of the
+                // text, so there can't be another match.
+                self.last_end = self.text.len() + 1;
+            }
+            self.last_match = Some(e);
+            return Some((s, e));
+        }
+    }
+}
+
+/// The byte sequence that separates lines, as used by
+/// [`Regex::find_iter_lines`](struct.Regex.html#method.find_iter_lines).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LineTerminator {
+    /// Lines end with `\n`.
+    Lf,
+    /// Lines end with `\n`, optionally preceded by `\r`. This handles both
+    /// Windows and Unix line endings.
+    CrLf,
+    /// Lines end with the given byte. For example, `Byte(0)` splits a list
+    /// of NUL terminated records.
+    Byte(u8),
+}
+
+/// An iterator over all non-overlapping matches in each line of a
+/// particular search text.
+///
+/// This iterator yields the same tuples as `Regex::find_iter`, with offsets
+/// into the entire text.
+///
+/// The lifetime variables are as follows:
+///
+/// * `'r` is the lifetime of the regular expression value itself.
+/// * `'t` is the lifetime of the text being searched.
+#[derive(Clone, Debug)]
+pub struct LineMatches<'r, 't, D: DFA + 'r> {
+    re: &'r Regex<D>,
+    text: &'t [u8],
+    terminator: LineTerminator,
+    /// The offset of the next line that hasn't been searched yet.
+    next_line: usize,
+    /// The matches in the current line, along with the line's offset.
+    line: Option<(usize, Matches<'r, 't, D>)>,
+}
+
+impl<'r, 't, D: DFA> LineMatches<'r, 't, D> {
+    fn new(
+        re: &'r Regex<D>,
+        text: &'t [u8],
+        terminator: LineTerminator,
+    ) -> LineMatches<'r, 't, D> {
+        LineMatches { re, text, terminator, next_line: 0, line: None }
+    }
+}
+
+impl<'r, 't, D: DFA> Iterator for LineMatches<'r, 't, D> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<(usize, usize)> {
+        loop {
+            if let Some((offset, ref mut matches)) = self.line {
+                if let Some((s, e)) = matches.next() {
+                    return Some((offset + s, offset + e));
+                }
+            }
+            if self.next_line >= self.text.len() {
+                return None;
+            }
+            let rest = &self.text[self.next_line..];
+            let byte = match self.terminator {
+                LineTerminator::Lf | LineTerminator::CrLf => b'\n',
+                LineTerminator::Byte(byte) => byte,
+            };
+            let (end, len) = match rest.iter().position(|&b| b == byte) {
+                None => (rest.len(), rest.len()),
+                Some(i) => match self.terminator {
+                    LineTerminator::CrLf if i > 0 && rest[i - 1] == b'\r' => {
+                        (i - 1, i + 1)
+                    }
+                    _ => (i, i + 1),
+                },
+            };
+            self.line =
+                Some((self.next_line, self.re.find_iter(&rest[..end])));
+            self.next_line += len;
+        }
+    }
+}
+
+/// A regular expression built from several patterns, which reports the span
+/// of each match along with the pattern that matched.
+///
+/// Searching behaves like a `Regex` for the alternation of every pattern, in
+/// order, with leftmost first semantics. That is, the match that starts
+/// earliest is reported, and when several patterns match at that position,
+/// the one given first wins. For example, with the patterns `[a-z]+` and
+/// `foo[0-9]+`, the haystack `foo123` matches `foo` with pattern `0`.
+///
+/// # How the start of a match is found
+///
+/// The forward DFA is the DFA of the alternation, in which each match state
+/// also records the pattern responsible for it. Under leftmost first
+/// semantics, the match states that follow one pattern's match drop every
+/// less preferred alternative, so each match state belongs to exactly one
+/// pattern, and the last match state seen by the forward search identifies
+/// the winning pattern.
+///
+/// Finding the start then requires a reverse DFA that matches only that
+/// pattern, since a reverse DFA for the whole alternation could report the
+/// start of a different pattern's match ending at the same position. This
+/// type keeps one anchored, reverse DFA per pattern, and runs the one for the
+/// winning pattern backwards from the end of the match. A single reverse DFA
+/// with tagged match states would use less memory, but it would need to
+/// track matches of every pattern at once, much like a
+/// [`MultiDFA`](struct.MultiDFA.html), which makes its states considerably
+/// more numerous.
+///
+/// A multi-pattern regex is built with
+/// [`RegexBuilder::build_many`](struct.RegexBuilder.html#method.build_many).
+///
+/// # Example
+///
+/// ```
+/// use regex_automata::RegexBuilder;
+///
+/// # fn example() -> Result<(), regex_automata::Error> {
+/// let re = RegexBuilder::new().build_many(&[r"[0-9]+", r"[a-z]+"])?;
+/// let matches: Vec<(usize, usize, usize)> = re.find_iter(b"abc 123 d4").collect();
+/// assert_eq!(matches, vec![(1, 0, 3), (0, 4, 7), (1, 8, 9), (0, 9, 10)]);
+/// # Ok(()) }; example().unwrap()
+/// ```
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct MultiRegex<D: DFA = DenseDFA<Vec<usize>, usize>> {
+    forward: D,
+    /// Every match state of `forward`, sorted by identifier, along with the
+    /// pattern that matches in it.
+    matches: Vec<(D::ID, PatternID)>,
+    /// An anchored reverse DFA for each pattern, indexed by pattern
+    /// identifier.
+    reverse: Vec<D>,
+    allow_empty_matches: bool,
+}
+
+#[cfg(feature = "std")]
+impl<D: DFA> MultiRegex<D> {
+    /// Return the number of patterns this regex was built from.
+    pub fn pattern_count(&self) -> usize {
+        self.reverse.len()
+    }
+
+    /// Returns the pattern and span of the leftmost first match in the given
+    /// bytes, as a `(pattern, start, end)` triple. If no pattern matches,
+    /// then `None` is returned.
+    pub fn find(&self, input: &[u8]) -> Option<(PatternID, usize, usize)> {
+        self.find_at(input, 0)
+    }
+
+    /// Returns the same as `find`, but starts the search at the given
+    /// offset.
+    ///
+    /// The pattern identifier in the returned triple is the index of the
+    /// matching pattern in the slice given to `build_many`. When more than
+    /// one pattern matches at the leftmost position, the one given first
+    /// wins, in the same way that earlier alternatives of a single pattern
+    /// take priority under leftmost first semantics. The start of the match
+    /// is then found with the reverse DFA of the winning pattern alone, so
+    /// the span is always one that this pattern matches by itself.
+    ///
+    /// As with
+    /// [`Regex::find_at`](struct.Regex.html#method.find_at), the offsets
+    /// returned are relative to the beginning of `input`, and the match
+    /// never begins before `start`, even if the winning pattern could match
+    /// the bytes before it. If the regex is anchored, then a match can only
+    /// occur when `start == 0`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::RegexBuilder;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = RegexBuilder::new().build_many(&["[a-z]+", "[0-9]+"])?;
+    /// let input = b"abc 123 xyz";
+    /// assert_eq!(Some((0, 0, 3)), re.find_at(input, 0));
+    /// assert_eq!(Some((1, 4, 7)), re.find_at(input, 3));
+    /// // The match is cut short at `start` instead of extending backwards.
+    /// assert_eq!(Some((1, 5, 7)), re.find_at(input, 5));
+    /// assert_eq!(None, re.find_at(input, 11));
+    ///
+    /// // Both patterns match at offset 0, so the first one given wins.
+    /// let re = RegexBuilder::new().build_many(&["foo", "foo[0-9]+"])?;
+    /// assert_eq!(Some((0, 0, 3)), re.find_at(b"foo123", 0));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn find_at(
+        &self,
+        input: &[u8],
+        start: usize,
+    ) -> Option<(PatternID, usize, usize)> {
+        let (end, id) = self.find_end(input, start)?;
+        let pattern = match self.matches.binary_search_by_key(&id, |m| m.0) {
+            Ok(i) => self.matches[i].1,
+            Err(_) => unreachable!("match state without a pattern"),
+        };
+        let start = self.reverse[pattern]
+            .rfind(&input[start..end])
+            .map(|i| start + i)
+            .expect("reverse search must match if forward search does");
+        Some((pattern, start, end))
+    }
+
+    /// Returns an iterator over all non-overlapping leftmost first matches
+    /// in the given bytes, as `(pattern, start, end)` triples.
+    ///
+    /// Empty matches and anchored regexes are handled in the same way as by
+    /// [`Regex::find_iter`](struct.Regex.html#method.find_iter).
+    pub fn find_iter<'r, 't>(
+        &'r self,
+        input: &'t [u8],
+    ) -> MultiMatches<'r, 't, D> {
+        MultiMatches::new(self, input)
+    }
+
+    /// Return the underlying DFA responsible for forward matching.
+    pub fn forward(&self) -> &D {
+        &self.forward
+    }
+
+    /// Return the reverse DFA for the given pattern.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `pattern` is not less than `pattern_count()`.
+    pub fn reverse(&self, pattern: PatternID) -> &D {
+        &self.reverse[pattern]
+    }
+
+    /// Run the forward DFA like `DFA::find_at`, and return the end of the
+    /// match along with the match state it ended in.
+    fn find_end(&self, input: &[u8], start: usize) -> Option<(usize, D::ID)> {
+        let dfa = &self.forward;
+        if dfa.is_anchored() && start > 0 {
             return None;
         }
-        let (s, e) = match self.re.find_at(self.text, self.last_end) {
-            None => return None,
-            Some((s, e)) => (s, e),
+        let mut state = dfa.start_state();
+        let mut last_match = if dfa.is_dead_state(state) {
+            return None;
+        } else if dfa.is_match_state(state) {
+            Some((start, state))
+        } else {
+            None
         };
-        if s == e {
-            // This is an empty match. To ensure we make progress, start
-            // the next search at the smallest possible starting position
-            // of the next match following this one.
-            self.last_end = e + 1;
-            // Don't accept empty matches immediately following a match.
-            // Just move on to the next match.
-            if Some(e) == self.last_match {
-                return self.next();
+        for (i, &b) in input[start..].iter().enumerate() {
+            state = unsafe { dfa.next_state_unchecked(state, b) };
+            if dfa.is_match_or_dead_state(state) {
+                if dfa.is_dead_state(state) {
+                    return last_match;
+                }
+                last_match = Some((start + i + 1, state));
             }
-        } else {
-            self.last_end = e;
         }
-        self.last_match = Some(e);
-        Some((s, e))
+        last_match
+    }
+}
+
+/// An iterator over all non-overlapping matches of a multi-pattern regex.
+///
+/// The iterator yields a `(PatternID, usize, usize)` value for each match,
+/// containing the pattern that matched, followed by the start (inclusive)
+/// and end (exclusive) of the match.
+///
+/// `'r` is the lifetime of the regex and `'t` is the lifetime of the text
+/// being searched.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct MultiMatches<'r, 't, D: DFA + 'r> {
+    re: &'r MultiRegex<D>,
+    text: &'t [u8],
+    last_end: usize,
+    last_match: Option<usize>,
+}
+
+#[cfg(feature = "std")]
+impl<'r, 't, D: DFA> MultiMatches<'r, 't, D> {
+    fn new(re: &'r MultiRegex<D>, text: &'t [u8]) -> MultiMatches<'r, 't, D> {
+        MultiMatches { re, text, last_end: 0, last_match: None }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'r, 't, D: DFA> Iterator for MultiMatches<'r, 't, D> {
+    type Item = (PatternID, usize, usize);
+
+    fn next(&mut self) -> Option<(PatternID, usize, usize)> {
+        loop {
+            if self.last_end > self.text.len() {
+                return None;
+            }
+            let (p, s, e) = self.re.find_at(self.text, self.last_end)?;
+            if s == e {
+                // As with `Matches`, an empty match moves the next search
+                // forward by one byte, and is skipped if it immediately
+                // follows the previous match.
+                self.last_end = e + 1;
+                if Some(e) == self.last_match || !self.re.allow_empty_matches {
+                    continue;
+                }
+            } else {
+                self.last_end = e;
+            }
+            if self.re.forward().is_anchored() {
+                self.last_end = self.text.len() + 1;
+            }
+            self.last_match = Some(e);
+            return Some((p, s, e));
+        }
     }
 }
 
@@ -471,13 +1189,14 @@ impl<'r, 't, D: DFA> Iterator for Matches<'r, 't, D> {
 #[derive(Clone, Debug)]
 pub struct RegexBuilder {
     dfa: dense::Builder,
+    allow_empty_matches: bool,
 }
 
 #[cfg(feature = "std")]
 impl RegexBuilder {
     /// Create a new regex builder with the default configuration.
     pub fn new() -> RegexBuilder {
-        RegexBuilder { dfa: dense::Builder::new() }
+        RegexBuilder { dfa: dense::Builder::new(), allow_empty_matches: true }
     }
 
     /// Build a regex from the given pattern.
@@ -533,7 +1252,60 @@ impl RegexBuilder {
             .reverse(true)
             .longest_match(true)
             .build_with_size(pattern)?;
-        Ok(Regex::from_dfas(forward, reverse))
+        let mut re = Regex::from_dfas(forward, reverse);
+        re.allow_empty_matches = self.allow_empty_matches;
+        Ok(re)
+    }
+
+    /// Build a regex that searches for all of the given patterns at once,
+    /// and reports which of them matched.
+    ///
+    /// Each pattern is identified by its index in `patterns`. See
+    /// [`MultiRegex`](struct.MultiRegex.html) for how matches are chosen
+    /// and how their spans are found.
+    ///
+    /// The `minimize` option has no effect on the forward DFA, since
+    /// minimization cannot yet distinguish between match states of different
+    /// patterns. It still applies to the reverse DFAs. The `match_priority`
+    /// option is ignored, and the prefilter is never used.
+    ///
+    /// If any pattern fails to parse or compile, then the first such error is
+    /// returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::RegexBuilder;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = RegexBuilder::new().build_many(&["foo[0-9]+", "[a-z]+"])?;
+    /// assert_eq!(Some((0, 2, 7)), re.find(b"  foo12 bar"));
+    /// assert_eq!(Some((1, 2, 5)), re.find(b"  fob12 bar"));
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn build_many<P: AsRef<str>>(
+        &self,
+        patterns: &[P],
+    ) -> Result<MultiRegex> {
+        let mut exprs = Vec::with_capacity(patterns.len());
+        for pattern in patterns {
+            exprs.push(self.dfa.build_hir(pattern.as_ref())?);
+        }
+        let mut matches = vec![];
+        let forward =
+            self.dfa.build_many_leftmost_first(&exprs, &mut matches)?;
+        let mut rev_builder = self.dfa.clone();
+        rev_builder.anchored(true).reverse(true).longest_match(true);
+        let mut reverse = Vec::with_capacity(exprs.len());
+        for expr in &exprs {
+            reverse.push(rev_builder.build_from_hir(expr)?);
+        }
+        Ok(MultiRegex {
+            forward,
+            matches,
+            reverse,
+            allow_empty_matches: self.allow_empty_matches,
+        })
     }
 
     /// Build a regex from the given pattern using a specific representation
@@ -545,7 +1317,47 @@ impl RegexBuilder {
         let re = self.build_with_size(pattern)?;
         let fwd = re.forward().to_sparse()?;
         let rev = re.reverse().to_sparse()?;
-        Ok(Regex::from_dfas(fwd, rev))
+        let mut sparse = Regex::from_dfas(fwd, rev);
+        sparse.allow_empty_matches = self.allow_empty_matches;
+        Ok(sparse)
+    }
+
+    /// Set whether [`find_iter`](struct.Regex.html#method.find_iter) reports
+    /// zero-width matches.
+    ///
+    /// A pattern that can match the empty string, such as `(?:)` or `a*`,
+    /// produces a zero-width match (where the start and end offsets
+    /// are equal) at every position where it can't match anything longer.
+    /// When this is enabled, those matches are reported by `find_iter` as
+    /// usual. When disabled, `find_iter` silently skips them and only yields
+    /// matches with at least one byte.
+    ///
+    /// Note that a skipped empty match is not replaced by a longer match
+    /// starting at the same position. For example, `|a` prefers the empty
+    /// alternative at every position under leftmost first semantics, so with
+    /// this disabled, `find_iter` never yields anything for it.
+    ///
+    /// This only affects match iteration. Routines that report a single
+    /// match, such as `is_match` and `find`, still report empty matches.
+    ///
+    /// This is enabled by default. A regex built with
+    /// [`Regex::from_dfas`](struct.Regex.html#method.from_dfas) always
+    /// allows empty matches.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::RegexBuilder;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = RegexBuilder::new().allow_empty_matches(false).build("a*")?;
+    /// let matches: Vec<(usize, usize)> = re.find_iter(b"baaab").collect();
+    /// assert_eq!(matches, vec![(1, 4)]);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn allow_empty_matches(&mut self, yes: bool) -> &mut RegexBuilder {
+        self.allow_empty_matches = yes;
+        self
     }
 
     /// Set whether matching must be anchored at the beginning of the input.
@@ -554,7 +1366,33 @@ impl RegexBuilder {
     /// disabled, the regex will act as if the pattern started with a `.*?`,
     /// which enables a match to appear anywhere.
     ///
+    /// This option only applies to the forward DFA. The reverse DFA, which
+    /// is used to find the start of a match, is always anchored at the end
+    /// of the match found by the forward DFA regardless of this setting. It
+    /// never scans before the position at which the forward search started,
+    /// so the start reported for an anchored regex is always the starting
+    /// position of the search.
+    ///
     /// By default this is disabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{RegexBuilder, DFA};
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = RegexBuilder::new().anchored(true).build("[a-z]+[0-9]")?;
+    /// assert_eq!(Some((0, 4)), re.find(b"abc1 xyz2"));
+    /// assert_eq!(None, re.find(b" abc1"));
+    /// assert!(re.forward().is_anchored());
+    ///
+    /// // The reverse DFA is anchored even when the forward DFA is not.
+    /// let re = RegexBuilder::new().anchored(false).build("[a-z]+[0-9]")?;
+    /// assert_eq!(Some((1, 5)), re.find(b" abc1"));
+    /// assert!(!re.forward().is_anchored());
+    /// assert!(re.reverse().is_anchored());
+    /// # Ok(()) }; example().unwrap()
+    /// ```
     pub fn anchored(&mut self, yes: bool) -> &mut RegexBuilder {
         self.dfa.anchored(yes);
         self
@@ -751,6 +1589,68 @@ impl RegexBuilder {
         self
     }
 
+    /// Set the priority given to matches when more than one is possible.
+    ///
+    /// This only affects the forward DFA, and therefore only the end of each
+    /// match. The start of a match is always the leftmost position from
+    /// which a match ends there. See
+    /// [`dense::Builder::match_priority`](dense/struct.Builder.html#method.match_priority)
+    /// for more details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::dense::MatchPriority;
+    /// use regex_automata::RegexBuilder;
+    ///
+    /// # fn example() -> Result<(), regex_automata::Error> {
+    /// let re = RegexBuilder::new()
+    ///     .match_priority(MatchPriority::ShortestFirst)
+    ///     .build("[a-z]+")?;
+    /// let matches: Vec<(usize, usize)> = re.find_iter(b"ab c").collect();
+    /// assert_eq!(matches, vec![(0, 1), (1, 2), (3, 4)]);
+    /// # Ok(()) }; example().unwrap()
+    /// ```
+    pub fn match_priority(
+        &mut self,
+        priority: dense::MatchPriority,
+    ) -> &mut RegexBuilder {
+        self.dfa.match_priority(priority);
+        self
+    }
+
+    /// Set a limit on the number of NFA states used to compile a pattern.
+    ///
+    /// The limit applies separately to the forward and reverse DFAs. See
+    /// [`dense::Builder::nfa_size_limit`](dense/struct.Builder.html#method.nfa_size_limit)
+    /// for more details.
+    pub fn nfa_size_limit(&mut self, limit: usize) -> &mut RegexBuilder {
+        self.dfa.nfa_size_limit(limit);
+        self
+    }
+
+    /// Set a limit on the number of states in each DFA built by
+    /// determinization.
+    ///
+    /// The limit applies separately to the forward and reverse DFAs. See
+    /// [`dense::Builder::dfa_size_limit`](dense/struct.Builder.html#method.dfa_size_limit)
+    /// for more details.
+    pub fn dfa_size_limit(&mut self, limit: usize) -> &mut RegexBuilder {
+        self.dfa.dfa_size_limit(limit);
+        self
+    }
+
+    /// Set a limit on the number of copies that counted repetitions in a
+    /// pattern may expand to.
+    ///
+    /// See
+    /// [`dense::Builder::repetition_limit`](dense/struct.Builder.html#method.repetition_limit)
+    /// for more details.
+    pub fn repetition_limit(&mut self, limit: usize) -> &mut RegexBuilder {
+        self.dfa.repetition_limit(limit);
+        self
+    }
+
     /// Apply best effort heuristics to shrink the NFA at the expense of more
     /// time/memory.
     ///
@@ -769,3 +1669,224 @@ impl Default for RegexBuilder {
         RegexBuilder::new()
     }
 }
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    fn matches(re: &Regex, haystack: &[u8]) -> Vec<(usize, usize)> {
+        re.find_iter(haystack).collect()
+    }
+
+    #[test]
+    fn full_match_dfas_behind_references() {
+        let fw = dense::Builder::new().full_match(true).build(r"\d+").unwrap();
+        let rv = dense::Builder::new()
+            .full_match(true)
+            .reverse(true)
+            .build(r"\d+")
+            .unwrap();
+        let by_ref = Regex::from_dfas(&fw, &rv);
+        assert_eq!(None, by_ref.find(b"123a"));
+        assert_eq!(Some((0, 3)), by_ref.find(b"123"));
+        assert!(!by_ref.is_match(b"123a"));
+        let owned = Regex::from_dfas(fw.clone(), rv.clone());
+        assert_eq!(None, owned.find(b"123a"));
+        assert_eq!(Some((0, 3)), owned.find(b"123"));
+    }
+
+    #[test]
+    fn for_each_match_visits_find_iter_matches() {
+        let re = Regex::new("a*|b").unwrap();
+        let haystack = b"baaabxa";
+        let mut visited = vec![];
+        re.for_each_match(haystack, |s, e| {
+            visited.push((s, e));
+            true
+        });
+        assert_eq!(matches(&re, haystack), visited);
+
+        let mut visited = vec![];
+        re.for_each_match(haystack, |s, e| {
+            visited.push((s, e));
+            visited.len() < 2
+        });
+        assert_eq!(matches(&re, haystack)[..2], visited[..]);
+    }
+
+    #[test]
+    fn find_iter_lines_edge_cases() {
+        let lines = |re: &Regex, text: &[u8], terminator| {
+            re.find_iter_lines(text, terminator).collect::<Vec<_>>()
+        };
+        let re = Regex::new(r"[a-z]+\r?").unwrap();
+
+        // A lone `\r` is part of the line, even at the end of the input.
+        let text = b"ab\r\ncd\ref\r";
+        assert_eq!(
+            vec![(0, 2), (4, 7), (7, 10)],
+            lines(&re, text, LineTerminator::CrLf)
+        );
+        assert_eq!(
+            vec![(0, 3), (4, 7), (7, 10)],
+            lines(&re, text, LineTerminator::Lf)
+        );
+        // Only the final line lacks a terminator here.
+        assert_eq!(
+            vec![(0, 2), (3, 5)],
+            lines(&re, b"ab\0cd", LineTerminator::Byte(0))
+        );
+
+        // Empty lines are searched, but not one after the final terminator.
+        let re = Regex::new("x*").unwrap();
+        assert_eq!(
+            vec![(0, 0), (2, 2), (3, 3), (4, 4), (5, 5)],
+            lines(&re, b"\r\n\nab\r\n", LineTerminator::CrLf)
+        );
+        assert_eq!(vec![(0, 0)], lines(&re, b"\n", LineTerminator::Lf));
+        assert!(lines(&re, b"", LineTerminator::Lf).is_empty());
+    }
+
+    #[test]
+    fn existence_checks_ignore_reverse_dfa() {
+        // A reverse DFA that never matches would make `find` fail, so these
+        // can only succeed if they never consult it.
+        let re =
+            Regex::from_dfas(DenseDFA::new("foo").unwrap(), DenseDFA::empty());
+        assert!(re.is_match(b"xfoo"));
+        assert_eq!(Some(4), re.shortest_match(b"xfoo"));
+        assert_eq!(Some(4), re.shortest_match_at(b"xfoo", 1));
+    }
+
+    #[test]
+    fn find_earliest_ends_at_shortest_match() {
+        let re = Regex::new(r"[0-9]+(?:\.[0-9]+)?|x+y").unwrap();
+        let haystacks: &[&[u8]] = &[b"ab 12.5 c", b"xxxy", b"axxy", b"z", b""];
+        for &h in haystacks {
+            let got = re.find_earliest(h);
+            assert_eq!(re.shortest_match(h), got.map(|(_, e)| e));
+            if let Some((s, e)) = got {
+                assert_eq!(Some((s, e)), re.find_at(&h[..e], s));
+            }
+        }
+        assert_eq!(Some((1, 4)), re.find_earliest_at(b"axxyxy", 1));
+        assert_eq!(Some((4, 6)), re.find_earliest_at(b"axxyxy", 4));
+    }
+
+    #[test]
+    fn find_match_agrees_with_find() {
+        let re = Regex::new(r"[0-9]*").unwrap();
+        let haystacks: &[&[u8]] = &[b"ab 12 c", b"007", b"z", b""];
+        for &h in haystacks {
+            for start in 0..=h.len() {
+                let m = re.find_match_at(h, start);
+                assert_eq!(
+                    re.find_at(h, start),
+                    m.map(|m| (m.start(), m.end()))
+                );
+                if let Some(m) = m {
+                    assert_eq!(m.is_empty(), m.len() == 0);
+                    assert_eq!(&h[m.start()..m.end()], m.as_slice(h));
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn match_rejects_inverted_span() {
+        Match::new(2, 1);
+    }
+
+    #[test]
+    fn nth_and_last_match_agree_with_find_iter() {
+        let unanchored = Regex::new("a*").unwrap();
+        let anchored = RegexBuilder::new().anchored(true).build("a*").unwrap();
+        let haystacks: &[&[u8]] = &[b"", b"baaab", b"aab", b"bbb"];
+        for re in &[unanchored, anchored] {
+            for &h in haystacks {
+                let all = matches(re, h);
+                for n in 0..=all.len() {
+                    assert_eq!(all.get(n).cloned(), re.nth_match(h, n));
+                }
+                assert_eq!(all.last().cloned(), re.last_match(h));
+            }
+        }
+    }
+
+    #[test]
+    fn build_many_reports_preferred_pattern() {
+        let patterns = &["a+b", "[a-c]+", "", "b{2}", "ab|cd"];
+        let re = RegexBuilder::new().build_many(patterns).unwrap();
+        let alt = Regex::new("(?:a+b)|(?:[a-c]+)|(?:)|(?:b{2})|(?:ab|cd)");
+        let alt = alt.unwrap();
+        let anchored: Vec<Regex> = patterns
+            .iter()
+            .map(|p| RegexBuilder::new().anchored(true).build(p).unwrap())
+            .collect();
+
+        let haystacks: &[&[u8]] = &[b"", b"aab", b"zbbcd", b"ccab x", b"xyz"];
+        for &h in haystacks {
+            let got: Vec<_> = re.find_iter(h).collect();
+            let spans: Vec<_> = got.iter().map(|&(_, s, e)| (s, e)).collect();
+            assert_eq!(matches(&alt, h), spans, "{:?}", h);
+            for &(p, s, e) in &got {
+                assert_eq!(Some((0, e - s)), anchored[p].find(&h[s..]));
+                for earlier in &anchored[..p] {
+                    assert_eq!(None, earlier.find(&h[s..]));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn empty_matches_reported_by_default() {
+        let re = Regex::new("").unwrap();
+        assert_eq!(matches(&re, b"ab"), vec![(0, 0), (1, 1), (2, 2)]);
+
+        let re = Regex::new("(?:)").unwrap();
+        assert_eq!(matches(&re, b""), vec![(0, 0)]);
+
+        let re = Regex::new("a*").unwrap();
+        assert_eq!(matches(&re, b"baaab"), vec![(0, 0), (1, 4), (5, 5)]);
+    }
+
+    #[test]
+    fn empty_matches_skipped_when_disabled() {
+        let mut builder = RegexBuilder::new();
+        builder.allow_empty_matches(false);
+
+        let re = builder.build("").unwrap();
+        assert_eq!(matches(&re, b"ab"), vec![]);
+        assert_eq!(re.find(b"ab"), Some((0, 0)));
+
+        let re = builder.build("(?:)").unwrap();
+        assert_eq!(matches(&re, b""), vec![]);
+
+        let re = builder.build("a*").unwrap();
+        assert_eq!(matches(&re, b"baaab"), vec![(1, 4)]);
+        assert_eq!(matches(&re, b"aab"), vec![(0, 2)]);
+
+        let re = builder.build_sparse("a*").unwrap();
+        assert_eq!(re.find_iter(b"baaab").collect::<Vec<_>>(), vec![(1, 4)]);
+    }
+
+    #[test]
+    fn anchored_find_iter_yields_at_most_one_match() {
+        let mut builder = RegexBuilder::new();
+        builder.anchored(true);
+
+        let re = builder.build("abc").unwrap();
+        assert_eq!(matches(&re, b"abcabc"), vec![(0, 3)]);
+        assert_eq!(matches(&re, b"xabcabc"), vec![]);
+
+        let re = builder.build("a*").unwrap();
+        assert_eq!(matches(&re, b"aab"), vec![(0, 2)]);
+        assert_eq!(matches(&re, b"baa"), vec![(0, 0)]);
+        assert_eq!(matches(&re, b""), vec![(0, 0)]);
+
+        let re = builder.build_many(&["abc", "[a-c]+"]).unwrap();
+        let got: Vec<_> = re.find_iter(b"abcabc").collect();
+        assert_eq!(got, vec![(0, 0, 3)]);
+    }
+}
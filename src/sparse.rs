@@ -13,7 +13,7 @@ use byteorder::{ByteOrder, NativeEndian};
 
 use classes::ByteClasses;
 use dense;
-use dfa::DFA;
+use dfa::{prefault_bytes, DFA};
 #[cfg(feature = "std")]
 use error::{Error, Result};
 #[cfg(feature = "std")]
@@ -21,6 +21,10 @@ use state_id::{dead_id, usize_to_state_id, write_state_id_bytes, StateID};
 #[cfg(not(feature = "std"))]
 use state_id::{dead_id, StateID};
 
+/// The label at the beginning of every serialized sparse DFA.
+#[cfg(feature = "std")]
+pub(crate) const LABEL: &[u8] = b"rust-regex-automata-sparse-dfa\x00";
+
 /// A sparse table-based deterministic finite automaton (DFA).
 ///
 /// In contrast to a [dense DFA](enum.DenseDFA.html), a sparse DFA uses a
@@ -189,6 +193,15 @@ impl<S: StateID> SparseDFA<Vec<u8>, S> {
     ) -> Result<SparseDFA<Vec<u8>, A>> {
         Repr::from_dense_sized(dfa).map(|r| r.into_sparse_dfa())
     }
+
+    /// Like `from_dense_sized`, but also writes a map from the index of each
+    /// state in the dense DFA to its identifier in the sparse DFA to `remap`.
+    pub(crate) fn from_dense_remapped<T: AsRef<[S]>, A: StateID>(
+        dfa: &dense::Repr<T, S>,
+        remap: &mut Vec<A>,
+    ) -> Result<SparseDFA<Vec<u8>, A>> {
+        Repr::from_dense_remapped(dfa, remap).map(|r| r.into_sparse_dfa())
+    }
 }
 
 impl<T: AsRef<[u8]>, S: StateID> SparseDFA<T, S> {
@@ -238,6 +251,13 @@ impl<T: AsRef<[u8]>, S: StateID> SparseDFA<T, S> {
         self.repr().memory_usage()
     }
 
+    /// Returns the byte equivalence classes used by this DFA.
+    ///
+    /// When byte classes are disabled, every byte is in its own class.
+    pub fn byte_classes(&self) -> &ByteClasses {
+        &self.repr().byte_classes
+    }
+
     fn repr(&self) -> &Repr<T, S> {
         match *self {
             SparseDFA::Standard(ref r) => &r.0,
@@ -440,6 +460,11 @@ impl<T: AsRef<[u8]>, S: StateID> DFA for SparseDFA<T, S> {
         self.repr().is_anchored()
     }
 
+    #[inline]
+    fn requires_full_match(&self) -> bool {
+        self.repr().full_match
+    }
+
     #[inline]
     fn next_state(&self, current: S, input: u8) -> S {
         match *self {
@@ -454,6 +479,11 @@ impl<T: AsRef<[u8]>, S: StateID> DFA for SparseDFA<T, S> {
         self.next_state(current, input)
     }
 
+    #[inline]
+    fn prefault(&self) {
+        self.repr().prefault()
+    }
+
     // We specialize the following methods because it lets us lift the
     // case analysis between the different types of sparse DFAs. Instead of
     // doing the case analysis for every transition, we do it once before
@@ -537,6 +567,11 @@ impl<T: AsRef<[u8]>, S: StateID> DFA for Standard<T, S> {
         self.0.is_anchored()
     }
 
+    #[inline]
+    fn requires_full_match(&self) -> bool {
+        self.0.full_match
+    }
+
     #[inline]
     fn next_state(&self, current: S, input: u8) -> S {
         self.0.state(current).next(input)
@@ -546,6 +581,11 @@ impl<T: AsRef<[u8]>, S: StateID> DFA for Standard<T, S> {
     unsafe fn next_state_unchecked(&self, current: S, input: u8) -> S {
         self.next_state(current, input)
     }
+
+    #[inline]
+    fn prefault(&self) {
+        self.0.prefault()
+    }
 }
 
 /// A sparse DFA that shrinks its alphabet.
@@ -598,6 +638,11 @@ impl<T: AsRef<[u8]>, S: StateID> DFA for ByteClass<T, S> {
         self.0.is_anchored()
     }
 
+    #[inline]
+    fn requires_full_match(&self) -> bool {
+        self.0.full_match
+    }
+
     #[inline]
     fn next_state(&self, current: S, input: u8) -> S {
         let input = self.0.byte_classes.get(input);
@@ -608,6 +653,11 @@ impl<T: AsRef<[u8]>, S: StateID> DFA for ByteClass<T, S> {
     unsafe fn next_state_unchecked(&self, current: S, input: u8) -> S {
         self.next_state(current, input)
     }
+
+    #[inline]
+    fn prefault(&self) {
+        self.0.prefault()
+    }
 }
 
 /// The underlying representation of a sparse DFA. This is shared by all of
@@ -616,6 +666,7 @@ impl<T: AsRef<[u8]>, S: StateID> DFA for ByteClass<T, S> {
 #[cfg_attr(not(feature = "std"), derive(Debug))]
 struct Repr<T: AsRef<[u8]>, S: StateID = usize> {
     anchored: bool,
+    full_match: bool,
     start: S,
     state_count: usize,
     max_match: S,
@@ -635,6 +686,7 @@ impl<T: AsRef<[u8]>, S: StateID> Repr<T, S> {
     fn as_ref<'a>(&'a self) -> Repr<&'a [u8], S> {
         Repr {
             anchored: self.anchored,
+            full_match: self.full_match,
             start: self.start,
             state_count: self.state_count,
             max_match: self.max_match,
@@ -647,6 +699,7 @@ impl<T: AsRef<[u8]>, S: StateID> Repr<T, S> {
     fn to_owned(&self) -> Repr<Vec<u8>, S> {
         Repr {
             anchored: self.anchored,
+            full_match: self.full_match,
             start: self.start,
             state_count: self.state_count,
             max_match: self.max_match,
@@ -683,6 +736,10 @@ impl<T: AsRef<[u8]>, S: StateID> Repr<T, S> {
         self.trans().len()
     }
 
+    fn prefault(&self) {
+        prefault_bytes(self.trans());
+    }
+
     fn start_state(&self) -> S {
         self.start
     }
@@ -736,6 +793,7 @@ impl<T: AsRef<[u8]>, S: StateID> Repr<T, S> {
 
         let mut new = Repr {
             anchored: self.anchored,
+            full_match: self.full_match,
             start: map[&self.start],
             state_count: self.state_count,
             max_match: map[&self.max_match],
@@ -766,7 +824,7 @@ impl<T: AsRef<[u8]>, S: StateID> Repr<T, S> {
     /// sparse DFA's transition table is always read as a sequence of bytes.
     #[cfg(feature = "std")]
     fn to_bytes<A: ByteOrder>(&self) -> Result<Vec<u8>> {
-        let label = b"rust-regex-automata-sparse-dfa\x00";
+        let label = LABEL;
         let size =
             // For human readable label.
             label.len()
@@ -801,8 +859,8 @@ impl<T: AsRef<[u8]>, S: StateID> Repr<T, S> {
         // endianness check
         A::write_u16(&mut buf[i..], 0xFEFF);
         i += 2;
-        // version number
-        A::write_u16(&mut buf[i..], 1);
+        // version number, which is written once the options are known
+        let version_at = i;
         i += 2;
         // size of state ID
         let state_size = size_of::<S>();
@@ -819,7 +877,12 @@ impl<T: AsRef<[u8]>, S: StateID> Repr<T, S> {
         if self.anchored {
             options |= dense::MASK_ANCHORED;
         }
+        if self.full_match {
+            options |= dense::MASK_FULL_MATCH;
+        }
         A::write_u16(&mut buf[i..], options);
+        let version = dense::version_for_options(options);
+        A::write_u16(&mut buf[version_at..], version);
         i += 2;
         // start state
         A::write_u64(&mut buf[i..], self.start.to_usize() as u64);
@@ -877,11 +940,13 @@ impl<'a, S: StateID> Repr<&'a [u8], S> {
         // check that the version number is supported
         let version = NativeEndian::read_u16(buf);
         buf = &buf[2..];
-        if version != 1 {
-            panic!(
-                "expected version 1, but found unsupported version {}",
+        match version {
+            1 | 2 => {}
+            _ => panic!(
+                "unsupported version {}, the newest supported version is {}",
                 version,
-            );
+                dense::VERSION,
+            ),
         }
 
         // read size of state
@@ -899,6 +964,15 @@ impl<'a, S: StateID> Repr<&'a [u8], S> {
         // read miscellaneous options
         let opts = NativeEndian::read_u16(buf);
         buf = &buf[2..];
+        if let Some(msg) = dense::check_version(version, opts) {
+            panic!("{} (version {}, options 0x{:X})", msg, version, opts);
+        }
+        if opts & dense::MASK_SKIP_BYTES > 0 {
+            panic!("SparseDFA can't skip bytes, but its options say it does");
+        }
+        if opts & dense::MASK_MATCH_SET > 0 {
+            panic!("SparseDFA always shuffles its match states");
+        }
 
         // read start state
         let start = S::from_usize(NativeEndian::read_u64(buf) as usize);
@@ -918,6 +992,7 @@ impl<'a, S: StateID> Repr<&'a [u8], S> {
 
         Repr {
             anchored: opts & dense::MASK_ANCHORED > 0,
+            full_match: opts & dense::MASK_FULL_MATCH > 0,
             start,
             state_count,
             max_match,
@@ -932,6 +1007,15 @@ impl<S: StateID> Repr<Vec<u8>, S> {
     /// The implementation for constructing a sparse DFA from a dense DFA.
     fn from_dense_sized<T: AsRef<[S]>, A: StateID>(
         dfa: &dense::Repr<T, S>,
+    ) -> Result<Repr<Vec<u8>, A>> {
+        Repr::from_dense_remapped(dfa, &mut vec![])
+    }
+
+    /// Construct a sparse DFA from a dense DFA, and write the map from dense
+    /// state index to sparse state identifier used to build it to `remap`.
+    fn from_dense_remapped<T: AsRef<[S]>, A: StateID>(
+        dfa: &dense::Repr<T, S>,
+        remap: &mut Vec<A>,
     ) -> Result<Repr<Vec<u8>, A>> {
         // In order to build the transition table, we need to be able to write
         // state identifiers for each of the "next" transitions in each state.
@@ -949,11 +1033,23 @@ impl<S: StateID> Repr<Vec<u8>, S> {
         //
         // In the second pass, we fill in the transitions based on the map
         // built in the first pass.
+        //
+        // States are laid out with the dead state and the match states first,
+        // which is already the order of a dense DFA unless its match states
+        // weren't shuffled. The sort is stable, so the order is otherwise
+        // preserved.
 
+        let mut states: Vec<_> = dfa.states().collect();
+        states.sort_by_key(|&(id, _)| !dfa.is_match_or_dead_state(id));
         let mut trans = Vec::with_capacity(size_of::<A>() * dfa.state_count());
-        let mut remap: Vec<A> = vec![dead_id(); dfa.state_count()];
-        for (old_id, state) in dfa.states() {
+        let mut max_match = dead_id();
+        remap.clear();
+        remap.resize(dfa.state_count(), dead_id());
+        for (old_id, state) in states {
             let pos = trans.len();
+            if dfa.is_match_state(old_id) {
+                max_match = usize_to_state_id(pos)?;
+            }
 
             remap[dfa.state_id_to_index(old_id)] = usize_to_state_id(pos)?;
             // zero-filled space for the transition count
@@ -976,9 +1072,10 @@ impl<S: StateID> Repr<Vec<u8>, S> {
 
         let mut new = Repr {
             anchored: dfa.is_anchored(),
+            full_match: dfa.requires_full_match(),
             start: remap[dfa.state_id_to_index(dfa.start_state())],
             state_count: dfa.state_count(),
-            max_match: remap[dfa.state_id_to_index(dfa.max_match_state())],
+            max_match,
             byte_classes: dfa.byte_classes().clone(),
             trans,
         };
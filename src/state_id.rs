@@ -18,13 +18,32 @@ mod std {
     /// Check that the premultiplication of the given state identifier can
     /// fit into the representation indicated by `S`. If it cannot, or if it
     /// overflows `usize` itself, then an error is returned.
+    ///
+    /// The error returned has kind
+    /// [`ErrorKind::PremultiplyOverflow`](enum.ErrorKind.html#variant.PremultiplyOverflow).
+    /// When the premultiplied identifier doesn't fit in a `usize`, both of
+    /// its fields are set to `usize`'s maximum value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::premultiply_overflow_error;
+    ///
+    /// assert!(premultiply_overflow_error(255u16, 256).is_ok());
+    /// assert!(premultiply_overflow_error(256u16, 256).is_err());
+    /// ```
     pub fn premultiply_overflow_error<S: StateID>(
         last_state: S,
         alphabet_len: usize,
     ) -> Result<()> {
         let requested = match last_state.to_usize().checked_mul(alphabet_len) {
             Some(requested) => requested,
-            None => return Err(Error::premultiply_overflow(0, 0)),
+            None => {
+                // The premultiplied ID doesn't even fit in a usize, which is
+                // reported by setting both fields to usize's maximum.
+                let max = !0;
+                return Err(Error::premultiply_overflow(max, max));
+            }
         };
         if requested > S::max_id() {
             return Err(Error::premultiply_overflow(S::max_id(), requested));
@@ -32,10 +51,51 @@ mod std {
         Ok(())
     }
 
+    /// Premultiply the given state identifier by the given alphabet length,
+    /// i.e., convert a state's index into the offset of its transitions in a
+    /// transition table.
+    ///
+    /// If the result cannot be represented by `S`, then the same error as
+    /// [`premultiply_overflow_error`](fn.premultiply_overflow_error.html)
+    /// is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::premultiply_state_id;
+    ///
+    /// assert_eq!(10 * 256, premultiply_state_id(10u16, 256).unwrap());
+    /// assert!(premultiply_state_id(300u16, 256).is_err());
+    /// ```
+    pub fn premultiply_state_id<S: StateID>(
+        id: S,
+        alphabet_len: usize,
+    ) -> Result<S> {
+        premultiply_overflow_error(id, alphabet_len)?;
+        Ok(S::from_usize(id.to_usize() * alphabet_len))
+    }
+
     /// Allocate the next sequential identifier for a fresh state given
     /// the previously constructed state identified by `current`. If the
     /// next sequential identifier would overflow `usize` or the chosen
     /// representation indicated by `S`, then an error is returned.
+    ///
+    /// The error returned has kind
+    /// [`ErrorKind::StateIDOverflow`](enum.ErrorKind.html#variant.StateIDOverflow),
+    /// whose `max` is the largest identifier representable by `S`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_automata::{next_state_id, ErrorKind};
+    ///
+    /// assert_eq!(6u8, next_state_id(5u8).unwrap());
+    ///
+    /// match *next_state_id(255u8).unwrap_err().kind() {
+    ///     ErrorKind::StateIDOverflow { max } => assert_eq!(255, max),
+    ///     ref kind => panic!("unexpected error: {:?}", kind),
+    /// }
+    /// ```
     pub fn next_state_id<S: StateID>(current: S) -> Result<S> {
         let next = match current.to_usize().checked_add(1) {
             Some(next) => next,
@@ -50,6 +110,9 @@ mod std {
     /// Convert the given `usize` to the chosen state identifier
     /// representation. If the given value cannot fit in the chosen
     /// representation, then an error is returned.
+    ///
+    /// As with [`next_state_id`](fn.next_state_id.html), the error returned
+    /// has kind `ErrorKind::StateIDOverflow`.
     pub fn usize_to_state_id<S: StateID>(value: usize) -> Result<S> {
         if value > S::max_id() {
             Err(Error::state_id_overflow(S::max_id()))
@@ -58,23 +121,38 @@ mod std {
         }
     }
 
+    /// Return the number of bytes used for each state identifier of the
+    /// representation `S` when serializing a DFA.
+    ///
+    /// This is the size of `S` rounded up to the next power of two, so that
+    /// representations of unusual sizes (such as 3 bytes) are widened to
+    /// the next standard width on disk. If `S` is bigger than 8 bytes, then
+    /// a serialization error is returned.
+    pub(crate) fn serialized_state_size<S: StateID>() -> Result<usize> {
+        let size = size_of::<S>();
+        if size == 0 || size > 8 {
+            return Err(Error::serialize(&format!(
+                "state size of {} not supported, must be at most 8",
+                size
+            )));
+        }
+        Ok(size.next_power_of_two())
+    }
+
     /// Write the given identifier to the given slice of bytes using the
     /// specified endianness. The given slice must have length at least
-    /// `size_of::<S>()`.
+    /// `size_of::<S>()` rounded up to the next power of two.
     ///
-    /// The given state identifier representation must have size 1, 2, 4 or 8.
+    /// The identifier is widened to that size, which must be at most 8. See
+    /// `serialized_state_size`.
     pub fn write_state_id_bytes<E: ByteOrder, S: StateID>(
         slice: &mut [u8],
         id: S,
     ) {
-        assert!(
-            1 == size_of::<S>()
-                || 2 == size_of::<S>()
-                || 4 == size_of::<S>()
-                || 8 == size_of::<S>()
-        );
-
-        match size_of::<S>() {
+        let width = size_of::<S>().next_power_of_two();
+        assert!(width <= 8);
+
+        match width {
             1 => slice[0] = id.to_usize() as u8,
             2 => E::write_u16(slice, id.to_usize() as u16),
             4 => E::write_u32(slice, id.to_usize() as u32),
@@ -102,6 +180,12 @@ pub fn dead_id<S: StateID>() -> S {
 /// `u64` and `usize`. (`u32` and `u64` are only provided for targets that can
 /// represent all corresponding values in a `usize`.)
 ///
+/// Code that allocates or transforms state identifiers generically should use
+/// [`next_state_id`](fn.next_state_id.html),
+/// [`usize_to_state_id`](fn.usize_to_state_id.html) and
+/// [`premultiply_state_id`](fn.premultiply_state_id.html), which check for
+/// overflow and report it in the same way as this crate's own builders.
+///
 /// # Safety
 ///
 /// This trait is unsafe because the correctness of its implementations may be
@@ -40,3 +40,40 @@ fn minimize_sets_correct_match_states() {
         .unwrap();
     assert_eq!(None, dfa.find(b"\xE2"));
 }
+
+// Zero-width assertions must be rejected when building a DFA rather than
+// being dropped from the pattern, even when they only appear in one branch
+// of an otherwise unanchored pattern. Silently ignoring them would produce a
+// DFA that reports matches the regex crate would not.
+#[test]
+fn assertions_are_rejected_not_ignored() {
+    use regex_automata::{ErrorKind, Regex};
+
+    let patterns = &[
+        r"\A",
+        r"\z",
+        r"^",
+        r"$",
+        r"(?m)^",
+        r"(?m)$",
+        r"\Afoo",
+        r"foo\z",
+        r"foo$",
+        r"foo|\Abar",
+        r"(?:bar\z)?foo",
+        r"a\bb",
+        r"a\Bb",
+        r"(?-u:\b)a",
+    ];
+    for &pattern in patterns {
+        let err = match Regex::new(pattern) {
+            Ok(_) => panic!("pattern {:?} should be rejected", pattern),
+            Err(err) => err,
+        };
+        match *err.kind() {
+            ErrorKind::Unsupported(_) => {}
+            ref kind => panic!("{:?}: unexpected error {:?}", pattern, kind),
+        }
+        assert!(dense::Builder::new().anchored(true).build(pattern).is_err());
+    }
+}
@@ -0,0 +1,27 @@
+use regex_automata::{DenseDFA, DFA};
+
+// This was generated by `DenseDFA::to_rust_source`, just as a build script
+// would, from the DFA built by `foo_dfa` below. Including it here checks that
+// the generated code compiles and loads a working DFA.
+include!("../data/rust-source/foo.rs");
+
+fn foo_dfa() -> DenseDFA<Vec<u16>, u16> {
+    DenseDFA::new("foo[0-9]+").unwrap().to_u16().unwrap()
+}
+
+#[test]
+fn included_source_searches() {
+    let dfa = FOO_dfa();
+    assert_eq!(Some(8), dfa.find(b"xx foo12 foo3"));
+    assert_eq!(None, dfa.find(b"foo bar"));
+    assert!(dfa.is_match(b"afoo9"));
+    assert_eq!(foo_dfa().as_ref(), dfa);
+}
+
+// If this fails because the serialization format changed, then regenerate
+// the fixture by writing `foo_dfa().to_rust_source("FOO")` to it.
+#[test]
+fn included_source_is_up_to_date() {
+    let source = foo_dfa().to_rust_source("FOO").unwrap();
+    assert_eq!(include_str!("../data/rust-source/foo.rs"), source);
+}
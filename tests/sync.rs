@@ -0,0 +1,74 @@
+use std::sync::Arc;
+use std::thread;
+
+use regex_automata::{dense, sparse, DenseDFA, Regex, RegexBuilder, DFA};
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+// Searching must never require mutation. If interior mutability (such as a
+// lazily populated cache) is ever added to one of these types, then this
+// will stop compiling.
+#[test]
+fn search_types_are_send_and_sync() {
+    assert_send_sync::<DenseDFA<Vec<usize>, usize>>();
+    assert_send_sync::<DenseDFA<&'static [u16], u16>>();
+    assert_send_sync::<DenseDFA<Arc<[u32]>, u32>>();
+    assert_send_sync::<dense::Standard<Vec<usize>, usize>>();
+    assert_send_sync::<dense::PremultipliedByteClass<Vec<usize>, usize>>();
+    assert_send_sync::<sparse::SparseDFA<Vec<u8>, usize>>();
+    assert_send_sync::<sparse::SparseDFA<&'static [u8], u16>>();
+    assert_send_sync::<Regex>();
+    assert_send_sync::<Regex<sparse::SparseDFA<Vec<u8>, usize>>>();
+    assert_send_sync::<dense::Builder>();
+    assert_send_sync::<RegexBuilder>();
+}
+
+#[test]
+fn concurrent_searches_share_one_dfa() {
+    let dfa = DenseDFA::new(r"foo[0-9]+").unwrap();
+    let haystacks: Vec<String> =
+        (0..8).map(|i| format!("{}foo{}", "x".repeat(i), i * 1000)).collect();
+
+    // Scoped threads aren't available in the minimum supported Rust version,
+    // so each thread borrows the DFA out of a shared `Arc` instead.
+    let shared = Arc::new(dfa.clone());
+    let handles: Vec<_> = haystacks
+        .iter()
+        .cloned()
+        .map(|haystack| {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || {
+                let dfa: &DenseDFA<Vec<usize>, usize> = &shared;
+                let mut ends = vec![];
+                for _ in 0..1000 {
+                    ends.push(dfa.find(haystack.as_bytes()));
+                }
+                ends
+            })
+        })
+        .collect();
+    for (haystack, handle) in haystacks.iter().zip(handles) {
+        let expected = dfa.find(haystack.as_bytes());
+        assert!(expected.is_some());
+        for end in handle.join().unwrap() {
+            assert_eq!(expected, end);
+        }
+    }
+}
+
+#[test]
+fn concurrent_regex_searches_share_one_regex() {
+    let re = Arc::new(Regex::new(r"[a-z]+@[a-z]+").unwrap());
+    let handles: Vec<_> = (0..8)
+        .map(|i| {
+            let re = Arc::clone(&re);
+            thread::spawn(move || {
+                let text = format!("{}user@example", " ".repeat(i));
+                re.find(text.as_bytes()).map(|(s, e)| (s - i, e - i))
+            })
+        })
+        .collect();
+    for handle in handles {
+        assert_eq!(Some((0, 12)), handle.join().unwrap());
+    }
+}
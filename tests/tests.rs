@@ -20,6 +20,10 @@ mod collection;
 #[cfg(feature = "std")]
 mod regression;
 #[cfg(feature = "std")]
+mod rust_source;
+#[cfg(feature = "std")]
 mod suite;
 #[cfg(feature = "std")]
+mod sync;
+#[cfg(feature = "std")]
 mod unescape;